@@ -0,0 +1,150 @@
+use axum::http::{HeaderMap, header};
+use axum::response::{IntoResponse, Response};
+use std::borrow::Cow;
+
+use crate::problem::{ProblemDetails, StatusCode};
+
+/// Picks which of `offered` the `Accept` header prefers, following `q` weights and wildcards.
+/// Ties go to whichever comes first in `offered`, and a missing header accepts the first one.
+/// `None` when nothing offered is acceptable.
+pub fn negotiate<'a>(headers: &HeaderMap, offered: &[&'a str]) -> Option<&'a str> {
+    let ranges: Vec<MediaRange> = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(MediaRange::parse)
+        .collect();
+
+    if ranges.is_empty() {
+        return offered.first().copied();
+    }
+
+    let mut best: Option<(&'a str, f32)> = None;
+    for &media_type in offered {
+        let q = ranges
+            .iter()
+            .filter_map(|r| r.specificity(media_type).map(|s| (s, r.q)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0.0, |(_, q)| q);
+
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((media_type, q));
+        }
+    }
+    best.map(|(media_type, _)| media_type)
+}
+
+struct MediaRange<'h> {
+    main: &'h str,
+    sub: &'h str,
+    q: f32,
+}
+
+impl<'h> MediaRange<'h> {
+    fn parse(range: &'h str) -> Option<Self> {
+        let mut params = range.split(';');
+        let (main, sub) = params.next()?.trim().split_once('/')?;
+        let q = params
+            .filter_map(|p| p.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .map_or(Some(1.0), |(_, q)| q.trim().parse().ok())?;
+
+        Some(Self {
+            main: main.trim(),
+            sub: sub.trim(),
+            q,
+        })
+    }
+
+    /// How closely this range matches `media_type`, `None` if it doesn't at all
+    fn specificity(&self, media_type: &str) -> Option<u8> {
+        let (main, sub) = media_type.split_once('/')?;
+        match (self.main, self.sub) {
+            ("*", "*") => Some(0),
+            (m, "*") if m.eq_ignore_ascii_case(main) => Some(1),
+            (m, s) if m.eq_ignore_ascii_case(main) && s.eq_ignore_ascii_case(sub) => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// Returned when the `Accept` header doesn't allow anything an endpoint can respond with
+#[derive(Debug, Clone, Copy)]
+pub struct NotAcceptable {
+    pub offered: &'static [&'static str],
+}
+
+impl ProblemDetails for NotAcceptable {
+    fn status(&self) -> StatusCode {
+        StatusCode::NOT_ACCEPTABLE
+    }
+
+    fn code(&self) -> &'static str {
+        "not_acceptable"
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        Some(Cow::Owned(format!(
+            "the response can only be one of {}",
+            self.offered.join(", ")
+        )))
+    }
+}
+
+impl IntoResponse for NotAcceptable {
+    fn into_response(self) -> Response {
+        self.to_problem().into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const OFFERED: [&str; 3] = ["application/json", "application/x-ndjson", "text/csv"];
+
+    fn accepting(accept: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+        headers
+    }
+
+    #[test]
+    fn missing_accept_picks_the_first_offered() {
+        assert_eq!(
+            Some("application/json"),
+            negotiate(&HeaderMap::new(), &OFFERED)
+        );
+    }
+
+    #[test]
+    fn weights_and_wildcards_are_followed() {
+        assert_eq!(
+            Some("text/csv"),
+            negotiate(&accepting("application/json;q=0.5, text/csv"), &OFFERED)
+        );
+        assert_eq!(Some("text/csv"), negotiate(&accepting("text/*"), &OFFERED));
+        assert_eq!(
+            Some("application/x-ndjson"),
+            negotiate(
+                &accepting("application/*;q=0.9, application/json;q=0.1"),
+                &OFFERED
+            )
+        );
+        assert_eq!(
+            Some("application/json"),
+            negotiate(&accepting("*/*"), &OFFERED)
+        );
+    }
+
+    #[test]
+    fn nothing_acceptable_is_none() {
+        assert_eq!(None, negotiate(&accepting("application/xml"), &OFFERED));
+        assert_eq!(
+            None,
+            negotiate(&accepting("*/*;q=0, application/xml"), &OFFERED)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use serde_json::{Map, Value};
+
+use crate::validation::{FieldError, FieldErrors, ValidationRule};
+
+/// Free-form fields a client keeps on a resource, like its owning team or SLA
+pub type Attributes = Map<String, Value>;
+
+pub const FIELD: &str = "attributes";
+pub const KEY_MAX_LEN: usize = 64;
+/// List query parameters starting with this filter on an attribute, like `attr.team=payments`
+pub const QUERY_PREFIX: &str = "attr.";
+
+/// A list filter on one attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeFilter {
+    /// The attribute is a string, number or bool whose text is `value`
+    Equals { key: String, value: String },
+    /// The attribute is set, whatever its value
+    Exists(String),
+}
+
+impl AttributeFilter {
+    /// Reads a query parameter like `attr.team=payments`, a blank value only checks the attribute
+    /// exists. `None` for parameters that aren't about attributes
+    pub fn from_query(name: &str, value: &str) -> Option<Self> {
+        let key = name.strip_prefix(QUERY_PREFIX)?.to_string();
+        if value.is_empty() {
+            Some(Self::Exists(key))
+        } else {
+            Some(Self::Equals {
+                key,
+                value: value.to_string(),
+            })
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            AttributeFilter::Equals { key, .. } | AttributeFilter::Exists(key) => key,
+        }
+    }
+
+    pub fn matches(&self, attributes: &Attributes) -> bool {
+        match self {
+            AttributeFilter::Equals { key, value } => attributes
+                .get(key)
+                .and_then(scalar_text)
+                .is_some_and(|text| text == *value),
+            AttributeFilter::Exists(key) => attributes.contains_key(key),
+        }
+    }
+}
+
+/// The text an equality filter compares against, objects, arrays and null never match
+pub fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+/// Keys are up to 64 letters, digits, `_` or `-`, so they can be used as query parameters and
+/// document paths as they are
+pub fn check_key(key: &str) -> Result<(), FieldError> {
+    if key.is_empty() {
+        return Err(FieldError::new(
+            FIELD,
+            ValidationRule::Empty,
+            format!("{FIELD} keys cannot be blank"),
+        ));
+    }
+    let len = key.chars().count();
+    if len > KEY_MAX_LEN {
+        return Err(FieldError::new(
+            FIELD,
+            ValidationRule::TooLong,
+            format!("{FIELD} keys must be at most {KEY_MAX_LEN} characters, was {len}"),
+        ));
+    }
+    if !key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+    {
+        return Err(FieldError::new(
+            FIELD,
+            ValidationRule::Format,
+            format!("{FIELD} key {key:?} can only have letters, digits, '_' and '-'"),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks every key, nulls are left in since they remove the attribute when patching
+pub fn validate(attributes: &Attributes) -> Result<(), FieldErrors> {
+    let mut errors = FieldErrors::default();
+    for key in attributes.keys() {
+        errors.check(check_key(key));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Sets every attribute in `patch` on `attributes`, removing the ones that are null
+pub fn merge(attributes: &mut Attributes, patch: Attributes) {
+    for (key, value) in patch {
+        if value.is_null() {
+            attributes.remove(&key);
+        } else {
+            attributes.insert(key, value);
+        }
+    }
+}
+
+/// The patch [merge] takes to turn `current` into `updated`
+pub fn diff(current: &Attributes, updated: Attributes) -> Attributes {
+    let mut patch: Attributes = current
+        .keys()
+        .filter(|key| !updated.contains_key(*key))
+        .map(|key| (key.clone(), Value::Null))
+        .collect();
+    for (key, value) in updated {
+        if current.get(&key) != Some(&value) {
+            patch.insert(key, value);
+        }
+    }
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn attributes(value: Value) -> Attributes {
+        match value {
+            Value::Object(attributes) => attributes,
+            _ => panic!("attributes are an object"),
+        }
+    }
+
+    #[test]
+    fn filters_match_scalars_by_their_text() {
+        let current = attributes(json!({ "team": "payments", "sla": 99, "owners": ["a"] }));
+        let equals =
+            |key: &str, value: &str| AttributeFilter::from_query(&format!("attr.{key}"), value);
+
+        assert!(equals("team", "payments").unwrap().matches(&current));
+        assert!(equals("sla", "99").unwrap().matches(&current));
+        assert!(!equals("owners", "[\"a\"]").unwrap().matches(&current));
+        assert!(equals("owners", "").unwrap().matches(&current));
+        assert!(!equals("tier", "").unwrap().matches(&current));
+        assert_eq!(None, AttributeFilter::from_query("team", "payments"));
+    }
+
+    #[test]
+    fn merging_a_diff_gives_the_updated_attributes() {
+        let mut current = attributes(json!({ "team": "payments", "sla": 99, "tier": 1 }));
+        let updated = attributes(json!({ "team": "orders", "tier": 1, "region": "eu" }));
+
+        let patch = diff(&current, updated.clone());
+        assert_eq!(
+            attributes(json!({ "team": "orders", "sla": null, "region": "eu" })),
+            patch
+        );
+        merge(&mut current, patch);
+        assert_eq!(updated, current);
+    }
+
+    #[test]
+    fn keys_have_to_be_simple_names() {
+        assert_eq!(Ok(()), check_key("cost-centre_2"));
+        assert_eq!(ValidationRule::Empty, check_key("").unwrap_err().rule);
+        assert_eq!(ValidationRule::Format, check_key("a.b").unwrap_err().rule);
+        assert_eq!(ValidationRule::Format, check_key("$set").unwrap_err().rule);
+        assert_eq!(
+            ValidationRule::TooLong,
+            check_key(&"k".repeat(KEY_MAX_LEN + 1)).unwrap_err().rule
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::{fmt::Debug, pin::Pin, sync::Arc};
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use error_stack::Report;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::{roles::Roles, user::AuthedUser},
+    problem::ProblemDetails,
+};
+
+pub mod routes;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+const API_KEY_PREFIX: &str = "tk_";
+
+pub type ApiKeyResult<T> = Result<T, Report<ApiKeyError>>;
+
+#[derive(Debug, thiserror::Error, Copy, Clone, PartialEq, Eq)]
+pub enum ApiKeyError {
+    #[error("failed to find api key")]
+    Find,
+    #[error("failed to create api key")]
+    Create,
+    #[error("failed to list api keys")]
+    List,
+    #[error("failed to revoke api key")]
+    Revoke,
+}
+
+impl ProblemDetails for ApiKeyError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn code(&self) -> &'static str {
+        "api_key_store_failed"
+    }
+}
+
+/// An API key as it is stored. The key itself is never stored, only its hash.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ApiKey {
+    pub id: Uuid,
+    /// Who the key was issued to. Requests made with this key are attributed to this owner.
+    pub owner: String,
+    /// The role names granted to the key, e.g. `TOPIC_WRITE`
+    pub roles: Vec<String>,
+    pub created: DateTime<Utc>,
+    pub revoked: Option<DateTime<Utc>>,
+}
+
+pub struct NewApiKey {
+    pub id: Uuid,
+    pub owner: String,
+    pub roles: Vec<String>,
+    pub key_hash: String,
+}
+
+/// Returned only when a key is created, this is the only time the key is visible.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// Send this in the `X-Api-Key` header. It cannot be retrieved again.
+    pub key: String,
+}
+
+pub trait ApiKeyRepository: Clone + Send + Sync + 'static {
+    fn find_by_hash(
+        &self,
+        key_hash: &str,
+    ) -> impl Future<Output = ApiKeyResult<Option<ApiKey>>> + Send;
+
+    fn create(&self, new_key: NewApiKey) -> impl Future<Output = ApiKeyResult<ApiKey>> + Send;
+
+    fn list(&self) -> impl Future<Output = ApiKeyResult<Vec<ApiKey>>> + Send;
+
+    /// Marks the key as revoked. Revoking an already revoked key keeps the original revoked time.
+    fn revoke(&self, id: Uuid) -> impl Future<Output = ApiKeyResult<Option<ApiKey>>> + Send;
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// object safe version of `ApiKeyRepository` so `AuthState` doesn't need to be generic over the repo
+trait DynApiKeyRepository: Send + Sync + 'static {
+    fn find_by_hash<'a>(&'a self, key_hash: &'a str)
+    -> BoxFuture<'a, ApiKeyResult<Option<ApiKey>>>;
+    fn create(&self, new_key: NewApiKey) -> BoxFuture<'_, ApiKeyResult<ApiKey>>;
+    fn list(&self) -> BoxFuture<'_, ApiKeyResult<Vec<ApiKey>>>;
+    fn revoke(&self, id: Uuid) -> BoxFuture<'_, ApiKeyResult<Option<ApiKey>>>;
+}
+
+impl<T: ApiKeyRepository> DynApiKeyRepository for T {
+    fn find_by_hash<'a>(
+        &'a self,
+        key_hash: &'a str,
+    ) -> BoxFuture<'a, ApiKeyResult<Option<ApiKey>>> {
+        Box::pin(ApiKeyRepository::find_by_hash(self, key_hash))
+    }
+
+    fn create(&self, new_key: NewApiKey) -> BoxFuture<'_, ApiKeyResult<ApiKey>> {
+        Box::pin(ApiKeyRepository::create(self, new_key))
+    }
+
+    fn list(&self) -> BoxFuture<'_, ApiKeyResult<Vec<ApiKey>>> {
+        Box::pin(ApiKeyRepository::list(self))
+    }
+
+    fn revoke(&self, id: Uuid) -> BoxFuture<'_, ApiKeyResult<Option<ApiKey>>> {
+        Box::pin(ApiKeyRepository::revoke(self, id))
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiKeys(Arc<dyn DynApiKeyRepository>);
+
+impl Debug for ApiKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ApiKeys").finish()
+    }
+}
+
+impl ApiKeys {
+    pub fn new(repo: impl ApiKeyRepository) -> Self {
+        Self(Arc::new(repo))
+    }
+
+    /// Finds the active key matching `key` and turns it into the same `AuthedUser` a validated token would produce.
+    pub async fn authenticate<R>(&self, key: &str) -> ApiKeyResult<Option<AuthedUser<R>>>
+    where
+        R: Roles,
+        R::Err: Debug,
+    {
+        let Some(api_key) = self.0.find_by_hash(&hash_key(key)).await? else {
+            debug!("no api key matched");
+            return Ok(None);
+        };
+
+        if api_key.revoked.is_some() {
+            warn!("api key {} was used after being revoked", api_key.id);
+            return Ok(None);
+        }
+
+        Ok(Some(AuthedUser {
+            id: api_key.owner.into(),
+            email: None,
+            roles: parse_roles(&api_key.roles),
+        }))
+    }
+
+    pub async fn create(&self, owner: String, roles: Vec<String>) -> ApiKeyResult<CreatedApiKey> {
+        let key = generate_key();
+        let api_key = self
+            .0
+            .create(NewApiKey {
+                id: Uuid::now_v7(),
+                owner,
+                roles,
+                key_hash: hash_key(&key),
+            })
+            .await?;
+
+        Ok(CreatedApiKey { api_key, key })
+    }
+
+    pub async fn list(&self) -> ApiKeyResult<Vec<ApiKey>> {
+        self.0.list().await
+    }
+
+    pub async fn revoke(&self, id: Uuid) -> ApiKeyResult<Option<ApiKey>> {
+        self.0.revoke(id).await
+    }
+}
+
+pub fn parse_roles<R>(names: &[String]) -> R
+where
+    R: Roles,
+    R::Err: Debug,
+{
+    names.iter().fold(R::none(), |mut r, name| {
+        r.add(name.parse().expect("roles flag parse is infallible"));
+        r
+    })
+}
+
+fn generate_key() -> String {
+    let bytes: [u8; 32] = rand::random();
+    format!("{API_KEY_PREFIX}{}", hex::encode(bytes))
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_are_prefixed_and_unique() {
+        let key1 = generate_key();
+        let key2 = generate_key();
+
+        assert!(key1.starts_with(API_KEY_PREFIX));
+        assert_eq!(API_KEY_PREFIX.len() + 64, key1.len());
+        assert_ne!(key1, key2);
+    }
+
+    #[test]
+    fn hash_key_is_stable_and_does_not_contain_key() {
+        let key = generate_key();
+
+        assert_eq!(hash_key(&key), hash_key(&key));
+        assert_eq!(64, hash_key(&key).len());
+        assert!(!hash_key(&key).contains(&key[API_KEY_PREFIX.len()..]));
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt::Debug;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+};
+use serde::Deserialize;
+use tracing::{info, instrument};
+use utoipa::{OpenApi, ToSchema};
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        api_key::{ApiKey, ApiKeyError, ApiKeys, CreatedApiKey},
+        roles::{Roles, require_roles},
+    },
+    error::EndpointError,
+    problem::{CommonProblems, Problem},
+};
+
+pub(crate) const API_KEY_ROOT_PATH: &str = "/api-keys";
+const API_KEY_LIST_PATH: &str = "/";
+const API_KEY_CREATE_PATH: &str = "/";
+const API_KEY_REVOKE_PATH: &str = "/{key_id}";
+
+#[derive(OpenApi)]
+#[openapi(paths(list_api_keys, create_api_key, revoke_api_key))]
+pub(crate) struct ApiKeyDocs;
+
+/// The api key admin routes. Every route requires `admin_roles`.
+pub(crate) fn router<S, R>(api_keys: ApiKeys, admin_roles: R) -> OpenApiRouter<S>
+where
+    S: Send + Sync + Clone + 'static,
+    R: Roles,
+    R::Err: Debug,
+{
+    OpenApiRouter::new()
+        .route(
+            API_KEY_LIST_PATH,
+            get(list_api_keys).post(create_api_key::<R>),
+        )
+        .route(API_KEY_REVOKE_PATH, delete(revoke_api_key))
+        .route_layer(middleware::from_fn_with_state(
+            admin_roles,
+            require_roles::<R>,
+        ))
+        .layer(Extension(api_keys))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    /// Who the key is for, usually the name of the calling service
+    pub owner: String,
+    /// The role names to grant, e.g. `["TOPIC_READ", "TOPIC_WRITE"]`
+    pub roles: Vec<String>,
+}
+
+fn unprocessable(detail: &'static str) -> Response {
+    Problem::from_status(StatusCode::UNPROCESSABLE_ENTITY)
+        .with_detail(detail)
+        .into_response()
+}
+
+/// List every api key, including revoked ones. The keys themselves are never returned.
+#[utoipa::path(
+    get,
+    path = API_KEY_LIST_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "All api keys", body = Vec<ApiKey>),
+    )
+)]
+#[instrument(skip_all, err(Debug))]
+async fn list_api_keys(
+    Extension(api_keys): Extension<ApiKeys>,
+) -> Result<Response, EndpointError<ApiKeyError>> {
+    Ok(Json(api_keys.list().await?).into_response())
+}
+
+/// Create a new api key. The key is only ever returned in this response.
+#[utoipa::path(
+    post,
+    path = API_KEY_CREATE_PATH,
+    responses(
+        CommonProblems,
+        (status = CREATED, description = "The api key was created", body = CreatedApiKey),
+        (status = UNPROCESSABLE_ENTITY, description = "The owner was blank, no roles were given or a role is unknown", body = Problem, content_type = "application/problem+json"),
+    ),
+    request_body = CreateApiKeyRequest
+)]
+#[instrument(skip_all, err(Debug), fields(req.owner = req.owner))]
+async fn create_api_key<R>(
+    Extension(api_keys): Extension<ApiKeys>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<Response, EndpointError<ApiKeyError>>
+where
+    R: Roles,
+    R::Err: Debug,
+{
+    if req.owner.trim().is_empty() {
+        return Ok(unprocessable("owner must not be blank"));
+    }
+
+    if req.roles.is_empty() {
+        return Ok(unprocessable("at least one role is required"));
+    }
+
+    let unknown: Vec<_> = req
+        .roles
+        .iter()
+        .filter(|name| !name.parse::<R>().is_ok_and(|r| !r.is_none()))
+        .collect();
+    if !unknown.is_empty() {
+        return Ok(
+            Problem::new(StatusCode::UNPROCESSABLE_ENTITY, "unknown_roles")
+                .with_detail("every role must be known")
+                .with_extension("roles", unknown)
+                .into_response(),
+        );
+    }
+
+    let created = api_keys.create(req.owner, req.roles).await?;
+    info!(
+        "api key {} created for {}",
+        created.api_key.id, created.api_key.owner
+    );
+
+    Ok((StatusCode::CREATED, Json(created)).into_response())
+}
+
+/// Revoke an api key. Requests using it will be rejected from now on.
+#[utoipa::path(
+    delete,
+    path = API_KEY_REVOKE_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The api key was revoked", body = ApiKey),
+        (status = NOT_FOUND, description = "No api key with the given id exists", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("key_id" = Uuid, Path, description = "The id of the api key to revoke"),
+    )
+)]
+#[instrument(skip(api_keys), err(Debug))]
+async fn revoke_api_key(
+    Extension(api_keys): Extension<ApiKeys>,
+    Path(key_id): Path<Uuid>,
+) -> Result<Response, EndpointError<ApiKeyError>> {
+    Ok(match api_keys.revoke(key_id).await? {
+        Some(revoked) => {
+            info!("api key {key_id} revoked");
+            Json(revoked).into_response()
+        }
+        None => Problem::new(StatusCode::NOT_FOUND, "api_key_not_found").into_response(),
+    })
+}
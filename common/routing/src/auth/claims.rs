@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::auth::{roles::Roles, user::AuthedUser};
+use crate::auth::{role_mapping::RoleMapping, roles::Roles, user::AuthedUser};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -21,39 +21,19 @@ pub struct Claims {
 }
 
 impl Claims {
-    pub fn into_authed_user<R>(self, roles_path: &str) -> AuthedUser<R>
+    pub fn into_authed_user<R>(self, role_mapping: &RoleMapping) -> AuthedUser<R>
     where
         R: Roles,
         R::Err: Debug,
     {
-        let parts = roles_path.split('.');
-
-        let mut current = Value::Object(self.extra);
-
-        for part in parts {
-            current = match current.get(part) {
-                Some(val) => val.clone(),
-                None => {
-                    return AuthedUser {
-                        id: self.sub.into(),
-                        email: self.email.map(|e| e.into()),
-                        roles: R::none(),
-                    };
-                }
-            }
-        }
-
-        let roles = match current {
-            Value::Array(roles) => roles.into_iter().fold(R::none(), |mut r, next| {
-                r.add(
-                    next.to_string()
-                        .parse()
-                        .expect("roles flag parse is infallible"),
-                );
-                r
-            }),
-            _ => R::none(),
-        };
+        let roles =
+            role_mapping
+                .role_names(&self.extra)
+                .into_iter()
+                .fold(R::none(), |mut r, next| {
+                    r.add(next.parse().expect("roles flag parse is infallible"));
+                    r
+                });
 
         AuthedUser {
             id: self.sub.into(),
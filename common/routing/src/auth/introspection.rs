@@ -0,0 +1,358 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use chrono::Utc;
+use error_stack::{Report, ResultExt};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+
+use crate::{
+    ArwLock,
+    auth::{
+        oauth::{MissingOAuthProperty, OAUTH_ROLE_MAP, OAuthResult},
+        role_mapping::RoleMapping,
+        roles::Roles,
+        user::AuthedUser,
+    },
+};
+
+const OAUTH_INTROSPECTION_URL: &str = "OAUTH_INTROSPECTION_URL";
+const OAUTH_INTROSPECTION_CLIENT_ID: &str = "OAUTH_INTROSPECTION_CLIENT_ID";
+const OAUTH_INTROSPECTION_CLIENT_SECRET: &str = "OAUTH_INTROSPECTION_CLIENT_SECRET";
+const OAUTH_INTROSPECTION_ROLES_PATH: &str = "OAUTH_INTROSPECTION_ROLES_PATH";
+
+/// Where to send opaque tokens to find out who they belong to (RFC 7662)
+#[derive(Debug, Clone, Deserialize)]
+pub struct IntrospectionConfig {
+    /// The introspection endpoint, tokens are POSTed here as a form
+    pub endpoint: String,
+    /// Sent as basic auth to the introspection endpoint
+    pub client_id: String,
+    pub client_secret: String,
+    /// Where roles are found in the introspection response, defaults to `scope` and `roles`
+    #[serde(default = "default_roles")]
+    pub roles: RoleMapping,
+}
+
+fn default_roles() -> RoleMapping {
+    RoleMapping::new(["scope", "roles"])
+}
+
+impl IntrospectionConfig {
+    /// Introspection is optional, `None` is returned if `OAUTH_INTROSPECTION_URL` isn't set.
+    /// If it is set, the client id and secret are required.
+    pub fn from_env() -> OAuthResult<Option<Self>> {
+        let Ok(endpoint) = std::env::var(OAUTH_INTROSPECTION_URL) else {
+            return Ok(None);
+        };
+
+        let mut roles = match std::env::var(OAUTH_INTROSPECTION_ROLES_PATH) {
+            Ok(paths) => {
+                RoleMapping::new(paths.split(',').map(str::trim).filter(|p| !p.is_empty()))
+            }
+            Err(_) => default_roles(),
+        };
+
+        if let Ok(role_map) = std::env::var(OAUTH_ROLE_MAP) {
+            roles.role_map = RoleMapping::parse_role_map(&role_map);
+        }
+
+        Ok(Some(Self {
+            endpoint,
+            client_id: std::env::var(OAUTH_INTROSPECTION_CLIENT_ID)
+                .change_context(MissingOAuthProperty(OAUTH_INTROSPECTION_CLIENT_ID))?,
+            client_secret: std::env::var(OAUTH_INTROSPECTION_CLIENT_SECRET)
+                .change_context(MissingOAuthProperty(OAUTH_INTROSPECTION_CLIENT_SECRET))?,
+            roles,
+        }))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("failed to introspect token")]
+pub struct IntrospectionErr;
+
+#[derive(Debug, Deserialize)]
+struct IntrospectionResponse {
+    active: bool,
+    sub: Option<String>,
+    username: Option<String>,
+    email: Option<String>,
+    exp: Option<i64>,
+    // scope, roles and anything else the server sends, for the role mapping
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    id: Arc<str>,
+    email: Option<Arc<str>>,
+    role_names: Vec<String>,
+    exp: i64,
+}
+
+impl CachedToken {
+    fn authed_user<R>(&self) -> AuthedUser<R>
+    where
+        R: Roles,
+        R::Err: Debug,
+    {
+        AuthedUser {
+            id: self.id.clone(),
+            email: self.email.clone(),
+            roles: self.role_names.iter().fold(R::none(), |mut r, name| {
+                r.add(name.parse().expect("roles flag parse is infallible"));
+                r
+            }),
+        }
+    }
+}
+
+/// Introspects opaque tokens, caching active tokens until they expire.
+/// Tokens are cached by their hash, so the tokens themselves aren't kept around.
+#[derive(Debug, Clone)]
+pub(crate) struct Introspector {
+    config: Arc<IntrospectionConfig>,
+    client: reqwest::Client,
+    cache: ArwLock<HashMap<String, CachedToken>>,
+}
+
+impl Introspector {
+    pub fn new(config: IntrospectionConfig) -> Self {
+        info!("token introspection enabled using {}", config.endpoint);
+        Self {
+            config: Arc::new(config),
+            client: reqwest::Client::new(),
+            cache: ArwLock::default(),
+        }
+    }
+
+    /// `None` means the token is not active
+    pub async fn introspect<R>(
+        &self,
+        token: &str,
+    ) -> Result<Option<AuthedUser<R>>, Report<IntrospectionErr>>
+    where
+        R: Roles,
+        R::Err: Debug,
+    {
+        let key = hex::encode(Sha256::digest(token.as_bytes()));
+        let now = Utc::now().timestamp();
+
+        if let Some(cached) = self.cache.read().await.get(&key)
+            && cached.exp > now
+        {
+            debug!("introspection cache hit");
+            return Ok(Some(cached.authed_user()));
+        }
+
+        let response: IntrospectionResponse = self
+            .client
+            .post(&self.config.endpoint)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[("token", token), ("token_type_hint", "access_token")])
+            .send()
+            .await
+            .change_context(IntrospectionErr)?
+            .error_for_status()
+            .change_context(IntrospectionErr)?
+            .json()
+            .await
+            .change_context(IntrospectionErr)?;
+
+        if !response.active || response.exp.is_some_and(|exp| exp <= now) {
+            debug!("token is not active");
+            return Ok(None);
+        }
+
+        let Some(id) = response.sub.or(response.username) else {
+            warn!("active token has neither a sub nor a username");
+            return Ok(None);
+        };
+
+        let cached = CachedToken {
+            id: id.into(),
+            email: response.email.map(Into::into),
+            role_names: self.config.roles.role_names(&response.extra),
+            exp: response.exp.unwrap_or(now),
+        };
+        let authed_user = cached.authed_user();
+
+        match response.exp {
+            Some(_) => {
+                let mut cache = self.cache.write().await;
+                cache.retain(|_, t| t.exp > now);
+                cache.insert(key, cached);
+            }
+            None => debug!("token has no exp, it will not be cached"),
+        }
+
+        Ok(Some(authed_user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{Form, Json, Router, extract::State, http::HeaderMap, routing::post};
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct TestRoles(u8);
+
+    impl std::fmt::Display for TestRoles {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::str::FromStr for TestRoles {
+        type Err = std::convert::Infallible;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Ok(match s {
+                "read" => Self(1),
+                "write" => Self(2),
+                _ => Self(0),
+            })
+        }
+    }
+
+    impl Roles for TestRoles {
+        fn none() -> Self {
+            Self(0)
+        }
+        fn is_none(&self) -> bool {
+            self.0 == 0
+        }
+        fn contains(&self, other: Self) -> bool {
+            self.0 & other.0 == other.0
+        }
+        fn add(&mut self, other: Self) {
+            self.0 |= other.0
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct StubState {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[derive(Deserialize)]
+    struct IntrospectionForm {
+        token: String,
+    }
+
+    // "Basic " + base64("client:secret")
+    const EXPECTED_AUTH: &str = "Basic Y2xpZW50OnNlY3JldA==";
+
+    async fn introspect_stub(
+        State(state): State<StubState>,
+        headers: HeaderMap,
+        Form(form): Form<IntrospectionForm>,
+    ) -> Json<Value> {
+        state.calls.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(
+            Some(EXPECTED_AUTH),
+            headers.get("Authorization").and_then(|h| h.to_str().ok())
+        );
+
+        let exp = Utc::now().timestamp() + 60;
+        Json(match form.token.as_str() {
+            "active" => json!({ "active": true, "sub": "svc", "scope": "read write", "exp": exp }),
+            "no-exp" => json!({ "active": true, "sub": "svc", "roles": ["read"] }),
+            _ => json!({ "active": false }),
+        })
+    }
+
+    async fn stub_server() -> (Introspector, StubState) {
+        let state = StubState::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/introspect", post(introspect_stub))
+            .with_state(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let introspector = Introspector::new(IntrospectionConfig {
+            endpoint: format!("http://{addr}/introspect"),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            roles: default_roles(),
+        });
+
+        (introspector, state)
+    }
+
+    #[tokio::test]
+    async fn active_token_maps_sub_and_scope() {
+        let (introspector, _) = stub_server().await;
+
+        let user = introspector
+            .introspect::<TestRoles>("active")
+            .await
+            .unwrap()
+            .expect("token is active");
+
+        assert_eq!("svc", &*user.id);
+        assert_eq!(TestRoles(3), user.roles);
+    }
+
+    #[tokio::test]
+    async fn inactive_token_returns_none() {
+        let (introspector, _) = stub_server().await;
+
+        assert!(
+            introspector
+                .introspect::<TestRoles>("revoked")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn active_token_is_cached_until_exp() {
+        let (introspector, stub) = stub_server().await;
+
+        introspector
+            .introspect::<TestRoles>("active")
+            .await
+            .unwrap();
+        introspector
+            .introspect::<TestRoles>("active")
+            .await
+            .unwrap();
+
+        assert_eq!(1, stub.calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn tokens_without_exp_and_inactive_tokens_are_not_cached() {
+        let (introspector, stub) = stub_server().await;
+
+        introspector
+            .introspect::<TestRoles>("no-exp")
+            .await
+            .unwrap();
+        introspector
+            .introspect::<TestRoles>("no-exp")
+            .await
+            .unwrap();
+        introspector
+            .introspect::<TestRoles>("revoked")
+            .await
+            .unwrap();
+        introspector
+            .introspect::<TestRoles>("revoked")
+            .await
+            .unwrap();
+
+        assert_eq!(4, stub.calls.load(Ordering::SeqCst));
+    }
+}
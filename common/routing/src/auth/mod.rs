@@ -1,5 +1,8 @@
+pub mod api_key;
 pub mod claims;
+pub mod introspection;
 pub mod oauth;
+pub mod role_mapping;
 pub mod roles;
 pub mod token;
 pub mod user;
@@ -1,32 +1,45 @@
+use std::path::Path;
+
 use error_stack::{Report, ResultExt};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::ArwLock;
+use crate::{ArwLock, auth::role_mapping::RoleMapping};
 
 pub type OAuthResult<T> = Result<T, Report<MissingOAuthProperty>>;
 
 #[derive(Debug, thiserror::Error)]
 #[error("{0} oauth property not specified")]
-pub struct MissingOAuthProperty(&'static str);
+pub struct MissingOAuthProperty(pub(crate) &'static str);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, thiserror::Error)]
+#[error("failed to load trusted issuers config")]
+pub struct OAuthConfigErr;
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct OAuthConfig {
     /// Where to fetch public keys
     pub jwks_url: String,
     /// Who issued the token (the URL of the issuer)
     pub issuer_url: String,
-    /// Key in JWT for roles
-    pub roles_claims_path: String,
+    /// Where roles are found in the JWT and how they map to the service's roles
+    pub roles: RoleMapping,
     /// this api's identifier.
     // might all be the same, could maybe hard code this, but also maybe it's best to pass that info in via the env
+    #[serde(default = "default_audience")]
     pub audience: String,
 }
 
 const OAUTH_JWKS_URL: &str = "OAUTH_JWKS_URL";
 const OAUTH_ISSUER_URL: &str = "OAUTH_ISSUER_URL";
 const OAUTH_ROLES_JWT_PATH: &str = "OAUTH_ROLES_JWT_PATH";
+pub(crate) const OAUTH_ROLE_MAP: &str = "OAUTH_ROLE_MAP";
 const OAUTH_AUDIENCE: &str = "OAUTH_AUDIENCE";
+const OAUTH_ISSUERS_FILE: &str = "OAUTH_ISSUERS_FILE";
+
+fn default_audience() -> String {
+    String::from("topics-api")
+}
 
 impl OAuthConfig {
     pub fn from_env() -> OAuthResult<Self> {
@@ -35,14 +48,64 @@ impl OAuthConfig {
                 .change_context(MissingOAuthProperty(OAUTH_JWKS_URL))?,
             issuer_url: std::env::var(OAUTH_ISSUER_URL)
                 .change_context(MissingOAuthProperty(OAUTH_ISSUER_URL))?,
-            roles_claims_path: std::env::var(OAUTH_ROLES_JWT_PATH)
-                .change_context(MissingOAuthProperty(OAUTH_ROLES_JWT_PATH))?,
+            roles: roles_from_env()?,
             audience: std::env::var(OAUTH_AUDIENCE).unwrap_or_else(|_| {
                 info!("OAUTH_AUDIENCE not specified, going with default");
-                String::from("topics-api")
+                default_audience()
             }),
         })
     }
+
+    /// Loads every trusted issuer. If `OAUTH_ISSUERS_FILE` is set, the issuers are read from that file,
+    /// otherwise a single issuer is built from the `OAUTH_*` env vars.
+    pub fn all_from_env() -> Result<Vec<Self>, Report<OAuthConfigErr>> {
+        match std::env::var(OAUTH_ISSUERS_FILE) {
+            Ok(path) => Self::all_from_file(path),
+            Err(_) => Ok(vec![Self::from_env().change_context(OAuthConfigErr)?]),
+        }
+    }
+
+    /// The file is expected to be a JSON array of issuer configs, e.g.
+    /// ```json
+    /// [{
+    ///     "jwks_url": "..",
+    ///     "issuer_url": "..",
+    ///     "roles": { "claim_paths": ["realm_access.roles"], "role_map": { "topics-reader": "TOPIC_READ" } },
+    ///     "audience": "topics-api"
+    /// }]
+    /// ```
+    pub fn all_from_file(path: impl AsRef<Path>) -> Result<Vec<Self>, Report<OAuthConfigErr>> {
+        let path = path.as_ref();
+        info!("loading trusted issuers from {}", path.display());
+        let contents = std::fs::read_to_string(path)
+            .change_context(OAuthConfigErr)
+            .attach_with(|| format!("could not read {}", path.display()))?;
+        parse_issuers(&contents)
+    }
+}
+
+/// `OAUTH_ROLES_JWT_PATH` is a comma separated list of claim paths,
+/// `OAUTH_ROLE_MAP` an optional comma separated list of `external=internal` role names
+fn roles_from_env() -> OAuthResult<RoleMapping> {
+    let claim_paths = std::env::var(OAUTH_ROLES_JWT_PATH)
+        .change_context(MissingOAuthProperty(OAUTH_ROLES_JWT_PATH))?;
+
+    let mut roles = RoleMapping::new(
+        claim_paths
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty()),
+    );
+
+    if let Ok(role_map) = std::env::var(OAUTH_ROLE_MAP) {
+        roles.role_map = RoleMapping::parse_role_map(&role_map);
+    }
+
+    Ok(roles)
+}
+
+fn parse_issuers(contents: &str) -> Result<Vec<OAuthConfig>, Report<OAuthConfigErr>> {
+    serde_json::from_str(contents).change_context(OAuthConfigErr)
 }
 
 #[derive(Debug, Clone)]
@@ -57,7 +120,7 @@ impl JwksState {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Jwks {
     pub keys: Vec<Jwk>,
 }
@@ -65,7 +128,7 @@ pub struct Jwks {
 // I understood this that night when I was using claude to figure out how this all works.
 // felt like a fever dream.
 // figure this out again
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Jwk {
     pub kid: String,
     // pub kty: String,
@@ -77,3 +140,46 @@ pub struct Jwk {
     pub e: String,
     // pub alg: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_issuers_defaults_audience() {
+        let issuers = parse_issuers(
+            r#"[
+                { "jwks_url": "http://old/certs", "issuer_url": "http://old", "roles": { "claim_paths": ["roles"] } },
+                {
+                    "jwks_url": "http://new/certs",
+                    "issuer_url": "http://new",
+                    "roles": {
+                        "claim_paths": ["realm_access.roles", "resource_access.topics-api.roles"],
+                        "role_map": { "topics-reader": "TOPIC_READ" }
+                    },
+                    "audience": "other-api"
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, issuers.len());
+        assert_eq!("topics-api", issuers[0].audience);
+        assert!(issuers[0].roles.role_map.is_empty());
+        assert_eq!(2, issuers[1].roles.claim_paths.len());
+        assert_eq!(
+            Some("TOPIC_READ"),
+            issuers[1]
+                .roles
+                .role_map
+                .get("topics-reader")
+                .map(String::as_str)
+        );
+        assert_eq!("other-api", issuers[1].audience);
+    }
+
+    #[test]
+    fn parse_issuers_missing_required_field_fails() {
+        assert!(parse_issuers(r#"[{ "jwks_url": "http://old/certs" }]"#).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+/// Describes where roles live in a token's claims and how they translate to the service's roles.
+///
+/// Roles found at every path in `claim_paths` are merged. A claim can either be an array of strings,
+/// or a single space separated string (like the `scope` claim).
+/// Each role name is then looked up in `role_map`, names without an entry are passed through unchanged.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RoleMapping {
+    /// Dotted paths into the claims, e.g. `realm_access.roles` or `resource_access.topics-api.roles`
+    pub claim_paths: Vec<String>,
+    /// External role name -> service role name, e.g. `topics-reader` -> `TOPIC_READ`
+    #[serde(default)]
+    pub role_map: HashMap<String, String>,
+}
+
+impl RoleMapping {
+    pub fn new<I, S>(claim_paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            claim_paths: claim_paths.into_iter().map(Into::into).collect(),
+            role_map: HashMap::new(),
+        }
+    }
+
+    pub fn with_role(mut self, external: impl Into<String>, internal: impl Into<String>) -> Self {
+        self.role_map.insert(external.into(), internal.into());
+        self
+    }
+
+    /// Parses a comma separated list of `external=internal` pairs, e.g. `topics-reader=TOPIC_READ,topics-writer=TOPIC_WRITE`
+    pub fn parse_role_map(s: &str) -> HashMap<String, String> {
+        s.split(',')
+            .filter(|pair| !pair.trim().is_empty())
+            .filter_map(|pair| match pair.split_once('=') {
+                Some((external, internal)) => {
+                    Some((external.trim().to_string(), internal.trim().to_string()))
+                }
+                None => {
+                    warn!("role mapping '{pair}' is not in the form external=internal. Ignoring");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Collects the service role names found in `claims`, in the order of `claim_paths`.
+    pub fn role_names(&self, claims: &Map<String, Value>) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for path in &self.claim_paths {
+            let Some(claim) = find_claim(claims, path) else {
+                debug!("no roles claim found at '{path}'");
+                continue;
+            };
+
+            match claim {
+                Value::Array(values) => {
+                    for value in values {
+                        match value {
+                            Value::String(name) => names.push(self.map_role(name)),
+                            other => warn!("non-string role {other} at '{path}'. Ignoring"),
+                        }
+                    }
+                }
+                Value::String(value) => {
+                    names.extend(value.split_whitespace().map(|name| self.map_role(name)))
+                }
+                other => warn!("roles claim at '{path}' is not a string or array: {other}"),
+            }
+        }
+
+        names
+    }
+
+    fn map_role(&self, name: &str) -> String {
+        self.role_map
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+fn find_claim<'a>(claims: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut current = claims.get(parts.next()?)?;
+
+    for part in parts {
+        current = current.get(part)?;
+    }
+
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("claims must be an object"),
+        }
+    }
+
+    #[test]
+    fn role_names_merges_every_claim_path() {
+        let mapping = RoleMapping::new(["realm_access.roles", "resource_access.topics-api.roles"]);
+        let claims = claims(json!({
+            "realm_access": { "roles": ["TOPIC_READ"] },
+            "resource_access": { "topics-api": { "roles": ["TOPIC_WRITE"] } },
+        }));
+
+        assert_eq!(
+            vec!["TOPIC_READ", "TOPIC_WRITE"],
+            mapping.role_names(&claims)
+        );
+    }
+
+    #[test]
+    fn role_names_does_not_keep_json_quotes() {
+        let mapping = RoleMapping::new(["roles"]);
+        let claims = claims(json!({ "roles": ["TOPIC_READ"] }));
+
+        assert_eq!(vec!["TOPIC_READ"], mapping.role_names(&claims));
+    }
+
+    #[test]
+    fn role_names_splits_string_claims() {
+        let mapping = RoleMapping::new(["scope"]);
+        let claims = claims(json!({ "scope": "TOPIC_READ  TOPIC_WRITE" }));
+
+        assert_eq!(
+            vec!["TOPIC_READ", "TOPIC_WRITE"],
+            mapping.role_names(&claims)
+        );
+    }
+
+    #[test]
+    fn role_names_maps_external_names_and_passes_through_unmapped() {
+        let mapping = RoleMapping::new(["roles"])
+            .with_role("topics-reader", "TOPIC_READ")
+            .with_role("topics-writer", "TOPIC_WRITE");
+        let claims = claims(json!({ "roles": ["topics-reader", "TOPIC_ADMIN", "topics-writer"] }));
+
+        assert_eq!(
+            vec!["TOPIC_READ", "TOPIC_ADMIN", "TOPIC_WRITE"],
+            mapping.role_names(&claims)
+        );
+    }
+
+    #[test]
+    fn role_names_ignores_missing_paths_and_non_string_roles() {
+        let mapping = RoleMapping::new(["missing.roles", "roles"]);
+        let claims = claims(json!({ "roles": [1, "TOPIC_READ", { "a": "b" }] }));
+
+        assert_eq!(vec!["TOPIC_READ"], mapping.role_names(&claims));
+    }
+
+    #[test]
+    fn parse_role_map_reads_pairs() {
+        let map = RoleMapping::parse_role_map(
+            "topics-reader=TOPIC_READ, topics-writer = TOPIC_WRITE,bad,",
+        );
+
+        assert_eq!(2, map.len());
+        assert_eq!(
+            Some("TOPIC_READ"),
+            map.get("topics-reader").map(String::as_str)
+        );
+        assert_eq!(
+            Some("TOPIC_WRITE"),
+            map.get("topics-writer").map(String::as_str)
+        );
+    }
+}
@@ -9,7 +9,7 @@ use axum::{
 };
 use tracing::{debug, error, warn};
 
-use crate::auth::user::AuthedUser;
+use crate::{auth::user::AuthedUser, problem::Problem};
 
 pub trait Roles: FromStr + Display + Clone + Send + Sync + 'static {
     fn none() -> Self;
@@ -22,10 +22,10 @@ pub async fn require_roles<R: Roles>(
     State(required_roles): State<R>,
     req: Request<Body>,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, Problem> {
     let user = req.extensions().get::<AuthedUser<R>>().ok_or_else(|| {
         error!("endpoint requires authorized user, none was found");
-        StatusCode::UNAUTHORIZED
+        Problem::from_status(StatusCode::UNAUTHORIZED)
     })?;
 
     debug!("required roles: {required_roles}");
@@ -33,6 +33,6 @@ pub async fn require_roles<R: Roles>(
         Ok(next.run(req).await)
     } else {
         warn!("User {} does not have the authority! (🧙‍♂️🚫➡️)", user.id);
-        Err(StatusCode::FORBIDDEN)
+        Err(Problem::from_status(StatusCode::FORBIDDEN))
     }
 }
@@ -1,4 +1,4 @@
-use std::{fmt::Debug, str::FromStr};
+use std::{collections::HashMap, fmt::Debug, str::FromStr, sync::Arc};
 
 use axum::{
     body::Body,
@@ -9,19 +9,35 @@ use axum::{
 };
 use error_stack::{Report, ResultExt};
 use jsonwebtoken::{Algorithm, DecodingKey, Validation};
-use tracing::{debug, error, info, instrument};
+use serde::Deserialize;
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::{
     ArwLock,
     auth::{
+        api_key::{API_KEY_HEADER, ApiKeyRepository, ApiKeys},
         claims::Claims,
+        introspection::{IntrospectionConfig, Introspector},
         oauth::{Jwk, Jwks, JwksState, OAuthConfig},
         roles::Roles,
+        user::AuthedUser,
     },
+    problem::Problem,
 };
 
+/// Holds every trusted issuer, keyed by the issuer URL found in a token's `iss` claim.
+/// Each issuer keeps its own JWKS cache and roles claim path.
+/// Api keys are only accepted when a key store has been added with `with_api_keys`,
+/// and opaque (non JWT) tokens only when introspection has been added with `with_introspection`.
 #[derive(Debug, Clone)]
 pub struct AuthState {
+    issuers: Arc<HashMap<String, IssuerState>>,
+    api_keys: Option<ApiKeys>,
+    introspection: Option<Introspector>,
+}
+
+#[derive(Debug, Clone)]
+struct IssuerState {
     jwks: JwksState,
     oauth_config: OAuthConfig,
 }
@@ -32,30 +48,120 @@ pub struct AuthStateCreationErr;
 
 impl AuthState {
     pub async fn create() -> Result<Self, Report<AuthStateCreationErr>> {
-        let oauth_config = OAuthConfig::from_env().change_context(AuthStateCreationErr)?;
-        Self::create_with(oauth_config).await
+        let issuers = OAuthConfig::all_from_env().change_context(AuthStateCreationErr)?;
+        let introspection = IntrospectionConfig::from_env().change_context(AuthStateCreationErr)?;
+
+        let state = Self::create_with_issuers(issuers).await?;
+        Ok(match introspection {
+            Some(config) => state.with_introspection(config),
+            None => state,
+        })
     }
 
     pub async fn create_with(
         oauth_config: OAuthConfig,
     ) -> Result<Self, Report<AuthStateCreationErr>> {
-        let jwks = refresh_jwks_from_url(&oauth_config.jwks_url)
-            .await
-            .change_context(AuthStateCreationErr)?;
+        Self::create_with_issuers([oauth_config]).await
+    }
+
+    pub async fn create_with_issuers(
+        oauth_configs: impl IntoIterator<Item = OAuthConfig>,
+    ) -> Result<Self, Report<AuthStateCreationErr>> {
+        let mut issuers = HashMap::new();
+
+        for oauth_config in oauth_configs {
+            let jwks = refresh_jwks_from_url(&oauth_config.jwks_url)
+                .await
+                .change_context(AuthStateCreationErr)
+                .attach_with(|| format!("issuer {}", oauth_config.issuer_url))?;
+
+            let issuer_url = oauth_config.issuer_url.clone();
+            let issuer = IssuerState {
+                jwks: JwksState {
+                    keys: ArwLock::new(jwks),
+                },
+                oauth_config,
+            };
+
+            if issuers.insert(issuer_url.clone(), issuer).is_some() {
+                warn!("issuer {issuer_url} was configured more than once, using the last one");
+            }
+        }
+
+        if issuers.is_empty() {
+            return Err(Report::new(AuthStateCreationErr))
+                .attach("at least one trusted issuer is required");
+        }
+
+        info!("{} trusted issuer(s) configured", issuers.len());
+
         Ok(Self {
+            issuers: Arc::new(issuers),
+            api_keys: None,
+            introspection: None,
+        })
+    }
+
+    /// Bearer tokens that aren't JWTs are checked against the introspection endpoint.
+    pub fn with_introspection(mut self, config: IntrospectionConfig) -> Self {
+        self.introspection = Some(Introspector::new(config));
+        self
+    }
+
+    /// Trust a single issuer with already known keys, skipping the JWKS fetch.
+    #[cfg(any(test, feature = "test-support"))]
+    pub(crate) fn with_jwks(oauth_config: OAuthConfig, keys: Vec<Jwk>) -> Self {
+        let issuer_url = oauth_config.issuer_url.clone();
+        let issuer = IssuerState {
             jwks: JwksState {
-                keys: ArwLock::new(jwks),
+                keys: ArwLock::new(keys),
             },
             oauth_config,
-        })
+        };
+
+        Self {
+            issuers: Arc::new(HashMap::from([(issuer_url, issuer)])),
+            api_keys: None,
+            introspection: None,
+        }
+    }
+
+    /// Accept the `X-Api-Key` header as an alternative to a bearer token.
+    pub fn with_api_keys(mut self, repo: impl ApiKeyRepository) -> Self {
+        self.api_keys = Some(ApiKeys::new(repo));
+        self
+    }
+
+    pub(crate) fn api_keys(&self) -> Option<&ApiKeys> {
+        self.api_keys.as_ref()
     }
 
+    /// Every issuer is refreshed even when others fail. An issuer that can't be reached keeps its
+    /// old keys, and the error lists each issuer that failed.
     #[instrument]
     pub async fn refresh_jwks(&mut self) -> Result<(), Report<RefreshJwksErr>> {
-        let jwks = refresh_jwks_from_url(&self.oauth_config.jwks_url).await?;
-        let mut keys = self.jwks.keys.write().await;
-        *keys = jwks;
-        Ok(())
+        let mut failed = Vec::new();
+        for (issuer_url, issuer) in self.issuers.iter() {
+            match refresh_jwks_from_url(&issuer.oauth_config.jwks_url).await {
+                Ok(jwks) => {
+                    let mut keys = issuer.jwks.keys.write().await;
+                    *keys = jwks;
+                }
+                Err(e) => {
+                    error!(
+                        "failed to refresh jwks for issuer {issuer_url}, keeping its old keys: {e:?}"
+                    );
+                    failed.push(issuer_url.as_str());
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(Report::new(RefreshJwksErr))
+                .attach_with(|| format!("failed issuers: {}", failed.join(", ")))
+        }
     }
 }
 
@@ -86,7 +192,7 @@ pub async fn validate_token<R>(
     State(state): State<AuthState>,
     mut request: Request<Body>,
     next: Next,
-) -> Result<Response, StatusCode>
+) -> Result<Response, Problem>
 where
     R: Roles,
     <R as FromStr>::Err: Debug,
@@ -95,50 +201,28 @@ where
         .headers()
         .get("Authorization")
         .and_then(|h| h.to_str().ok());
+    let api_key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|h| h.to_str().ok());
 
     // not all endpoints will require quthorization, this should allow those to go through.
     // those that require authorization will expect an `AuthedUser` to exist in extensions
-    if let Some(auth_header) = auth_header {
-        if !auth_header.starts_with("Bearer ") {
-            error!("invalid authorization type");
-            return Err(StatusCode::UNAUTHORIZED);
-        }
-
-        let token = &auth_header["Bearer ".len()..];
-
-        let header = jsonwebtoken::decode_header(token).map_err(|_| {
-            error!("JWT token decoding (without verification) failed");
-            StatusCode::UNAUTHORIZED
-        })?;
-        let kid = header.kid.ok_or_else(|| {
-            error!("invalid token: kid missing");
-            StatusCode::UNAUTHORIZED
-        })?;
-
-        let jwk = state.jwks.find_key(&kid).await.ok_or_else(|| {
-            error!("kid key not found");
-            StatusCode::UNAUTHORIZED
-        })?;
-
-        let decoding_key = DecodingKey::from_rsa_components(&jwk.n, &jwk.e).map_err(|_| {
-            error!("failed to create decoding key");
-            StatusCode::UNAUTHORIZED
-        })?;
-
-        let mut validation = Validation::new(Algorithm::RS256);
-        validation.set_audience(&[&state.oauth_config.audience]);
-        validation.set_issuer(&[&state.oauth_config.issuer_url]);
-
-        let token_data = jsonwebtoken::decode::<Claims>(token, &decoding_key, &validation)
-            .map_err(|e| {
-                error!("Token validation error: {e}");
-                StatusCode::UNAUTHORIZED
-            })?;
-
-        let authed_user = token_data
-            .claims
-            .into_authed_user::<R>(&state.oauth_config.roles_claims_path);
+    let authed_user = match (auth_header, api_key) {
+        (Some(auth_header), _) => Some(
+            authenticate_bearer::<R>(&state, auth_header)
+                .await
+                .map_err(Problem::from_status)?,
+        ),
+        (None, Some(api_key)) => Some(
+            authenticate_api_key::<R>(&state, api_key)
+                .await
+                .map_err(Problem::from_status)?,
+        ),
+        (None, None) => None,
+    };
 
+    if let Some(authed_user) = authed_user {
         tracing::Span::current().record("user_id", authed_user.id.to_string());
         match &authed_user.email {
             Some(email) => {
@@ -148,7 +232,10 @@ where
                 );
             }
             None => {
-                debug!("token validated. user's roles: {}", authed_user.roles)
+                debug!(
+                    "'{}' authenticated. roles: {}",
+                    authed_user.id, authed_user.roles
+                )
             }
         }
 
@@ -156,3 +243,194 @@ where
     }
     Ok(next.run(request).await)
 }
+
+async fn authenticate_bearer<R>(
+    state: &AuthState,
+    auth_header: &str,
+) -> Result<AuthedUser<R>, StatusCode>
+where
+    R: Roles,
+    <R as FromStr>::Err: Debug,
+{
+    if !auth_header.starts_with("Bearer ") {
+        error!("invalid authorization type");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let token = &auth_header["Bearer ".len()..];
+
+    let header = match (jsonwebtoken::decode_header(token), &state.introspection) {
+        (Ok(header), _) => header,
+        (Err(_), Some(introspector)) => {
+            debug!("token is not a JWT, introspecting");
+            return introspect::<R>(introspector, token).await;
+        }
+        (Err(_), None) => {
+            error!("JWT token decoding (without verification) failed");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    let iss = unverified_issuer(token)?;
+    let issuer = state.issuers.get(&iss).ok_or_else(|| {
+        error!("token issuer '{iss}' is not trusted");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let kid = header.kid.ok_or_else(|| {
+        error!("invalid token: kid missing");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let jwk = issuer.jwks.find_key(&kid).await.ok_or_else(|| {
+        error!("kid key not found");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let decoding_key = DecodingKey::from_rsa_components(&jwk.n, &jwk.e).map_err(|_| {
+        error!("failed to create decoding key");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&[&issuer.oauth_config.audience]);
+    validation.set_issuer(&[&issuer.oauth_config.issuer_url]);
+
+    let token_data =
+        jsonwebtoken::decode::<Claims>(token, &decoding_key, &validation).map_err(|e| {
+            error!("Token validation error: {e}");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+    Ok(token_data
+        .claims
+        .into_authed_user::<R>(&issuer.oauth_config.roles))
+}
+
+async fn introspect<R>(
+    introspector: &Introspector,
+    token: &str,
+) -> Result<AuthedUser<R>, StatusCode>
+where
+    R: Roles,
+    <R as FromStr>::Err: Debug,
+{
+    match introspector.introspect::<R>(token).await {
+        Ok(Some(authed_user)) => Ok(authed_user),
+        Ok(None) => {
+            error!("token is not active");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+        Err(e) => {
+            error!("failed to introspect token: {e:?}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn authenticate_api_key<R>(
+    state: &AuthState,
+    api_key: &str,
+) -> Result<AuthedUser<R>, StatusCode>
+where
+    R: Roles,
+    <R as FromStr>::Err: Debug,
+{
+    let Some(api_keys) = &state.api_keys else {
+        error!("api key given but api keys are not enabled");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    match api_keys.authenticate::<R>(api_key).await {
+        Ok(Some(authed_user)) => Ok(authed_user),
+        Ok(None) => {
+            error!("api key is unknown or revoked");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+        Err(e) => {
+            error!("failed to look up api key: {e:?}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UnverifiedIssuer {
+    iss: String,
+}
+
+/// Reads the `iss` claim without verifying the token. This is only used to pick which issuer's
+/// keys the token should be verified against, the full validation happens afterwards.
+fn unverified_issuer(token: &str) -> Result<String, StatusCode> {
+    jsonwebtoken::dangerous::insecure_decode::<UnverifiedIssuer>(token)
+        .map(|data| data.claims.iss)
+        .map_err(|_| {
+            error!("invalid token: iss missing");
+            StatusCode::UNAUTHORIZED
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestIssuer;
+
+    fn issuer_state(issuer: &TestIssuer, jwks_url: &str, keys: Vec<Jwk>) -> IssuerState {
+        IssuerState {
+            jwks: JwksState {
+                keys: ArwLock::new(keys),
+            },
+            oauth_config: issuer.oauth_config(jwks_url),
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_continues_past_an_unreachable_issuer() {
+        let healthy = TestIssuer::new("http://healthy-issuer");
+        let unreachable = TestIssuer::new("http://unreachable-issuer");
+        let server = healthy.serve_jwks().await;
+
+        let mut state = AuthState {
+            issuers: Arc::new(HashMap::from([
+                (
+                    healthy.issuer_url().to_string(),
+                    issuer_state(&healthy, server.url(), Vec::new()),
+                ),
+                (
+                    unreachable.issuer_url().to_string(),
+                    issuer_state(
+                        &unreachable,
+                        "http://127.0.0.1:1/certs",
+                        unreachable.jwks().keys,
+                    ),
+                ),
+            ])),
+            api_keys: None,
+            introspection: None,
+        };
+
+        let refreshed = state.refresh_jwks().await;
+
+        let failed = format!("{:?}", refreshed.expect_err("one issuer is unreachable"));
+        assert!(failed.contains("http://unreachable-issuer"));
+        assert!(!failed.contains("http://healthy-issuer"));
+
+        let kid = &healthy.jwks().keys[0].kid;
+        assert!(
+            state.issuers[healthy.issuer_url()]
+                .jwks
+                .find_key(kid)
+                .await
+                .is_some(),
+            "the healthy issuer is refreshed"
+        );
+        assert!(
+            state.issuers[unreachable.issuer_url()]
+                .jwks
+                .find_key(kid)
+                .await
+                .is_some(),
+            "the unreachable issuer keeps its old keys"
+        );
+    }
+}
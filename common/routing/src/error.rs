@@ -1,7 +1,9 @@
-use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use error_stack::Report;
 use std::error::Error;
+use tracing::error;
+
+use crate::problem::ProblemDetails;
 
 #[derive(thiserror::Error)]
 #[error("there was an error running the endpoint")]
@@ -22,8 +24,15 @@ where
     }
 }
 
-impl<T: Error> IntoResponse for EndpointError<T> {
+impl<T> IntoResponse for EndpointError<T>
+where
+    T: Error + ProblemDetails + Send + Sync + 'static,
+{
     fn into_response(self) -> axum::response::Response {
-        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        let problem = T::report_problem(&self.0);
+        if problem.status_code().is_server_error() {
+            error!("endpoint failed: {:?}", self.0);
+        }
+        problem.into_response()
     }
 }
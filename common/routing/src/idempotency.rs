@@ -0,0 +1,736 @@
+use std::{borrow::Cow, fmt::Debug, pin::Pin, sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use error_stack::Report;
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+use crate::{
+    accept::negotiate,
+    auth::{roles::Roles, user::AuthedUser},
+    ndjson::is_ndjson,
+    problem::ProblemDetails,
+    wire::WireFormat,
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Set on responses that were replayed instead of being handled again
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+const MAX_KEY_LEN: usize = 255;
+/// Bodies are held in memory to hash them, this is the same as axum's default body limit
+const MAX_BODY_LEN: usize = 2 * 1024 * 1024;
+
+pub type IdempotencyResult<T> = Result<T, Report<IdempotencyError>>;
+
+#[derive(Debug, thiserror::Error, Copy, Clone, PartialEq, Eq)]
+pub enum IdempotencyError {
+    #[error("failed to reserve idempotency key")]
+    Reserve,
+    #[error("failed to store idempotent response")]
+    Complete,
+    #[error("failed to release idempotency key")]
+    Release,
+    #[error("failed to purge expired idempotency keys")]
+    Purge,
+}
+
+impl ProblemDetails for IdempotencyError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn code(&self) -> &'static str {
+        "idempotency_store_failed"
+    }
+}
+
+/// Why a request with an `Idempotency-Key` wasn't handled
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum IdempotencyProblem {
+    InvalidKey,
+    BodyTooLarge,
+    KeyReused,
+    InProgress,
+}
+
+impl ProblemDetails for IdempotencyProblem {
+    fn status(&self) -> StatusCode {
+        match self {
+            IdempotencyProblem::InvalidKey => StatusCode::BAD_REQUEST,
+            IdempotencyProblem::BodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            IdempotencyProblem::KeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            IdempotencyProblem::InProgress => StatusCode::CONFLICT,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            IdempotencyProblem::InvalidKey => "invalid_idempotency_key",
+            IdempotencyProblem::BodyTooLarge => "body_too_large",
+            IdempotencyProblem::KeyReused => "idempotency_key_reused",
+            IdempotencyProblem::InProgress => "idempotency_key_in_progress",
+        }
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        Some(Cow::Owned(match self {
+            IdempotencyProblem::InvalidKey => format!(
+                "{IDEMPOTENCY_KEY_HEADER} must be 1 to {MAX_KEY_LEN} visible ascii characters"
+            ),
+            IdempotencyProblem::BodyTooLarge => {
+                format!("idempotent requests can be at most {MAX_BODY_LEN} bytes")
+            }
+            IdempotencyProblem::KeyReused => {
+                "the key was already used for a request with a different body or response format"
+                    .to_string()
+            }
+            IdempotencyProblem::InProgress => {
+                "a request with the same key is still being handled".to_string()
+            }
+        }))
+    }
+}
+
+impl IntoResponse for IdempotencyProblem {
+    fn into_response(self) -> Response {
+        self.to_problem().into_response()
+    }
+}
+
+/// Keys are scoped to who sent them, so two callers can't see each other's responses
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+    /// The id of the authed user, empty for anonymous requests
+    pub owner: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// A request that holds a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotentRequest {
+    /// A hash of the method, uri, response format and body, a retry has to match it
+    pub request_hash: String,
+    /// `None` while the request is still being handled
+    pub response: Option<StoredResponse>,
+}
+
+pub trait IdempotencyRepository: Clone + Send + Sync + 'static {
+    /// Reserves `key` for a request until `expires`. If an unexpired request already holds the key
+    /// nothing is reserved and that request is returned instead.
+    fn reserve(
+        &self,
+        key: &IdempotencyKey,
+        request_hash: &str,
+        expires: DateTime<Utc>,
+    ) -> impl Future<Output = IdempotencyResult<Option<IdempotentRequest>>> + Send;
+
+    fn complete(
+        &self,
+        key: &IdempotencyKey,
+        response: &StoredResponse,
+    ) -> impl Future<Output = IdempotencyResult<()>> + Send;
+
+    /// Frees a reserved key so the request can be retried. Completed keys are left alone.
+    fn release(&self, key: &IdempotencyKey) -> impl Future<Output = IdempotencyResult<()>> + Send;
+
+    /// Deletes every expired key, returning how many there were
+    fn purge_expired(&self) -> impl Future<Output = IdempotencyResult<u64>> + Send;
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// object safe version of `IdempotencyRepository` so routers don't need to be generic over the repo
+trait DynIdempotencyRepository: Send + Sync + 'static {
+    fn dyn_reserve<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+        request_hash: &'a str,
+        expires: DateTime<Utc>,
+    ) -> BoxFuture<'a, IdempotencyResult<Option<IdempotentRequest>>>;
+    fn dyn_complete<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+        response: &'a StoredResponse,
+    ) -> BoxFuture<'a, IdempotencyResult<()>>;
+    fn dyn_release<'a>(&'a self, key: &'a IdempotencyKey) -> BoxFuture<'a, IdempotencyResult<()>>;
+}
+
+impl<T: IdempotencyRepository> DynIdempotencyRepository for T {
+    fn dyn_reserve<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+        request_hash: &'a str,
+        expires: DateTime<Utc>,
+    ) -> BoxFuture<'a, IdempotencyResult<Option<IdempotentRequest>>> {
+        Box::pin(IdempotencyRepository::reserve(
+            self,
+            key,
+            request_hash,
+            expires,
+        ))
+    }
+
+    fn dyn_complete<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+        response: &'a StoredResponse,
+    ) -> BoxFuture<'a, IdempotencyResult<()>> {
+        Box::pin(IdempotencyRepository::complete(self, key, response))
+    }
+
+    fn dyn_release<'a>(&'a self, key: &'a IdempotencyKey) -> BoxFuture<'a, IdempotencyResult<()>> {
+        Box::pin(IdempotencyRepository::release(self, key))
+    }
+}
+
+/// Deletes expired keys every `period`. Reserving a key only replaces that key when it has expired,
+/// so without this every other expired key is kept around.
+pub fn spawn_purge(repo: impl IdempotencyRepository, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match repo.purge_expired().await {
+                Ok(purged) => debug!("purged {purged} expired idempotency keys"),
+                Err(e) => error!("{e:?}"),
+            }
+        }
+    })
+}
+
+/// Replays the stored response when a request is retried with the same `Idempotency-Key`.
+/// Keys are kept for a day unless changed with [`Idempotency::with_ttl`].
+#[derive(Clone)]
+pub struct Idempotency {
+    repo: Arc<dyn DynIdempotencyRepository>,
+    ttl: TimeDelta,
+}
+
+impl Debug for Idempotency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Idempotency")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Idempotency {
+    pub fn new(repo: impl IdempotencyRepository) -> Self {
+        Self {
+            repo: Arc::new(repo),
+            ttl: TimeDelta::days(1),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: TimeDelta) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    async fn handle(
+        &self,
+        key: IdempotencyKey,
+        request_hash: String,
+        request: Request,
+        next: Next,
+    ) -> IdempotencyResult<Response> {
+        let expires = Utc::now() + self.ttl;
+        if let Some(existing) = self.repo.dyn_reserve(&key, &request_hash, expires).await? {
+            return Ok(match existing {
+                IdempotentRequest {
+                    request_hash: existing_hash,
+                    ..
+                } if existing_hash != request_hash => {
+                    warn!("idempotency key was reused with a different request");
+                    IdempotencyProblem::KeyReused.into_response()
+                }
+                IdempotentRequest { response: None, .. } => {
+                    IdempotencyProblem::InProgress.into_response()
+                }
+                IdempotentRequest {
+                    response: Some(stored),
+                    ..
+                } => {
+                    debug!("replaying stored response");
+                    replay(stored)
+                }
+            });
+        }
+
+        let reserved = ReservedKey {
+            repo: self.repo.clone(),
+            key: Some(key),
+        };
+        let response = next.run(request).await;
+
+        // a failure on our side might not happen again, so the same key can be retried
+        if response.status().is_server_error() {
+            reserved.release().await;
+            return Ok(response);
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(e) => {
+                reserved.release().await;
+                return Err(Report::new(IdempotencyError::Complete).attach(e.to_string()));
+            }
+        };
+        let key = reserved.keep();
+
+        let stored = StoredResponse {
+            status: parts.status.as_u16(),
+            content_type: parts
+                .headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            body: body.to_vec(),
+        };
+        // the request was handled, so the response is still sent. Retries will see the key as in
+        // progress until it expires
+        if let Err(e) = self.repo.dyn_complete(&key, &stored).await {
+            error!("{e:?}");
+        }
+
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+}
+
+/// A key reserved for a request that's being handled. If the request is dropped before the key is
+/// kept or released, because the handler panicked or the client went away, the key is released in
+/// the background so it can be retried instead of staying in progress until it expires.
+struct ReservedKey {
+    repo: Arc<dyn DynIdempotencyRepository>,
+    key: Option<IdempotencyKey>,
+}
+
+impl ReservedKey {
+    /// The request was handled, the key now belongs to its response
+    fn keep(mut self) -> IdempotencyKey {
+        self.key.take().expect("a reserved key is only taken once")
+    }
+
+    async fn release(mut self) {
+        let key = self.key.take().expect("a reserved key is only taken once");
+        if let Err(e) = self.repo.dyn_release(&key).await {
+            error!("{e:?}");
+        }
+    }
+}
+
+impl Drop for ReservedKey {
+    fn drop(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        warn!("request was dropped while holding its idempotency key, releasing it");
+
+        // drop can't await, and a panicking handler is still unwinding through here
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            error!("no runtime to release the idempotency key on, it's held until it expires");
+            return;
+        };
+        let repo = self.repo.clone();
+        runtime.spawn(async move {
+            if let Err(e) = repo.dyn_release(&key).await {
+                error!("{e:?}");
+            }
+        });
+    }
+}
+
+/// Handles a request with an `Idempotency-Key` once, replaying its response for retries with the
+/// same key. A retry with a different body, or asking for a different response format, gets a
+/// 422, and one sent while the first is still being handled gets a 409. Server errors aren't
+/// stored, so those requests can be retried.
+/// Requests without the header, and streamed NDJSON requests, are passed straight through.
+pub async fn idempotent<R: Roles>(
+    State(idempotency): State<Idempotency>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let Some(key) = key.to_str().ok().filter(|k| is_valid_key(k)) else {
+        return IdempotencyProblem::InvalidKey.into_response();
+    };
+
+    // a streamed body would have to be held in full to be hashed
+    if is_ndjson(request.headers()) {
+        debug!("ignoring idempotency key for a streamed body");
+        return next.run(request).await;
+    }
+
+    let key = IdempotencyKey {
+        owner: request
+            .extensions()
+            .get::<AuthedUser<R>>()
+            .map(|user| user.id.to_string())
+            .unwrap_or_default(),
+        key: key.to_string(),
+    };
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_BODY_LEN).await else {
+        return IdempotencyProblem::BodyTooLarge.into_response();
+    };
+    let request_hash = request_hash(&parts, &body);
+
+    idempotency
+        .handle(
+            key,
+            request_hash,
+            Request::from_parts(parts, Body::from(body)),
+            next,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            error!("{e:?}");
+            e.current_context().to_problem().into_response()
+        })
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LEN && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// The response format is part of the hash, so a stored response is never replayed to a retry
+/// asking for a different one
+fn request_hash(parts: &Parts, body: &Bytes) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(b" ");
+    hasher.update(parts.uri.to_string());
+    hasher.update(b"\n");
+    hasher.update(negotiate(&parts.headers, WireFormat::OFFERED).unwrap_or_default());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let mut response = (
+        StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK),
+        stored.body,
+    )
+        .into_response();
+    let headers = response.headers_mut();
+    match stored
+        .content_type
+        .and_then(|c| HeaderValue::from_str(&c).ok())
+    {
+        Some(content_type) => headers.insert(header::CONTENT_TYPE, content_type),
+        None => headers.remove(header::CONTENT_TYPE),
+    };
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub use in_memory::InMemoryIdempotencyRepo;
+
+#[cfg(any(test, feature = "test-support"))]
+mod in_memory {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::ArwLock;
+
+    /// Keeps keys in memory, for tests
+    #[derive(Debug, Clone, Default)]
+    pub struct InMemoryIdempotencyRepo(
+        ArwLock<HashMap<IdempotencyKey, (IdempotentRequest, DateTime<Utc>)>>,
+    );
+
+    impl IdempotencyRepository for InMemoryIdempotencyRepo {
+        async fn reserve(
+            &self,
+            key: &IdempotencyKey,
+            request_hash: &str,
+            expires: DateTime<Utc>,
+        ) -> IdempotencyResult<Option<IdempotentRequest>> {
+            let mut keys = self.0.write().await;
+            let existing = keys
+                .get(key)
+                .filter(|(_, existing_expires)| *existing_expires > Utc::now());
+            if let Some((existing, _)) = existing {
+                return Ok(Some(existing.clone()));
+            }
+
+            let reserved = IdempotentRequest {
+                request_hash: request_hash.to_string(),
+                response: None,
+            };
+            keys.insert(key.clone(), (reserved, expires));
+            Ok(None)
+        }
+
+        async fn complete(
+            &self,
+            key: &IdempotencyKey,
+            response: &StoredResponse,
+        ) -> IdempotencyResult<()> {
+            if let Some((request, _)) = self.0.write().await.get_mut(key) {
+                request.response = Some(response.clone());
+            }
+            Ok(())
+        }
+
+        async fn release(&self, key: &IdempotencyKey) -> IdempotencyResult<()> {
+            let mut keys = self.0.write().await;
+            if keys
+                .get(key)
+                .is_some_and(|(request, _)| request.response.is_none())
+            {
+                keys.remove(key);
+            }
+            Ok(())
+        }
+
+        async fn purge_expired(&self) -> IdempotencyResult<u64> {
+            let mut keys = self.0.write().await;
+            let before = keys.len();
+            keys.retain(|_, (_, expires)| *expires > Utc::now());
+            Ok((before - keys.len()) as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{Router, middleware, routing::post};
+    use tower::ServiceExt;
+    use tower_http::catch_panic::CatchPanicLayer;
+
+    use super::*;
+    use crate::{problem, wire::CBOR_CONTENT_TYPE};
+
+    #[derive(Debug, Clone)]
+    struct NoRoles;
+
+    impl std::fmt::Display for NoRoles {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "none")
+        }
+    }
+
+    impl std::str::FromStr for NoRoles {
+        type Err = std::convert::Infallible;
+
+        fn from_str(_: &str) -> Result<Self, Self::Err> {
+            Ok(Self)
+        }
+    }
+
+    impl Roles for NoRoles {
+        fn none() -> Self {
+            Self
+        }
+        fn is_none(&self) -> bool {
+            true
+        }
+        fn contains(&self, _: Self) -> bool {
+            false
+        }
+        fn add(&mut self, _: Self) {}
+    }
+
+    /// Layered like the router builds it, with the panic catcher outside the idempotency layer
+    fn router(calls: Arc<AtomicUsize>) -> Router {
+        let handler = move || async move {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first call fails");
+            }
+            (StatusCode::CREATED, "created")
+        };
+
+        Router::new()
+            .route(
+                "/topics",
+                post(handler).layer(middleware::from_fn_with_state(
+                    Idempotency::new(InMemoryIdempotencyRepo::default()),
+                    idempotent::<NoRoles>,
+                )),
+            )
+            .layer(CatchPanicLayer::custom(problem::panic_problem))
+    }
+
+    fn create(key: &str) -> Request {
+        axum::http::Request::post("/topics")
+            .header(IDEMPOTENCY_KEY_HEADER, key)
+            .body(Body::from("{\"name\":\"orders\"}"))
+            .unwrap()
+    }
+
+    fn key(key: &str) -> IdempotencyKey {
+        IdempotencyKey {
+            owner: "svc-a".to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn keys_must_be_visible_ascii() {
+        assert!(is_valid_key("2f1c-4a9b"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("has space"));
+        assert!(!is_valid_key(&"k".repeat(MAX_KEY_LEN + 1)));
+    }
+
+    #[test]
+    fn request_hash_covers_method_uri_and_body() {
+        let (post, _) = axum::http::Request::post("/topics")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let (put, _) = axum::http::Request::put("/topics")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let body = Bytes::from_static(b"{\"name\":\"orders\"}");
+        assert_eq!(request_hash(&post, &body), request_hash(&post, &body));
+        assert_ne!(request_hash(&post, &body), request_hash(&put, &body));
+        assert_ne!(
+            request_hash(&post, &body),
+            request_hash(&post, &Bytes::from_static(b"{}"))
+        );
+    }
+
+    #[test]
+    fn request_hash_covers_the_negotiated_response_format() {
+        let (json, _) = axum::http::Request::post("/topics")
+            .header(header::ACCEPT, "application/json")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let (no_accept, _) = axum::http::Request::post("/topics")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let (cbor, _) = axum::http::Request::post("/topics")
+            .header(header::ACCEPT, CBOR_CONTENT_TYPE)
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let body = Bytes::from_static(b"{}");
+        assert_eq!(request_hash(&json, &body), request_hash(&no_accept, &body));
+        assert_ne!(request_hash(&json, &body), request_hash(&cbor, &body));
+    }
+
+    #[tokio::test]
+    async fn key_is_released_when_the_handler_panics() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = router(calls.clone());
+
+        let response = router.clone().oneshot(create("k1")).await.unwrap();
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+
+        // the key is released in a spawned task
+        tokio::task::yield_now().await;
+
+        let response = router.clone().oneshot(create("k1")).await.unwrap();
+        assert_eq!(StatusCode::CREATED, response.status());
+        assert!(!response.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+
+        let response = router.oneshot(create("k1")).await.unwrap();
+        assert_eq!(StatusCode::CREATED, response.status());
+        assert_eq!("true", response.headers()[IDEMPOTENT_REPLAYED_HEADER]);
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn retry_asking_for_another_format_is_rejected() {
+        let router = router(Arc::new(AtomicUsize::new(1)));
+
+        let response = router.clone().oneshot(create("k1")).await.unwrap();
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        let mut retry = create("k1");
+        retry
+            .headers_mut()
+            .insert(header::ACCEPT, HeaderValue::from_static(CBOR_CONTENT_TYPE));
+        let response = router.oneshot(retry).await.unwrap();
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+    }
+
+    #[tokio::test]
+    async fn in_memory_repo_replaces_expired_and_released_keys() {
+        let repo = InMemoryIdempotencyRepo::default();
+        let later = Utc::now() + TimeDelta::hours(1);
+
+        assert_eq!(None, repo.reserve(&key("a"), "hash", later).await.unwrap());
+        let in_progress = repo.reserve(&key("a"), "other", later).await.unwrap();
+        assert_eq!(
+            Some(IdempotentRequest {
+                request_hash: "hash".to_string(),
+                response: None,
+            }),
+            in_progress
+        );
+
+        repo.release(&key("a")).await.unwrap();
+        assert_eq!(None, repo.reserve(&key("a"), "hash", later).await.unwrap());
+
+        let earlier = Utc::now() - TimeDelta::hours(1);
+        assert_eq!(
+            None,
+            repo.reserve(&key("b"), "hash", earlier).await.unwrap()
+        );
+        assert_eq!(None, repo.reserve(&key("b"), "hash", later).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn in_memory_purge_only_deletes_expired_keys() {
+        let repo = InMemoryIdempotencyRepo::default();
+        repo.reserve(&key("old"), "hash", Utc::now() - TimeDelta::hours(1))
+            .await
+            .unwrap();
+        repo.reserve(&key("new"), "hash", Utc::now() + TimeDelta::hours(1))
+            .await
+            .unwrap();
+
+        assert_eq!(1, repo.purge_expired().await.unwrap());
+        assert!(
+            repo.reserve(&key("new"), "hash", Utc::now())
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn replayed_responses_keep_status_and_content_type() {
+        let response = replay(StoredResponse {
+            status: 201,
+            content_type: Some("application/cbor".to_string()),
+            body: vec![1, 2, 3],
+        });
+
+        assert_eq!(StatusCode::CREATED, response.status());
+        assert_eq!("application/cbor", response.headers()[header::CONTENT_TYPE]);
+        assert_eq!("true", response.headers()[IDEMPOTENT_REPLAYED_HEADER]);
+    }
+}
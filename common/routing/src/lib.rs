@@ -6,22 +6,36 @@ use utoipa::{
     openapi::{RefOr, Schema},
 };
 
+pub mod accept;
+pub mod attributes;
 pub mod error;
+pub mod idempotency;
 pub mod list_criteria;
+pub mod ndjson;
 pub mod pagination;
+pub mod patch;
+pub mod problem;
 pub mod stream;
+pub mod validation;
+pub mod wire;
 
 mod auth;
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
 pub use auth::oauth::{Jwk, Jwks};
 
 mod metrics;
 pub mod router;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 pub use auth::{
+    api_key,
+    introspection::IntrospectionConfig,
     oauth::OAuthConfig,
+    role_mapping::RoleMapping,
     roles::Roles,
     token::{AuthState, validate_token},
+    user::AuthedUser,
 };
 
 #[derive(Debug, Clone, Default)]
@@ -0,0 +1,183 @@
+use axum::body::{Body, BodyDataStream};
+use axum::http::{HeaderMap, header};
+use error_stack::Report;
+use std::borrow::Cow;
+use tokio_stream::StreamExt;
+
+use crate::problem::{ProblemDetails, StatusCode};
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+pub fn is_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case(NDJSON_CONTENT_TYPE))
+}
+
+#[derive(Debug, thiserror::Error, Clone, Copy, PartialEq, Eq)]
+pub enum NdjsonError {
+    #[error("a line was longer than {0} bytes")]
+    LineTooLong(usize),
+    #[error("failed to read the request body")]
+    Body,
+}
+
+impl ProblemDetails for NdjsonError {
+    fn status(&self) -> StatusCode {
+        match self {
+            NdjsonError::LineTooLong(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            NdjsonError::Body => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            NdjsonError::LineTooLong(_) => "line_too_long",
+            NdjsonError::Body => "unreadable_body",
+        }
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        Some(Cow::Owned(self.to_string()))
+    }
+}
+
+/// Splits a request body into lines as it arrives, so only one line is held at a time.
+/// Blank lines are skipped and a trailing `\r` is removed.
+pub struct NdjsonLines {
+    body: BodyDataStream,
+    buf: Vec<u8>,
+    // everything before this has already been checked for a newline
+    scanned: usize,
+    max_line_len: usize,
+    done: bool,
+}
+
+impl NdjsonLines {
+    pub fn new(body: Body, max_line_len: usize) -> Self {
+        Self {
+            body: body.into_data_stream(),
+            buf: Vec::new(),
+            scanned: 0,
+            max_line_len,
+            done: false,
+        }
+    }
+
+    /// Nothing more is read once an error has been returned
+    pub async fn next_line(&mut self) -> Option<Result<Vec<u8>, Report<NdjsonError>>> {
+        loop {
+            if let Some(pos) = self.buf[self.scanned..].iter().position(|b| *b == b'\n') {
+                let mut line: Vec<u8> = self.buf.drain(..=self.scanned + pos).collect();
+                self.scanned = 0;
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                if line.len() > self.max_line_len {
+                    return Some(Err(self.fail(NdjsonError::LineTooLong(self.max_line_len))));
+                }
+                if is_blank(&line) {
+                    continue;
+                }
+                return Some(Ok(line));
+            }
+            self.scanned = self.buf.len();
+
+            if self.buf.len() > self.max_line_len {
+                return Some(Err(self.fail(NdjsonError::LineTooLong(self.max_line_len))));
+            }
+
+            if self.done {
+                let line = std::mem::take(&mut self.buf);
+                self.scanned = 0;
+                return (!is_blank(&line)).then_some(Ok(line));
+            }
+
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buf.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    return Some(Err(self.fail(NdjsonError::Body).attach(e.to_string())));
+                }
+                None => self.done = true,
+            }
+        }
+    }
+
+    fn fail(&mut self, error: NdjsonError) -> Report<NdjsonError> {
+        self.done = true;
+        self.buf.clear();
+        self.scanned = 0;
+        Report::new(error)
+    }
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    async fn all_lines(mut lines: NdjsonLines) -> Vec<Result<String, NdjsonError>> {
+        let mut all = Vec::new();
+        while let Some(line) = lines.next_line().await {
+            all.push(
+                line.map(|l| String::from_utf8(l).unwrap())
+                    .map_err(|e| *e.current_context()),
+            );
+        }
+        all
+    }
+
+    #[tokio::test]
+    async fn lines_are_split_and_blank_lines_skipped() {
+        let body = Body::from("{\"a\":1}\r\n\n  \n{\"b\":2}\n{\"c\":3}");
+
+        let lines = all_lines(NdjsonLines::new(body, 64)).await;
+
+        assert_eq!(
+            vec![
+                Ok("{\"a\":1}".to_string()),
+                Ok("{\"b\":2}".to_string()),
+                Ok("{\"c\":3}".to_string()),
+            ],
+            lines
+        );
+    }
+
+    #[tokio::test]
+    async fn long_lines_stop_reading() {
+        let body = Body::from("{\"a\":1}\n0123456789\n{\"b\":2}\n");
+
+        let lines = all_lines(NdjsonLines::new(body, 8)).await;
+
+        assert_eq!(
+            vec![
+                Ok("{\"a\":1}".to_string()),
+                Err(NdjsonError::LineTooLong(8)),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn content_type_parameters_are_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson; charset=utf-8"),
+        );
+        assert!(is_ndjson(&headers));
+
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        assert!(!is_ndjson(&headers));
+    }
+}
@@ -0,0 +1,469 @@
+use axum::Json;
+use axum::extract::{FromRequest, Request};
+use axum::http::{HeaderMap, header};
+use axum::response::{IntoResponse, Response};
+use optional_field::Field;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use utoipa::ToSchema;
+
+use crate::problem::{ProblemDetails, StatusCode};
+use crate::wire::{Wire, WireRejection};
+
+/// RFC 7396, a partial document where null removes a field
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+/// RFC 6902, an array of operations applied in order
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+/// A JSON Patch operation. Paths are JSON Pointers into the resource as it's returned
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add {
+        path: String,
+        value: Value,
+    },
+    Remove {
+        path: String,
+    },
+    Replace {
+        path: String,
+        value: Value,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Fails the whole patch unless the value at `path` equals `value`
+    Test {
+        path: String,
+        value: Value,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PatchError {
+    #[error("a merge patch has to be an object")]
+    NotAnObject,
+    #[error("{0} is not a field")]
+    UnknownField(String),
+    #[error("{0} cannot be changed")]
+    ImmutableField(String),
+    #[error("{0} is not a valid path for this operation")]
+    InvalidPath(String),
+    #[error("nothing exists at {0}")]
+    PathNotFound(String),
+    #[error("{0} is the wrong type")]
+    WrongType(String),
+    #[error("the test at {0} failed")]
+    TestFailed(String),
+    #[error("the resource could not be turned into a document to patch")]
+    Unserializable,
+}
+
+impl ProblemDetails for PatchError {
+    fn status(&self) -> StatusCode {
+        match self {
+            PatchError::TestFailed(_) => StatusCode::CONFLICT,
+            PatchError::Unserializable => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            PatchError::NotAnObject => "invalid_patch",
+            PatchError::UnknownField(_) => "unknown_field",
+            PatchError::ImmutableField(_) => "immutable_field",
+            PatchError::InvalidPath(_) | PatchError::PathNotFound(_) => "invalid_patch_path",
+            PatchError::WrongType(_) => "invalid_patch_value",
+            PatchError::TestFailed(_) => "patch_test_failed",
+            PatchError::Unserializable => "internal_error",
+        }
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        match self {
+            PatchError::Unserializable => None,
+            e => Some(Cow::Owned(e.to_string())),
+        }
+    }
+}
+
+impl IntoResponse for PatchError {
+    fn into_response(self) -> Response {
+        self.to_problem().into_response()
+    }
+}
+
+/// The body of a PATCH, picked from its `Content-Type`. Anything that isn't a merge patch or a
+/// JSON Patch is read as `T` in any [`crate::wire::WireFormat`], like before either existed
+#[derive(Debug)]
+pub enum PatchBody<T> {
+    Fields(T),
+    Merge(Value),
+    Json(Vec<PatchOp>),
+}
+
+impl<T, S> FromRequest<S> for PatchBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = WireRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match content_type_essence(req.headers()).as_deref() {
+            Some(MERGE_PATCH_CONTENT_TYPE) => {
+                let Json(patch) = Json::from_request(req, state).await?;
+                Ok(PatchBody::Merge(patch))
+            }
+            Some(JSON_PATCH_CONTENT_TYPE) => {
+                let Json(ops) = Json::from_request(req, state).await?;
+                Ok(PatchBody::Json(ops))
+            }
+            _ => {
+                let Wire(fields) = Wire::from_request(req, state).await?;
+                Ok(PatchBody::Fields(fields))
+            }
+        }
+    }
+}
+
+fn content_type_essence(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let essence = content_type.split(';').next()?.trim();
+    Some(essence.to_ascii_lowercase())
+}
+
+/// Which top level fields of a resource a patch can change. The rest of its fields can be read by
+/// `test` and `copy`, but never written. Mutable fields are expected to be scalars or objects.
+#[derive(Debug, Clone, Copy)]
+pub struct PatchFields {
+    mutable: &'static [&'static str],
+    immutable: &'static [&'static str],
+}
+
+impl PatchFields {
+    pub const fn new(mutable: &'static [&'static str], immutable: &'static [&'static str]) -> Self {
+        Self { mutable, immutable }
+    }
+
+    /// The fields a merge patch sets, null meaning the field is cleared
+    pub fn merge(&self, patch: Value) -> Result<PatchedFields, PatchError> {
+        let Value::Object(fields) = patch else {
+            return Err(PatchError::NotAnObject);
+        };
+        for field in fields.keys() {
+            self.writable(field)?;
+        }
+        Ok(PatchedFields(fields))
+    }
+
+    /// Applies `ops` to `current` in order, returning only the fields that ended up different.
+    /// Nothing is changed if any op fails.
+    pub fn apply<R: Serialize>(
+        &self,
+        current: &R,
+        ops: Vec<PatchOp>,
+    ) -> Result<PatchedFields, PatchError> {
+        let current = serde_json::to_value(current).map_err(|_| PatchError::Unserializable)?;
+        let mut document = current.clone();
+
+        for op in ops {
+            match op {
+                PatchOp::Add { path, value } => {
+                    self.writable_path(&path)?;
+                    add(&mut document, &path, value)?;
+                }
+                PatchOp::Remove { path } => {
+                    self.writable_path(&path)?;
+                    remove(&mut document, &path)?;
+                }
+                PatchOp::Replace { path, value } => {
+                    self.writable_path(&path)?;
+                    *document
+                        .pointer_mut(&path)
+                        .ok_or(PatchError::PathNotFound(path))? = value;
+                }
+                PatchOp::Move { from, path } => {
+                    self.writable_path(&from)?;
+                    self.writable_path(&path)?;
+                    let value = remove(&mut document, &from)?;
+                    add(&mut document, &path, value)?;
+                }
+                PatchOp::Copy { from, path } => {
+                    self.readable_path(&from)?;
+                    self.writable_path(&path)?;
+                    let value = document
+                        .pointer(&from)
+                        .cloned()
+                        .ok_or(PatchError::PathNotFound(from))?;
+                    add(&mut document, &path, value)?;
+                }
+                PatchOp::Test { path, value } => {
+                    self.readable_path(&path)?;
+                    if document.pointer(&path) != Some(&value) {
+                        return Err(PatchError::TestFailed(path));
+                    }
+                }
+            }
+        }
+
+        let changed = self
+            .mutable
+            .iter()
+            .filter(|field| current.get(**field) != document.get(**field))
+            .map(|field| {
+                let value = document.get(*field).cloned().unwrap_or(Value::Null);
+                (field.to_string(), value)
+            })
+            .collect();
+        Ok(PatchedFields(changed))
+    }
+
+    fn writable(&self, field: &str) -> Result<(), PatchError> {
+        if self.mutable.contains(&field) {
+            Ok(())
+        } else if self.immutable.contains(&field) {
+            Err(PatchError::ImmutableField(field.to_string()))
+        } else {
+            Err(PatchError::UnknownField(field.to_string()))
+        }
+    }
+
+    fn writable_path(&self, path: &str) -> Result<(), PatchError> {
+        self.writable(&top_level_field(path)?)
+    }
+
+    fn readable_path(&self, path: &str) -> Result<(), PatchError> {
+        let field = top_level_field(path)?;
+        if self.mutable.contains(&field.as_str()) || self.immutable.contains(&field.as_str()) {
+            Ok(())
+        } else {
+            Err(PatchError::UnknownField(field))
+        }
+    }
+}
+
+/// The fields a patch changes, and what they changed to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatchedFields(Map<String, Value>);
+
+impl PatchedFields {
+    /// `Missing` if the patch left `field` alone, `Present(None)` if it cleared it
+    pub fn text(&self, field: &str) -> Result<Field<String>, PatchError> {
+        match self.0.get(field) {
+            None => Ok(Field::Missing),
+            Some(Value::Null) => Ok(Field::Present(None)),
+            Some(Value::String(s)) => Ok(Field::Present(Some(s.clone()))),
+            Some(_) => Err(PatchError::WrongType(field.to_string())),
+        }
+    }
+
+    /// Like [text](Self::text) for a field that's an object. A merge patch gives the object to
+    /// merge in, a JSON Patch the whole object as the ops left it
+    pub fn object(&self, field: &str) -> Result<Field<Map<String, Value>>, PatchError> {
+        match self.0.get(field) {
+            None => Ok(Field::Missing),
+            Some(Value::Null) => Ok(Field::Present(None)),
+            Some(Value::Object(fields)) => Ok(Field::Present(Some(fields.clone()))),
+            Some(_) => Err(PatchError::WrongType(field.to_string())),
+        }
+    }
+}
+
+/// The whole document can't be the target, since that would replace immutable fields too
+fn top_level_field(path: &str) -> Result<String, PatchError> {
+    match path.strip_prefix('/') {
+        Some(rest) => Ok(unescape(rest.split('/').next().unwrap_or_default())),
+        None => Err(PatchError::InvalidPath(path.to_string())),
+    }
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// The parent of `path` and the last token of it
+fn split_path(path: &str) -> Result<(&str, String), PatchError> {
+    path.rsplit_once('/')
+        .map(|(parent, last)| (parent, unescape(last)))
+        .ok_or_else(|| PatchError::InvalidPath(path.to_string()))
+}
+
+fn add(document: &mut Value, path: &str, value: Value) -> Result<(), PatchError> {
+    let (parent, last) = split_path(path)?;
+    let not_found = || PatchError::PathNotFound(path.to_string());
+
+    match document.pointer_mut(parent).ok_or_else(not_found)? {
+        Value::Object(fields) => {
+            fields.insert(last, value);
+        }
+        Value::Array(items) if last == "-" => items.push(value),
+        Value::Array(items) => {
+            let index = last
+                .parse::<usize>()
+                .ok()
+                .filter(|i| *i <= items.len())
+                .ok_or_else(not_found)?;
+            items.insert(index, value);
+        }
+        _ => return Err(not_found()),
+    }
+    Ok(())
+}
+
+fn remove(document: &mut Value, path: &str) -> Result<Value, PatchError> {
+    let (parent, last) = split_path(path)?;
+    let not_found = || PatchError::PathNotFound(path.to_string());
+
+    match document.pointer_mut(parent).ok_or_else(not_found)? {
+        Value::Object(fields) => fields.remove(&last).ok_or_else(not_found),
+        Value::Array(items) => {
+            let index = last
+                .parse::<usize>()
+                .ok()
+                .filter(|i| *i < items.len())
+                .ok_or_else(not_found)?;
+            Ok(items.remove(index))
+        }
+        _ => Err(not_found()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FIELDS: PatchFields = PatchFields::new(&["name", "description"], &["id", "created"]);
+
+    fn current() -> Value {
+        json!({ "id": 1, "name": "orders", "description": "all orders", "created": "today" })
+    }
+
+    fn ops(ops: Value) -> Vec<PatchOp> {
+        serde_json::from_value(ops).unwrap()
+    }
+
+    #[test]
+    fn merge_patch_null_clears_and_missing_is_left_alone() {
+        let fields = FIELDS
+            .merge(json!({ "name": "customers", "description": null }))
+            .unwrap();
+
+        assert_eq!(
+            Field::Present(Some("customers".to_string())),
+            fields.text("name").unwrap()
+        );
+        assert_eq!(Field::Present(None), fields.text("description").unwrap());
+
+        let fields = FIELDS.merge(json!({ "name": "customers" })).unwrap();
+        assert_eq!(Field::Missing, fields.text("description").unwrap());
+    }
+
+    #[test]
+    fn merge_patch_rejects_immutable_and_unknown_fields() {
+        assert_eq!(
+            Err(PatchError::ImmutableField("id".to_string())),
+            FIELDS.merge(json!({ "id": 2 }))
+        );
+        assert_eq!(
+            Err(PatchError::UnknownField("colour".to_string())),
+            FIELDS.merge(json!({ "colour": "red" }))
+        );
+        assert_eq!(Err(PatchError::NotAnObject), FIELDS.merge(json!([])));
+    }
+
+    #[test]
+    fn json_patch_only_returns_changed_fields() {
+        let fields = FIELDS
+            .apply(
+                &current(),
+                ops(json!([
+                    { "op": "test", "path": "/id", "value": 1 },
+                    { "op": "replace", "path": "/name", "value": "customers" },
+                    { "op": "copy", "from": "/name", "path": "/description" },
+                    { "op": "replace", "path": "/name", "value": "orders" },
+                ])),
+            )
+            .unwrap();
+
+        assert_eq!(Field::Missing, fields.text("name").unwrap());
+        assert_eq!(
+            Field::Present(Some("customers".to_string())),
+            fields.text("description").unwrap()
+        );
+    }
+
+    #[test]
+    fn json_patch_remove_clears_a_field() {
+        let fields = FIELDS
+            .apply(
+                &current(),
+                ops(json!([{ "op": "remove", "path": "/description" }])),
+            )
+            .unwrap();
+
+        assert_eq!(Field::Present(None), fields.text("description").unwrap());
+    }
+
+    #[test]
+    fn json_patch_failed_test_rejects_the_whole_patch() {
+        let result = FIELDS.apply(
+            &current(),
+            ops(json!([
+                { "op": "replace", "path": "/name", "value": "customers" },
+                { "op": "test", "path": "/description", "value": "something else" },
+            ])),
+        );
+
+        assert_eq!(
+            Err(PatchError::TestFailed("/description".to_string())),
+            result
+        );
+        assert_eq!(
+            StatusCode::CONFLICT,
+            result.unwrap_err().to_problem().status_code()
+        );
+    }
+
+    #[test]
+    fn json_patch_rejects_writes_outside_the_mutable_fields() {
+        let apply = |op: Value| FIELDS.apply(&current(), ops(json!([op])));
+
+        assert_eq!(
+            Err(PatchError::ImmutableField("created".to_string())),
+            apply(json!({ "op": "replace", "path": "/created", "value": "tomorrow" }))
+        );
+        assert_eq!(
+            Err(PatchError::ImmutableField("id".to_string())),
+            apply(json!({ "op": "move", "from": "/id", "path": "/name" }))
+        );
+        assert_eq!(
+            Err(PatchError::UnknownField("colour".to_string())),
+            apply(json!({ "op": "add", "path": "/colour", "value": "red" }))
+        );
+        assert_eq!(
+            Err(PatchError::InvalidPath(String::new())),
+            apply(json!({ "op": "replace", "path": "", "value": {} }))
+        );
+    }
+
+    #[test]
+    fn non_text_values_are_the_wrong_type() {
+        let fields = FIELDS.merge(json!({ "name": 5 })).unwrap();
+
+        assert_eq!(
+            Err(PatchError::WrongType("name".to_string())),
+            fields.text("name")
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{any::Any, borrow::Cow, error::Error};
+
+pub use axum::http::StatusCode;
+use axum::{
+    Json,
+    http::{HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use error_stack::Report;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tracing::error;
+use utoipa::{IntoResponses, ToSchema};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// An RFC 7807 problem. `code` is a stable, machine readable identifier for the problem,
+/// clients should match on it rather than on `title` or `detail`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: Cow<'static, str>,
+    /// The reason phrase of `status`
+    pub title: Cow<'static, str>,
+    pub status: u16,
+    pub code: Cow<'static, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<Cow<'static, str>>,
+    /// Anything else specific to the problem, e.g. the fields that failed validation
+    #[serde(flatten, skip_serializing_if = "Map::is_empty")]
+    #[schema(value_type = Object)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            problem_type: Cow::Borrowed("about:blank"),
+            title: Cow::Borrowed(status.canonical_reason().unwrap_or("Unknown")),
+            status: status.as_u16(),
+            code: code.into(),
+            detail: None,
+            extensions: Map::new(),
+        }
+    }
+
+    /// For failures that have nothing more specific to say than their status, like auth failures
+    pub fn from_status(status: StatusCode) -> Self {
+        let code = match status {
+            StatusCode::BAD_REQUEST => "bad_request",
+            StatusCode::UNAUTHORIZED => "unauthorized",
+            StatusCode::FORBIDDEN => "forbidden",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::CONFLICT => "conflict",
+            StatusCode::UNPROCESSABLE_ENTITY => "validation_failed",
+            _ => "internal_error",
+        };
+        Self::new(status, code)
+    }
+
+    pub fn internal_error() -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<Cow<'static, str>>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_extension(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.extensions.insert(name.into(), value);
+            }
+            Err(e) => error!("failed to serialize problem extension: {e}"),
+        }
+        self
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let mut response = (self.status_code(), Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
+}
+
+/// The problems any protected endpoint can return, add to a path's `responses(..)` to document them
+#[derive(IntoResponses)]
+pub enum CommonProblems {
+    /// No valid token or api key was given
+    #[response(status = UNAUTHORIZED, content_type = "application/problem+json")]
+    Unauthorized(Problem),
+    /// The caller doesn't have the roles the endpoint requires
+    #[response(status = FORBIDDEN, content_type = "application/problem+json")]
+    Forbidden(Problem),
+    /// Something failed on the service's side
+    #[response(status = INTERNAL_SERVER_ERROR, content_type = "application/problem+json")]
+    InternalError(Problem),
+}
+
+/// Maps a domain error to the problem returned to the client.
+pub trait ProblemDetails {
+    fn status(&self) -> StatusCode;
+
+    fn code(&self) -> &'static str;
+
+    /// Safe to show to clients. Defaults to nothing, since error messages often contain internals
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        None
+    }
+
+    fn to_problem(&self) -> Problem {
+        let problem = Problem::new(self.status(), self.code());
+        match self.detail() {
+            Some(detail) => problem.with_detail(detail),
+            None => problem,
+        }
+    }
+
+    /// The problem for a whole report. Errors that only wrap others (like a service error wrapping
+    /// a repo error) can override this to look further down the report for something more specific.
+    fn report_problem(report: &Report<Self>) -> Problem
+    where
+        Self: Error + Send + Sync + Sized + 'static,
+    {
+        report.current_context().to_problem()
+    }
+}
+
+/// Used with `CatchPanicLayer` so a panicking handler still gets a problem response
+pub(crate) fn panic_problem(panic: Box<dyn Any + Send + 'static>) -> Response {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    error!("handler panicked: {message}");
+
+    Problem::internal_error().into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn problem_serializes_as_rfc7807_with_code() {
+        let problem = Problem::new(StatusCode::NOT_FOUND, "topic_not_found")
+            .with_detail("the requested topic does not exist");
+
+        assert_eq!(
+            json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "code": "topic_not_found",
+                "detail": "the requested topic does not exist",
+            }),
+            serde_json::to_value(problem).unwrap()
+        );
+    }
+
+    #[test]
+    fn extensions_are_flattened() {
+        let problem = Problem::from_status(StatusCode::UNPROCESSABLE_ENTITY)
+            .with_extension("errors", json!([{ "field": "name" }]));
+
+        let value = serde_json::to_value(problem).unwrap();
+
+        assert_eq!("validation_failed", value["code"]);
+        assert_eq!(json!([{ "field": "name" }]), value["errors"]);
+    }
+
+    #[test]
+    fn problem_response_has_problem_content_type() {
+        let response = Problem::internal_error().into_response();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert_eq!(
+            PROBLEM_JSON,
+            response.headers()[header::CONTENT_TYPE].to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn panics_become_internal_errors() {
+        let response = panic_problem(Box::new("boom"));
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    }
+}
@@ -8,12 +8,18 @@ use axum::{
     routing::{delete, get, patch, post, put},
 };
 use metrics_exporter_prometheus::PrometheusHandle;
-use tracing::debug;
-use utoipa::openapi::OpenApi;
+use tower_http::catch_panic::CatchPanicLayer;
+use tracing::{debug, warn};
+use utoipa::{OpenApi as _, openapi::OpenApi};
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::{AuthState, Roles, auth::roles::require_roles, metrics, validate_token};
+use crate::{
+    AuthState, Roles,
+    auth::{api_key::routes as api_key_routes, roles::require_roles},
+    idempotency::{Idempotency, idempotent},
+    metrics, problem, validate_token,
+};
 
 struct Route<R> {
     method: &'static str,
@@ -45,6 +51,8 @@ pub struct RouterBuilder<S, R> {
     inner: OpenApiRouter<S>,
     root_path: &'static str,
     routes: Vec<Route<R>>,
+    api_key_admin: Option<R>,
+    idempotency: Option<Idempotency>,
 }
 
 impl<S, R> RouterBuilder<S, R>
@@ -58,9 +66,25 @@ where
             inner: OpenApiRouter::new(),
             root_path,
             routes: Vec::new(),
+            api_key_admin: None,
+            idempotency: None,
         }
     }
 
+    /// Mount the api key admin routes under `/api-keys`, requiring `roles` to use them.
+    /// They are only mounted when the `AuthState` has an api key store.
+    pub fn with_api_key_admin(mut self, roles: R) -> Self {
+        self.api_key_admin = Some(roles);
+        self
+    }
+
+    /// Where the responses of routes added with `idempotent_post` are kept. Without it those
+    /// routes are plain posts. Has to be given before the routes are added.
+    pub fn with_idempotency(mut self, idempotency: Option<Idempotency>) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+
     pub fn get<T, F>(mut self, path: &'static str, handler: F) -> Self
     where
         F: Handler<T, S>,
@@ -133,6 +157,68 @@ where
         self
     }
 
+    /// A post that replays its response when retried with the same `Idempotency-Key`. Panics are
+    /// caught inside the idempotency layer, so the key is released before the 500 is sent.
+    pub fn idempotent_post<T, F>(mut self, path: &'static str, handler: F) -> Self
+    where
+        F: Handler<T, S>,
+        T: 'static,
+    {
+        let Some(idempotency) = self.idempotency.clone() else {
+            warn!("no idempotency store was given, {path} won't be idempotent");
+            return self.post(path, handler);
+        };
+
+        self.inner = self.inner.route(
+            path,
+            post(handler)
+                .layer(CatchPanicLayer::custom(problem::panic_problem))
+                .layer(middleware::from_fn_with_state(idempotency, idempotent::<R>)),
+        );
+        self.routes.push(Route {
+            method: "POST",
+            root_path: self.root_path,
+            relative_path: path,
+            required_roles: None,
+        });
+        self
+    }
+
+    /// Roles are checked before the key, so callers without them can't use up keys
+    pub fn role_protected_idempotent_post<T, F>(
+        mut self,
+        path: &'static str,
+        handler: F,
+        roles: R,
+    ) -> Self
+    where
+        F: Handler<T, S>,
+        T: 'static,
+    {
+        let Some(idempotency) = self.idempotency.clone() else {
+            warn!("no idempotency store was given, {path} won't be idempotent");
+            return self.role_protected_post(path, handler, roles);
+        };
+
+        self.inner = self.inner.route(
+            path,
+            post(handler)
+                .layer(CatchPanicLayer::custom(problem::panic_problem))
+                .layer(middleware::from_fn_with_state(idempotency, idempotent::<R>))
+                .layer(middleware::from_fn_with_state(
+                    roles.clone(),
+                    require_roles::<R>,
+                )),
+        );
+        self.routes.push(Route {
+            method: "POST",
+            root_path: self.root_path,
+            relative_path: path,
+            required_roles: Some(roles),
+        });
+        self
+    }
+
     pub fn put<T, F>(mut self, path: &'static str, handler: F) -> Self
     where
         F: Handler<T, S>,
@@ -245,7 +331,14 @@ where
         self.log_routes();
         let main_router = self.inner
             .route("/metrics", get(|| async { (StatusCode::SERVICE_UNAVAILABLE, "Metrics endpoint is disabled. Metrics must be enabled and the service restarted")}));
-        build::<S, R>(self.root_path, main_router, app_state, auth_state, api_doc)
+        build::<S, R>(
+            self.root_path,
+            main_router,
+            app_state,
+            auth_state,
+            api_doc,
+            self.api_key_admin,
+        )
     }
 
     pub fn build_with_metrics(
@@ -262,7 +355,14 @@ where
             .route("/metrics", get(|| async move { metrics_handle.render() }))
             .route_layer(middleware::from_fn(metrics::track_http));
 
-        build::<S, R>(self.root_path, main_router, app_state, auth_state, api_doc)
+        build::<S, R>(
+            self.root_path,
+            main_router,
+            app_state,
+            auth_state,
+            api_doc,
+            self.api_key_admin,
+        )
     }
 
     fn log_routes(&self) {
@@ -277,19 +377,43 @@ fn build<S, R>(
     main_router: OpenApiRouter<S>,
     app_state: S,
     auth_state: AuthState,
-    api_doc: OpenApi,
+    mut api_doc: OpenApi,
+    api_key_admin: Option<R>,
 ) -> Router
 where
     S: Send + Sync + Clone + 'static,
     R: Roles,
     <R as FromStr>::Err: std::fmt::Debug,
 {
-    let main_routes = OpenApiRouter::new()
-        .nest(root_path, main_router)
+    let mut main_routes = OpenApiRouter::new().nest(root_path, main_router);
+
+    match (api_key_admin, auth_state.api_keys()) {
+        (Some(roles), Some(api_keys)) => {
+            debug!(
+                "Building route - GET|POST|DELETE: {} (requires roles {roles})",
+                api_key_routes::API_KEY_ROOT_PATH
+            );
+            main_routes = main_routes.nest(
+                api_key_routes::API_KEY_ROOT_PATH,
+                api_key_routes::router(api_keys.clone(), roles),
+            );
+            api_doc = api_doc.nest(
+                api_key_routes::API_KEY_ROOT_PATH,
+                api_key_routes::ApiKeyDocs::openapi(),
+            );
+        }
+        (Some(_), None) => {
+            warn!("api key admin routes were requested but api keys are not enabled");
+        }
+        (None, _) => {}
+    }
+
+    let main_routes = main_routes
         .layer(middleware::from_fn_with_state(
             auth_state,
             validate_token::<R>,
         ))
+        .layer(CatchPanicLayer::custom(problem::panic_problem))
         // TODO metrics
         .with_state(app_state);
     let (router, api) = OpenApiRouter::with_openapi(api_doc)
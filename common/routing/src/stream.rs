@@ -1,21 +1,36 @@
-use axum::http::StatusCode;
+use axum::body::Body;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum_streams::StreamBodyAs;
 use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::time::Duration;
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
+use tracing::error;
+
+use crate::accept::{NotAcceptable, negotiate};
+use crate::ndjson::NDJSON_CONTENT_TYPE;
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
 
 /// Can be used as the return type of an endpoint where
 /// a transform needs to be done on an existing collection.
 /// This can prevent unnecessary allocations into a new collection
-/// of a different type before returning to the user.
+/// of a different type before returning to the user. It can also be fed from a
+/// stream, like rows coming out of a database, so nothing is collected at all.
 /// Can also be used to "throttle" the output, i.e. put a delay between
 /// when each element is streamed back to the user.
 #[derive(Debug)]
 pub struct StreamingResponse<T> {
     status_code: StatusCode,
-    stream: StreamBodyAs<'static>,
+    body: Body,
+    content_type: &'static str,
     _phantom: PhantomData<T>,
 }
 
@@ -45,13 +60,59 @@ where
         I::IntoIter: Send + Sync + 'static,
     {
         let stream = tokio_stream::iter(iter);
-        Self {
+        Self::with_body(
             status_code,
-            stream: StreamBodyAs::json_array(stream),
-            _phantom: PhantomData,
+            StreamBodyAs::json_array(stream),
+            JSON_CONTENT_TYPE,
+        )
+    }
+
+    /// Elements are rendered in `format` as the stream produces them, so nothing is collected
+    /// first. The status is sent before the first element, so an error part way through ends the
+    /// body early, which clients see as a broken response instead of a short one.
+    pub fn from_stream<S, E>(format: StreamFormat, status_code: StatusCode, stream: S) -> Self
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        E: Debug,
+    {
+        let stream = stream.map(|element| {
+            element.map_err(|e| {
+                error!("ending streamed response early: {e:?}");
+                axum::Error::new(format!("{e:?}"))
+            })
+        });
+        match format {
+            StreamFormat::JsonArray => Self::with_body(
+                status_code,
+                StreamBodyAs::json_array_with_errors(stream),
+                JSON_CONTENT_TYPE,
+            ),
+            StreamFormat::Ndjson => Self::with_body(
+                status_code,
+                StreamBodyAs::json_nl_with_errors(stream),
+                NDJSON_CONTENT_TYPE,
+            ),
+            StreamFormat::Csv => Self::with_body(
+                status_code,
+                Body::from_stream(csv_rows(stream)),
+                CSV_CONTENT_TYPE,
+            ),
         }
     }
 
+    /// One JSON document per line as each element is produced. The status is sent before the
+    /// first element, so it can't depend on the elements.
+    pub fn ndjson<S>(status_code: StatusCode, stream: S) -> Self
+    where
+        S: Stream<Item = T> + Send + 'static,
+    {
+        Self::with_body(
+            status_code,
+            StreamBodyAs::json_nl(stream),
+            NDJSON_CONTENT_TYPE,
+        )
+    }
+
     #[allow(unused)]
     pub fn with_throttle<I>(status_code: StatusCode, iter: I, throttle_mills: u64) -> Self
     where
@@ -59,9 +120,22 @@ where
         I::IntoIter: Send + Sync + 'static,
     {
         let stream = tokio_stream::iter(iter).throttle(Duration::from_millis(throttle_mills));
+        Self::with_body(
+            status_code,
+            StreamBodyAs::json_array(stream),
+            JSON_CONTENT_TYPE,
+        )
+    }
+
+    fn with_body(
+        status_code: StatusCode,
+        body: impl IntoResponse,
+        content_type: &'static str,
+    ) -> Self {
         Self {
             status_code,
-            stream: StreamBodyAs::json_array(stream),
+            body: body.into_response().into_body(),
+            content_type,
             _phantom: PhantomData,
         }
     }
@@ -69,6 +143,150 @@ where
 
 impl<T> IntoResponse for StreamingResponse<T> {
     fn into_response(self) -> Response {
-        (self.status_code, self.stream).into_response()
+        let mut response = (self.status_code, self.body).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(self.content_type),
+        );
+        response
+    }
+}
+
+/// How a stream is rendered, picked from the `Accept` header. Anything other than JSON,
+/// NDJSON or CSV is rejected with a 406.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    JsonArray,
+    Ndjson,
+    /// A header row then a row per element. Nested objects are flattened into dotted columns,
+    /// like a set's `key.topic_id`
+    Csv,
+}
+
+impl StreamFormat {
+    pub const OFFERED: &[&str] = &[JSON_CONTENT_TYPE, NDJSON_CONTENT_TYPE, CSV_CONTENT_TYPE];
+}
+
+impl<S> FromRequestParts<S> for StreamFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = NotAcceptable;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        match negotiate(&parts.headers, Self::OFFERED) {
+            Some(NDJSON_CONTENT_TYPE) => Ok(Self::Ndjson),
+            Some(CSV_CONTENT_TYPE) => Ok(Self::Csv),
+            Some(_) => Ok(Self::JsonArray),
+            None => Err(NotAcceptable {
+                offered: Self::OFFERED,
+            }),
+        }
+    }
+}
+
+/// The columns come from the first element, later elements missing one of them leave it empty
+fn csv_rows<S, T>(stream: S) -> impl Stream<Item = Result<Vec<u8>, axum::Error>> + Send + 'static
+where
+    S: Stream<Item = Result<T, axum::Error>> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    let mut columns: Vec<String> = Vec::new();
+    stream.map(move |element| {
+        let row = flatten_row(&element?)?;
+        let mut csv = csv::Writer::from_writer(Vec::new());
+        if columns.is_empty() {
+            columns = row.keys().cloned().collect();
+            csv.write_record(&columns).map_err(axum::Error::new)?;
+        }
+        csv.write_record(
+            columns
+                .iter()
+                .map(|c| row.get(c).map_or("", String::as_str)),
+        )
+        .map_err(axum::Error::new)?;
+        csv.into_inner()
+            .map_err(|e| axum::Error::new(e.into_error()))
+    })
+}
+
+fn flatten_row<T: Serialize>(element: &T) -> Result<BTreeMap<String, String>, axum::Error> {
+    let value = serde_json::to_value(element).map_err(axum::Error::new)?;
+    let mut row = BTreeMap::new();
+    flatten_into(&mut row, String::new(), value);
+    Ok(row)
+}
+
+fn flatten_into(row: &mut BTreeMap<String, String>, column: String, value: Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields {
+                let nested = if column.is_empty() {
+                    name
+                } else {
+                    format!("{column}.{name}")
+                };
+                flatten_into(row, nested, value);
+            }
+        }
+        Value::Null => {
+            row.insert(column, String::new());
+        }
+        Value::String(s) => {
+            row.insert(column, s);
+        }
+        other => {
+            row.insert(column, other.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn body_text<T>(response: StreamingResponse<T>) -> String {
+        let body = response.into_response().into_body();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn csv_flattens_nested_fields_into_columns() {
+        let elements = vec![
+            Ok::<_, ()>(
+                json!({ "key": { "set_id": 1, "topic_id": 2 }, "name": "a, b", "description": null }),
+            ),
+            Ok(json!({ "key": { "set_id": 3, "topic_id": 2 }, "name": "c", "description": "d" })),
+        ];
+
+        let response = StreamingResponse::from_stream(
+            StreamFormat::Csv,
+            StatusCode::OK,
+            tokio_stream::iter(elements),
+        );
+
+        assert_eq!(
+            "description,key.set_id,key.topic_id,name\n,1,2,\"a, b\"\nd,3,2,c\n",
+            body_text(response).await
+        );
+    }
+
+    #[tokio::test]
+    async fn ndjson_is_a_document_per_line() {
+        let elements = vec![Ok::<_, ()>(json!({ "a": 1 })), Ok(json!({ "a": 2 }))];
+
+        let response = StreamingResponse::from_stream(
+            StreamFormat::Ndjson,
+            StatusCode::OK,
+            tokio_stream::iter(elements),
+        );
+
+        let body = body_text(response).await;
+        assert_eq!(
+            vec!["{\"a\":1}", "{\"a\":2}"],
+            body.lines().collect::<Vec<_>>()
+        );
     }
 }
@@ -0,0 +1,327 @@
+//! Offline auth for tests. Mints RS256 tokens that `AuthState` accepts without a real identity provider.
+//!
+//! ```ignore
+//! let issuer = TestIssuer::default();
+//! let auth_state = issuer.auth_state();
+//! let token = issuer.token().roles(["TOPIC_READ"]).mint();
+//! ```
+use std::sync::LazyLock;
+
+use chrono::{Duration, Utc};
+use jsonwebtoken::{
+    Algorithm, EncodingKey, Header,
+    jwk::{AlgorithmParameters, Jwk as JsonWebKey},
+};
+use rsa::{RsaPrivateKey, pkcs1::EncodeRsaPrivateKey, rand_core::OsRng};
+use serde_json::{Map, Value, json};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::{AuthState, Jwk, Jwks, OAuthConfig, RoleMapping};
+
+pub const TEST_ISSUER: &str = "http://test-issuer";
+pub const TEST_AUDIENCE: &str = "topics-api";
+const TEST_KID: &str = "test-kid";
+
+struct KeyPair {
+    encoding_key: EncodingKey,
+    jwk: Jwk,
+}
+
+// generating a key takes a while in debug builds, so every test in the binary shares one
+static KEY_PAIR: LazyLock<KeyPair> = LazyLock::new(|| {
+    let private_key = RsaPrivateKey::new(&mut OsRng, 2048).expect("rsa key generation");
+    let der = private_key
+        .to_pkcs1_der()
+        .expect("rsa key can be pkcs1 encoded");
+    let encoding_key = EncodingKey::from_rsa_der(der.as_bytes());
+
+    let public = JsonWebKey::from_encoding_key(&encoding_key, Algorithm::RS256)
+        .expect("rsa key converts to a jwk");
+    let AlgorithmParameters::RSA(rsa) = public.algorithm else {
+        unreachable!("an rsa key always has rsa parameters")
+    };
+
+    KeyPair {
+        encoding_key,
+        jwk: Jwk {
+            kid: TEST_KID.to_string(),
+            n: rsa.n,
+            e: rsa.e,
+        },
+    }
+});
+
+/// A fake identity provider. Every `TestIssuer` signs with the same key, they only differ in
+/// the issuer, audience and where roles are put in the token.
+#[derive(Debug, Clone)]
+pub struct TestIssuer {
+    issuer_url: String,
+    audience: String,
+    roles: RoleMapping,
+}
+
+impl Default for TestIssuer {
+    fn default() -> Self {
+        Self::new(TEST_ISSUER)
+    }
+}
+
+impl TestIssuer {
+    pub fn new(issuer_url: impl Into<String>) -> Self {
+        Self {
+            issuer_url: issuer_url.into(),
+            audience: TEST_AUDIENCE.to_string(),
+            roles: RoleMapping::new(["roles"]),
+        }
+    }
+
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = audience.into();
+        self
+    }
+
+    /// Minted roles are put at the first claim path of `roles`
+    pub fn with_roles(mut self, roles: RoleMapping) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    pub fn issuer_url(&self) -> &str {
+        &self.issuer_url
+    }
+
+    pub fn oauth_config(&self, jwks_url: impl Into<String>) -> OAuthConfig {
+        OAuthConfig {
+            jwks_url: jwks_url.into(),
+            issuer_url: self.issuer_url.clone(),
+            roles: self.roles.clone(),
+            audience: self.audience.clone(),
+        }
+    }
+
+    pub fn jwks(&self) -> Jwks {
+        Jwks {
+            keys: vec![KEY_PAIR.jwk.clone()],
+        }
+    }
+
+    /// An `AuthState` trusting only this issuer, with the keys injected directly
+    pub fn auth_state(&self) -> AuthState {
+        AuthState::with_jwks(self.oauth_config("http://unused/certs"), self.jwks().keys)
+    }
+
+    /// Serves this issuer's JWKS on an ephemeral local port, for tests that go through `AuthState::create_with`
+    pub async fn serve_jwks(&self) -> JwksServer {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind an ephemeral port");
+        let addr = listener.local_addr().expect("listener has an address");
+
+        let jwks = self.jwks();
+        let app = axum::Router::new().route(
+            "/certs",
+            axum::routing::get(move || async move { axum::Json(jwks) }),
+        );
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("jwks server runs");
+        });
+
+        JwksServer {
+            url: format!("http://{addr}/certs"),
+            handle,
+        }
+    }
+
+    /// A token for `test-user` that expires in an hour, without any roles
+    pub fn token(&self) -> TokenBuilder {
+        let roles_path = self
+            .roles
+            .claim_paths
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "roles".to_string());
+
+        TokenBuilder {
+            claims: Map::from_iter([
+                ("sub".to_string(), json!("test-user")),
+                ("iss".to_string(), json!(self.issuer_url)),
+                ("aud".to_string(), json!(self.audience)),
+                (
+                    "exp".to_string(),
+                    json!((Utc::now() + Duration::hours(1)).timestamp()),
+                ),
+            ]),
+            roles_path,
+            roles: Vec::new(),
+            kid: TEST_KID.to_string(),
+        }
+    }
+}
+
+/// Stops serving when dropped
+pub struct JwksServer {
+    url: String,
+    handle: JoinHandle<()>,
+}
+
+impl JwksServer {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for JwksServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+pub struct TokenBuilder {
+    claims: Map<String, Value>,
+    roles_path: String,
+    roles: Vec<String>,
+    kid: String,
+}
+
+impl TokenBuilder {
+    pub fn subject(self, sub: impl Into<String>) -> Self {
+        self.claim("sub", sub.into())
+    }
+
+    pub fn email(self, email: impl Into<String>) -> Self {
+        self.claim("email", email.into())
+    }
+
+    pub fn audience(self, aud: impl Into<String>) -> Self {
+        self.claim("aud", aud.into())
+    }
+
+    pub fn issuer(self, iss: impl Into<String>) -> Self {
+        self.claim("iss", iss.into())
+    }
+
+    pub fn roles<I, S>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.roles.extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    /// Negative durations mint an already expired token
+    pub fn expires_in(self, duration: Duration) -> Self {
+        self.claim("exp", (Utc::now() + duration).timestamp())
+    }
+
+    /// Expired long enough ago to be outside of the default leeway
+    pub fn expired(self) -> Self {
+        self.expires_in(Duration::hours(-1))
+    }
+
+    /// Signs with a kid the issuer doesn't publish
+    pub fn unknown_kid(mut self) -> Self {
+        self.kid = "unknown-kid".to_string();
+        self
+    }
+
+    pub fn claim(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.claims.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn mint(self) -> String {
+        let mut claims = self.claims;
+        insert_at_path(&mut claims, &self.roles_path, json!(self.roles));
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.kid);
+
+        jsonwebtoken::encode(&header, &claims, &KEY_PAIR.encoding_key).expect("token signs")
+    }
+}
+
+// `a.b.c` -> `{ "a": { "b": { "c": value } } }`, keeping anything already in `claims`
+fn insert_at_path(claims: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            claims.insert(path.to_string(), value);
+        }
+        Some((head, rest)) => {
+            let child = claims
+                .entry(head)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            if let Value::Object(child) = child {
+                insert_at_path(child, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{DecodingKey, Validation};
+
+    use super::*;
+    use crate::auth::claims::Claims;
+
+    fn decode(token: &str, issuer: &TestIssuer) -> jsonwebtoken::errors::Result<Claims> {
+        let jwk = &issuer.jwks().keys[0];
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[TEST_AUDIENCE]);
+        validation.set_issuer(&[issuer.issuer_url()]);
+
+        jsonwebtoken::decode::<Claims>(
+            token,
+            &DecodingKey::from_rsa_components(&jwk.n, &jwk.e).unwrap(),
+            &validation,
+        )
+        .map(|data| data.claims)
+    }
+
+    #[test]
+    fn minted_token_validates_against_published_key() {
+        let issuer = TestIssuer::default();
+
+        let token = issuer.token().roles(["TOPIC_READ"]).mint();
+
+        assert!(decode(&token, &issuer).is_ok());
+    }
+
+    #[test]
+    fn expired_token_fails_validation() {
+        let issuer = TestIssuer::default();
+
+        let token = issuer.token().expired().mint();
+
+        assert!(decode(&token, &issuer).is_err());
+    }
+
+    #[test]
+    fn roles_are_put_at_the_first_claim_path() {
+        let mut claims = Map::new();
+        claims.insert("realm_access".to_string(), json!({ "other": true }));
+
+        insert_at_path(&mut claims, "realm_access.roles", json!(["TOPIC_READ"]));
+
+        assert_eq!(
+            json!({ "realm_access": { "other": true, "roles": ["TOPIC_READ"] } }),
+            Value::Object(claims)
+        );
+    }
+
+    #[tokio::test]
+    async fn served_jwks_can_create_auth_state() {
+        let issuer = TestIssuer::default();
+        let server = issuer.serve_jwks().await;
+
+        assert!(
+            AuthState::create_with(issuer.oauth_config(server.url()))
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::borrow::Cow;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::problem::Problem;
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationRule {
+    /// The field was null or missing but is required
+    Required,
+    /// The field was blank once trimmed
+    Empty,
+    TooLong,
+    ControlCharacters,
+    /// The value has characters the field doesn't allow
+    Format,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: Cow<'static, str>,
+    pub rule: ValidationRule,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<Cow<'static, str>>, rule: ValidationRule, message: String) -> Self {
+        Self {
+            field: field.into(),
+            rule,
+            message,
+        }
+    }
+
+    pub fn required(field: &'static str) -> Self {
+        Self::new(
+            field,
+            ValidationRule::Required,
+            format!("{field} cannot be null"),
+        )
+    }
+}
+
+/// Every rule that failed, so a client can fix all of them at once
+#[derive(Debug, Clone, Default, Serialize, ToSchema, PartialEq, Eq, thiserror::Error)]
+#[serde(transparent)]
+#[error("input failed validation")]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn push(&mut self, error: FieldError) {
+        self.0.push(error);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&FieldError> {
+        self.0.first()
+    }
+
+    pub fn extend(&mut self, errors: FieldErrors) {
+        self.0.extend(errors.0);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FieldError> {
+        self.0.iter()
+    }
+
+    /// Adds the errors of one item in an array, prefixing their fields with `[index].`
+    pub fn extend_indexed(&mut self, index: usize, errors: FieldErrors) {
+        self.0.extend(errors.0.into_iter().map(|mut e| {
+            e.field = format!("[{index}].{}", e.field).into();
+            e
+        }));
+    }
+
+    /// Keeps the value if it's valid, otherwise records the error
+    pub fn check<T>(&mut self, result: Result<T, FieldError>) -> Option<T> {
+        result.map_err(|e| self.push(e)).ok()
+    }
+
+    pub fn into_problem(self) -> Problem {
+        Problem::from_status(StatusCode::UNPROCESSABLE_ENTITY)
+            .with_detail("input failed validation")
+            .with_extension("errors", self)
+    }
+}
+
+impl From<FieldError> for FieldErrors {
+    fn from(error: FieldError) -> Self {
+        Self(vec![error])
+    }
+}
+
+impl IntoResponse for FieldErrors {
+    fn into_response(self) -> Response {
+        self.into_problem().into_response()
+    }
+}
+
+/// Rules for a free text field. Values are trimmed before they're checked, and the trimmed
+/// value is what should be stored.
+#[derive(Debug, Clone, Copy)]
+pub struct TextField {
+    name: &'static str,
+    max_chars: usize,
+    multiline: bool,
+}
+
+impl TextField {
+    pub const fn new(name: &'static str, max_chars: usize) -> Self {
+        Self {
+            name,
+            max_chars,
+            multiline: false,
+        }
+    }
+
+    /// Allows newlines and tabs, every other control character is still rejected
+    pub const fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn required(&self, value: &str) -> Result<String, FieldError> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(FieldError::new(
+                self.name,
+                ValidationRule::Empty,
+                format!("{} cannot be blank", self.name),
+            ));
+        }
+        self.check(value).map(str::to_string)
+    }
+
+    /// Blank values are treated as not given
+    pub fn optional(&self, value: Option<&str>) -> Result<Option<String>, FieldError> {
+        match value.map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(value) => self.check(value).map(|v| Some(v.to_string())),
+        }
+    }
+
+    fn check<'a>(&self, value: &'a str) -> Result<&'a str, FieldError> {
+        // the database limits are in characters, not bytes
+        let len = value.chars().count();
+        if len > self.max_chars {
+            return Err(FieldError::new(
+                self.name,
+                ValidationRule::TooLong,
+                format!(
+                    "{} must be at most {} characters, was {len}",
+                    self.name, self.max_chars
+                ),
+            ));
+        }
+
+        if value
+            .chars()
+            .any(|c| c.is_control() && !(self.multiline && matches!(c, '\n' | '\r' | '\t')))
+        {
+            return Err(FieldError::new(
+                self.name,
+                ValidationRule::ControlCharacters,
+                format!("{} cannot contain control characters", self.name),
+            ));
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME: TextField = TextField::new("name", 5);
+    const DESCRIPTION: TextField = TextField::new("description", 5).multiline();
+
+    #[test]
+    fn required_is_trimmed() {
+        assert_eq!(Ok("abc".to_string()), NAME.required("  abc \n"));
+    }
+
+    #[test]
+    fn required_blank_is_empty() {
+        assert_eq!(
+            ValidationRule::Empty,
+            NAME.required("   ").unwrap_err().rule
+        );
+    }
+
+    #[test]
+    fn length_is_counted_in_chars() {
+        assert!(NAME.required("ééééé").is_ok());
+        assert_eq!(
+            ValidationRule::TooLong,
+            NAME.required("éééééé").unwrap_err().rule
+        );
+    }
+
+    #[test]
+    fn control_characters_are_rejected() {
+        assert_eq!(
+            ValidationRule::ControlCharacters,
+            NAME.required("a\u{0}b").unwrap_err().rule
+        );
+        assert_eq!(
+            ValidationRule::ControlCharacters,
+            NAME.required("a\nb").unwrap_err().rule
+        );
+    }
+
+    #[test]
+    fn multiline_allows_newlines_and_tabs_only() {
+        assert!(DESCRIPTION.optional(Some("a\nb\tc")).is_ok());
+        assert_eq!(
+            ValidationRule::ControlCharacters,
+            DESCRIPTION.optional(Some("a\u{7}")).unwrap_err().rule
+        );
+    }
+
+    #[test]
+    fn optional_blank_is_none() {
+        assert_eq!(Ok(None), DESCRIPTION.optional(Some("  ")));
+        assert_eq!(Ok(None), DESCRIPTION.optional(None));
+    }
+
+    #[test]
+    fn field_errors_become_a_422_problem_listing_every_error() {
+        let mut errors = FieldErrors::default();
+        errors.check(NAME.required(""));
+        errors.check(DESCRIPTION.optional(Some("too long!")));
+
+        let problem = serde_json::to_value(errors.into_problem()).unwrap();
+
+        assert_eq!(422, problem["status"]);
+        assert_eq!("validation_failed", problem["code"]);
+        assert_eq!("name", problem["errors"][0]["field"]);
+        assert_eq!("empty", problem["errors"][0]["rule"]);
+        assert_eq!("too_long", problem["errors"][1]["rule"]);
+    }
+
+    #[test]
+    fn indexed_errors_name_the_item() {
+        let mut errors = FieldErrors::default();
+        errors.extend_indexed(2, FieldError::required("name").into());
+
+        assert_eq!("[2].name", errors.first().unwrap().field);
+    }
+}
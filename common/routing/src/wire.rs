@@ -0,0 +1,352 @@
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::rejection::{BytesRejection, JsonRejection};
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::response::{IntoResponse, Response};
+use bson::{Bson, Document};
+use error_stack::Report;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use tracing::error;
+
+use crate::accept::{NotAcceptable, negotiate};
+use crate::problem::{Problem, StatusCode};
+use crate::stream::JSON_CONTENT_TYPE;
+
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+pub const BSON_CONTENT_TYPE: &str = "application/bson";
+
+/// The formats request and response bodies can be in. Every format is serialized from the same
+/// serde types, so a body has the same fields whichever one is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+    /// BSON bodies have to be documents, so arrays are sent as the document BSON stores arrays
+    /// as, keyed by index (`"0"`, `"1"`, ...)
+    Bson,
+}
+
+#[derive(Debug, thiserror::Error, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+    #[error("failed to serialize a {0:?} body")]
+    Serialize(WireFormat),
+    #[error("failed to deserialize a {0:?} body")]
+    Deserialize(WireFormat),
+}
+
+impl WireFormat {
+    pub const OFFERED: &[&str] = &[
+        JSON_CONTENT_TYPE,
+        MSGPACK_CONTENT_TYPE,
+        CBOR_CONTENT_TYPE,
+        BSON_CONTENT_TYPE,
+    ];
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            WireFormat::Json => JSON_CONTENT_TYPE,
+            WireFormat::MessagePack => MSGPACK_CONTENT_TYPE,
+            WireFormat::Cbor => CBOR_CONTENT_TYPE,
+            WireFormat::Bson => BSON_CONTENT_TYPE,
+        }
+    }
+
+    /// `None` for anything that isn't one of the formats, a missing content type is JSON
+    pub fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+        let Some(content_type) = headers.get(header::CONTENT_TYPE) else {
+            return Some(WireFormat::Json);
+        };
+        let essence = content_type
+            .to_str()
+            .ok()?
+            .split(';')
+            .next()?
+            .trim()
+            .to_ascii_lowercase();
+
+        match essence.as_str() {
+            MSGPACK_CONTENT_TYPE | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(WireFormat::MessagePack)
+            }
+            CBOR_CONTENT_TYPE => Some(WireFormat::Cbor),
+            BSON_CONTENT_TYPE => Some(WireFormat::Bson),
+            json if json == JSON_CONTENT_TYPE || json.ends_with("+json") => Some(WireFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<Vec<u8>, Report<WireError>> {
+        let fail = |e: &dyn Debug| Report::new(WireError::Serialize(self)).attach(format!("{e:?}"));
+
+        match self {
+            WireFormat::Json => serde_json::to_vec(value).map_err(|e| fail(&e)),
+            WireFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| fail(&e)),
+            WireFormat::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|e| fail(&e))?;
+                Ok(bytes)
+            }
+            WireFormat::Bson => {
+                let document = match bson::to_bson(value).map_err(|e| fail(&e))? {
+                    Bson::Document(document) => document,
+                    Bson::Array(items) => items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, item)| (i.to_string(), item))
+                        .collect(),
+                    other => return Err(fail(&format!("{other} is not a document or array"))),
+                };
+                let mut bytes = Vec::new();
+                document.to_writer(&mut bytes).map_err(|e| fail(&e))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, Report<WireError>> {
+        let fail =
+            |e: &dyn Debug| Report::new(WireError::Deserialize(self)).attach(format!("{e:?}"));
+
+        match self {
+            WireFormat::Json => serde_json::from_slice(bytes).map_err(|e| fail(&e)),
+            WireFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| fail(&e)),
+            WireFormat::Cbor => ciborium::from_reader(bytes).map_err(|e| fail(&e)),
+            WireFormat::Bson => {
+                let mut reader = bytes;
+                let document = Document::from_reader(&mut reader).map_err(|e| fail(&e))?;
+                bson_from_document(document).map_err(|e| fail(&e))
+            }
+        }
+    }
+
+    pub fn respond<T: Serialize>(self, status_code: StatusCode, body: T) -> WireResponse<T> {
+        WireResponse {
+            format: self,
+            status_code,
+            body,
+        }
+    }
+}
+
+/// A document keyed `"0"`, `"1"`, ... is tried as an array first, since that's how arrays are sent
+fn bson_from_document<T: DeserializeOwned>(document: Document) -> bson::de::Result<T> {
+    let is_array = document
+        .keys()
+        .enumerate()
+        .all(|(i, key)| *key == i.to_string());
+    if is_array {
+        let items = document.values().cloned().collect();
+        if let Ok(value) = bson::from_bson(Bson::Array(items)) {
+            return Ok(value);
+        }
+    }
+    bson::from_document(document)
+}
+
+/// The response format the `Accept` header prefers, a 406 if it allows none of them
+impl<S> FromRequestParts<S> for WireFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = NotAcceptable;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        match negotiate(&parts.headers, Self::OFFERED) {
+            Some(MSGPACK_CONTENT_TYPE) => Ok(WireFormat::MessagePack),
+            Some(CBOR_CONTENT_TYPE) => Ok(WireFormat::Cbor),
+            Some(BSON_CONTENT_TYPE) => Ok(WireFormat::Bson),
+            Some(_) => Ok(WireFormat::Json),
+            None => Err(NotAcceptable {
+                offered: Self::OFFERED,
+            }),
+        }
+    }
+}
+
+/// Like `Json`, but the body can be in any [`WireFormat`], picked from its `Content-Type`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wire<T>(pub T);
+
+impl<T, S> FromRequest<S> for Wire<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = WireRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let format = WireFormat::from_content_type(req.headers())
+            .ok_or(WireRejection::UnsupportedMediaType)?;
+
+        // JSON keeps the rejections it always had
+        if format == WireFormat::Json {
+            let Json(value) = Json::from_request(req, state).await?;
+            return Ok(Wire(value));
+        }
+
+        let bytes = Bytes::from_request(req, state).await?;
+        format
+            .deserialize(&bytes)
+            .map(Wire)
+            .map_err(|_| WireRejection::Malformed(format))
+    }
+}
+
+#[derive(Debug)]
+pub enum WireRejection {
+    Json(JsonRejection),
+    Bytes(BytesRejection),
+    UnsupportedMediaType,
+    Malformed(WireFormat),
+}
+
+impl From<JsonRejection> for WireRejection {
+    fn from(rejection: JsonRejection) -> Self {
+        WireRejection::Json(rejection)
+    }
+}
+
+impl From<BytesRejection> for WireRejection {
+    fn from(rejection: BytesRejection) -> Self {
+        WireRejection::Bytes(rejection)
+    }
+}
+
+impl IntoResponse for WireRejection {
+    fn into_response(self) -> Response {
+        match self {
+            WireRejection::Json(rejection) => rejection.into_response(),
+            WireRejection::Bytes(rejection) => rejection.into_response(),
+            WireRejection::UnsupportedMediaType => {
+                Problem::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type")
+                    .with_detail(format!(
+                        "the body can only be one of {}",
+                        WireFormat::OFFERED.join(", ")
+                    ))
+                    .into_response()
+            }
+            WireRejection::Malformed(format) => {
+                Problem::new(StatusCode::BAD_REQUEST, "malformed_body")
+                    .with_detail(format!(
+                        "the body isn't a valid {} request",
+                        format.content_type()
+                    ))
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// `body` rendered in `format`, made with [`WireFormat::respond`]
+#[derive(Debug)]
+pub struct WireResponse<T> {
+    format: WireFormat,
+    status_code: StatusCode,
+    body: T,
+}
+
+impl<T: Serialize> IntoResponse for WireResponse<T> {
+    fn into_response(self) -> Response {
+        match self.format.serialize(&self.body) {
+            Ok(bytes) => {
+                let mut response = (self.status_code, bytes).into_response();
+                response.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(self.format.content_type()),
+                );
+                response
+            }
+            Err(e) => {
+                error!("{e:?}");
+                Problem::internal_error().into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Outcome {
+        name: String,
+        description: Option<String>,
+        count: usize,
+    }
+
+    fn outcomes() -> Vec<Outcome> {
+        vec![
+            Outcome {
+                name: "orders".to_string(),
+                description: None,
+                count: 2,
+            },
+            Outcome {
+                name: "customers".to_string(),
+                description: Some("all of them".to_string()),
+                count: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn every_format_round_trips_arrays_and_documents() {
+        for format in [
+            WireFormat::Json,
+            WireFormat::MessagePack,
+            WireFormat::Cbor,
+            WireFormat::Bson,
+        ] {
+            let bytes = format.serialize(&outcomes()).unwrap();
+            let decoded: Vec<Outcome> = format.deserialize(&bytes).unwrap();
+            assert_eq!(outcomes(), decoded, "{format:?} array");
+
+            let bytes = format.serialize(&outcomes()[1]).unwrap();
+            let decoded: Outcome = format.deserialize(&bytes).unwrap();
+            assert_eq!(outcomes()[1], decoded, "{format:?} document");
+        }
+    }
+
+    #[test]
+    fn messagepack_keeps_field_names() {
+        let bytes = WireFormat::MessagePack.serialize(&outcomes()[0]).unwrap();
+
+        let decoded: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+
+        assert_eq!(
+            json!({ "name": "orders", "description": null, "count": 2 }),
+            decoded
+        );
+    }
+
+    #[test]
+    fn content_type_picks_the_format() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            Some(WireFormat::Json),
+            WireFormat::from_content_type(&headers)
+        );
+
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-msgpack"),
+        );
+        assert_eq!(
+            Some(WireFormat::MessagePack),
+            WireFormat::from_content_type(&headers)
+        );
+
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/xml"));
+        assert_eq!(None, WireFormat::from_content_type(&headers));
+    }
+}
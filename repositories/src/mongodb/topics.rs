@@ -2,20 +2,31 @@ use bson::oid::ObjectId;
 use bson::{Bson, Document, doc};
 use chrono::{DateTime, Utc};
 use error_stack::{IntoReport, Report, ResultExt};
-use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
-use mongodb::{Client, Database};
+use mongodb::error::{ErrorKind, InsertManyError, TRANSIENT_TRANSACTION_ERROR, WriteFailure};
+use mongodb::options::{
+    Collation, CollationStrength, FindOneAndUpdateOptions, FindOptions, IndexOptions,
+    ReturnDocument,
+};
+use mongodb::{Client, ClientSession, Database, IndexModel};
 use optional_field::Field;
+use routing::attributes::{AttributeFilter, Attributes};
 use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
-use tokio_stream::StreamExt;
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt};
 use topics_core::TopicRepository;
-use topics_core::list_filter::TopicListCriteria;
-use topics_core::model::{NewTopic, PatchTopic, Topic};
+use topics_core::list_filter::{TopicListCriteria, attribute_filters, status_filter, tag_filters};
+use topics_core::model::{
+    DeletePolicy, LinkDirection, LinkType, NewTopic, PartialTopic, PatchTopic, StatusChange,
+    TagCount, Topic, TopicField, TopicLink, TopicStatus, TopicVersion, Upserted,
+    undeletable_parents,
+};
 use topics_core::result::{CreateErrorType, OptRepoResult, RepoResult, TopicRepoError};
 use tracing::{debug, error, warn};
 use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy, Hash)]
 #[repr(transparent)]
 #[schema(value_type = String)]
 pub struct TopicId(#[serde(serialize_with = "obj_id_serialize")] ObjectId);
@@ -45,21 +56,165 @@ impl Display for TopicId {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct NewTopicCreated {
+    // generated here rather than by the server, so a partially failed insert_many still tells
+    // us the ids of the topics that were created
+    #[serde(rename = "_id")]
+    id: ObjectId,
     name: String,
     description: Option<String>,
+    parent_id: Option<ObjectId>,
+    attributes: Attributes,
+    status: TopicStatus,
     created: DateTime<Utc>,
+    version: i64,
 }
 
 impl NewTopicCreated {
-    fn new(name: String, description: Option<String>, created: DateTime<Utc>) -> Self {
+    fn new(new_topic: NewTopic<TopicId>, created: DateTime<Utc>) -> Self {
         Self {
-            name,
-            description,
+            id: ObjectId::new(),
+            name: new_topic.name,
+            description: new_topic.description,
+            parent_id: new_topic.parent_id.map(|p| p.0),
+            attributes: new_topic.attributes,
+            status: TopicStatus::default(),
             created,
+            version: 1,
+        }
+    }
+
+    fn into_topic(self) -> Topic<TopicId> {
+        Topic::new(
+            TopicId(self.id),
+            self.name,
+            self.description,
+            self.created,
+            None,
+        )
+        .with_parent(self.parent_id.map(TopicId))
+        .with_attributes(self.attributes)
+        .with_status(self.status)
+    }
+}
+
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/// The parts of an `update` command response we need to match failures back to statements
+#[derive(Debug, Deserialize)]
+struct UpdateCommandResponse {
+    #[serde(rename = "writeErrors", default)]
+    write_errors: Vec<CommandWriteError>,
+    #[serde(rename = "writeConcernError")]
+    write_concern_error: Option<Document>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandWriteError {
+    index: usize,
+    code: i32,
+}
+
+/// The index and error code of every topic an `insert_many` was refused, attached to the report
+/// of a failed chunk of a bulk create
+#[derive(Debug)]
+struct RefusedTopics(Vec<(usize, i32)>);
+
+/// The index and error code of every document an `insert_many` was refused. `None` if it failed
+/// for some other reason
+fn refused_inserts(e: &mongodb::error::Error) -> Option<Vec<(usize, i32)>> {
+    match e.kind.as_ref() {
+        ErrorKind::InsertMany(InsertManyError {
+            write_errors: Some(write_errors),
+            write_concern_error: None,
+            ..
+        }) => Some(write_errors.iter().map(|e| (e.index, e.code)).collect()),
+        _ => None,
+    }
+}
+
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == DUPLICATE_KEY_CODE,
+        ErrorKind::Command(e) => e.code == DUPLICATE_KEY_CODE,
+        ErrorKind::InsertMany(InsertManyError {
+            write_errors: Some(write_errors),
+            ..
+        }) => write_errors.iter().any(|e| e.code == DUPLICATE_KEY_CODE),
+        _ => false,
+    }
+}
+
+/// The fields to `$set` and `$unset`, both empty if the patch changes nothing. Attributes are set
+/// one by one so the ones the patch doesn't mention are kept, and the null ones are unset
+fn patch_document(patch: PatchTopic<TopicId>) -> Result<(Document, Document), bson::ser::Error> {
+    let mut update_document = Document::new();
+    let mut unset_document = Document::new();
+    if let Some(name) = patch.name {
+        update_document.insert("name", name);
+    }
+
+    if let Field::Present(desc) = patch.description {
+        match desc {
+            Some(d) => {
+                update_document.insert("description", d);
+            }
+            None => {
+                update_document.insert("description", Bson::Null);
+            }
+        }
+    }
+
+    if let Field::Present(parent_id) = patch.parent_id {
+        update_document.insert("parent_id", parent_id.map(|p| p.0));
+    }
+
+    for (key, value) in patch.attributes.into_iter().flatten() {
+        let path = format!("attributes.{key}");
+        if value.is_null() {
+            unset_document.insert(path, "");
+        } else {
+            update_document.insert(path, bson::to_bson(&value)?);
         }
     }
+
+    Ok((update_document, unset_document))
+}
+
+/// Every change moves the topic on to its next version
+fn update_document(set: Document, unset: Document) -> Document {
+    let mut update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    update
+}
+
+/// Follows `connect_from` to `connect_to` from the topic with `id`, giving every topic reached
+/// except the topic itself, sorted by `sort`
+fn graph_lookup_pipeline(
+    id: TopicId,
+    connect_from: &str,
+    connect_to: &str,
+    sort: Document,
+) -> Vec<Document> {
+    vec![
+        doc! { "$match": { "_id": id } },
+        doc! {
+            "$graphLookup": {
+                "from": TOPICS_COLLECTION_NAME,
+                "startWith": format!("${connect_from}"),
+                "connectFromField": connect_from,
+                "connectToField": connect_to,
+                "as": "reached",
+                "depthField": "depth",
+            }
+        },
+        doc! { "$unwind": "$reached" },
+        doc! { "$replaceRoot": { "newRoot": "$reached" } },
+        doc! { "$sort": sort },
+    ]
 }
 
 pub enum ConnectionDetails {
@@ -70,24 +225,91 @@ pub enum ConnectionDetails {
 #[error("failed to create client connection to mongodb instance")]
 pub struct ConnectError;
 
+#[derive(Debug, thiserror::Error)]
+#[error("failed to create topic indexes")]
+pub struct IndexError;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct MongoTopic {
     #[serde(rename = "_id")]
     id: TopicId,
+    // stored as an object id rather than a hex string, so it can be matched against `_id`
+    #[serde(default)]
+    parent_id: Option<ObjectId>,
     name: String,
     description: Option<String>,
+    #[serde(default)]
+    attributes: Attributes,
+    #[serde(default = "unset_status")]
+    status: TopicStatus,
     created: DateTime<Utc>,
     updated: Option<DateTime<Utc>>,
+    // kept on the topic so lists can filter on them with a multikey match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// The version the topic is at. Topics from before versions were kept have none stored, and
+    /// their history starts at their next change
+    #[serde(default)]
+    version: i64,
+}
+
+/// What `upsert_once` wrote
+struct MongoUpserted {
+    topic: MongoTopic,
+    created: bool,
+}
+
+/// Matches a topic still at `version`, including topics from before versions were kept
+fn version_condition(version: i64) -> Bson {
+    if version == 0 {
+        Bson::Document(doc! { "$in": [0_i64, Bson::Null] })
+    } else {
+        Bson::Int64(version)
+    }
+}
+
+/// Topics from before statuses were added have none stored, and were already in use
+fn unset_status() -> TopicStatus {
+    TopicStatus::Active
+}
+
+/// Matches a topic in any of `statuses`, including the ones stored without a status
+fn status_condition(statuses: &[TopicStatus]) -> Document {
+    let mut names: Vec<_> = statuses.iter().map(|s| Bson::from(s.name())).collect();
+    if statuses.contains(&unset_status()) {
+        names.push(Bson::Null);
+    }
+    doc! { "$in": names }
+}
+
+impl MongoTopic {
+    fn sorted_tags(mut self) -> Vec<String> {
+        self.tags.sort();
+        self.tags
+    }
+
+    /// The topic, and a copy of it as the version it's at
+    fn versioned(self, recorded: DateTime<Utc>) -> (Topic<TopicId>, MongoTopicVersion) {
+        let version = self.version;
+        let topic: Topic<TopicId> = self.into();
+        let copy = MongoTopicVersion::new(version, topic.clone(), recorded);
+        (topic, copy)
+    }
 }
 
 impl From<Topic<TopicId>> for MongoTopic {
     fn from(value: Topic<TopicId>) -> Self {
         Self {
             id: value.id,
+            parent_id: value.parent_id.map(|p| p.0),
             name: value.name,
             description: value.description,
+            attributes: value.attributes,
+            status: value.status,
             created: value.created,
             updated: value.updated,
+            tags: Vec::new(),
+            version: 0,
         }
     }
 }
@@ -96,14 +318,278 @@ impl From<MongoTopic> for Topic<TopicId> {
     fn from(value: MongoTopic) -> Self {
         Self {
             id: value.id,
+            parent_id: value.parent_id.map(TopicId),
             name: value.name,
             description: value.description,
+            attributes: value.attributes,
+            status: value.status,
+            created: value.created,
+            updated: value.updated,
+        }
+    }
+}
+
+/// The topics a link joins. It's the link's _id, so there's only one link between two topics
+#[derive(Debug, Serialize, Deserialize)]
+struct MongoLinkKey {
+    source_id: ObjectId,
+    target_id: ObjectId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MongoTopicLink {
+    #[serde(rename = "_id")]
+    key: MongoLinkKey,
+    link_type: LinkType,
+    created: DateTime<Utc>,
+    updated: Option<DateTime<Utc>>,
+}
+
+impl From<MongoTopicLink> for TopicLink<TopicId> {
+    fn from(value: MongoTopicLink) -> Self {
+        Self {
+            source_id: TopicId(value.key.source_id),
+            target_id: TopicId(value.key.target_id),
+            link_type: value.link_type,
             created: value.created,
             updated: value.updated,
         }
     }
 }
 
+fn link_filter(source_id: TopicId, target_id: TopicId) -> Document {
+    doc! { "_id.source_id": source_id, "_id.target_id": target_id }
+}
+
+/// Matches the topics with every tag in `TopicFilter::AllTags`, any in `TopicFilter::AnyTag`, the
+/// attributes in `TopicFilter::Attributes` and one of the statuses in `TopicFilter::Status`
+fn list_filter(list_criteria: &TopicListCriteria) -> Document {
+    let (all, any) = tag_filters(list_criteria);
+    let mut tags = Document::new();
+    if let Some(all) = all {
+        tags.insert("$all", all.to_vec());
+    }
+    if let Some(any) = any {
+        tags.insert("$in", any.to_vec());
+    }
+
+    let mut filter = Document::new();
+    if !tags.is_empty() {
+        filter.insert("tags", tags);
+    }
+    let attributes: Vec<_> = attribute_filters(list_criteria)
+        .iter()
+        .map(attribute_filter)
+        .collect();
+    if !attributes.is_empty() {
+        filter.insert("$and", attributes);
+    }
+    if let Some(statuses) = status_filter(list_criteria) {
+        filter.insert("status", status_condition(statuses));
+    }
+    filter
+}
+
+/// The filter value is text, so it matches a string attribute, or a number or bool it parses as.
+/// Arrays are ruled out, since `$in` would match any of their elements
+fn attribute_filter(filter: &AttributeFilter) -> Document {
+    let condition = match filter {
+        AttributeFilter::Exists(_) => doc! { "$exists": true },
+        AttributeFilter::Equals { value, .. } => {
+            let mut values = vec![Bson::String(value.clone())];
+            if let Ok(n) = value.parse::<i64>() {
+                values.push(Bson::Int64(n));
+            } else if let Ok(n) = value.parse::<f64>() {
+                values.push(Bson::Double(n));
+            }
+            if let Ok(b) = value.parse::<bool>() {
+                values.push(Bson::Boolean(b));
+            }
+            doc! { "$in": values, "$not": { "$type": "array" } }
+        }
+    };
+
+    let mut document = Document::new();
+    document.insert(format!("attributes.{}", filter.key()), condition);
+    document
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MongoStatusChange {
+    topic_id: ObjectId,
+    from: TopicStatus,
+    to: TopicStatus,
+    reason: Option<String>,
+    changed: DateTime<Utc>,
+}
+
+impl MongoStatusChange {
+    fn new(topic_id: TopicId, change: StatusChange) -> Self {
+        Self {
+            topic_id: topic_id.0,
+            from: change.from,
+            to: change.to,
+            reason: change.reason,
+            changed: change.changed,
+        }
+    }
+}
+
+impl From<MongoStatusChange> for StatusChange {
+    fn from(value: MongoStatusChange) -> Self {
+        Self {
+            from: value.from,
+            to: value.to,
+            reason: value.reason,
+            changed: value.changed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MongoTopicVersion {
+    topic_id: ObjectId,
+    version: i64,
+    parent_id: Option<ObjectId>,
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    attributes: Attributes,
+    status: TopicStatus,
+    created: DateTime<Utc>,
+    updated: Option<DateTime<Utc>>,
+    // a bson date rather than a string, so reads as of a time can compare it
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    recorded: DateTime<Utc>,
+}
+
+impl MongoTopicVersion {
+    fn new(version: i64, topic: Topic<TopicId>, recorded: DateTime<Utc>) -> Self {
+        Self {
+            topic_id: topic.id.0,
+            version,
+            parent_id: topic.parent_id.map(|p| p.0),
+            name: topic.name,
+            description: topic.description,
+            attributes: topic.attributes,
+            status: topic.status,
+            created: topic.created,
+            updated: topic.updated,
+            recorded,
+        }
+    }
+}
+
+/// New topics start at version 1, from when they were created
+fn first_version(topic: &Topic<TopicId>) -> MongoTopicVersion {
+    MongoTopicVersion::new(1, topic.clone(), topic.created)
+}
+
+impl From<MongoTopicVersion> for TopicVersion<TopicId> {
+    fn from(value: MongoTopicVersion) -> Self {
+        let topic = Topic::new(
+            TopicId(value.topic_id),
+            value.name,
+            value.description,
+            value.created,
+            value.updated,
+        )
+        .with_parent(value.parent_id.map(TopicId))
+        .with_attributes(value.attributes)
+        .with_status(value.status);
+        TopicVersion::new(value.version as u64, topic, value.recorded)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MongoTagCount {
+    #[serde(rename = "_id")]
+    tag: String,
+    count: i64,
+}
+
+fn links_touching_filter(ids: Vec<ObjectId>) -> Document {
+    doc! {
+        "$or": [
+            { "_id.source_id": { "$in": ids.clone() } },
+            { "_id.target_id": { "$in": ids } },
+        ]
+    }
+}
+
+/// A topic read with a projection, fields that weren't projected are missing
+#[derive(Debug, Deserialize)]
+struct MongoPartialTopic {
+    #[serde(rename = "_id")]
+    id: Option<TopicId>,
+    parent_id: Option<ObjectId>,
+    name: Option<String>,
+    description: Option<String>,
+    attributes: Option<Attributes>,
+    status: Option<TopicStatus>,
+    created: Option<DateTime<Utc>>,
+    updated: Option<DateTime<Utc>>,
+}
+
+impl MongoPartialTopic {
+    /// A null parent, description or updated is only `Some(None)` when it was projected
+    fn into_partial(self, fields: &[TopicField]) -> PartialTopic<TopicId> {
+        let read = |field| fields.contains(&field);
+        PartialTopic {
+            id: self.id,
+            parent_id: read(TopicField::ParentId).then_some(self.parent_id.map(TopicId)),
+            name: self.name,
+            description: read(TopicField::Description).then_some(self.description),
+            // topics from before attributes were added have none stored
+            attributes: read(TopicField::Attributes).then(|| self.attributes.unwrap_or_default()),
+            status: read(TopicField::Status).then(|| self.status.unwrap_or_else(unset_status)),
+            created: self.created,
+            updated: read(TopicField::Updated).then_some(self.updated),
+        }
+    }
+}
+
+fn projection(fields: &[TopicField]) -> Document {
+    // _id is the only field that's projected unless it's excluded
+    let mut projection = doc! { "_id": i32::from(fields.contains(&TopicField::Id)) };
+    for field in fields.iter().filter(|f| **f != TopicField::Id) {
+        projection.insert(field.name(), 1);
+    }
+    projection
+}
+
+/// Skips to the page `list_criteria` asks for
+fn page_options(list_criteria: &TopicListCriteria) -> RepoResult<FindOptions> {
+    let actual_page = list_criteria
+        .page()
+        .saturating_sub(1)
+        .checked_mul(list_criteria.page_size())
+        .ok_or(TopicRepoError::List)
+        .attach_with(|| {
+            format!(
+                "invalid page ({}) and page size ({})",
+                list_criteria.page(),
+                list_criteria.page_size()
+            )
+        })?;
+
+    let page_size = if list_criteria.page_size() > i64::MAX as u64 {
+        return Err(TopicRepoError::List.into_report()).attach_with(|| {
+            format!(
+                "invalid page_size {}. It is too large and not supported",
+                list_criteria.page_size()
+            )
+        });
+    } else {
+        list_criteria.page_size() as i64
+    };
+
+    Ok(FindOptions::builder()
+        .skip(actual_page)
+        .limit(page_size)
+        .build())
+}
+
 #[derive(Debug, Clone)]
 pub struct TopicRepo {
     db: Database,
@@ -111,6 +597,24 @@ pub struct TopicRepo {
 
 const TOPICS_DB_NAME: &str = "topics";
 const TOPICS_COLLECTION_NAME: &str = "topics";
+const TOPIC_LINKS_COLLECTION_NAME: &str = "topic_links";
+const TOPIC_STATUS_CHANGES_COLLECTION_NAME: &str = "topic_status_changes";
+const TOPIC_VERSIONS_COLLECTION_NAME: &str = "topic_versions";
+const TOPIC_HIERARCHY_LOCK_COLLECTION_NAME: &str = "topic_hierarchy_lock";
+
+/// How many times a write is tried when another one keeps getting to the topic first
+const WRITE_ATTEMPTS: usize = 3;
+
+/// How many topics a bulk create inserts in each transaction
+const CREATE_MANY_CHUNK_SIZE: usize = 500;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A transaction that lost to another one can be tried again
+fn is_transient(e: &Report<TopicRepoError>) -> bool {
+    e.downcast_ref::<mongodb::error::Error>()
+        .is_some_and(|e| e.contains_label(TRANSIENT_TRANSACTION_ERROR))
+}
 
 impl TopicRepo {
     pub fn new(client: Client) -> Self {
@@ -128,206 +632,1627 @@ impl TopicRepo {
                 .change_context(ConnectError)?,
         };
 
-        Ok(Self {
+        let repo = Self {
             db: client.database(TOPICS_DB_NAME),
-        })
+        };
+        repo.create_version_index()
+            .await
+            .change_context(ConnectError)?;
+        Ok(repo)
     }
-}
 
-impl TopicRepository for TopicRepo {
-    type TopicId = TopicId;
+    /// Each version of a topic is kept once
+    async fn create_version_index(&self) -> Result<(), Report<IndexError>> {
+        let index = IndexModel::builder()
+            .keys(doc! { "topic_id": 1, "version": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("topic_versions_unique_idx".to_string())
+                    .unique(true)
+                    .build(),
+            )
+            .build();
 
-    async fn get(&self, id: Self::TopicId) -> OptRepoResult<Topic<Self::TopicId>> {
         self.db
-            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
-            .find_one(doc! { "_id": id })
+            .collection::<MongoTopicVersion>(TOPIC_VERSIONS_COLLECTION_NAME)
+            .create_index(index)
             .await
-            .change_context(TopicRepoError::Get)
-            .map(|t| t.map(From::from))
+            .change_context(IndexError)?;
+
+        Ok(())
     }
 
-    async fn list(
-        &self,
-        list_criteria: TopicListCriteria,
-    ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
-        let actual_page = list_criteria
-            .page()
-            .saturating_sub(1)
-            .checked_mul(list_criteria.page_size())
-            .ok_or(TopicRepoError::List)
-            .attach_with(|| {
-                format!(
-                    "invalid page ({}) and page size ({})",
-                    list_criteria.page(),
-                    list_criteria.page_size()
-                )
-            })?;
+    /// Transactions need a replica set or a sharded cluster
+    async fn supports_transactions(&self) -> Result<bool, mongodb::error::Error> {
+        let hello = self.db.run_command(doc! { "hello": 1 }).await?;
 
-        let page_size = if list_criteria.page_size() > i64::MAX as u64 {
-            return Err(TopicRepoError::List.into_report()).attach_with(|| {
-                format!(
-                    "invalid page_size {}. It is too large and not supported",
-                    list_criteria.page_size()
-                )
-            });
-        } else {
-            list_criteria.page_size() as i64
-        };
+        Ok(
+            hello.contains_key("setName")
+                || hello.get_str("msg").is_ok_and(|msg| msg == "isdbgrid"),
+        )
+    }
 
-        let options = FindOptions::builder()
-            .skip(actual_page)
-            .limit(page_size)
+    /// Rejects topics whose names only differ by case. Fails if the collection already holds
+    /// duplicates, and the index is left in place if this is no longer called.
+    pub async fn enforce_unique_names(&self) -> Result<(), Report<IndexError>> {
+        let collation = Collation::builder()
+            .locale("en")
+            .strength(CollationStrength::Secondary)
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! { "name": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("topics_name_unique_idx".to_string())
+                    .unique(true)
+                    .collation(collation)
+                    .build(),
+            )
             .build();
 
         self.db
             .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
-            .find(Document::default())
-            .with_options(options)
+            .create_index(index)
             .await
-            .change_context(TopicRepoError::List)?
+            .change_context(IndexError)?;
+
+        Ok(())
+    }
+
+    /// The topics a `$graphLookup` reaches from the topic with `id` by following `connect_from`
+    /// to `connect_to`, sorted by `sort`. The topic itself isn't included.
+    async fn graph_lookup(
+        &self,
+        id: TopicId,
+        connect_from: &str,
+        connect_to: &str,
+        sort: Document,
+        on_fail: TopicRepoError,
+    ) -> RepoResult<Vec<Topic<TopicId>>> {
+        self.db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .aggregate(graph_lookup_pipeline(id, connect_from, connect_to, sort))
+            .with_type::<MongoTopic>()
+            .await
+            .change_context(on_fail)?
             .map(|t| t.map(From::from))
             .collect::<Result<_, _>>()
             .await
-            .change_context(TopicRepoError::List)
+            .change_context(on_fail)
     }
 
-    async fn create(&self, new_topic: NewTopic) -> RepoResult<Topic<Self::TopicId>> {
-        let created = Utc::now();
-        // block is here to end the borrow of `new_topic` before we create Topic at the end
-        let topic = NewTopicCreated::new(new_topic.name, new_topic.description, created);
+    /// Links go with either of their topics
+    async fn delete_links_touching(&self, ids: Vec<ObjectId>) -> RepoResult<()> {
+        self.db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .delete_many(links_touching_filter(ids))
+            .await
+            .change_context(TopicRepoError::Delete)?;
+        Ok(())
+    }
+
+    async fn delete_status_changes(&self, ids: Vec<ObjectId>) -> RepoResult<()> {
+        self.db
+            .collection::<MongoStatusChange>(TOPIC_STATUS_CHANGES_COLLECTION_NAME)
+            .delete_many(doc! { "topic_id": { "$in": ids } })
+            .await
+            .change_context(TopicRepoError::Delete)?;
+        Ok(())
+    }
+
+    /// Keeps copies of topics at the versions they're at. Written through `session`, so in a
+    /// transaction they're kept along with the change. A copy of a version that's already kept
+    /// is skipped.
+    async fn record_versions(
+        &self,
+        session: &mut ClientSession,
+        versions: Vec<MongoTopicVersion>,
+        on_fail: TopicRepoError,
+    ) -> RepoResult<()> {
+        if versions.is_empty() {
+            return Ok(());
+        }
 
         let result = self
             .db
-            .collection::<NewTopicCreated>(TOPICS_COLLECTION_NAME)
-            .insert_one(&topic)
-            .await
-            .change_context(TopicRepoError::Create(CreateErrorType::DbError))?;
-
-        Ok(Topic {
-            id: TopicId::new_with(
-                result
-                    .inserted_id
-                    .as_object_id()
-                    .ok_or(TopicRepoError::Create(CreateErrorType::DbError))
-                    .attach_with(|| "inserted id for {new_topic:?} was not an ObjectId")?,
-            ),
-            name: topic.name,
-            description: topic.description,
-            created,
-            updated: None,
-        })
+            .collection::<MongoTopicVersion>(TOPIC_VERSIONS_COLLECTION_NAME)
+            .insert_many(versions)
+            .ordered(false)
+            .session(session)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => match e.kind.as_ref() {
+                ErrorKind::InsertMany(InsertManyError {
+                    write_errors: Some(write_errors),
+                    write_concern_error: None,
+                    ..
+                }) if write_errors.iter().all(|e| e.code == DUPLICATE_KEY_CODE) => Ok(()),
+                _ => Err(e.into_report()).change_context(on_fail),
+            },
+        }
     }
 
-    async fn create_many(
+    async fn session(&self, on_fail: TopicRepoError) -> RepoResult<ClientSession> {
+        self.db
+            .client()
+            .start_session()
+            .await
+            .change_context(on_fail)
+    }
+
+    async fn delete_versions(&self, ids: Vec<ObjectId>) -> RepoResult<()> {
+        self.db
+            .collection::<MongoTopicVersion>(TOPIC_VERSIONS_COLLECTION_NAME)
+            .delete_many(doc! { "topic_id": { "$in": ids } })
+            .await
+            .change_context(TopicRepoError::Delete)?;
+        Ok(())
+    }
+
+    /// Fails with `ParentNotFound` if there's no topic with `parent_id`
+    async fn check_parent(
         &self,
-        new_topics: Vec<NewTopic>,
-    ) -> RepoResult<Vec<RepoResult<Topic<Self::TopicId>>>> {
-        if new_topics.is_empty() {
-            return Ok(vec![]);
+        parent_id: Option<TopicId>,
+        on_fail: TopicRepoError,
+    ) -> RepoResult<()> {
+        let Some(parent_id) = parent_id else {
+            return Ok(());
+        };
+
+        if self.get(parent_id).await.change_context(on_fail)?.is_none() {
+            return Err(TopicRepoError::ParentNotFound.into_report())
+                .attach_with(|| format!("parent topic {parent_id} doesn't exist"));
         }
+        Ok(())
+    }
 
-        let create_requests = new_topics
-            .into_iter()
-            .map(|t| NewTopicCreated::new(t.name, t.description, Utc::now()))
-            .collect::<Vec<_>>();
+    /// Like `check_parent`, but read through `session` and also failing with `ParentCycle` if
+    /// `parent_id` is the topic with `id` or one of its descendants
+    async fn check_move(
+        &self,
+        session: &mut ClientSession,
+        id: TopicId,
+        parent_id: Option<TopicId>,
+        on_fail: TopicRepoError,
+    ) -> RepoResult<()> {
+        let Some(parent_id) = parent_id else {
+            return Ok(());
+        };
 
-        let mut result = self
-            .db
-            .collection::<NewTopicCreated>(TOPICS_COLLECTION_NAME)
-            .insert_many(&create_requests)
+        if id == parent_id {
+            return Err(TopicRepoError::ParentCycle.into_report());
+        }
+
+        let collection = self.db.collection::<MongoTopic>(TOPICS_COLLECTION_NAME);
+        let parent = collection
+            .find_one(doc! { "_id": parent_id })
+            .session(&mut *session)
             .await
-            .change_context(TopicRepoError::Create(CreateErrorType::DbError))?;
+            .change_context(on_fail)?;
+        if parent.is_none() {
+            return Err(TopicRepoError::ParentNotFound.into_report())
+                .attach_with(|| format!("parent topic {parent_id} doesn't exist"));
+        }
+
+        let mut ancestors = collection
+            .aggregate(graph_lookup_pipeline(
+                parent_id,
+                "parent_id",
+                "_id",
+                doc! { "depth": 1 },
+            ))
+            .with_type::<MongoTopic>()
+            .session(&mut *session)
+            .await
+            .change_context(on_fail)?;
+        while let Some(ancestor) = ancestors.next(&mut *session).await {
+            if ancestor.change_context(on_fail)?.id == id {
+                return Err(TopicRepoError::ParentCycle.into_report())
+                    .attach_with(|| format!("topic {parent_id} is nested under topic {id}"));
+            }
+        }
 
-        let mut topics = Vec::with_capacity(create_requests.len());
+        Ok(())
+    }
 
-        let mut persisted_topics = 0;
+    /// Replaces the topic with `id`, keeping its status, tags and created time, or creates it.
+    /// `None` if the topic was created, changed or deleted after it was read, so nothing was
+    /// written.
+    async fn upsert_once(
+        &self,
+        session: &mut ClientSession,
+        id: TopicId,
+        topic: NewTopic<TopicId>,
+        now: DateTime<Utc>,
+    ) -> RepoResult<Option<MongoUpserted>> {
+        let collection = self.db.collection::<MongoTopic>(TOPICS_COLLECTION_NAME);
+        let existing = collection
+            .find_one(doc! { "_id": id })
+            .session(&mut *session)
+            .await
+            .change_context(TopicRepoError::Upsert)?;
+        let parent_id = topic.parent_id.map(|p| p.0);
 
-        for (i, create_req) in create_requests.into_iter().enumerate() {
-            let Some(id) = result.inserted_ids.remove(&i) else {
-                error!("failed to match inserted id to topic status");
-                topics.push(
-                    Err(TopicRepoError::Create(CreateErrorType::MatchFailure).into_report())
-                        as RepoResult<Topic<Self::TopicId>>,
-                );
-                continue;
-            };
+        let result = match existing {
+            Some(existing) => {
+                let set = doc! {
+                    "parent_id": parent_id,
+                    "name": topic.name,
+                    "description": topic.description,
+                    "attributes": bson::to_bson(&topic.attributes)
+                        .change_context(TopicRepoError::Upsert)?,
+                    "updated": now.to_rfc3339(),
+                };
+                let options = FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build();
 
-            match id.as_object_id() {
-                Some(id) => {
-                    topics.push(Ok(Topic::new(
-                        TopicId(id),
-                        create_req.name,
-                        create_req.description,
-                        create_req.created,
-                        None,
-                    )));
-                    persisted_topics += 1;
-                }
-                None => {
-                    error!("topic was created but the id given back was not an Object ID: {id:?}");
-                    topics.push(Err(
-                        TopicRepoError::Create(CreateErrorType::MatchFailure).into_report()
-                    ));
-                }
+                // only matches while the topic is still at the version that was read, and leaves
+                // its status and tags alone
+                collection
+                    .find_one_and_update(
+                        doc! { "_id": id, "version": version_condition(existing.version) },
+                        update_document(set, Document::new()),
+                    )
+                    .with_options(options)
+                    .session(&mut *session)
+                    .await
+                    .map(|topic| {
+                        topic.map(|topic| MongoUpserted {
+                            topic,
+                            created: false,
+                        })
+                    })
             }
-        }
+            None => {
+                let created = MongoTopic {
+                    id,
+                    parent_id,
+                    name: topic.name,
+                    description: topic.description,
+                    attributes: topic.attributes,
+                    status: TopicStatus::default(),
+                    created: now,
+                    updated: None,
+                    tags: Vec::new(),
+                    version: 1,
+                };
+                let mut insert =
+                    bson::to_document(&created).change_context(TopicRepoError::Upsert)?;
+                insert.remove("_id");
 
-        debug!("successfully persisted {} new topics", persisted_topics);
+                // only inserts while there's still no topic with the id
+                collection
+                    .update_one(doc! { "_id": id }, doc! { "$setOnInsert": insert })
+                    .upsert(true)
+                    .session(&mut *session)
+                    .await
+                    .map(|result| {
+                        result.upserted_id.is_some().then_some(MongoUpserted {
+                            topic: created,
+                            created: true,
+                        })
+                    })
+            }
+        };
 
-        Ok(topics)
+        match result {
+            Ok(upserted) => Ok(upserted),
+            Err(e) if is_duplicate_key(&e) => {
+                Err(e.into_report()).change_context(TopicRepoError::DuplicateName)
+            }
+            Err(e) => Err(e.into_report()).change_context(TopicRepoError::Upsert),
+        }
     }
 
-    async fn patch(
+    /// Runs `write` on a session. When the server can run transactions it's run in one, so the
+    /// versions `write` keeps are committed along with its change, and it's tried again if it
+    /// loses to another transaction. A write that `moves` a topic under a parent first bumps the
+    /// one hierarchy lock document, so moves conflict with each other and a `check_move` made in
+    /// `write` can't race another move into a cycle. A standalone server can't run transactions,
+    /// so there nothing serialises moves and a failure after the change loses its version.
+    async fn versioned_write<R, F>(
         &self,
-        id: Self::TopicId,
-        patch: PatchTopic,
-    ) -> OptRepoResult<Topic<Self::TopicId>> {
-        let mut update_document = Document::new();
-        if let Some(name) = patch.name {
-            update_document.insert("name", name);
+        moves: bool,
+        on_fail: TopicRepoError,
+        mut write: F,
+    ) -> RepoResult<R>
+    where
+        R: Send,
+        F: for<'s> FnMut(&'s mut ClientSession) -> BoxFuture<'s, RepoResult<R>> + Send,
+    {
+        let mut session = self.session(on_fail).await?;
+
+        if !self.supports_transactions().await.change_context(on_fail)? {
+            return write(&mut session).await;
         }
 
-        if let Field::Present(desc) = patch.description {
-            match desc {
-                Some(d) => {
-                    update_document.insert("description", d);
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .versioned_transaction(&mut session, moves, on_fail, &mut write)
+                .await;
+            match result {
+                Err(e) if attempt < WRITE_ATTEMPTS && is_transient(&e) => {
+                    debug!("retrying a topic write that conflicted with another: {e:?}");
+                    attempt += 1;
                 }
-                None => {
-                    update_document.insert("description", Bson::Null);
+                Err(e) if is_transient(&e) => {
+                    return Err(e.change_context(TopicRepoError::ConcurrentChange));
                 }
+                result => return result,
             }
         }
+    }
 
-        if update_document.is_empty() {
-            warn!("no topic patch fields specified, returning existing topic");
-            return self.get(id).await.change_context(TopicRepoError::Patch);
-        }
-
-        update_document.insert("updated", Utc::now().to_rfc3339());
+    async fn versioned_transaction<R, F>(
+        &self,
+        session: &mut ClientSession,
+        moves: bool,
+        on_fail: TopicRepoError,
+        write: &mut F,
+    ) -> RepoResult<R>
+    where
+        F: for<'s> FnMut(&'s mut ClientSession) -> BoxFuture<'s, RepoResult<R>>,
+    {
+        session.start_transaction().await.change_context(on_fail)?;
 
-        debug!("Updating document {:?}", update_document);
+        let locked = if moves {
+            self.db
+                .collection::<Document>(TOPIC_HIERARCHY_LOCK_COLLECTION_NAME)
+                .update_one(
+                    doc! { "_id": "hierarchy" },
+                    doc! { "$inc": { "moves": 1_i64 } },
+                )
+                .upsert(true)
+                .session(&mut *session)
+                .await
+                .map(|_| ())
+                .change_context(on_fail)
+        } else {
+            Ok(())
+        };
+        let result = match locked {
+            Ok(()) => write(&mut *session).await,
+            Err(e) => Err(e),
+        };
 
-        let options = FindOneAndUpdateOptions::builder()
-            .return_document(ReturnDocument::After)
-            .build();
+        match result {
+            Ok(written) => {
+                session.commit_transaction().await.change_context(on_fail)?;
+                Ok(written)
+            }
+            Err(e) => {
+                if let Err(abort_err) = session.abort_transaction().await {
+                    warn!("failed to abort topic write transaction: {abort_err}");
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Inserts the topics along with their first versions in one transaction. A topic the server
+    /// refuses, like one with a taken name, aborts the transaction, so it's left out and the rest
+    /// are tried again. Returns the error code of each refused topic by its index in `chunk`
+    async fn create_chunk(
+        &self,
+        chunk: &[NewTopicCreated],
+        on_fail: TopicRepoError,
+    ) -> RepoResult<HashMap<usize, i32>> {
+        let mut refused = HashMap::new();
+        loop {
+            let pending: Vec<_> = (0..chunk.len())
+                .filter(|i| !refused.contains_key(i))
+                .collect();
+            if pending.is_empty() {
+                return Ok(refused);
+            }
+
+            let topics: Vec<_> = pending.iter().map(|&i| chunk[i].clone()).collect();
+            let result = self
+                .versioned_write(false, on_fail, |session| {
+                    let repo = self.clone();
+                    let topics = topics.clone();
+                    Box::pin(async move {
+                        let result = repo
+                            .db
+                            .collection::<NewTopicCreated>(TOPICS_COLLECTION_NAME)
+                            .insert_many(&topics)
+                            .ordered(false)
+                            .session(&mut *session)
+                            .await;
+                        if let Err(e) = result {
+                            let report = match refused_inserts(&e) {
+                                Some(refused) => e
+                                    .into_report()
+                                    .change_context(on_fail)
+                                    .attach_opaque(RefusedTopics(refused)),
+                                None => e.into_report().change_context(on_fail),
+                            };
+                            return Err(report);
+                        }
+
+                        let versions = topics
+                            .into_iter()
+                            .map(|t| first_version(&t.into_topic()))
+                            .collect();
+                        repo.record_versions(session, versions, on_fail).await
+                    })
+                })
+                .await;
+
+            match result {
+                Ok(()) => return Ok(refused),
+                Err(e) => {
+                    let codes = match e.downcast_ref::<RefusedTopics>() {
+                        Some(RefusedTopics(codes)) if !codes.is_empty() => codes,
+                        _ => return Err(e),
+                    };
+                    for &(i, code) in codes {
+                        refused.insert(pending[i], code);
+                    }
+                }
+            }
+        }
+    }
+}
 
+impl TopicRepository for TopicRepo {
+    type TopicId = TopicId;
+
+    async fn get(&self, id: Self::TopicId) -> OptRepoResult<Topic<Self::TopicId>> {
         self.db
             .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
-            .find_one_and_update(doc! { "_id": id }, doc! { "$set": update_document })
+            .find_one(doc! { "_id": id })
+            .await
+            .change_context(TopicRepoError::Get)
+            .map(|t| t.map(From::from))
+    }
+
+    async fn get_many(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<Option<Topic<Self::TopicId>>>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let requested = ids.iter().map(|id| id.0).collect::<Vec<_>>();
+        let mut cursor = self
+            .db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find(doc! { "_id": { "$in": requested } })
+            .await
+            .change_context(TopicRepoError::Get)?;
+
+        let mut found = HashMap::with_capacity(ids.len());
+        while let Some(topic) = cursor.next().await {
+            let topic: Topic<TopicId> = topic.change_context(TopicRepoError::Get)?.into();
+            found.insert(topic.id.0, topic);
+        }
+
+        Ok(ids.iter().map(|id| found.get(&id.0).cloned()).collect())
+    }
+
+    async fn list(
+        &self,
+        list_criteria: TopicListCriteria,
+    ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
+        self.list_stream(list_criteria)
+            .await?
+            .collect::<Result<_, _>>()
+            .await
+    }
+
+    async fn list_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+    ) -> RepoResult<impl Stream<Item = RepoResult<Topic<Self::TopicId>>> + Send + 'static + use<>>
+    {
+        let options = page_options(&list_criteria)?;
+
+        let cursor = self
+            .db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find(list_filter(&list_criteria))
+            .with_options(options)
+            .await
+            .change_context(TopicRepoError::List)?;
+
+        Ok(cursor.map(|t| t.map(From::from).change_context(TopicRepoError::List)))
+    }
+
+    async fn list_fields_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+        fields: Vec<TopicField>,
+    ) -> RepoResult<
+        impl Stream<Item = RepoResult<PartialTopic<Self::TopicId>>> + Send + 'static + use<>,
+    > {
+        let options = page_options(&list_criteria)?;
+
+        let cursor = self
+            .db
+            .collection::<MongoPartialTopic>(TOPICS_COLLECTION_NAME)
+            .find(list_filter(&list_criteria))
+            .with_options(options)
+            .projection(projection(&fields))
+            .await
+            .change_context(TopicRepoError::List)?;
+
+        Ok(cursor.map(move |t| {
+            t.map(|t| t.into_partial(&fields))
+                .change_context(TopicRepoError::List)
+        }))
+    }
+
+    async fn children(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let options = page_options(&list_criteria)?;
+
+        let children: Vec<Topic<TopicId>> = self
+            .db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find(doc! { "parent_id": id })
             .with_options(options)
+            .sort(doc! { "created": 1, "_id": 1 })
             .await
-            .change_context(TopicRepoError::Patch)
+            .change_context(TopicRepoError::List)?
             .map(|t| t.map(From::from))
+            .collect::<Result<_, _>>()
+            .await
+            .change_context(TopicRepoError::List)?;
+
+        // no children could also mean there's no topic
+        if children.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(children))
     }
 
-    async fn delete(&self, id: Self::TopicId) -> OptRepoResult<()> {
-        let result = self
+    async fn ancestors(&self, id: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        // the root is the furthest away
+        let ancestors = self
+            .graph_lookup(
+                id,
+                "parent_id",
+                "_id",
+                doc! { "depth": -1 },
+                TopicRepoError::Get,
+            )
+            .await?;
+
+        if ancestors.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(ancestors))
+    }
+
+    async fn subtree(&self, id: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let Some(topic) = self.get(id).await? else {
+            return Ok(None);
+        };
+
+        let descendants = self
+            .graph_lookup(
+                id,
+                "_id",
+                "parent_id",
+                doc! { "depth": 1, "created": 1, "_id": 1 },
+                TopicRepoError::Get,
+            )
+            .await?;
+
+        let mut subtree = Vec::with_capacity(descendants.len() + 1);
+        subtree.push(topic);
+        subtree.extend(descendants);
+        Ok(Some(subtree))
+    }
+
+    async fn create(&self, new_topic: NewTopic<Self::TopicId>) -> RepoResult<Topic<Self::TopicId>> {
+        let on_fail = TopicRepoError::Create(CreateErrorType::DbError);
+        self.check_parent(new_topic.parent_id, on_fail).await?;
+
+        let topic = NewTopicCreated::new(new_topic, Utc::now());
+
+        self.versioned_write(false, on_fail, |session| {
+            let repo = self.clone();
+            let topic = topic.clone();
+            Box::pin(async move {
+                let result = repo
+                    .db
+                    .collection::<NewTopicCreated>(TOPICS_COLLECTION_NAME)
+                    .insert_one(&topic)
+                    .session(&mut *session)
+                    .await;
+
+                if let Err(e) = result {
+                    let context = if is_duplicate_key(&e) {
+                        TopicRepoError::DuplicateName
+                    } else {
+                        on_fail
+                    };
+                    return Err(e.into_report()).change_context(context);
+                }
+
+                let topic = topic.into_topic();
+                repo.record_versions(session, vec![first_version(&topic)], on_fail)
+                    .await?;
+                Ok(topic)
+            })
+        })
+        .await
+    }
+
+    async fn create_many(
+        &self,
+        new_topics: Vec<NewTopic<Self::TopicId>>,
+    ) -> RepoResult<Vec<RepoResult<Topic<Self::TopicId>>>> {
+        if new_topics.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let total = new_topics.len();
+        let on_fail = TopicRepoError::Create(CreateErrorType::DbError);
+
+        let transactions = self.supports_transactions().await.change_context(on_fail)?;
+
+        // topics with a bad parent fail on their own without being sent to the server
+        let mut rejected = HashMap::new();
+        let mut create_requests = Vec::with_capacity(total);
+        for (i, new_topic) in new_topics.into_iter().enumerate() {
+            match self.check_parent(new_topic.parent_id, on_fail).await {
+                Ok(()) => create_requests.push(NewTopicCreated::new(new_topic, Utc::now())),
+                Err(e) => {
+                    rejected.insert(i, e);
+                }
+            }
+        }
+
+        let failed: HashMap<usize, i32> = if transactions {
+            // each chunk is inserted in a transaction, so the first versions are kept along with
+            // the topics
+            let mut failed = HashMap::new();
+            for (chunk_index, chunk) in create_requests.chunks(CREATE_MANY_CHUNK_SIZE).enumerate() {
+                let offset = chunk_index * CREATE_MANY_CHUNK_SIZE;
+                failed.extend(
+                    self.create_chunk(chunk, on_fail)
+                        .await?
+                        .into_iter()
+                        .map(|(i, code)| (offset + i, code)),
+                );
+            }
+            failed
+        } else if create_requests.is_empty() {
+            HashMap::new()
+        } else {
+            // unordered, so one duplicate name doesn't stop the rest from being inserted
+            let result = self
+                .db
+                .collection::<NewTopicCreated>(TOPICS_COLLECTION_NAME)
+                .insert_many(&create_requests)
+                .ordered(false)
+                .await;
+            match result {
+                Ok(_) => HashMap::new(),
+                Err(e) => match refused_inserts(&e) {
+                    Some(refused) => refused.into_iter().collect(),
+                    None => return Err(e.into_report()).change_context(on_fail),
+                },
+            }
+        };
+
+        let mut topics = Vec::with_capacity(total);
+        let mut inserted = create_requests.into_iter().enumerate();
+
+        for i in 0..total {
+            if let Some(e) = rejected.remove(&i) {
+                topics.push(Err(e));
+                continue;
+            }
+
+            let Some((insert_index, create_req)) = inserted.next() else {
+                break;
+            };
+            match failed.get(&insert_index) {
+                None => topics.push(Ok(create_req.into_topic())),
+                Some(&DUPLICATE_KEY_CODE) => {
+                    topics.push(Err(TopicRepoError::DuplicateName.into_report()))
+                }
+                Some(code) => {
+                    error!("topic {i} failed to be inserted with code {code}");
+                    topics.push(Err(on_fail.into_report()));
+                }
+            }
+        }
+
+        if !transactions {
+            let versions = topics.iter().flatten().map(first_version).collect();
+            let mut session = self.session(on_fail).await?;
+            self.record_versions(&mut session, versions, on_fail)
+                .await?;
+        }
+
+        debug!(
+            "successfully persisted {} new topics",
+            topics.iter().filter(|t| t.is_ok()).count()
+        );
+
+        Ok(topics)
+    }
+
+    async fn create_many_atomic(
+        &self,
+        new_topics: Vec<NewTopic<Self::TopicId>>,
+    ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
+        if new_topics.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let on_fail = TopicRepoError::Create(CreateErrorType::DbError);
+        for new_topic in &new_topics {
+            self.check_parent(new_topic.parent_id, on_fail).await?;
+        }
+
+        let created = Utc::now();
+        let create_requests = new_topics
+            .into_iter()
+            .map(|t| NewTopicCreated::new(t, created))
+            .collect::<Vec<_>>();
+        let topics: Vec<_> = create_requests
+            .iter()
+            .cloned()
+            .map(NewTopicCreated::into_topic)
+            .collect();
+        let versions: Vec<_> = topics.iter().map(first_version).collect();
+        let collection = self
+            .db
+            .collection::<NewTopicCreated>(TOPICS_COLLECTION_NAME);
+
+        let mut session = self.session(on_fail).await?;
+        let transactions = self.supports_transactions().await.change_context(on_fail)?;
+        let result = if transactions {
+            session.start_transaction().await.change_context(on_fail)?;
+
+            match collection
+                .insert_many(&create_requests)
+                .session(&mut session)
+                .await
+            {
+                Ok(_) => {
+                    // the first versions are kept along with the topics
+                    let recorded = self
+                        .record_versions(&mut session, versions.clone(), on_fail)
+                        .await;
+                    if let Err(e) = recorded {
+                        if let Err(abort_err) = session.abort_transaction().await {
+                            warn!("failed to abort topic create transaction: {abort_err}");
+                        }
+                        return Err(e);
+                    }
+                    session.commit_transaction().await
+                }
+                Err(e) => {
+                    if let Err(abort_err) = session.abort_transaction().await {
+                        warn!("failed to abort topic create transaction: {abort_err}");
+                    }
+                    Err(e)
+                }
+            }
+        } else {
+            // a standalone server can't run transactions, so undo whatever the ordered insert
+            // managed before it failed
+            let result = collection.insert_many(&create_requests).await;
+            if result.is_err() {
+                let ids = create_requests.iter().map(|t| t.id).collect::<Vec<_>>();
+                collection
+                    .delete_many(doc! { "_id": { "$in": ids } })
+                    .await
+                    .change_context(on_fail)
+                    .attach("failed to remove topics from a partially failed insert")?;
+            }
+            result.map(|_| ())
+        };
+
+        if let Err(e) = result {
+            let context = if is_duplicate_key(&e) {
+                TopicRepoError::DuplicateName
+            } else {
+                on_fail
+            };
+            return Err(e.into_report()).change_context(context);
+        }
+
+        debug!("atomically persisted {} new topics", create_requests.len());
+
+        if !transactions {
+            self.record_versions(&mut session, versions, on_fail)
+                .await?;
+        }
+        Ok(topics)
+    }
+
+    async fn upsert(
+        &self,
+        id: Self::TopicId,
+        topic: NewTopic<Self::TopicId>,
+    ) -> RepoResult<Upserted<Topic<Self::TopicId>>> {
+        let parent_id = topic.parent_id;
+
+        for _ in 0..WRITE_ATTEMPTS {
+            let now = Utc::now();
+            let topic = topic.clone();
+            let upserted = self
+                .versioned_write(parent_id.is_some(), TopicRepoError::Upsert, |session| {
+                    let repo = self.clone();
+                    let topic = topic.clone();
+                    Box::pin(async move {
+                        repo.check_move(session, id, parent_id, TopicRepoError::Upsert)
+                            .await?;
+                        let Some(upserted) = repo.upsert_once(session, id, topic, now).await?
+                        else {
+                            return Ok(None);
+                        };
+
+                        let (topic, version) = upserted.topic.versioned(now);
+                        repo.record_versions(session, vec![version], TopicRepoError::Upsert)
+                            .await?;
+                        Ok(Some(if upserted.created {
+                            Upserted::Created(topic)
+                        } else {
+                            Upserted::Replaced(topic)
+                        }))
+                    })
+                })
+                .await?;
+
+            if let Some(upserted) = upserted {
+                return Ok(upserted);
+            }
+            debug!("topic {id} changed while it was being replaced, trying again");
+        }
+
+        Err(TopicRepoError::ConcurrentChange.into_report())
+            .attach_with(|| format!("topic {id} kept changing while it was being replaced"))
+    }
+
+    async fn patch(
+        &self,
+        id: Self::TopicId,
+        patch: PatchTopic<Self::TopicId>,
+    ) -> OptRepoResult<Topic<Self::TopicId>> {
+        let parent_id = match patch.parent_id {
+            Field::Present(Some(parent_id)) => Some(parent_id),
+            _ => None,
+        };
+
+        let (mut set, unset) = patch_document(patch).change_context(TopicRepoError::Patch)?;
+
+        if set.is_empty() && unset.is_empty() {
+            warn!("no topic patch fields specified, returning existing topic");
+            return self.get(id).await.change_context(TopicRepoError::Patch);
+        }
+
+        let updated = Utc::now();
+        set.insert("updated", updated.to_rfc3339());
+
+        debug!("Updating document {:?}", set);
+
+        let update = update_document(set, unset);
+        self.versioned_write(parent_id.is_some(), TopicRepoError::Patch, |session| {
+            let repo = self.clone();
+            let update = update.clone();
+            Box::pin(async move {
+                repo.check_move(session, id, parent_id, TopicRepoError::Patch)
+                    .await?;
+
+                let options = FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build();
+
+                let topic = match repo
+                    .db
+                    .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+                    .find_one_and_update(doc! { "_id": id }, update)
+                    .with_options(options)
+                    .session(&mut *session)
+                    .await
+                {
+                    Ok(Some(topic)) => topic,
+                    Ok(None) => return Ok(None),
+                    Err(e) if is_duplicate_key(&e) => {
+                        return Err(e.into_report()).change_context(TopicRepoError::DuplicateName);
+                    }
+                    Err(e) => {
+                        return Err(e.into_report()).change_context(TopicRepoError::Patch);
+                    }
+                };
+
+                let (topic, version) = topic.versioned(updated);
+                repo.record_versions(session, vec![version], TopicRepoError::Patch)
+                    .await?;
+                Ok(Some(topic))
+            })
+        })
+        .await
+    }
+
+    async fn patch_many(
+        &self,
+        patches: Vec<(Self::TopicId, PatchTopic<Self::TopicId>)>,
+    ) -> RepoResult<Vec<OptRepoResult<Topic<Self::TopicId>>>> {
+        if patches.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // with transactions each topic is patched in its own, so its version is kept along with
+        // it and a duplicate name still only fails its own topic
+        if self
+            .supports_transactions()
+            .await
+            .change_context(TopicRepoError::Patch)?
+        {
+            let mut topics = Vec::with_capacity(patches.len());
+            for (id, patch) in patches {
+                topics.push(self.patch(id, patch).await);
+            }
+            return Ok(topics);
+        }
+
+        let now = Utc::now();
+        let updated = now.to_rfc3339();
+        let ids = patches.iter().map(|(id, _)| id.0).collect::<Vec<_>>();
+
+        // patches that change nothing are left out of the command, so keep track of which
+        // topic each update statement belongs to
+        let mut statement_indexes = Vec::with_capacity(patches.len());
+        let mut updates = Vec::with_capacity(patches.len());
+        let mut rejected = HashMap::new();
+        // moves under a parent have to be checked for cycles along with the write, so they go
+        // through one at a time
+        let mut moved = HashMap::new();
+
+        for (i, (id, patch)) in patches.into_iter().enumerate() {
+            if let Field::Present(Some(_)) = patch.parent_id {
+                moved.insert(i, self.patch(id, patch).await);
+                continue;
+            }
+
+            let (mut set, unset) = match patch_document(patch) {
+                Ok(documents) => documents,
+                Err(e) => {
+                    rejected.insert(i, e.into_report().change_context(TopicRepoError::Patch));
+                    continue;
+                }
+            };
+            if set.is_empty() && unset.is_empty() {
+                continue;
+            }
+            set.insert("updated", updated.clone());
+            statement_indexes.push(i);
+            updates.push(doc! { "q": { "_id": id }, "u": update_document(set, unset) });
+        }
+
+        let mut failed = HashMap::new();
+
+        if !updates.is_empty() {
+            // one unordered command, so a duplicate name only fails its own topic
+            let response = self
+                .db
+                .run_command(doc! {
+                    "update": TOPICS_COLLECTION_NAME,
+                    "updates": updates,
+                    "ordered": false,
+                })
+                .await
+                .change_context(TopicRepoError::Patch)?;
+
+            let response: UpdateCommandResponse =
+                bson::from_document(response).change_context(TopicRepoError::Patch)?;
+
+            if let Some(e) = response.write_concern_error {
+                return Err(TopicRepoError::Patch.into_report())
+                    .attach_with(|| format!("write concern error: {e}"));
+            }
+
+            for e in response.write_errors {
+                if let Some(&i) = statement_indexes.get(e.index) {
+                    failed.insert(i, e.code);
+                }
+            }
+        }
+
+        let found: HashMap<ObjectId, (Topic<TopicId>, MongoTopicVersion)> = self
             .db
-            .collection::<Topic<Self::TopicId>>(TOPICS_COLLECTION_NAME)
-            .delete_one(doc! { "_id": id })
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find(doc! { "_id": { "$in": ids.clone() } })
+            .await
+            .change_context(TopicRepoError::Patch)?
+            .map(|t| t.map(|t| (t.id.0, t.versioned(now))))
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .change_context(TopicRepoError::Patch)?
+            .into_iter()
+            .collect();
+
+        // only the topics an update statement went through for are at a new version
+        let versions = statement_indexes
+            .iter()
+            .copied()
+            .filter(|i| !failed.contains_key(i))
+            .filter_map(|i| found.get(&ids[i]))
+            .map(|(_, version)| version.clone())
+            .collect();
+        let mut session = self.session(TopicRepoError::Patch).await?;
+        self.record_versions(&mut session, versions, TopicRepoError::Patch)
+            .await?;
+
+        let topics = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                if let Some(e) = rejected.remove(&i) {
+                    return Err(e);
+                }
+                if let Some(patched) = moved.remove(&i) {
+                    return patched;
+                }
+                match failed.get(&i) {
+                    None => Ok(found.get(id).map(|(topic, _)| topic.clone())),
+                    Some(&DUPLICATE_KEY_CODE) => Err(TopicRepoError::DuplicateName.into_report()),
+                    Some(code) => {
+                        error!("topic {i} failed to be patched with code {code}");
+                        Err(TopicRepoError::Patch.into_report())
+                    }
+                }
+            })
+            .collect();
+
+        Ok(topics)
+    }
+
+    async fn delete(&self, id: Self::TopicId, policy: DeletePolicy) -> OptRepoResult<()> {
+        let collection = self.db.collection::<MongoTopic>(TOPICS_COLLECTION_NAME);
+
+        let Some(topic) = self.get(id).await.change_context(TopicRepoError::Delete)? else {
+            return Ok(None);
+        };
+
+        // without a transaction the subtree is deleted in one go, so a failure can't leave
+        // topics pointing at a parent that's gone
+        let mut ids = vec![id.0];
+        match policy {
+            DeletePolicy::Reject => {
+                let children = collection
+                    .count_documents(doc! { "parent_id": id })
+                    .await
+                    .change_context(TopicRepoError::Delete)?;
+                if children > 0 {
+                    return Err(TopicRepoError::HasChildren.into_report())
+                        .attach_with(|| format!("topic {id} has {children} children"));
+                }
+            }
+            DeletePolicy::Cascade => {
+                let descendants = self
+                    .graph_lookup(
+                        id,
+                        "_id",
+                        "parent_id",
+                        doc! { "depth": 1 },
+                        TopicRepoError::Delete,
+                    )
+                    .await?;
+                ids.extend(descendants.iter().map(|t| t.id.0));
+            }
+            DeletePolicy::Reparent => {
+                let children: Vec<ObjectId> = collection
+                    .distinct("_id", doc! { "parent_id": id })
+                    .await
+                    .change_context(TopicRepoError::Delete)?
+                    .into_iter()
+                    .filter_map(|id| id.as_object_id())
+                    .collect();
+                let now = Utc::now();
+                let grandparent_id = topic.parent_id.map(|p| p.0);
+                self.versioned_write(true, TopicRepoError::Delete, |session| {
+                    let repo = self.clone();
+                    let children = children.clone();
+                    Box::pin(async move {
+                        let collection = repo.db.collection::<MongoTopic>(TOPICS_COLLECTION_NAME);
+                        collection
+                            .update_many(
+                                doc! { "_id": { "$in": children.clone() } },
+                                update_document(
+                                    doc! {
+                                        "parent_id": grandparent_id,
+                                        "updated": now.to_rfc3339(),
+                                    },
+                                    Document::new(),
+                                ),
+                            )
+                            .session(&mut *session)
+                            .await
+                            .change_context(TopicRepoError::Delete)?;
+
+                        let mut moved = collection
+                            .find(doc! { "_id": { "$in": children } })
+                            .session(&mut *session)
+                            .await
+                            .change_context(TopicRepoError::Delete)?;
+                        let mut versions = Vec::new();
+                        while let Some(child) = moved.next(&mut *session).await {
+                            versions.push(
+                                child
+                                    .change_context(TopicRepoError::Delete)?
+                                    .versioned(now)
+                                    .1,
+                            );
+                        }
+                        repo.record_versions(session, versions, TopicRepoError::Delete)
+                            .await
+                    })
+                })
+                .await?;
+            }
+        }
+
+        let result = collection
+            .delete_many(doc! { "_id": { "$in": ids.clone() } })
             .await
             .change_context(TopicRepoError::Delete)?;
+        self.delete_status_changes(ids.clone()).await?;
+        self.delete_versions(ids.clone()).await?;
+        self.delete_links_touching(ids).await?;
+
+        Ok((result.deleted_count > 0).then_some(()))
+    }
+
+    async fn delete_many(&self, ids: Vec<Self::TopicId>) -> RepoResult<Vec<OptRepoResult<()>>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let collection = self.db.collection::<MongoTopic>(TOPICS_COLLECTION_NAME);
+        let requested = ids.iter().map(|id| id.0).collect::<Vec<_>>();
+
+        // delete_many only reports a count, so find out which of the topics exist first
+        let existing: HashSet<ObjectId> = collection
+            .distinct("_id", doc! { "_id": { "$in": requested.clone() } })
+            .await
+            .change_context(TopicRepoError::Delete)?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+
+        // a topic's children have to be deleted along with it
+        let children: Vec<(ObjectId, ObjectId)> = collection
+            .find(doc! { "parent_id": { "$in": requested.clone() } })
+            .await
+            .change_context(TopicRepoError::Delete)?
+            .map(|t| t.map(|t| t.parent_id.map(|parent_id| (t.id.0, parent_id))))
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .change_context(TopicRepoError::Delete)?
+            .into_iter()
+            .flatten()
+            .collect();
+        let undeletable = undeletable_parents(&requested, &children);
+
+        let deletable = existing
+            .iter()
+            .copied()
+            .filter(|id| !undeletable.contains(id))
+            .collect::<Vec<_>>();
+        if !deletable.is_empty() {
+            collection
+                .delete_many(doc! { "_id": { "$in": deletable.clone() } })
+                .await
+                .change_context(TopicRepoError::Delete)?;
+            self.delete_status_changes(deletable.clone()).await?;
+            self.delete_versions(deletable.clone()).await?;
+            self.delete_links_touching(deletable).await?;
+        }
+
+        Ok(ids
+            .iter()
+            .map(|id| {
+                if undeletable.contains(&id.0) {
+                    Err(TopicRepoError::HasChildren.into_report()).attach_with(|| {
+                        format!("topic {id} has children that aren't being deleted")
+                    })
+                } else {
+                    Ok(existing.contains(&id.0).then_some(()))
+                }
+            })
+            .collect())
+    }
+
+    async fn create_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        if source_id == target_id {
+            return Err(TopicRepoError::SelfLink.into_report());
+        }
+
+        if self.get(source_id).await?.is_none() {
+            return Ok(None);
+        }
+        if self.get(target_id).await?.is_none() {
+            return Err(TopicRepoError::LinkTargetNotFound.into_report())
+                .attach_with(|| format!("topic {target_id} doesn't exist"));
+        }
+
+        let link = MongoTopicLink {
+            key: MongoLinkKey {
+                source_id: source_id.0,
+                target_id: target_id.0,
+            },
+            link_type,
+            created: Utc::now(),
+            updated: None,
+        };
+
+        // the key is the _id, so a second link between the same topics is a duplicate key
+        match self
+            .db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .insert_one(&link)
+            .await
+        {
+            Ok(_) => Ok(Some(link.into())),
+            Err(e) if is_duplicate_key(&e) => {
+                Err(e.into_report()).change_context(TopicRepoError::LinkExists)
+            }
+            Err(e) => Err(e.into_report()).change_context(TopicRepoError::Link),
+        }
+    }
+
+    async fn get_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        self.db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .find_one(link_filter(source_id, target_id))
+            .await
+            .change_context(TopicRepoError::Link)
+            .map(|l| l.map(From::from))
+    }
+
+    async fn update_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        self.db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .find_one_and_update(
+                link_filter(source_id, target_id),
+                doc! {
+                    "$set": {
+                        "link_type": link_type.name(),
+                        "updated": Utc::now().to_rfc3339(),
+                    }
+                },
+            )
+            .with_options(options)
+            .await
+            .change_context(TopicRepoError::Link)
+            .map(|l| l.map(From::from))
+    }
+
+    async fn delete_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> OptRepoResult<()> {
+        let result = self
+            .db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .delete_one(link_filter(source_id, target_id))
+            .await
+            .change_context(TopicRepoError::Link)?;
 
         Ok((result.deleted_count > 0).then_some(()))
     }
+
+    async fn links(
+        &self,
+        id: Self::TopicId,
+        direction: LinkDirection,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicLink<Self::TopicId>>> {
+        let options = page_options(&list_criteria)?;
+        let filter = match direction {
+            LinkDirection::Outgoing => doc! { "_id.source_id": id },
+            LinkDirection::Incoming => doc! { "_id.target_id": id },
+            LinkDirection::Both => {
+                doc! { "$or": [{ "_id.source_id": id }, { "_id.target_id": id }] }
+            }
+        };
+
+        let links: Vec<TopicLink<TopicId>> = self
+            .db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .find(filter)
+            .with_options(options)
+            .sort(doc! { "created": 1, "_id.source_id": 1, "_id.target_id": 1 })
+            .await
+            .change_context(TopicRepoError::Link)?
+            .map(|l| l.map(From::from))
+            .collect::<Result<_, _>>()
+            .await
+            .change_context(TopicRepoError::Link)?;
+
+        // no links could also mean there's no topic
+        if links.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(links))
+    }
+
+    async fn links_touching(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<TopicLink<Self::TopicId>>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids = ids.iter().map(|id| id.0).collect::<Vec<_>>();
+
+        self.db
+            .collection::<MongoTopicLink>(TOPIC_LINKS_COLLECTION_NAME)
+            .find(links_touching_filter(ids))
+            .await
+            .change_context(TopicRepoError::Link)?
+            .map(|l| l.map(From::from))
+            .collect::<Result<_, _>>()
+            .await
+            .change_context(TopicRepoError::Link)
+    }
+
+    async fn tags(&self, id: Self::TopicId) -> OptRepoResult<Vec<String>> {
+        let topic = self
+            .db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find_one(doc! { "_id": id })
+            .await
+            .change_context(TopicRepoError::Tags)?;
+
+        Ok(topic.map(MongoTopic::sorted_tags))
+    }
+
+    async fn add_tags(&self, id: Self::TopicId, tags: Vec<String>) -> OptRepoResult<Vec<String>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let topic = self
+            .db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": id },
+                doc! { "$addToSet": { "tags": { "$each": tags } } },
+            )
+            .with_options(options)
+            .await
+            .change_context(TopicRepoError::Tags)?;
+
+        Ok(topic.map(MongoTopic::sorted_tags))
+    }
+
+    async fn remove_tags(
+        &self,
+        id: Self::TopicId,
+        tags: Vec<String>,
+    ) -> OptRepoResult<Vec<String>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let topic = self
+            .db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": id },
+                doc! { "$pull": { "tags": { "$in": tags } } },
+            )
+            .with_options(options)
+            .await
+            .change_context(TopicRepoError::Tags)?;
+
+        Ok(topic.map(MongoTopic::sorted_tags))
+    }
+
+    async fn tag_counts(&self) -> RepoResult<Vec<TagCount>> {
+        let pipeline = vec![
+            doc! { "$unwind": "$tags" },
+            doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+
+        self.db
+            .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+            .aggregate(pipeline)
+            .with_type::<MongoTagCount>()
+            .await
+            .change_context(TopicRepoError::Tags)?
+            .map(|c| {
+                c.map(|c| TagCount {
+                    tag: c.tag,
+                    count: c.count as u64,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .await
+            .change_context(TopicRepoError::Tags)
+    }
+
+    async fn change_status(
+        &self,
+        id: Self::TopicId,
+        change: StatusChange,
+    ) -> OptRepoResult<Topic<Self::TopicId>> {
+        let changed = self
+            .versioned_write(false, TopicRepoError::Status, |session| {
+                let repo = self.clone();
+                let change = change.clone();
+                Box::pin(async move {
+                    let options = FindOneAndUpdateOptions::builder()
+                        .return_document(ReturnDocument::After)
+                        .build();
+
+                    // only matches while the topic is still in the status it's moving from
+                    let topic = repo
+                        .db
+                        .collection::<MongoTopic>(TOPICS_COLLECTION_NAME)
+                        .find_one_and_update(
+                            doc! { "_id": id, "status": status_condition(&[change.from]) },
+                            update_document(
+                                doc! {
+                                    "status": change.to.name(),
+                                    "updated": change.changed.to_rfc3339(),
+                                },
+                                Document::new(),
+                            ),
+                        )
+                        .with_options(options)
+                        .session(&mut *session)
+                        .await
+                        .change_context(TopicRepoError::Status)?;
+
+                    let Some(topic) = topic else {
+                        return Ok(None);
+                    };
+
+                    let (topic, version) = topic.versioned(change.changed);
+                    repo.record_versions(&mut *session, vec![version], TopicRepoError::Status)
+                        .await?;
+                    repo.db
+                        .collection::<MongoStatusChange>(TOPIC_STATUS_CHANGES_COLLECTION_NAME)
+                        .insert_one(MongoStatusChange::new(id, change))
+                        .session(&mut *session)
+                        .await
+                        .change_context(TopicRepoError::Status)?;
+
+                    Ok(Some(topic))
+                })
+            })
+            .await?;
+
+        let Some(topic) = changed else {
+            // nothing changed, so tell a missing topic apart from one that's already moved on
+            return match self.get(id).await? {
+                None => Ok(None),
+                Some(topic) => Err(TopicRepoError::StatusChanged.into_report()).attach_with(|| {
+                    format!(
+                        "topic is {}, not {}",
+                        topic.status.name(),
+                        change.from.name()
+                    )
+                }),
+            };
+        };
+
+        Ok(Some(topic))
+    }
+
+    async fn status_changes(&self, id: Self::TopicId) -> OptRepoResult<Vec<StatusChange>> {
+        // object ids are generated in order, so they sort the changes oldest first
+        let options = FindOptions::builder().sort(doc! { "_id": 1 }).build();
+
+        let changes: Vec<StatusChange> = self
+            .db
+            .collection::<MongoStatusChange>(TOPIC_STATUS_CHANGES_COLLECTION_NAME)
+            .find(doc! { "topic_id": id })
+            .with_options(options)
+            .await
+            .change_context(TopicRepoError::Status)?
+            .map(|c| c.map(From::from))
+            .collect::<Result<_, _>>()
+            .await
+            .change_context(TopicRepoError::Status)?;
+
+        // no changes could also mean there's no topic
+        if changes.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(changes))
+    }
+
+    async fn versions(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicVersion<Self::TopicId>>> {
+        let options = page_options(&list_criteria)?;
+
+        let versions: Vec<TopicVersion<TopicId>> = self
+            .db
+            .collection::<MongoTopicVersion>(TOPIC_VERSIONS_COLLECTION_NAME)
+            .find(doc! { "topic_id": id })
+            .with_options(options)
+            .sort(doc! { "version": 1 })
+            .await
+            .change_context(TopicRepoError::Versions)?
+            .map(|v| v.map(From::from))
+            .collect::<Result<_, _>>()
+            .await
+            .change_context(TopicRepoError::Versions)?;
+
+        // no versions could also mean there's no topic
+        if versions.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(versions))
+    }
+
+    async fn version(
+        &self,
+        id: Self::TopicId,
+        version: u64,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        let Ok(version) = i64::try_from(version) else {
+            return Ok(None);
+        };
+
+        self.db
+            .collection::<MongoTopicVersion>(TOPIC_VERSIONS_COLLECTION_NAME)
+            .find_one(doc! { "topic_id": id, "version": version })
+            .await
+            .change_context(TopicRepoError::Versions)
+            .map(|v| v.map(From::from))
+    }
+
+    async fn version_at(
+        &self,
+        id: Self::TopicId,
+        at: DateTime<Utc>,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        self.db
+            .collection::<MongoTopicVersion>(TOPIC_VERSIONS_COLLECTION_NAME)
+            .find_one(doc! {
+                "topic_id": id,
+                "recorded": { "$lte": bson::DateTime::from_chrono(at) },
+            })
+            .sort(doc! { "version": -1 })
+            .await
+            .change_context(TopicRepoError::Versions)
+            .map(|v| v.map(From::from))
+    }
 }
@@ -0,0 +1,95 @@
+use crate::postgres::RepoInitErr;
+use crate::postgres::statements::ApiKeyStatements;
+use deadpool_postgres::{Object, Pool};
+use error_stack::{Report, ResultExt};
+use routing::api_key::{ApiKey, ApiKeyError, ApiKeyRepository, ApiKeyResult, NewApiKey};
+use std::borrow::Borrow;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ApiKeyRepo {
+    pool: Pool,
+    statements: ApiKeyStatements,
+}
+
+impl ApiKeyRepo {
+    pub async fn new(pool: Pool) -> Result<Self, Report<RepoInitErr>> {
+        let mut handle = pool.get().await.change_context(RepoInitErr::api_keys())?;
+
+        let client = &mut **handle;
+
+        Ok(Self {
+            statements: ApiKeyStatements::prepare(client)
+                .await
+                .change_context(RepoInitErr::api_keys())?,
+            pool,
+        })
+    }
+
+    async fn client(&self, on_err: ApiKeyError) -> ApiKeyResult<Object> {
+        self.pool.get().await.change_context(on_err)
+    }
+}
+
+fn row_to_api_key(row: impl Borrow<Row>) -> ApiKey {
+    let row = row.borrow();
+    ApiKey {
+        id: row.get("id"),
+        owner: row.get("owner"),
+        roles: row.get("roles"),
+        created: row.get("created"),
+        revoked: row.get("revoked"),
+    }
+}
+
+impl ApiKeyRepository for ApiKeyRepo {
+    async fn find_by_hash(&self, key_hash: &str) -> ApiKeyResult<Option<ApiKey>> {
+        Ok(self
+            .client(ApiKeyError::Find)
+            .await?
+            .query_opt(&self.statements.find_by_hash, &[&key_hash])
+            .await
+            .change_context(ApiKeyError::Find)?
+            .map(row_to_api_key))
+    }
+
+    async fn create(&self, new_key: NewApiKey) -> ApiKeyResult<ApiKey> {
+        self.client(ApiKeyError::Create)
+            .await?
+            .query_one(
+                &self.statements.create,
+                &[
+                    &new_key.id,
+                    &new_key.key_hash,
+                    &new_key.owner,
+                    &new_key.roles,
+                ],
+            )
+            .await
+            .change_context(ApiKeyError::Create)
+            .map(row_to_api_key)
+    }
+
+    async fn list(&self) -> ApiKeyResult<Vec<ApiKey>> {
+        Ok(self
+            .client(ApiKeyError::List)
+            .await?
+            .query(&self.statements.list, &[])
+            .await
+            .change_context(ApiKeyError::List)?
+            .iter()
+            .map(row_to_api_key)
+            .collect())
+    }
+
+    async fn revoke(&self, id: Uuid) -> ApiKeyResult<Option<ApiKey>> {
+        Ok(self
+            .client(ApiKeyError::Revoke)
+            .await?
+            .query_opt(&self.statements.revoke, &[&id])
+            .await
+            .change_context(ApiKeyError::Revoke)?
+            .map(row_to_api_key))
+    }
+}
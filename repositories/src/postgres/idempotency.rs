@@ -0,0 +1,119 @@
+use crate::postgres::RepoInitErr;
+use crate::postgres::statements::IdempotencyStatements;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{Object, Pool};
+use error_stack::{Report, ResultExt};
+use routing::idempotency::{
+    IdempotencyError, IdempotencyKey, IdempotencyRepository, IdempotencyResult, IdempotentRequest,
+    StoredResponse,
+};
+use tokio_postgres::Row;
+
+#[derive(Clone)]
+pub struct IdempotencyRepo {
+    pool: Pool,
+    statements: IdempotencyStatements,
+}
+
+impl IdempotencyRepo {
+    pub async fn new(pool: Pool) -> Result<Self, Report<RepoInitErr>> {
+        let mut handle = pool
+            .get()
+            .await
+            .change_context(RepoInitErr::idempotency())?;
+
+        let client = &mut **handle;
+
+        Ok(Self {
+            statements: IdempotencyStatements::prepare(client)
+                .await
+                .change_context(RepoInitErr::idempotency())?,
+            pool,
+        })
+    }
+
+    async fn client(&self, on_err: IdempotencyError) -> IdempotencyResult<Object> {
+        self.pool.get().await.change_context(on_err)
+    }
+}
+
+fn row_to_request(row: &Row) -> IdempotentRequest {
+    let status: Option<i16> = row.get("status");
+    IdempotentRequest {
+        request_hash: row.get("request_hash"),
+        response: status.map(|status| StoredResponse {
+            status: status as u16,
+            content_type: row.get("content_type"),
+            body: row.get::<_, Option<Vec<u8>>>("body").unwrap_or_default(),
+        }),
+    }
+}
+
+impl IdempotencyRepository for IdempotencyRepo {
+    async fn reserve(
+        &self,
+        key: &IdempotencyKey,
+        request_hash: &str,
+        expires: DateTime<Utc>,
+    ) -> IdempotencyResult<Option<IdempotentRequest>> {
+        let client = self.client(IdempotencyError::Reserve).await?;
+
+        let reserved = client
+            .query_opt(
+                &self.statements.reserve,
+                &[&key.owner, &key.key, &request_hash, &expires],
+            )
+            .await
+            .change_context(IdempotencyError::Reserve)?;
+        if reserved.is_some() {
+            return Ok(None);
+        }
+
+        client
+            .query_opt(&self.statements.find, &[&key.owner, &key.key])
+            .await
+            .change_context(IdempotencyError::Reserve)?
+            .map(|row| Some(row_to_request(&row)))
+            .ok_or_else(|| Report::new(IdempotencyError::Reserve))
+            .attach("the key was released while it was being reserved")
+    }
+
+    async fn complete(
+        &self,
+        key: &IdempotencyKey,
+        response: &StoredResponse,
+    ) -> IdempotencyResult<()> {
+        self.client(IdempotencyError::Complete)
+            .await?
+            .execute(
+                &self.statements.complete,
+                &[
+                    &key.owner,
+                    &key.key,
+                    &(response.status as i16),
+                    &response.content_type,
+                    &response.body,
+                ],
+            )
+            .await
+            .change_context(IdempotencyError::Complete)?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> IdempotencyResult<u64> {
+        self.client(IdempotencyError::Purge)
+            .await?
+            .execute(&self.statements.purge_expired, &[])
+            .await
+            .change_context(IdempotencyError::Purge)
+    }
+
+    async fn release(&self, key: &IdempotencyKey) -> IdempotencyResult<()> {
+        self.client(IdempotencyError::Release)
+            .await?
+            .execute(&self.statements.release, &[&key.owner, &key.key])
+            .await
+            .change_context(IdempotencyError::Release)?;
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::postgres::api_keys::ApiKeyRepo;
+use crate::postgres::idempotency::IdempotencyRepo;
 use crate::postgres::sets::SetRepo;
 use crate::postgres::topics::TopicRepo;
 use crate::postgres::{ConnectionDetails, RepoInitErr, RepoMigrationErr};
@@ -12,6 +14,14 @@ mod embedded {
     embed_migrations!("./src/postgres/migrations");
 }
 
+// opt in, so these are tracked separately from the migrations every repo runs
+mod unique_names {
+    use refinery::embed_migrations;
+    embed_migrations!("./src/postgres/unique_names_migrations");
+}
+
+const UNIQUE_NAMES_HISTORY_TABLE: &str = "refinery_unique_names_history";
+
 pub trait Init {
     type Repo;
     fn init(self, pool: Pool) -> impl Future<Output = Result<Self::Repo, Report<RepoInitErr>>>;
@@ -75,6 +85,31 @@ where
     }
 }
 
+impl<T1, T2, T3, T4> Init for (T1, T2, T3, T4)
+where
+    T1: Init,
+    T2: Init,
+    T3: Init,
+    T4: Init,
+{
+    type Repo = (T1::Repo, T2::Repo, T3::Repo, T4::Repo);
+
+    async fn init(self, pool: Pool) -> Result<Self::Repo, Report<RepoInitErr>> {
+        let r1 = self.0.init(pool.clone()).await?;
+        let r2 = self.1.init(pool.clone()).await?;
+        let r3 = self.2.init(pool.clone()).await?;
+        let r4 = self.3.init(pool).await?;
+        Ok((r1, r2, r3, r4))
+    }
+
+    async fn run_migrations(&self, client: &mut Client) -> Result<(), Report<RepoMigrationErr>> {
+        self.0.run_migrations(client).await?;
+        self.1.run_migrations(client).await?;
+        self.2.run_migrations(client).await?;
+        self.3.run_migrations(client).await
+    }
+}
+
 pub struct TopicInit;
 impl Init for TopicInit {
     type Repo = TopicRepo;
@@ -111,8 +146,45 @@ impl Init for SetInit {
     }
 }
 
+pub struct ApiKeyInit;
+impl Init for ApiKeyInit {
+    type Repo = ApiKeyRepo;
+
+    async fn init(self, pool: Pool) -> Result<Self::Repo, Report<RepoInitErr>> {
+        ApiKeyRepo::new(pool).await
+    }
+
+    async fn run_migrations(&self, client: &mut Client) -> Result<(), Report<RepoMigrationErr>> {
+        embedded::migrations::runner()
+            .run_async(client)
+            .await
+            .change_context(RepoMigrationErr)
+            .attach("api keys repo")?;
+        Ok(())
+    }
+}
+
+pub struct IdempotencyInit;
+impl Init for IdempotencyInit {
+    type Repo = IdempotencyRepo;
+
+    async fn init(self, pool: Pool) -> Result<Self::Repo, Report<RepoInitErr>> {
+        IdempotencyRepo::new(pool).await
+    }
+
+    async fn run_migrations(&self, client: &mut Client) -> Result<(), Report<RepoMigrationErr>> {
+        embedded::migrations::runner()
+            .run_async(client)
+            .await
+            .change_context(RepoMigrationErr)
+            .attach("idempotency keys repo")?;
+        Ok(())
+    }
+}
+
 pub struct RepoCreator<T: Init = ()> {
     initializer: T,
+    unique_names: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -123,6 +195,14 @@ impl<T> RepoCreator<T>
 where
     T: Init,
 {
+    /// Rejects topics whose names only differ by case, and the same for sets within a topic.
+    /// Creating the unique indexes fails if the tables already hold duplicates, and turning this
+    /// off again doesn't drop them.
+    pub fn with_unique_names(mut self) -> Self {
+        self.unique_names = true;
+        self
+    }
+
     pub async fn create(
         self,
         connection_details: ConnectionDetails,
@@ -163,13 +243,27 @@ where
 
         let client = &mut **handle;
 
-        self.initializer.run_migrations(client).await
+        self.initializer.run_migrations(client).await?;
+
+        if self.unique_names {
+            unique_names::migrations::runner()
+                .set_migration_table_name(UNIQUE_NAMES_HISTORY_TABLE)
+                .run_async(client)
+                .await
+                .change_context(RepoMigrationErr)
+                .attach("unique names")?;
+        }
+
+        Ok(())
     }
 }
 
 impl Default for RepoCreator<()> {
     fn default() -> Self {
-        Self { initializer: () }
+        Self {
+            initializer: (),
+            unique_names: false,
+        }
     }
 }
 
@@ -177,6 +271,7 @@ impl RepoCreator<()> {
     pub fn with_topics(self) -> RepoCreator<TopicInit> {
         RepoCreator {
             initializer: TopicInit,
+            unique_names: self.unique_names,
         }
     }
 
@@ -184,6 +279,7 @@ impl RepoCreator<()> {
     pub fn with_sets(self) -> RepoCreator<(TopicInit, SetInit)> {
         RepoCreator {
             initializer: (TopicInit, SetInit),
+            unique_names: self.unique_names,
         }
     }
 }
@@ -192,6 +288,50 @@ impl RepoCreator<TopicInit> {
     pub fn with_sets(self) -> RepoCreator<(TopicInit, SetInit)> {
         RepoCreator {
             initializer: (TopicInit, SetInit),
+            unique_names: self.unique_names,
+        }
+    }
+
+    pub fn with_api_keys(self) -> RepoCreator<(TopicInit, ApiKeyInit)> {
+        RepoCreator {
+            initializer: (TopicInit, ApiKeyInit),
+            unique_names: self.unique_names,
+        }
+    }
+
+    pub fn with_idempotency_keys(self) -> RepoCreator<(TopicInit, IdempotencyInit)> {
+        RepoCreator {
+            initializer: (TopicInit, IdempotencyInit),
+            unique_names: self.unique_names,
+        }
+    }
+}
+
+impl RepoCreator<(TopicInit, ApiKeyInit)> {
+    pub fn with_idempotency_keys(self) -> RepoCreator<(TopicInit, ApiKeyInit, IdempotencyInit)> {
+        RepoCreator {
+            initializer: (TopicInit, ApiKeyInit, IdempotencyInit),
+            unique_names: self.unique_names,
+        }
+    }
+}
+
+impl RepoCreator<(TopicInit, SetInit)> {
+    pub fn with_api_keys(self) -> RepoCreator<(TopicInit, SetInit, ApiKeyInit)> {
+        RepoCreator {
+            initializer: (TopicInit, SetInit, ApiKeyInit),
+            unique_names: self.unique_names,
+        }
+    }
+}
+
+impl RepoCreator<(TopicInit, SetInit, ApiKeyInit)> {
+    pub fn with_idempotency_keys(
+        self,
+    ) -> RepoCreator<(TopicInit, SetInit, ApiKeyInit, IdempotencyInit)> {
+        RepoCreator {
+            initializer: (TopicInit, SetInit, ApiKeyInit, IdempotencyInit),
+            unique_names: self.unique_names,
         }
     }
 }
@@ -68,6 +68,7 @@ pub struct InsertManyBuilder<const COLS: usize, T> {
     table: &'static str,
     col_names: [&'static str; COLS],
     value_sets: Vec<ValueSet<COLS, T>>,
+    on_conflict_do_nothing: bool,
     returning: Option<&'static [&'static str]>,
 }
 
@@ -81,6 +82,7 @@ impl<const COLS: usize, T> InsertManyBuilder<COLS, T> {
             table,
             col_names,
             value_sets: vec![starting_set],
+            on_conflict_do_nothing: false,
             returning: None,
         }
     }
@@ -90,6 +92,12 @@ impl<const COLS: usize, T> InsertManyBuilder<COLS, T> {
         self
     }
 
+    /// Rows that would violate a unique constraint are skipped, and so missing from `RETURNING`
+    pub fn on_conflict_do_nothing(&mut self) -> &mut Self {
+        self.on_conflict_do_nothing = true;
+        self
+    }
+
     pub fn returning(&mut self, cols: &'static [&'static str]) -> &mut Self {
         self.returning = Some(cols);
         self
@@ -129,6 +137,10 @@ impl<const COLS: usize, T> InsertManyBuilder<COLS, T> {
             params.extend(value_set.values.into_iter().map(|v| v.0));
         }
 
+        if self.on_conflict_do_nothing {
+            query += " ON CONFLICT DO NOTHING";
+        }
+
         if let Some(returning) = self.returning {
             query += " RETURNING ";
 
@@ -237,4 +249,16 @@ mod tests {
             insert_many.query,
         )
     }
+
+    #[test]
+    fn build_with_on_conflict_do_nothing_and_returning() {
+        let mut builder = InsertManyBuilder::new(TEST_TABLE_NAME, ["col1"], value_set!(42 => i32));
+        builder.on_conflict_do_nothing().returning(&["id"]);
+        let insert_many = builder.build();
+
+        assert_eq!(
+            "INSERT INTO test (col1) VALUES ($1) ON CONFLICT DO NOTHING RETURNING id",
+            insert_many.query,
+        )
+    }
 }
@@ -0,0 +1,90 @@
+/*
+Every insert or change of a topic or set keeps a copy of the whole row, numbered from 1 for each
+topic or set. The history goes with the topic or set when it's deleted
+ */
+create table if not exists topic_versions (
+    topic_id uuid not null,
+    version integer not null,
+    parent_id uuid,
+    name varchar(255) not null,
+    description varchar(4096),
+    attributes jsonb not null,
+    status varchar(16) not null,
+    created timestamp with time zone,
+    updated timestamp with time zone,
+    recorded timestamp with time zone not null default now(),
+    constraint tv_pk primary key (topic_id, version),
+    constraint tv_topic_fk foreign key (topic_id) references topics (id) on delete cascade
+);
+
+create index if not exists topic_versions_recorded on topic_versions (topic_id, recorded);
+
+create table if not exists set_versions (
+    set_id uuid not null,
+    version integer not null,
+    topic_id uuid,
+    name varchar(255) not null,
+    description varchar(4096),
+    attributes jsonb not null,
+    created timestamp with time zone,
+    updated timestamp with time zone,
+    recorded timestamp with time zone not null default now(),
+    constraint sv_pk primary key (set_id, version),
+    constraint sv_set_fk foreign key (set_id) references sets (id) on delete cascade
+);
+
+-- what's already there is its first version
+insert into topic_versions (topic_id, version, parent_id, name, description, attributes, status, created, updated, recorded)
+select id, 1, parent_id, name, description, attributes, status, created, updated, coalesce(updated, created, now())
+from topics
+on conflict do nothing;
+
+insert into set_versions (set_id, version, topic_id, name, description, attributes, created, updated, recorded)
+select id, 1, topic_id, name, description, attributes, created, updated, coalesce(updated, created, now())
+from sets
+on conflict do nothing;
+
+/*
+Concurrent changes to a row wait on its lock, so the next version number is read after the last
+one was committed
+ */
+create or replace function topics_record_version() returns trigger as $$
+begin
+    insert into topic_versions (topic_id, version, parent_id, name, description, attributes, status, created, updated)
+    select new.id, coalesce(max(version), 0) + 1, new.parent_id, new.name, new.description, new.attributes,
+        new.status, new.created, new.updated
+    from topic_versions where topic_id = new.id;
+    return null;
+end;
+$$ language plpgsql;
+
+drop trigger if exists topics_version_inserted on topics;
+create trigger topics_version_inserted
+    after insert on topics
+    for each row execute function topics_record_version();
+
+-- updates that leave the row as it was aren't a new version
+drop trigger if exists topics_version_updated on topics;
+create trigger topics_version_updated
+    after update on topics
+    for each row when (old.* is distinct from new.*) execute function topics_record_version();
+
+create or replace function sets_record_version() returns trigger as $$
+begin
+    insert into set_versions (set_id, version, topic_id, name, description, attributes, created, updated)
+    select new.id, coalesce(max(version), 0) + 1, new.topic_id, new.name, new.description, new.attributes,
+        new.created, new.updated
+    from set_versions where set_id = new.id;
+    return null;
+end;
+$$ language plpgsql;
+
+drop trigger if exists sets_version_inserted on sets;
+create trigger sets_version_inserted
+    after insert on sets
+    for each row execute function sets_record_version();
+
+drop trigger if exists sets_version_updated on sets;
+create trigger sets_version_updated
+    after update on sets
+    for each row when (old.* is distinct from new.*) execute function sets_record_version();
@@ -0,0 +1,8 @@
+create table if not exists api_keys (
+    id uuid primary key,
+    key_hash varchar(64) not null unique,
+    owner varchar(255) not null,
+    roles text[] not null,
+    created timestamp with time zone not null default now(),
+    revoked timestamp with time zone
+);
@@ -0,0 +1,13 @@
+create table if not exists idempotency_keys (
+    owner varchar(255) not null,
+    key varchar(255) not null,
+    request_hash varchar(64) not null,
+    status smallint,
+    content_type varchar(255),
+    body bytea,
+    created timestamp with time zone not null default now(),
+    expires timestamp with time zone not null,
+    primary key (owner, key)
+);
+
+create index if not exists idempotency_keys_expires on idempotency_keys (expires);
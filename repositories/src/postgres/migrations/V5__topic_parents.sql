@@ -0,0 +1,36 @@
+alter table topics add column if not exists parent_id uuid constraint t_parent_fk references topics (id);
+
+create index if not exists topics_parent_id on topics (parent_id);
+
+/*
+A topic can't be nested under itself or any of its descendants. Hierarchy changes take a lock so
+two concurrent moves can't each pass the check and form a cycle between them.
+ */
+create or replace function topics_prevent_parent_cycle() returns trigger as $$
+begin
+    if new.parent_id is null then
+        return new;
+    end if;
+
+    perform pg_advisory_xact_lock(hashtext('topics_parent_id'));
+
+    if new.parent_id = new.id or exists (
+        with recursive ancestors as (
+            select id, parent_id from topics where id = new.parent_id
+            union
+            select t.id, t.parent_id from topics t join ancestors a on t.id = a.parent_id
+        )
+        select 1 from ancestors where id = new.id
+    ) then
+        raise exception 'topic % cannot be nested under its own descendant %', new.id, new.parent_id
+            using errcode = 'check_violation';
+    end if;
+
+    return new;
+end;
+$$ language plpgsql;
+
+drop trigger if exists topics_parent_cycle on topics;
+create trigger topics_parent_cycle
+    before insert or update of parent_id on topics
+    for each row execute function topics_prevent_parent_cycle();
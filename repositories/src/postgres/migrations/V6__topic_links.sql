@@ -0,0 +1,15 @@
+create table if not exists topic_links (
+    source_id uuid not null,
+    target_id uuid not null,
+    link_type varchar(32) not null,
+    created timestamp with time zone default now(),
+    updated timestamp with time zone,
+    primary key (source_id, target_id),
+    constraint tl_source_fk foreign key (source_id) references topics (id) on delete cascade,
+    constraint tl_target_fk foreign key (target_id) references topics (id) on delete cascade,
+    constraint tl_not_self check (source_id <> target_id),
+    constraint tl_link_type check (link_type in ('supersedes', 'related_to', 'derived_from'))
+);
+
+-- the primary key already covers lookups by source
+create index if not exists topic_links_target_id on topic_links (target_id);
@@ -0,0 +1,17 @@
+create table if not exists topic_tags (
+    topic_id uuid not null,
+    tag varchar(64) not null,
+    primary key (topic_id, tag),
+    constraint tt_topic_fk foreign key (topic_id) references topics (id) on delete cascade
+);
+
+create table if not exists set_tags (
+    set_id uuid not null,
+    tag varchar(64) not null,
+    primary key (set_id, tag),
+    constraint st_set_fk foreign key (set_id) references sets (id) on delete cascade
+);
+
+-- the primary keys cover reading a topic's or set's tags, these cover finding what has a tag
+create index if not exists topic_tags_tag on topic_tags (tag);
+create index if not exists set_tags_tag on set_tags (tag);
@@ -0,0 +1,6 @@
+alter table topics add column if not exists attributes jsonb not null default '{}'::jsonb;
+alter table sets add column if not exists attributes jsonb not null default '{}'::jsonb;
+
+-- the default jsonb_ops class, since list filters check keys exist as well as containment
+create index if not exists topics_attributes on topics using gin (attributes);
+create index if not exists sets_attributes on sets using gin (attributes);
@@ -0,0 +1,80 @@
+-- topics from before statuses were added are already in use, new ones start as drafts
+alter table topics add column if not exists status varchar(16) not null default 'active'
+    constraint t_status_check check (status in ('draft', 'active', 'deprecated', 'archived'));
+alter table topics alter column status set default 'draft';
+
+create index if not exists topics_status on topics (status);
+
+create table if not exists topic_status_changes (
+    topic_id uuid not null,
+    from_status varchar(16) not null,
+    to_status varchar(16) not null,
+    reason varchar(1024),
+    changed timestamp with time zone not null default now(),
+    constraint tsc_topic_fk foreign key (topic_id) references topics (id) on delete cascade
+);
+
+create index if not exists topic_status_changes_topic on topic_status_changes (topic_id, changed);
+
+/*
+The sets of an archived topic, and their tags, are read-only. Sets removed because their topic was
+deleted aren't stopped, since the topic is already gone when the delete cascades to them
+ */
+create or replace function sets_reject_archived_topic() returns trigger as $$
+declare
+    topic_ids uuid[];
+begin
+    if tg_op = 'INSERT' then
+        topic_ids := array[new.topic_id];
+    elsif tg_op = 'UPDATE' then
+        topic_ids := array[new.topic_id, old.topic_id];
+    else
+        topic_ids := array[old.topic_id];
+    end if;
+
+    if exists (select 1 from topics where id = any(topic_ids) and status = 'archived') then
+        raise exception 'the sets of archived topics are read-only'
+            using errcode = 'object_not_in_prerequisite_state';
+    end if;
+
+    if tg_op = 'DELETE' then
+        return old;
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+drop trigger if exists sets_archived_topic on sets;
+create trigger sets_archived_topic
+    before insert or update or delete on sets
+    for each row execute function sets_reject_archived_topic();
+
+create or replace function set_tags_reject_archived_topic() returns trigger as $$
+declare
+    tagged_set uuid;
+begin
+    if tg_op = 'DELETE' then
+        tagged_set := old.set_id;
+    else
+        tagged_set := new.set_id;
+    end if;
+
+    if exists (
+        select 1 from sets s join topics t on t.id = s.topic_id
+        where s.id = tagged_set and t.status = 'archived'
+    ) then
+        raise exception 'the sets of archived topics are read-only'
+            using errcode = 'object_not_in_prerequisite_state';
+    end if;
+
+    if tg_op = 'DELETE' then
+        return old;
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+drop trigger if exists set_tags_archived_topic on set_tags;
+create trigger set_tags_archived_topic
+    before insert or delete on set_tags
+    for each row execute function set_tags_reject_archived_topic();
@@ -1,3 +1,5 @@
+pub mod api_keys;
+pub mod idempotency;
 // #[cfg(feature = "postgres-topics")]
 pub mod initializer;
 mod insert_many;
@@ -5,12 +7,17 @@ pub mod sets;
 mod statements;
 pub mod topics;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
+pub mod set_test_repos;
+#[cfg(any(test, feature = "test-support"))]
 pub mod topic_test_repos;
 
 use error_stack::Report;
+use routing::attributes::{AttributeFilter, Attributes};
 use routing::list_criteria::ListCriteria;
+use serde_json::Value;
 use std::error::Error;
+use tokio_postgres::error::SqlState;
 
 macro_rules! validate_pagination_field {
     ($field_name:literal, $field:expr; $e:expr) => {{
@@ -61,6 +68,62 @@ fn sanitize_pagination<const N: usize, T, E: Error + Send + Sync + Copy + 'stati
     Ok(SanitizedPagination { page, page_size })
 }
 
+/// Reads the `attributes` column, which is always an object
+fn row_attributes(row: &tokio_postgres::Row) -> Attributes {
+    match row.get("attributes") {
+        Value::Object(attributes) => attributes,
+        _ => Attributes::new(),
+    }
+}
+
+/// The keys every filter needs to exist, and an object of the text each equality filter needs the
+/// attribute to have. Either is `None` when there's nothing to filter on
+fn attribute_params(filters: &[AttributeFilter]) -> (Option<Vec<&str>>, Option<Value>) {
+    if filters.is_empty() {
+        return (None, None);
+    }
+
+    let keys = filters.iter().map(AttributeFilter::key).collect();
+    let equals: serde_json::Map<_, _> = filters
+        .iter()
+        .filter_map(|filter| match filter {
+            AttributeFilter::Equals { key, value } => {
+                Some((key.clone(), Value::String(value.clone())))
+            }
+            AttributeFilter::Exists(_) => None,
+        })
+        .collect();
+
+    (
+        Some(keys),
+        (!equals.is_empty()).then_some(Value::Object(equals)),
+    )
+}
+
+/// Only happens on names when the repos were created [with unique names](initializer::RepoCreator::with_unique_names)
+fn is_unique_violation(e: &tokio_postgres::Error) -> bool {
+    e.code()
+        .is_some_and(|c| c.code() == SqlState::UNIQUE_VIOLATION.code())
+}
+
+/// A topic's parent doesn't exist, or a deleted topic still has children
+fn is_foreign_key_violation(e: &tokio_postgres::Error) -> bool {
+    e.code()
+        .is_some_and(|c| c.code() == SqlState::FOREIGN_KEY_VIOLATION.code())
+}
+
+/// Raised by the trigger that stops a topic being nested under its own descendant
+fn is_check_violation(e: &tokio_postgres::Error) -> bool {
+    e.code()
+        .is_some_and(|c| c.code() == SqlState::CHECK_VIOLATION.code())
+}
+
+/// Raised by the triggers that keep the sets of an archived topic read-only
+fn is_topic_archived(e: &tokio_postgres::Error) -> bool {
+    e.code()
+        .is_some_and(|c| c.code() == SqlState::OBJECT_NOT_IN_PREREQUISITE_STATE.code())
+}
+
 pub enum ConnectionDetails {
     Url(String),
 }
@@ -76,6 +139,14 @@ impl RepoInitErr {
     fn sets() -> Self {
         Self("sets")
     }
+
+    fn api_keys() -> Self {
+        Self("api keys")
+    }
+
+    fn idempotency() -> Self {
+        Self("idempotency keys")
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
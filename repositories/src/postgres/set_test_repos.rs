@@ -0,0 +1,383 @@
+use chrono::{DateTime, Utc};
+use error_stack::IntoReport;
+use indexmap::IndexMap;
+use optional_field::Field;
+use routing::{ArwLock, attributes};
+use sets_core::list_filter::{SetFilter, SetListCriteria};
+use sets_core::model::{NewSet, PatchSet, Set, TagCount, Upserted};
+use sets_core::result::{OptRepoResult, Reason, RepoResult, SetRepoError};
+use sets_core::{SetKey, SetRepository};
+use std::collections::{BTreeSet, HashMap};
+use tokio_stream::Stream;
+use topics_core::TopicRepository;
+use topics_core::model::TopicStatus;
+
+use crate::postgres::sets::{PostgresSetKey, SetId};
+use crate::postgres::topic_test_repos::InMemoryTopicsRepo;
+use crate::postgres::topics::TopicId;
+
+/// A set's versions, oldest first, with when each was recorded
+type SetHistory = Vec<(DateTime<Utc>, Set<PostgresSetKey>)>;
+
+/// Sets kept in memory, belonging to the topics of an [`InMemoryTopicsRepo`]. Deleting a topic
+/// from it leaves the topic's sets behind. Like postgres, the sets of an archived topic can't be
+/// written and every change of a set is kept as a version.
+#[derive(Clone)]
+pub struct InMemorySetsRepo {
+    topics: InMemoryTopicsRepo,
+    db: ArwLock<IndexMap<SetId, Set<PostgresSetKey>>>,
+    tags: ArwLock<HashMap<SetId, BTreeSet<String>>>,
+    versions: ArwLock<IndexMap<SetId, SetHistory>>,
+}
+
+impl InMemorySetsRepo {
+    pub fn new(topics: InMemoryTopicsRepo) -> Self {
+        Self {
+            topics,
+            db: ArwLock::default(),
+            tags: ArwLock::default(),
+            versions: ArwLock::default(),
+        }
+    }
+
+    /// Whether the set is in the topic, a missing topic is an error
+    async fn set_exists(&self, key: PostgresSetKey) -> RepoResult<bool> {
+        self.topic_exists(key.topic_id(), SetRepoError::Tags(Reason::TopicNotFound))
+            .await?;
+
+        Ok(self
+            .db
+            .read()
+            .await
+            .get(&key.set_id())
+            .is_some_and(|s| s.key.topic_id() == key.topic_id()))
+    }
+
+    async fn topic_exists(&self, topic_id: TopicId, on_fail: SetRepoError) -> RepoResult<()> {
+        match self.topics.get(topic_id).await {
+            Ok(Some(_)) => Ok(()),
+            _ => Err(on_fail.into_report()),
+        }
+    }
+
+    /// Fails with `TopicNotFound` or `TopicArchived` for the operation
+    async fn topic_writable(
+        &self,
+        topic_id: TopicId,
+        on_fail: fn(Reason) -> SetRepoError,
+    ) -> RepoResult<()> {
+        match self.topics.get(topic_id).await {
+            Ok(Some(topic)) if topic.status == TopicStatus::Archived => {
+                Err(on_fail(Reason::TopicArchived).into_report())
+            }
+            Ok(Some(_)) => Ok(()),
+            _ => Err(on_fail(Reason::TopicNotFound).into_report()),
+        }
+    }
+
+    /// Keeps the set as its next version, like the postgres history trigger
+    async fn record_version(&self, set: &Set<PostgresSetKey>) {
+        self.versions
+            .write()
+            .await
+            .entry(set.key.set_id())
+            .or_default()
+            .push((Utc::now(), set.clone()));
+    }
+
+    async fn topic_archived(&self, topic_id: TopicId) -> bool {
+        matches!(
+            self.topics.get(topic_id).await,
+            Ok(Some(topic)) if topic.status == TopicStatus::Archived
+        )
+    }
+}
+
+fn new_set(key: PostgresSetKey, set: NewSet) -> Set<PostgresSetKey> {
+    Set {
+        key,
+        name: set.name,
+        description: set.description,
+        attributes: set.attributes,
+        created: Utc::now(),
+        updated: None,
+    }
+}
+
+impl SetRepository for InMemorySetsRepo {
+    type SetKey = PostgresSetKey;
+
+    async fn get(&self, key: Self::SetKey) -> OptRepoResult<Set<Self::SetKey>> {
+        self.topic_exists(key.topic_id(), SetRepoError::Get(Reason::TopicNotFound))
+            .await?;
+        let db = self.db.read().await;
+
+        Ok(db
+            .get(&key.set_id())
+            .filter(|s| s.key.topic_id() == key.topic_id())
+            .cloned())
+    }
+
+    async fn get_many(
+        &self,
+        topic_id: TopicId,
+        set_ids: Vec<SetId>,
+    ) -> RepoResult<Vec<Option<Set<Self::SetKey>>>> {
+        self.topic_exists(topic_id, SetRepoError::Get(Reason::TopicNotFound))
+            .await?;
+        let db = self.db.read().await;
+
+        Ok(set_ids
+            .iter()
+            .map(|id| db.get(id).filter(|s| s.key.topic_id() == topic_id).cloned())
+            .collect())
+    }
+
+    async fn list(
+        &self,
+        topic_id: TopicId,
+        list_criteria: SetListCriteria,
+    ) -> RepoResult<Vec<Set<Self::SetKey>>> {
+        self.topic_exists(topic_id, SetRepoError::List(Reason::TopicNotFound))
+            .await?;
+        let db = self.db.read().await;
+        let tags = self.tags.read().await;
+        let no_tags = BTreeSet::new();
+
+        Ok(db
+            .values()
+            .filter(|set| set.key.topic_id() == topic_id)
+            .filter(|set| {
+                let set_tags = tags.get(&set.key.set_id()).unwrap_or(&no_tags);
+                list_criteria
+                    .filters()
+                    .unwrap_or_default()
+                    .iter()
+                    .all(|f| match f {
+                        SetFilter::Name(n) => set.name.contains(n),
+                        SetFilter::AllTags(all) => all.iter().all(|t| set_tags.contains(t)),
+                        SetFilter::AnyTag(any) => any.iter().any(|t| set_tags.contains(t)),
+                        SetFilter::Attributes(attributes) => {
+                            attributes.iter().all(|a| a.matches(&set.attributes))
+                        }
+                    })
+            })
+            .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
+            .take(list_criteria.page_size() as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_at(
+        &self,
+        topic_id: TopicId,
+        at: DateTime<Utc>,
+        list_criteria: SetListCriteria,
+    ) -> RepoResult<Vec<Set<Self::SetKey>>> {
+        self.topic_exists(topic_id, SetRepoError::List(Reason::TopicNotFound))
+            .await?;
+        let versions = self.versions.read().await;
+
+        Ok(versions
+            .values()
+            .filter_map(|history| {
+                history
+                    .iter()
+                    .rev()
+                    .find(|(recorded, _)| *recorded <= at)
+                    .map(|(_, set)| set)
+            })
+            .filter(|set| set.key.topic_id() == topic_id)
+            .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
+            .take(list_criteria.page_size() as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_stream(
+        &self,
+        topic_id: TopicId,
+        list_criteria: SetListCriteria,
+    ) -> RepoResult<impl Stream<Item = RepoResult<Set<Self::SetKey>>> + Send + 'static> {
+        let sets = self.list(topic_id, list_criteria).await?;
+        Ok(tokio_stream::iter(sets.into_iter().map(Ok)))
+    }
+
+    async fn create(&self, topic_id: TopicId, set: NewSet) -> RepoResult<Set<Self::SetKey>> {
+        self.topic_writable(topic_id, SetRepoError::Create).await?;
+        let set = new_set(PostgresSetKey(topic_id, SetId::new()), set);
+        self.db.write().await.insert(set.key.set_id(), set.clone());
+        self.record_version(&set).await;
+
+        Ok(set)
+    }
+
+    async fn create_many(
+        &self,
+        topic_id: TopicId,
+        sets: Vec<NewSet>,
+    ) -> RepoResult<Vec<RepoResult<Set<Self::SetKey>>>> {
+        let created = self.create_many_atomic(topic_id, sets).await?;
+        Ok(created.into_iter().map(Ok).collect())
+    }
+
+    async fn create_many_atomic(
+        &self,
+        topic_id: TopicId,
+        sets: Vec<NewSet>,
+    ) -> RepoResult<Vec<Set<Self::SetKey>>> {
+        self.topic_writable(topic_id, SetRepoError::CreateMany)
+            .await?;
+        let mut db = self.db.write().await;
+
+        let created: Vec<_> = sets
+            .into_iter()
+            .map(|set| {
+                let set = new_set(PostgresSetKey(topic_id, SetId::new()), set);
+                db.insert(set.key.set_id(), set.clone());
+                set
+            })
+            .collect();
+        for set in &created {
+            self.record_version(set).await;
+        }
+        Ok(created)
+    }
+
+    async fn upsert(
+        &self,
+        key: Self::SetKey,
+        set: NewSet,
+    ) -> RepoResult<Upserted<Set<Self::SetKey>>> {
+        self.topic_writable(key.topic_id(), SetRepoError::Upsert)
+            .await?;
+        let mut db = self.db.write().await;
+
+        if let Some(existing) = db.get_mut(&key.set_id()) {
+            if existing.key.topic_id() != key.topic_id() {
+                return Err(SetRepoError::Upsert(Reason::IdTaken).into_report());
+            }
+            existing.name = set.name;
+            existing.description = set.description;
+            existing.attributes = set.attributes;
+            existing.updated = Some(Utc::now());
+            let replaced = existing.clone();
+            self.record_version(&replaced).await;
+            return Ok(Upserted::Replaced(replaced));
+        }
+
+        let set = new_set(key, set);
+        db.insert(key.set_id(), set.clone());
+        self.record_version(&set).await;
+        Ok(Upserted::Created(set))
+    }
+
+    async fn patch(&self, key: Self::SetKey, patch: PatchSet) -> OptRepoResult<Set<Self::SetKey>> {
+        self.topic_writable(key.topic_id(), SetRepoError::Patch)
+            .await?;
+        let mut db = self.db.write().await;
+
+        let Some(set) = db
+            .get_mut(&key.set_id())
+            .filter(|s| s.key.topic_id() == key.topic_id())
+        else {
+            return Ok(None);
+        };
+
+        let changed = patch.name.is_some()
+            || matches!(patch.description, Field::Present(_))
+            || patch.attributes.is_some();
+        if let Some(name) = patch.name {
+            set.name = name;
+        }
+        if let Field::Present(description) = patch.description {
+            set.description = description;
+        }
+        if let Some(patch) = patch.attributes {
+            attributes::merge(&mut set.attributes, patch);
+        }
+        if changed {
+            set.updated = Some(Utc::now());
+            self.record_version(set).await;
+        }
+
+        Ok(Some(set.clone()))
+    }
+
+    async fn delete(&self, key: Self::SetKey) -> OptRepoResult<()> {
+        let mut db = self.db.write().await;
+
+        let in_topic = db
+            .get(&key.set_id())
+            .is_some_and(|s| s.key.topic_id() == key.topic_id());
+        if !in_topic {
+            return Ok(None);
+        }
+        if self.topic_archived(key.topic_id()).await {
+            return Err(SetRepoError::Delete(Reason::TopicArchived).into_report());
+        }
+
+        db.shift_remove(&key.set_id());
+        self.tags.write().await.remove(&key.set_id());
+        self.versions.write().await.shift_remove(&key.set_id());
+        Ok(Some(()))
+    }
+
+    async fn tags(&self, key: Self::SetKey) -> OptRepoResult<Vec<String>> {
+        if !self.set_exists(key).await? {
+            return Ok(None);
+        }
+
+        let tags = self.tags.read().await;
+        Ok(Some(
+            tags.get(&key.set_id())
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    async fn add_tags(&self, key: Self::SetKey, tags: Vec<String>) -> OptRepoResult<Vec<String>> {
+        if !self.set_exists(key).await? {
+            return Ok(None);
+        }
+        self.topic_writable(key.topic_id(), SetRepoError::Tags)
+            .await?;
+
+        let mut all = self.tags.write().await;
+        let set_tags = all.entry(key.set_id()).or_default();
+        set_tags.extend(tags);
+        Ok(Some(set_tags.iter().cloned().collect()))
+    }
+
+    async fn remove_tags(
+        &self,
+        key: Self::SetKey,
+        tags: Vec<String>,
+    ) -> OptRepoResult<Vec<String>> {
+        if !self.set_exists(key).await? {
+            return Ok(None);
+        }
+        self.topic_writable(key.topic_id(), SetRepoError::Tags)
+            .await?;
+
+        let mut all = self.tags.write().await;
+        let set_tags = all.entry(key.set_id()).or_default();
+        set_tags.retain(|t| !tags.contains(t));
+        Ok(Some(set_tags.iter().cloned().collect()))
+    }
+
+    async fn tag_counts(&self) -> RepoResult<Vec<TagCount>> {
+        let mut counts = IndexMap::<String, u64>::new();
+        for tag in self.tags.read().await.values().flatten() {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+        counts.sort_keys();
+
+        Ok(counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    }
+}
@@ -1,26 +1,32 @@
 use crate::postgres::insert_many::{InsertMany, InsertManyBuilder, value_set};
 use crate::postgres::statements::SetStatements;
 use crate::postgres::topics::TopicId;
-use crate::postgres::{RepoInitErr, sanitize_pagination};
+use crate::postgres::{
+    RepoInitErr, attribute_params, is_topic_archived, is_unique_violation, row_attributes,
+    sanitize_pagination,
+};
+use chrono::{DateTime, Utc};
 use deadpool_postgres::{Object, Pool};
 use error_stack::{IntoReport, Report, ResultExt};
 use optional_field::Field;
 use serde::{Deserialize, Serialize};
-use sets_core::list_filter::SetListCriteria;
-use sets_core::model::{NewSet, PatchSet, Set};
+use serde_json::Value;
+use sets_core::list_filter::{SetListCriteria, attribute_filters, tag_filters};
+use sets_core::model::{NewSet, PatchSet, Set, TagCount, Upserted};
 use sets_core::result::{OptRepoResult, Reason, RepoResult, SetRepoError};
 use sets_core::{SetKey, SetRepository};
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::pin::pin;
 use tokio_postgres::Row;
 use tokio_postgres::error::SqlState;
 use tokio_postgres::types::ToSql;
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
 use tracing::warn;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy, Hash)]
 #[serde(transparent)]
 pub struct SetId(Uuid);
 
@@ -39,7 +45,7 @@ impl SetId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PostgresSetKey(pub TopicId, pub SetId);
 impl SetKey for PostgresSetKey {
     type SetId = SetId;
@@ -77,6 +83,33 @@ impl SetRepo {
     async fn client(&self, on_err: SetRepoError) -> RepoResult<Object> {
         self.pool.get().await.change_context(on_err)
     }
+
+    /// Whether the set is in the topic, a missing topic is an error
+    async fn set_exists(&self, key: PostgresSetKey) -> RepoResult<bool> {
+        let row = self
+            .client(SetRepoError::Tags(Reason::Db))
+            .await?
+            .query_opt(&self.statements.get, &[&key.topic_id().0, &key.set_id().0])
+            .await
+            .change_context(SetRepoError::Tags(Reason::Db))?;
+
+        match row {
+            None => Err(SetRepoError::Tags(Reason::TopicNotFound).into_report()),
+            Some(row) => Ok(row.get("set_exists")),
+        }
+    }
+
+    async fn read_tags(&self, set_id: SetId) -> RepoResult<Vec<String>> {
+        Ok(self
+            .client(SetRepoError::Tags(Reason::Db))
+            .await?
+            .query(&self.statements.tags, &[&set_id.0])
+            .await
+            .change_context(SetRepoError::Tags(Reason::Db))?
+            .into_iter()
+            .map(|row| row.get("tag"))
+            .collect())
+    }
 }
 
 enum GetOutcome {
@@ -102,6 +135,7 @@ fn row_to_set(row: impl Borrow<Row>) -> Set<PostgresSetKey> {
         key: PostgresSetKey(TopicId(row.get("topic_id")), SetId(row.get("id"))),
         name: row.get("name"),
         description: row.get("description"),
+        attributes: row_attributes(row),
         created: row.get("created"),
         updated: row.get("updated"),
     }
@@ -128,40 +162,119 @@ impl SetRepository for SetRepo {
         }
     }
 
+    async fn get_many(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        set_ids: Vec<<Self::SetKey as SetKey>::SetId>,
+    ) -> RepoResult<Vec<Option<Set<Self::SetKey>>>> {
+        let ids: Vec<Uuid> = set_ids.into_iter().map(|id| id.0).collect();
+
+        let rows = self
+            .client(SetRepoError::Get(Reason::Db))
+            .await?
+            .query(&self.statements.get_many, &[&topic_id.0, &ids])
+            .await
+            .change_context(SetRepoError::Get(Reason::Db))?;
+        if rows.is_empty() {
+            return Err(SetRepoError::Get(Reason::TopicNotFound).into_report());
+        }
+
+        let found: HashMap<Uuid, Set<PostgresSetKey>> = rows
+            .into_iter()
+            .filter_map(|row| match GetOutcome::from(row) {
+                GetOutcome::SetFound(set) => Some((set.key.set_id().0, set)),
+                GetOutcome::SetNotFound => None,
+            })
+            .collect();
+
+        Ok(ids.iter().map(|id| found.get(id).cloned()).collect())
+    }
+
     async fn list(
         &self,
         topic_id: <Self::SetKey as SetKey>::TopicId,
         list_criteria: SetListCriteria,
+    ) -> RepoResult<Vec<Set<Self::SetKey>>> {
+        self.list_stream(topic_id, list_criteria)
+            .await?
+            .collect::<Result<_, _>>()
+            .await
+    }
+
+    async fn list_stream(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        list_criteria: SetListCriteria,
+    ) -> RepoResult<impl Stream<Item = RepoResult<Set<Self::SetKey>>> + Send + 'static> {
+        let pagination =
+            sanitize_pagination(&list_criteria, SetRepoError::List(Reason::Validation))?;
+
+        let (all_tags, any_tag) = tag_filters(&list_criteria);
+        let (attribute_keys, attribute_values) =
+            attribute_params(attribute_filters(&list_criteria));
+        let params: [&(dyn ToSql + Sync); 7] = [
+            &topic_id.0,
+            &pagination.page,
+            &pagination.page_size,
+            &all_tags,
+            &any_tag,
+            &attribute_keys,
+            &attribute_values,
+        ];
+
+        let client = self.client(SetRepoError::List(Reason::Db)).await?;
+        let mut rows = Box::pin(
+            client
+                .query_raw(&self.statements.list, params)
+                .await
+                .change_context(SetRepoError::List(Reason::Db))?,
+        );
+
+        // with the structure of the query, we'll get one row of null column values
+        // if the topic does exist. So if the query is empty, this topic didn't exist
+        let Some(first) = rows.next().await else {
+            return Err(SetRepoError::List(Reason::TopicNotFound).into_report());
+        };
+        let first = first.change_context(SetRepoError::List(Reason::Db))?;
+        let set_id: Option<Uuid> = first.get("id");
+        let first = set_id.is_some().then_some(Ok(first));
+
+        // the stream owns the connection, so it only goes back to the pool once the stream is dropped
+        Ok(tokio_stream::iter(first).chain(rows).map(move |row| {
+            let _ = &client;
+            row.map(row_to_set)
+                .change_context(SetRepoError::List(Reason::Db))
+        }))
+    }
+
+    async fn list_at(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        at: DateTime<Utc>,
+        list_criteria: SetListCriteria,
     ) -> RepoResult<Vec<Set<Self::SetKey>>> {
         let pagination =
             sanitize_pagination(&list_criteria, SetRepoError::List(Reason::Validation))?;
 
-        // TODO find a way to do this with RowStream so we're not allocating a new Vec
         let rows = self
             .client(SetRepoError::List(Reason::Db))
             .await?
             .query(
-                &self.statements.list,
-                &[&topic_id.0, &pagination.page, &pagination.page_size],
+                &self.statements.list_at,
+                &[&topic_id.0, &pagination.page, &pagination.page_size, &at],
             )
             .await
             .change_context(SetRepoError::List(Reason::Db))?;
 
-        // with the structure of the query, we'll get one row of null column values
-        // if the topic does exist. So if the query is empty, this topic didn't exist
+        // like `list_stream`, a topic that had no sets then still gives a row of nulls
         if rows.is_empty() {
-            Err(SetRepoError::List(Reason::TopicNotFound).into_report())
-        } else {
-            let first = &rows[0];
-
-            let set_id: Option<Uuid> = first.get("id");
-            let sets = if set_id.is_some() {
-                rows.into_iter().map(row_to_set).collect()
-            } else {
-                vec![]
-            };
-            Ok(sets)
+            return Err(SetRepoError::List(Reason::TopicNotFound).into_report());
         }
+        Ok(rows
+            .into_iter()
+            .filter(|row| row.get::<_, Option<Uuid>>("id").is_some())
+            .map(row_to_set)
+            .collect())
     }
 
     async fn create(
@@ -175,7 +288,13 @@ impl SetRepository for SetRepo {
             .await?
             .query_one(
                 &self.statements.create,
-                &[&set_id.0, &topic_id.0, &new_set.name, &new_set.description],
+                &[
+                    &set_id.0,
+                    &topic_id.0,
+                    &new_set.name,
+                    &new_set.description,
+                    &Value::Object(new_set.attributes),
+                ],
             )
             .await;
 
@@ -187,6 +306,12 @@ impl SetRepository for SetRepo {
             {
                 Err(e.into_report()).change_context(SetRepoError::Create(Reason::TopicNotFound))
             }
+            Err(e) if is_unique_violation(&e) => {
+                Err(e.into_report()).change_context(SetRepoError::Create(Reason::DuplicateName))
+            }
+            Err(e) if is_topic_archived(&e) => {
+                Err(e.into_report()).change_context(SetRepoError::Create(Reason::TopicArchived))
+            }
             Err(e) => Err(e.into_report()).change_context(SetRepoError::Create(Reason::Db)),
         }
     }
@@ -196,7 +321,7 @@ impl SetRepository for SetRepo {
         topic_id: <Self::SetKey as SetKey>::TopicId,
         sets: Vec<NewSet>,
     ) -> RepoResult<Vec<RepoResult<Set<Self::SetKey>>>> {
-        let Some(insert_many) = generate_insert_many(topic_id, sets) else {
+        let Some((insert_many, set_ids)) = generate_insert_many(topic_id, sets, true) else {
             warn!("no set requests sent to data layer, not creating any new topics");
             return Ok(vec![]);
         };
@@ -208,35 +333,158 @@ impl SetRepository for SetRepo {
             .await
             .change_context(SetRepoError::CreateMany(Reason::Db))?;
 
-        let mut set_results = Vec::new();
+        let mut created = HashMap::with_capacity(set_ids.len());
 
         let mut stream = pin!(stream);
-        let mut i = 0;
 
+        // the insert is a single statement, so any failing row means none were created
         while let Some(row_result) = stream.next().await {
             match row_result {
-                Ok(row) => set_results.push(Ok(row_to_set(row))),
+                Ok(row) => {
+                    let set = row_to_set(row);
+                    created.insert(set.key.1.0, set);
+                }
                 Err(e)
                     if e.code()
                         .is_some_and(|c| c.code() == SqlState::FOREIGN_KEY_VIOLATION.code()) =>
                 {
                     return Err(SetRepoError::CreateMany(Reason::TopicNotFound).into_report());
                 }
+                Err(e) if is_topic_archived(&e) => {
+                    return Err(e.into_report())
+                        .change_context(SetRepoError::CreateMany(Reason::TopicArchived));
+                }
                 Err(e) => {
-                    warn!("Result row {i} failed: {e}");
-                    set_results.push(Err(SetRepoError::CreateMany(Reason::Db).into_report()));
+                    return Err(e.into_report())
+                        .change_context(SetRepoError::CreateMany(Reason::Db));
                 }
             }
-            i += 1;
         }
 
+        // conflicting rows are skipped rather than failing the insert, so they're the ones missing
+        let set_results = set_ids
+            .into_iter()
+            .map(|id| {
+                created
+                    .remove(&id)
+                    .ok_or_else(|| SetRepoError::CreateMany(Reason::DuplicateName).into_report())
+            })
+            .collect();
+
         Ok(set_results)
     }
 
+    async fn create_many_atomic(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        sets: Vec<NewSet>,
+    ) -> RepoResult<Vec<Set<Self::SetKey>>> {
+        let Some((insert_many, set_ids)) = generate_insert_many(topic_id, sets, false) else {
+            warn!("no set requests sent to data layer, not creating any new sets");
+            return Ok(vec![]);
+        };
+
+        let mut client = self.client(SetRepoError::CreateMany(Reason::Db)).await?;
+        let transaction = client
+            .transaction()
+            .await
+            .change_context(SetRepoError::CreateMany(Reason::Db))?;
+
+        // dropping the transaction on any error rolls it back
+        let result = match transaction
+            .query_raw(&insert_many.query, insert_many.params())
+            .await
+        {
+            Ok(rows) => {
+                rows.map(|r| r.map(|row| (row.get::<_, Uuid>("id"), row_to_set(row))))
+                    .collect::<Result<Vec<_>, _>>()
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let mut created: HashMap<_, _> = match result {
+            Ok(created) => created.into_iter().collect(),
+            Err(e)
+                if e.code()
+                    .is_some_and(|c| c.code() == SqlState::FOREIGN_KEY_VIOLATION.code()) =>
+            {
+                return Err(e.into_report())
+                    .change_context(SetRepoError::CreateMany(Reason::TopicNotFound));
+            }
+            Err(e) if is_unique_violation(&e) => {
+                return Err(e.into_report())
+                    .change_context(SetRepoError::CreateMany(Reason::DuplicateName));
+            }
+            Err(e) if is_topic_archived(&e) => {
+                return Err(e.into_report())
+                    .change_context(SetRepoError::CreateMany(Reason::TopicArchived));
+            }
+            Err(e) => {
+                return Err(e.into_report()).change_context(SetRepoError::CreateMany(Reason::Db));
+            }
+        };
+
+        transaction
+            .commit()
+            .await
+            .change_context(SetRepoError::CreateMany(Reason::Db))?;
+
+        set_ids
+            .into_iter()
+            .map(|id| {
+                created
+                    .remove(&id)
+                    .ok_or_else(|| SetRepoError::CreateMany(Reason::Db).into_report())
+                    .attach_with(|| format!("set {id} missing from the insert's returned rows"))
+            })
+            .collect()
+    }
+
+    async fn upsert(
+        &self,
+        key: Self::SetKey,
+        set: NewSet,
+    ) -> RepoResult<Upserted<Set<Self::SetKey>>> {
+        let result = self
+            .client(SetRepoError::Upsert(Reason::Db))
+            .await?
+            .query_opt(
+                &self.statements.upsert,
+                &[
+                    &key.1.0,
+                    &key.0.0,
+                    &set.name,
+                    &set.description,
+                    &Value::Object(set.attributes),
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(Some(row)) if row.get("inserted") => Ok(Upserted::Created(row_to_set(row))),
+            Ok(Some(row)) => Ok(Upserted::Replaced(row_to_set(row))),
+            Ok(None) => Err(SetRepoError::Upsert(Reason::IdTaken).into_report()),
+            Err(e)
+                if e.code()
+                    .is_some_and(|c| c.code() == SqlState::FOREIGN_KEY_VIOLATION.code()) =>
+            {
+                Err(e.into_report()).change_context(SetRepoError::Upsert(Reason::TopicNotFound))
+            }
+            Err(e) if is_unique_violation(&e) => {
+                Err(e.into_report()).change_context(SetRepoError::Upsert(Reason::DuplicateName))
+            }
+            Err(e) if is_topic_archived(&e) => {
+                Err(e.into_report()).change_context(SetRepoError::Upsert(Reason::TopicArchived))
+            }
+            Err(e) => Err(e.into_report()).change_context(SetRepoError::Upsert(Reason::Db)),
+        }
+    }
+
     async fn patch(&self, key: Self::SetKey, patch: PatchSet) -> OptRepoResult<Set<Self::SetKey>> {
         let (stmt, params) = match (&patch.name, &patch.description) {
             (Some(n), Field::Present(d)) => (
-                &self.statements.patch_name_desc,
+                Some(&self.statements.patch_name_desc),
                 &[
                     n as &(dyn ToSql + Sync),
                     d as &(dyn ToSql + Sync),
@@ -245,7 +493,7 @@ impl SetRepository for SetRepo {
                 ] as &[&(dyn ToSql + Sync)],
             ),
             (Some(n), Field::Missing) => (
-                &self.statements.patch_name,
+                Some(&self.statements.patch_name),
                 &[
                     n as &(dyn ToSql + Sync),
                     &key.1.0 as &(dyn ToSql + Sync),
@@ -253,75 +501,223 @@ impl SetRepository for SetRepo {
                 ] as &[&(dyn ToSql + Sync)],
             ),
             (None, Field::Present(d)) => (
-                &self.statements.patch_desc,
+                Some(&self.statements.patch_desc),
                 &[
                     d as &(dyn ToSql + Sync),
                     &key.1.0 as &(dyn ToSql + Sync),
                     &key.0.0 as &(dyn ToSql + Sync),
                 ] as &[&(dyn ToSql + Sync)],
             ),
-            (None, Field::Missing) => (
-                &self.statements.get,
-                &[
-                    &key.1.0 as &(dyn ToSql + Sync),
-                    &key.0.0 as &(dyn ToSql + Sync),
-                ] as &[&(dyn ToSql + Sync)],
-            ),
+            (None, Field::Missing) => (None, &[] as &[&(dyn ToSql + Sync)]),
         };
 
-        let result = self
-            .client(SetRepoError::Patch(Reason::Db))
-            .await?
-            .query_opt(stmt, params)
+        let mut client = self.client(SetRepoError::Patch(Reason::Db)).await?;
+        let transaction = client
+            .transaction()
             .await
-            .change_context(SetRepoError::Patch(Reason::Db))?
-            .map(GetOutcome::from);
+            .change_context(SetRepoError::Patch(Reason::Db))?;
+
+        // dropping the transaction on any error rolls it back
+        let patched_fields = stmt.is_some();
+        let mut row = match stmt {
+            Some(stmt) => match transaction.query_opt(stmt, params).await {
+                Ok(row) => row,
+                Err(e) if is_unique_violation(&e) => {
+                    return Err(e.into_report())
+                        .change_context(SetRepoError::Patch(Reason::DuplicateName));
+                }
+                Err(e) if is_topic_archived(&e) => {
+                    return Err(e.into_report())
+                        .change_context(SetRepoError::Patch(Reason::TopicArchived));
+                }
+                Err(e) => {
+                    return Err(e.into_report()).change_context(SetRepoError::Patch(Reason::Db));
+                }
+            },
+            None => None,
+        };
 
-        match result {
-            // no topics in database
-            None => Err(SetRepoError::Patch(Reason::TopicNotFound).into_report()),
-            // topic in database, but could not find a set with this id associated with the topic id
-            Some(GetOutcome::SetNotFound) => Ok(None),
-            Some(GetOutcome::SetFound(set)) => Ok(Some(set)),
+        // attributes are merged in once the other fields are patched, unless there's no set to patch
+        let merge = patch
+            .attributes
+            .filter(|_| row.is_some() || !patched_fields);
+        if let Some(attributes) = merge {
+            row = match transaction
+                .query_opt(
+                    &self.statements.merge_attributes,
+                    &[&key.0.0, &key.1.0, &Value::Object(attributes)],
+                )
+                .await
+            {
+                Ok(row) => row,
+                Err(e) if is_topic_archived(&e) => {
+                    return Err(e.into_report())
+                        .change_context(SetRepoError::Patch(Reason::TopicArchived));
+                }
+                Err(e) => {
+                    return Err(e.into_report()).change_context(SetRepoError::Patch(Reason::Db));
+                }
+            };
         }
+
+        let set = match row {
+            Some(row) => Some(row_to_set(row)),
+            // nothing was updated, so tell a missing set apart from a missing topic
+            None => match transaction
+                .query_opt(&self.statements.get, &[&key.0.0, &key.1.0])
+                .await
+                .change_context(SetRepoError::Patch(Reason::Db))?
+                .map(GetOutcome::from)
+            {
+                None => return Err(SetRepoError::Patch(Reason::TopicNotFound).into_report()),
+                Some(GetOutcome::SetNotFound) => None,
+                Some(GetOutcome::SetFound(set)) => Some(set),
+            },
+        };
+
+        transaction
+            .commit()
+            .await
+            .change_context(SetRepoError::Patch(Reason::Db))?;
+
+        Ok(set)
     }
 
     // TODO check if topic exists if delete does nothing
     async fn delete(&self, key: Self::SetKey) -> OptRepoResult<()> {
-        let deleted = self
+        let deleted = match self
             .client(SetRepoError::Delete(Reason::Db))
             .await?
             .execute(&self.statements.delete, &[&key.1.0, &key.0.0])
             .await
-            .change_context(SetRepoError::Delete(Reason::Db))?;
+        {
+            Ok(deleted) => deleted,
+            Err(e) if is_topic_archived(&e) => {
+                return Err(e.into_report())
+                    .change_context(SetRepoError::Delete(Reason::TopicArchived));
+            }
+            Err(e) => {
+                return Err(e.into_report()).change_context(SetRepoError::Delete(Reason::Db));
+            }
+        };
 
         if deleted == 0 { Ok(None) } else { Ok(Some(())) }
     }
+
+    async fn tags(&self, key: Self::SetKey) -> OptRepoResult<Vec<String>> {
+        if !self.set_exists(key).await? {
+            return Ok(None);
+        }
+        self.read_tags(key.set_id()).await.map(Some)
+    }
+
+    async fn add_tags(&self, key: Self::SetKey, tags: Vec<String>) -> OptRepoResult<Vec<String>> {
+        if !self.set_exists(key).await? {
+            return Ok(None);
+        }
+
+        let result = self
+            .client(SetRepoError::Tags(Reason::Db))
+            .await?
+            .execute(&self.statements.add_tags, &[&key.set_id().0, &tags])
+            .await;
+
+        match result {
+            Ok(_) => self.read_tags(key.set_id()).await.map(Some),
+            // the set was deleted since it was checked
+            Err(e)
+                if e.code()
+                    .is_some_and(|c| c.code() == SqlState::FOREIGN_KEY_VIOLATION.code()) =>
+            {
+                Ok(None)
+            }
+            Err(e) if is_topic_archived(&e) => {
+                Err(e.into_report()).change_context(SetRepoError::Tags(Reason::TopicArchived))
+            }
+            Err(e) => Err(e.into_report()).change_context(SetRepoError::Tags(Reason::Db)),
+        }
+    }
+
+    async fn remove_tags(
+        &self,
+        key: Self::SetKey,
+        tags: Vec<String>,
+    ) -> OptRepoResult<Vec<String>> {
+        if !self.set_exists(key).await? {
+            return Ok(None);
+        }
+
+        let result = self
+            .client(SetRepoError::Tags(Reason::Db))
+            .await?
+            .execute(&self.statements.remove_tags, &[&key.set_id().0, &tags])
+            .await;
+
+        match result {
+            Ok(_) => self.read_tags(key.set_id()).await.map(Some),
+            Err(e) if is_topic_archived(&e) => {
+                Err(e.into_report()).change_context(SetRepoError::Tags(Reason::TopicArchived))
+            }
+            Err(e) => Err(e.into_report()).change_context(SetRepoError::Tags(Reason::Db)),
+        }
+    }
+
+    async fn tag_counts(&self) -> RepoResult<Vec<TagCount>> {
+        let rows = self
+            .client(SetRepoError::Tags(Reason::Db))
+            .await?
+            .query(&self.statements.tag_counts, &[])
+            .await
+            .change_context(SetRepoError::Tags(Reason::Db))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TagCount {
+                tag: row.get("tag"),
+                count: row.get::<_, i64>("count") as u64,
+            })
+            .collect())
+    }
 }
 
-fn generate_insert_many(topic_id: TopicId, sets: Vec<NewSet>) -> Option<InsertMany> {
+/// The set ids are in the same order as `sets`. Conflicting names are skipped rather than
+/// failing the insert if `skip_conflicts` is set.
+fn generate_insert_many(
+    topic_id: TopicId,
+    sets: Vec<NewSet>,
+    skip_conflicts: bool,
+) -> Option<(InsertMany, Vec<Uuid>)> {
     let mut set_iter = sets.into_iter();
+    let mut set_ids = Vec::with_capacity(set_iter.len());
 
     let first = set_iter.next()?;
+    let set_id = SetId::new().0;
+    set_ids.push(set_id);
 
     let mut builder = InsertManyBuilder::new(
         "sets",
-        ["id", "topic_id", "name", "description"],
-        value_set![SetId::new().0 => Uuid, topic_id.0 => Uuid, first.name => String, first.description => Option<String>],
+        ["id", "topic_id", "name", "description", "attributes"],
+        value_set![set_id => Uuid, topic_id.0 => Uuid, first.name => String, first.description => Option<String>, Value::Object(first.attributes) => Value],
     );
 
     for set in set_iter {
-        builder.add_value_set(value_set![SetId::new().0 => Uuid, topic_id.0 => Uuid, set.name => String, set.description => Option<String>]);
+        let set_id = SetId::new().0;
+        set_ids.push(set_id);
+        builder.add_value_set(value_set![set_id => Uuid, topic_id.0 => Uuid, set.name => String, set.description => Option<String>, Value::Object(set.attributes) => Value]);
     }
 
+    if skip_conflicts {
+        builder.on_conflict_do_nothing();
+    }
     builder.returning(&[
         "id",
         "topic_id",
         "name",
         "description",
+        "attributes",
         "created",
         "updated",
     ]);
 
-    Some(builder.build())
+    Some((builder.build(), set_ids))
 }
@@ -1,20 +1,185 @@
 use error_stack::{Report, ResultExt};
 use tokio_postgres::types::Type;
 use tokio_postgres::{Client, Statement};
+use topics_core::model::TopicField;
 
 #[derive(Debug, thiserror::Error)]
 #[error("failed to prepare topics statement")]
 pub struct StatementPrepareError;
 
+/*
+Each topic's patch is a row of the unnested arrays. A null name or attributes leaves them alone, and
+set_description and set_parent say whether those were given at all, since null is a valid value.
+Attributes are merged in, with the ones patched to null removed
+ */
+const PATCH_MANY_TOPICS: &str = r#"
+UPDATE topics t
+SET
+  name = coalesce(p.name, t.name),
+  description = CASE WHEN p.set_description THEN p.description ELSE t.description END,
+  parent_id = CASE WHEN p.set_parent THEN p.parent_id ELSE t.parent_id END,
+  attributes = CASE WHEN p.attributes IS NULL THEN t.attributes
+    ELSE (t.attributes || p.attributes) - array(SELECT key FROM jsonb_each(p.attributes) WHERE jsonb_typeof(value) = 'null') END,
+  updated = CASE WHEN p.name IS NULL AND NOT p.set_description AND NOT p.set_parent AND p.attributes IS NULL THEN t.updated ELSE now() END
+FROM unnest($1::uuid[], $2::varchar[], $3::bool[], $4::varchar[], $5::bool[], $6::uuid[], $7::jsonb[])
+  AS p(id, name, set_description, description, set_parent, parent_id, attributes)
+WHERE t.id = p.id
+RETURNING t.id, t.parent_id, t.name, t.description, t.attributes, t.status, t.created, t.updated;
+"#;
+
+/*
+xmax is only set on a row that already existed, so it says whether the row was inserted or updated
+ */
+const UPSERT_TOPIC: &str = r#"
+INSERT INTO topics (id, name, description, parent_id, attributes)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (id) DO UPDATE
+SET name = excluded.name, description = excluded.description, parent_id = excluded.parent_id,
+  attributes = excluded.attributes, updated = now()
+RETURNING id, parent_id, name, description, attributes, status, created, updated, (xmax = 0) AS inserted;
+"#;
+
+/*
+Only used when the parent changes, the name and description are patched like PATCH_MANY_TOPICS
+ */
+const PATCH_TOPIC_PARENT: &str = r#"
+UPDATE topics
+SET
+  name = coalesce($2, name),
+  description = CASE WHEN $3 THEN $4 ELSE description END,
+  parent_id = $5,
+  updated = now()
+WHERE id = $1
+RETURNING id, parent_id, name, description, attributes, status, created, updated;
+"#;
+
+/*
+Walks up from the topic to its root. The topic itself is depth 0, so an empty result means it
+doesn't exist. The cycle trigger keeps the walk finite
+ */
+const TOPIC_ANCESTORS: &str = r#"
+WITH RECURSIVE ancestors AS (
+  SELECT id, parent_id, name, description, attributes, status, created, updated, 0 AS depth
+  FROM topics WHERE id = $1
+  UNION ALL
+  SELECT p.id, p.parent_id, p.name, p.description, p.attributes, p.status, p.created, p.updated, a.depth + 1
+  FROM topics p JOIN ancestors a ON p.id = a.parent_id
+)
+SELECT id, parent_id, name, description, attributes, status, created, updated FROM ancestors ORDER BY depth DESC;
+"#;
+
+const TOPIC_SUBTREE: &str = r#"
+WITH RECURSIVE subtree AS (
+  SELECT id, parent_id, name, description, attributes, status, created, updated, 0 AS depth
+  FROM topics WHERE id = $1
+  UNION ALL
+  SELECT c.id, c.parent_id, c.name, c.description, c.attributes, c.status, c.created, c.updated, s.depth + 1
+  FROM topics c JOIN subtree s ON c.parent_id = s.id
+)
+SELECT id, parent_id, name, description, attributes, status, created, updated FROM subtree ORDER BY depth, created, id;
+"#;
+
+/*
+The whole subtree is deleted in one statement, so the parent foreign key is only checked once
+every row is gone
+ */
+const DELETE_TOPIC_SUBTREE: &str = r#"
+WITH RECURSIVE subtree AS (
+  SELECT id FROM topics WHERE id = $1
+  UNION ALL
+  SELECT c.id FROM topics c JOIN subtree s ON c.parent_id = s.id
+)
+DELETE FROM topics WHERE id IN (SELECT id FROM subtree);
+"#;
+
+const REPARENT_TOPIC_CHILDREN: &str = r#"
+UPDATE topics c
+SET parent_id = t.parent_id, updated = now()
+FROM topics t
+WHERE t.id = $1 AND c.parent_id = t.id;
+"#;
+
+/*
+Keeps topics that have every tag in $3 and any tag in $4, every attribute key in $5, the
+attributes in $6 as strings, numbers or bools with that text, and one of the statuses in $7. $5 has
+the keys of $6 too, so the attributes index narrows the search. Any of them is skipped when it's
+null
+ */
+const TOPIC_LIST_FILTER: &str = r#"
+($3::text[] IS NULL OR $3::text[] <@ array(SELECT tag::text FROM topic_tags tt WHERE tt.topic_id = topics.id))
+AND ($4::text[] IS NULL OR $4::text[] && array(SELECT tag::text FROM topic_tags tt WHERE tt.topic_id = topics.id))
+AND ($5::text[] IS NULL OR attributes ?& $5::text[])
+AND ($6::jsonb IS NULL OR NOT EXISTS (
+  SELECT 1 FROM jsonb_each_text($6::jsonb) f
+  WHERE jsonb_typeof(attributes -> f.key) NOT IN ('string', 'number', 'boolean')
+    OR attributes ->> f.key <> f.value
+))
+AND ($7::text[] IS NULL OR status = any($7::text[]))
+"#;
+
+/*
+Only moves the topic if it's still in status $2, recording the change in the same statement. No
+rows means the topic is missing or its status already changed
+ */
+const CHANGE_TOPIC_STATUS: &str = r#"
+WITH changed AS (
+  UPDATE topics SET status = $3, updated = $5 WHERE id = $1 AND status = $2
+  RETURNING id, parent_id, name, description, attributes, status, created, updated
+), recorded AS (
+  INSERT INTO topic_status_changes (topic_id, from_status, to_status, reason, changed)
+  SELECT id, $2, $3, $4, $5 FROM changed
+)
+SELECT * FROM changed
+"#;
+
+/// The same page of topics as `list`, with only `fields` selected. Prepared per request since
+/// there's a statement for every combination of fields
+pub fn list_topic_fields(fields: &[TopicField]) -> String {
+    let columns: Vec<_> = fields.iter().map(|f| f.name()).collect();
+    format!(
+        "select {} from topics where {TOPIC_LIST_FILTER} order by created, id offset $1 limit $2",
+        columns.join(", ")
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct TopicStatements {
     pub get: Statement,
+    pub get_many: Statement,
     pub list: Statement,
+    pub children: Statement,
+    pub ancestors: Statement,
+    pub subtree: Statement,
     pub create: Statement,
+    pub upsert: Statement,
     pub patch_name_desc: Statement,
     pub patch_name: Statement,
     pub patch_desc: Statement,
+    pub patch_parent: Statement,
+    pub patch_many: Statement,
+    pub names_taken: Statement,
     pub delete: Statement,
+    pub delete_subtree: Statement,
+    pub reparent_children: Statement,
+    pub delete_many: Statement,
+    pub children_of_any: Statement,
+    pub create_link: Statement,
+    pub get_link: Statement,
+    pub update_link: Statement,
+    pub delete_link: Statement,
+    pub outgoing_links: Statement,
+    pub incoming_links: Statement,
+    pub links: Statement,
+    pub links_touching: Statement,
+    pub tags: Statement,
+    pub add_tags: Statement,
+    pub remove_tags: Statement,
+    pub tag_counts: Statement,
+    pub change_status: Statement,
+    pub status_changes: Statement,
+    pub versions: Statement,
+    pub version: Statement,
+    pub version_at: Statement,
 }
 
 impl TopicStatements {
@@ -22,46 +187,112 @@ impl TopicStatements {
         Ok(Self {
             get: client
                 .prepare_typed(
-                    "select id, name, description, created, updated from topics where id = $1",
+                    "select id, parent_id, name, description, attributes, status, created, updated from topics where id = $1",
                     &[Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            get_many: client
+                .prepare_typed(
+                    "select id, parent_id, name, description, attributes, status, created, updated from topics where id = any($1)",
+                    &[Type::UUID_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
             list: client
                 .prepare_typed(
-                    "select id, name, description, created, updated from topics offset $1 limit $2",
-                    &[Type::INT8, Type::INT8],
+                    &list_topic_fields(&TopicField::ALL),
+                    &[
+                        Type::INT8,
+                        Type::INT8,
+                        Type::TEXT_ARRAY,
+                        Type::TEXT_ARRAY,
+                        Type::TEXT_ARRAY,
+                        Type::JSONB,
+                        Type::TEXT_ARRAY,
+                    ],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            children: client
+                .prepare_typed(
+                    "select id, parent_id, name, description, attributes, status, created, updated from topics where parent_id = $1 order by created, id offset $2 limit $3",
+                    &[Type::UUID, Type::INT8, Type::INT8],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            ancestors: client
+                .prepare_typed(TOPIC_ANCESTORS, &[Type::UUID])
+                .await
+                .change_context(StatementPrepareError)?,
+            subtree: client
+                .prepare_typed(TOPIC_SUBTREE, &[Type::UUID])
+                .await
+                .change_context(StatementPrepareError)?,
             create: client
                 .prepare_typed(
-                    "insert into topics (id, name, description) values ($1, $2, $3) returning id, name, description, created, updated",
-                    &[Type::UUID, Type::VARCHAR, Type::VARCHAR],
+                    "insert into topics (id, name, description, parent_id, attributes) values ($1, $2, $3, $4, $5) returning id, parent_id, name, description, attributes, status, created, updated",
+                    &[Type::UUID, Type::VARCHAR, Type::VARCHAR, Type::UUID, Type::JSONB],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            upsert: client
+                .prepare_typed(
+                    UPSERT_TOPIC,
+                    &[Type::UUID, Type::VARCHAR, Type::VARCHAR, Type::UUID, Type::JSONB],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             patch_name_desc: client
                 .prepare_typed(
-                    "update topics set name = $1, description = $2, updated = now() where id = $3 returning id, name, description, created, updated",
+                    "update topics set name = $1, description = $2, updated = now() where id = $3 returning id, parent_id, name, description, attributes, status, created, updated",
                     &[Type::VARCHAR, Type::VARCHAR, Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             patch_name: client
                 .prepare_typed(
-                    "update topics set name = $1, updated = now() where id = $2 returning id, name, description, created, updated",
+                    "update topics set name = $1, updated = now() where id = $2 returning id, parent_id, name, description, attributes, status, created, updated",
                     &[Type::VARCHAR, Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             patch_desc: client
                 .prepare_typed(
-                    "update topics set description = $1, updated = now() where id = $2 returning id, name, description, created, updated",
+                    "update topics set description = $1, updated = now() where id = $2 returning id, parent_id, name, description, attributes, status, created, updated",
                     &[Type::VARCHAR, Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            patch_parent: client
+                .prepare_typed(
+                    PATCH_TOPIC_PARENT,
+                    &[Type::UUID, Type::VARCHAR, Type::BOOL, Type::VARCHAR, Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            patch_many: client
+                .prepare_typed(
+                    PATCH_MANY_TOPICS,
+                    &[
+                        Type::UUID_ARRAY,
+                        Type::VARCHAR_ARRAY,
+                        Type::BOOL_ARRAY,
+                        Type::VARCHAR_ARRAY,
+                        Type::BOOL_ARRAY,
+                        Type::UUID_ARRAY,
+                        Type::JSONB_ARRAY,
+                    ],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            names_taken: client
+                .prepare_typed(
+                    "select id, lower(name) as name from topics where lower(name) = any($1)",
+                    &[Type::TEXT_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
             delete: client
                 .prepare_typed(
                     "delete from topics where id = $1",
@@ -69,6 +300,153 @@ impl TopicStatements {
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            delete_subtree: client
+                .prepare_typed(DELETE_TOPIC_SUBTREE, &[Type::UUID])
+                .await
+                .change_context(StatementPrepareError)?,
+            reparent_children: client
+                .prepare_typed(REPARENT_TOPIC_CHILDREN, &[Type::UUID])
+                .await
+                .change_context(StatementPrepareError)?,
+            delete_many: client
+                .prepare_typed(
+                    "delete from topics where id = any($1) returning id",
+                    &[Type::UUID_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            children_of_any: client
+                .prepare_typed(
+                    "select id, parent_id from topics where parent_id = any($1)",
+                    &[Type::UUID_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            create_link: client
+                .prepare_typed(
+                    "insert into topic_links (source_id, target_id, link_type) values ($1, $2, $3) returning source_id, target_id, link_type, created, updated",
+                    &[Type::UUID, Type::UUID, Type::VARCHAR],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            get_link: client
+                .prepare_typed(
+                    "select source_id, target_id, link_type, created, updated from topic_links where source_id = $1 and target_id = $2",
+                    &[Type::UUID, Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            update_link: client
+                .prepare_typed(
+                    "update topic_links set link_type = $3, updated = now() where source_id = $1 and target_id = $2 returning source_id, target_id, link_type, created, updated",
+                    &[Type::UUID, Type::UUID, Type::VARCHAR],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            delete_link: client
+                .prepare_typed(
+                    "delete from topic_links where source_id = $1 and target_id = $2",
+                    &[Type::UUID, Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            outgoing_links: client
+                .prepare_typed(
+                    "select source_id, target_id, link_type, created, updated from topic_links where source_id = $1 order by created, target_id offset $2 limit $3",
+                    &[Type::UUID, Type::INT8, Type::INT8],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            incoming_links: client
+                .prepare_typed(
+                    "select source_id, target_id, link_type, created, updated from topic_links where target_id = $1 order by created, source_id offset $2 limit $3",
+                    &[Type::UUID, Type::INT8, Type::INT8],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            links: client
+                .prepare_typed(
+                    "select source_id, target_id, link_type, created, updated from topic_links where source_id = $1 or target_id = $1 order by created, source_id, target_id offset $2 limit $3",
+                    &[Type::UUID, Type::INT8, Type::INT8],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            links_touching: client
+                .prepare_typed(
+                    "select source_id, target_id, link_type, created, updated from topic_links where source_id = any($1) or target_id = any($1)",
+                    &[Type::UUID_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            tags: client
+                .prepare_typed(
+                    "select tag from topic_tags where topic_id = $1 order by tag",
+                    &[Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            add_tags: client
+                .prepare_typed(
+                    "insert into topic_tags (topic_id, tag) select $1, unnest($2::varchar[]) on conflict do nothing",
+                    &[Type::UUID, Type::VARCHAR_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            remove_tags: client
+                .prepare_typed(
+                    "delete from topic_tags where topic_id = $1 and tag = any($2)",
+                    &[Type::UUID, Type::VARCHAR_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            tag_counts: client
+                .prepare_typed(
+                    "select tag, count(*) as count from topic_tags group by tag order by tag",
+                    &[],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            change_status: client
+                .prepare_typed(
+                    CHANGE_TOPIC_STATUS,
+                    &[
+                        Type::UUID,
+                        Type::VARCHAR,
+                        Type::VARCHAR,
+                        Type::VARCHAR,
+                        Type::TIMESTAMPTZ,
+                    ],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            status_changes: client
+                .prepare_typed(
+                    "select from_status, to_status, reason, changed from topic_status_changes where topic_id = $1 order by changed",
+                    &[Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            versions: client
+                .prepare_typed(
+                    "select topic_id as id, version, parent_id, name, description, attributes, status, created, updated, recorded from topic_versions where topic_id = $1 order by version offset $2 limit $3",
+                    &[Type::UUID, Type::INT8, Type::INT8],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            version: client
+                .prepare_typed(
+                    "select topic_id as id, version, parent_id, name, description, attributes, status, created, updated, recorded from topic_versions where topic_id = $1 and version = $2",
+                    &[Type::UUID, Type::INT4],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            version_at: client
+                .prepare_typed(
+                    "select topic_id as id, version, parent_id, name, description, attributes, status, created, updated, recorded from topic_versions where topic_id = $1 and recorded <= $2 order by version desc limit 1",
+                    &[Type::UUID, Type::TIMESTAMPTZ],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
         })
     }
 }
@@ -89,24 +467,102 @@ LEFT JOIN sets s ON s.topic_id = t.id AND s.id = $2
 WHERE t.id = $1;
 "#;
 
+/*
+Same as getting a set, with a row for each of the given sets found in the topic. A single row with
+no set means the topic exists but none of the sets do
+ */
+const GET_MANY_SETS: &str = r#"
+SELECT
+  s.id IS NOT NULL AS set_exists,
+  s.*
+FROM topics t
+LEFT JOIN sets s ON s.topic_id = t.id AND s.id = ANY($2)
+WHERE t.id = $1;
+"#;
+
+/*
+Sets that don't have every tag in $4 or any tag in $5, or don't have the attributes in $6 and $7
+the way topics are filtered, are left out of the join. So a missing topic is still told apart from
+a topic with no matching sets
+ */
 const LIST_SET: &str = r#"
 SELECT
     s.*
 FROM topics t
          LEFT JOIN sets s ON s.topic_id = t.id
+    AND ($4::text[] IS NULL OR $4::text[] <@ array(SELECT tag::text FROM set_tags st WHERE st.set_id = s.id))
+    AND ($5::text[] IS NULL OR $5::text[] && array(SELECT tag::text FROM set_tags st WHERE st.set_id = s.id))
+    AND ($6::text[] IS NULL OR s.attributes ?& $6::text[])
+    AND ($7::jsonb IS NULL OR NOT EXISTS (
+      SELECT 1 FROM jsonb_each_text($7::jsonb) f
+      WHERE jsonb_typeof(s.attributes -> f.key) NOT IN ('string', 'number', 'boolean')
+        OR s.attributes ->> f.key <> f.value
+    ))
 WHERE t.id = $1
+ORDER BY s.created, s.id
 OFFSET $2 LIMIT $3;
 "#;
 
+/*
+The topic's sets as they were at $4, from the last version of each recorded by then. Like
+LIST_SET, a topic with no sets then still gives one row of nulls
+ */
+const LIST_SET_AT: &str = r#"
+SELECT
+    s.*
+FROM topics t
+         LEFT JOIN LATERAL (
+    SELECT DISTINCT ON (v.set_id) v.set_id AS id, v.topic_id, v.name, v.description, v.attributes, v.created, v.updated
+    FROM set_versions v
+    WHERE v.topic_id = t.id AND v.recorded <= $4
+    ORDER BY v.set_id, v.version DESC
+    ) s ON true
+WHERE t.id = $1
+ORDER BY s.created, s.id
+OFFSET $2 LIMIT $3;
+"#;
+
+/*
+Same as upserting a topic, except a set id used in another topic isn't updated, so no row comes back
+ */
+const UPSERT_SET: &str = r#"
+INSERT INTO sets (id, topic_id, name, description, attributes)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (id) DO UPDATE
+SET name = excluded.name, description = excluded.description, attributes = excluded.attributes, updated = now()
+WHERE sets.topic_id = excluded.topic_id
+RETURNING id, topic_id, name, description, attributes, created, updated, (xmax = 0) AS inserted;
+"#;
+
+/*
+Sets the attributes in $3 on the set, removing the ones that are null, the same way topics are patched
+ */
+const MERGE_SET_ATTRIBUTES: &str = r#"
+UPDATE sets
+SET
+  attributes = (attributes || $3) - array(SELECT key FROM jsonb_each($3) WHERE jsonb_typeof(value) = 'null'),
+  updated = now()
+WHERE topic_id = $1 AND id = $2
+RETURNING id, topic_id, name, description, attributes, created, updated;
+"#;
+
 #[derive(Debug, Clone)]
 pub struct SetStatements {
     pub get: Statement,
+    pub get_many: Statement,
     pub list: Statement,
+    pub list_at: Statement,
     pub create: Statement,
+    pub upsert: Statement,
     pub patch_name_desc: Statement,
     pub patch_name: Statement,
     pub patch_desc: Statement,
+    pub merge_attributes: Statement,
     pub delete: Statement,
+    pub tags: Statement,
+    pub add_tags: Statement,
+    pub remove_tags: Statement,
+    pub tag_counts: Statement,
 }
 
 impl SetStatements {
@@ -119,41 +575,77 @@ impl SetStatements {
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            get_many: client
+                .prepare_typed(
+                    GET_MANY_SETS,
+                    &[Type::UUID, Type::UUID_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
             list: client
                 .prepare_typed(
                     LIST_SET,
-                    &[Type::UUID, Type::INT8, Type::INT8],
+                    &[
+                        Type::UUID,
+                        Type::INT8,
+                        Type::INT8,
+                        Type::TEXT_ARRAY,
+                        Type::TEXT_ARRAY,
+                        Type::TEXT_ARRAY,
+                        Type::JSONB,
+                    ],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            list_at: client
+                .prepare_typed(
+                    LIST_SET_AT,
+                    &[Type::UUID, Type::INT8, Type::INT8, Type::TIMESTAMPTZ],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             create: client
                 .prepare_typed(
-                    "insert into sets (id, topic_id, name, description) values ($1, $2, $3, $4) returning id, topic_id, name, description, created, updated",
-                    &[Type::UUID, Type::UUID, Type::VARCHAR, Type::VARCHAR],
+                    "insert into sets (id, topic_id, name, description, attributes) values ($1, $2, $3, $4, $5) returning id, topic_id, name, description, attributes, created, updated",
+                    &[Type::UUID, Type::UUID, Type::VARCHAR, Type::VARCHAR, Type::JSONB],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            upsert: client
+                .prepare_typed(
+                    UPSERT_SET,
+                    &[Type::UUID, Type::UUID, Type::VARCHAR, Type::VARCHAR, Type::JSONB],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             patch_name_desc: client
                 .prepare_typed(
-                    "update sets set name = $1, description = $2, updated = now() where id = $3 and topic_id = $4 returning id, topic_id, name, description, created, updated",
+                    "update sets set name = $1, description = $2, updated = now() where id = $3 and topic_id = $4 returning id, topic_id, name, description, attributes, created, updated",
                     &[Type::VARCHAR, Type::VARCHAR, Type::UUID, Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             patch_name: client
                 .prepare_typed(
-                    "update sets set name = $1, updated = now() where id = $2 and topic_id = $3 returning id, topic_id, name, description, created, updated",
+                    "update sets set name = $1, updated = now() where id = $2 and topic_id = $3 returning id, topic_id, name, description, attributes, created, updated",
                     &[Type::VARCHAR, Type::UUID, Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
             patch_desc: client
                 .prepare_typed(
-                    "update sets set description = $1, updated = now() where id = $2 and topic_id = $3 returning id, topic_id, name, description, created, updated",
+                    "update sets set description = $1, updated = now() where id = $2 and topic_id = $3 returning id, topic_id, name, description, attributes, created, updated",
                     &[Type::VARCHAR, Type::UUID, Type::UUID],
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            merge_attributes: client
+                .prepare_typed(
+                    MERGE_SET_ATTRIBUTES,
+                    &[Type::UUID, Type::UUID, Type::JSONB],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
             delete: client
                 .prepare_typed(
                     "delete from sets where id = $1 and topic_id = $2",
@@ -161,6 +653,139 @@ impl SetStatements {
                 )
                 .await
                 .change_context(StatementPrepareError)?,
+            tags: client
+                .prepare_typed(
+                    "select tag from set_tags where set_id = $1 order by tag",
+                    &[Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            add_tags: client
+                .prepare_typed(
+                    "insert into set_tags (set_id, tag) select $1, unnest($2::varchar[]) on conflict do nothing",
+                    &[Type::UUID, Type::VARCHAR_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            remove_tags: client
+                .prepare_typed(
+                    "delete from set_tags where set_id = $1 and tag = any($2)",
+                    &[Type::UUID, Type::VARCHAR_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            tag_counts: client
+                .prepare_typed(
+                    "select tag, count(*) as count from set_tags group by tag order by tag",
+                    &[],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyStatements {
+    pub find_by_hash: Statement,
+    pub create: Statement,
+    pub list: Statement,
+    pub revoke: Statement,
+}
+
+impl ApiKeyStatements {
+    pub async fn prepare(client: &Client) -> Result<Self, Report<StatementPrepareError>> {
+        Ok(Self {
+            find_by_hash: client
+                .prepare_typed(
+                    "select id, owner, roles, created, revoked from api_keys where key_hash = $1",
+                    &[Type::VARCHAR],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            create: client
+                .prepare_typed(
+                    "insert into api_keys (id, key_hash, owner, roles) values ($1, $2, $3, $4) returning id, owner, roles, created, revoked",
+                    &[Type::UUID, Type::VARCHAR, Type::VARCHAR, Type::TEXT_ARRAY],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            list: client
+                .prepare_typed(
+                    "select id, owner, roles, created, revoked from api_keys order by created, id",
+                    &[],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            revoke: client
+                .prepare_typed(
+                    "update api_keys set revoked = coalesce(revoked, now()) where id = $1 returning id, owner, roles, created, revoked",
+                    &[Type::UUID],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+        })
+    }
+}
+
+/*
+Only takes the key over when it has expired, so reserving doesn't touch any other row. Expired
+keys that aren't reserved again are left for the periodic purge
+ */
+const RESERVE_IDEMPOTENCY_KEY: &str = r#"
+INSERT INTO idempotency_keys (owner, key, request_hash, expires)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (owner, key) DO UPDATE
+SET request_hash = excluded.request_hash, status = NULL, content_type = NULL, body = NULL,
+  created = now(), expires = excluded.expires
+WHERE idempotency_keys.expires <= now()
+RETURNING owner;
+"#;
+
+#[derive(Debug, Clone)]
+pub struct IdempotencyStatements {
+    pub purge_expired: Statement,
+    pub reserve: Statement,
+    pub find: Statement,
+    pub complete: Statement,
+    pub release: Statement,
+}
+
+impl IdempotencyStatements {
+    pub async fn prepare(client: &Client) -> Result<Self, Report<StatementPrepareError>> {
+        Ok(Self {
+            purge_expired: client
+                .prepare_typed("delete from idempotency_keys where expires <= now()", &[])
+                .await
+                .change_context(StatementPrepareError)?,
+            reserve: client
+                .prepare_typed(
+                    RESERVE_IDEMPOTENCY_KEY,
+                    &[Type::VARCHAR, Type::VARCHAR, Type::VARCHAR, Type::TIMESTAMPTZ],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            find: client
+                .prepare_typed(
+                    "select request_hash, status, content_type, body from idempotency_keys where owner = $1 and key = $2",
+                    &[Type::VARCHAR, Type::VARCHAR],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            complete: client
+                .prepare_typed(
+                    "update idempotency_keys set status = $3, content_type = $4, body = $5 where owner = $1 and key = $2",
+                    &[Type::VARCHAR, Type::VARCHAR, Type::INT2, Type::VARCHAR, Type::BYTEA],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
+            release: client
+                .prepare_typed(
+                    "delete from idempotency_keys where owner = $1 and key = $2 and status is null",
+                    &[Type::VARCHAR, Type::VARCHAR],
+                )
+                .await
+                .change_context(StatementPrepareError)?,
         })
     }
 }
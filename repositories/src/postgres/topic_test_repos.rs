@@ -1,21 +1,119 @@
+use chrono::{DateTime, Utc};
 use error_stack::IntoReport;
 use indexmap::IndexMap;
 use optional_field::Field;
-use routing::ArwLock;
+use routing::{ArwLock, attributes};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio_stream::Stream;
 use topics_core::{
     TopicRepository,
     list_filter::{TopicFilter, TopicListCriteria},
-    model::{NewTopic, PatchTopic, Topic},
+    model::{
+        DeletePolicy, LinkDirection, LinkType, NewTopic, PartialTopic, PatchTopic, StatusChange,
+        TagCount, Topic, TopicField, TopicLink, TopicVersion, Upserted, undeletable_parents,
+    },
     result::{CreateErrorType, OptRepoResult, RepoResult, TopicRepoError},
 };
 
 use crate::postgres::topics::TopicId;
 
+#[cfg(test)]
 mod tests;
 
 #[derive(Clone, Default)]
 pub struct InMemoryTopicsRepo {
     db: ArwLock<IndexMap<TopicId, Topic<TopicId>>>,
+    links: ArwLock<IndexMap<(TopicId, TopicId), TopicLink<TopicId>>>,
+    tags: ArwLock<HashMap<TopicId, BTreeSet<String>>>,
+    status_changes: ArwLock<HashMap<TopicId, Vec<StatusChange>>>,
+    versions: ArwLock<HashMap<TopicId, Vec<TopicVersion<TopicId>>>>,
+    unique_names: bool,
+    panic_on_create: Arc<AtomicBool>,
+}
+
+impl InMemoryTopicsRepo {
+    /// Behaves like a postgres repo created with unique names
+    pub fn with_unique_names() -> Self {
+        Self {
+            unique_names: true,
+            ..Default::default()
+        }
+    }
+
+    /// Makes the next create panic before it writes anything, like a bug in a handler would
+    pub fn panic_on_next_create(&self) {
+        self.panic_on_create.store(true, Ordering::SeqCst);
+    }
+}
+
+fn name_taken(
+    db: &IndexMap<TopicId, Topic<TopicId>>,
+    unique_names: bool,
+    name: &str,
+    except: Option<TopicId>,
+) -> bool {
+    unique_names
+        && db
+            .values()
+            .any(|t| Some(t.id) != except && t.name.to_lowercase() == name.to_lowercase())
+}
+
+/// Walks up from `parent_id` the same way the postgres cycle trigger does
+fn check_parent(
+    db: &IndexMap<TopicId, Topic<TopicId>>,
+    id: Option<TopicId>,
+    parent_id: Option<TopicId>,
+) -> RepoResult<()> {
+    let Some(mut ancestor) = parent_id else {
+        return Ok(());
+    };
+    if !db.contains_key(&ancestor) {
+        return Err(TopicRepoError::ParentNotFound.into_report());
+    }
+    loop {
+        if Some(ancestor) == id {
+            return Err(TopicRepoError::ParentCycle.into_report());
+        }
+        match db.get(&ancestor).and_then(|t| t.parent_id) {
+            Some(parent) => ancestor = parent,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// The topic followed by everything nested under it, parents before their children
+fn subtree_ids(db: &IndexMap<TopicId, Topic<TopicId>>, id: TopicId) -> Vec<TopicId> {
+    let mut subtree = vec![id];
+    let mut next = 0;
+    while let Some(&parent) = subtree.get(next) {
+        subtree.extend(
+            db.values()
+                .filter(|t| t.parent_id == Some(parent))
+                .map(|t| t.id),
+        );
+        next += 1;
+    }
+    subtree
+}
+
+/// Keeps the topic as its next version, like the postgres history trigger
+fn record_version(
+    versions: &mut HashMap<TopicId, Vec<TopicVersion<TopicId>>>,
+    topic: &Topic<TopicId>,
+) {
+    let history = versions.entry(topic.id).or_default();
+    let version = history.len() as u64 + 1;
+    history.push(TopicVersion::new(version, topic.clone(), Utc::now()));
+}
+
+/// Links go with either of their topics, like the postgres cascade
+fn drop_dangling_links(
+    links: &mut IndexMap<(TopicId, TopicId), TopicLink<TopicId>>,
+    db: &IndexMap<TopicId, Topic<TopicId>>,
+) {
+    links.retain(|_, l| db.contains_key(&l.source_id) && db.contains_key(&l.target_id));
 }
 
 impl TopicRepository for InMemoryTopicsRepo {
@@ -27,75 +125,606 @@ impl TopicRepository for InMemoryTopicsRepo {
         Ok(db.get(&id).cloned())
     }
 
+    async fn get_many(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<Option<Topic<Self::TopicId>>>> {
+        let db = self.db.read().await;
+
+        Ok(ids.iter().map(|id| db.get(id).cloned()).collect())
+    }
+
     async fn list(
         &self,
         list_criteria: TopicListCriteria,
     ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
         let db = self.db.read().await;
+        let tags = self.tags.read().await;
+        let no_tags = BTreeSet::new();
 
+        // filtered before paging, like a where clause
         db.values()
-            .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
             .filter(|topic| {
-                if let Some(filters) = list_criteria.filters() {
-                    filters.iter().any(|f| match f {
+                let topic_tags = tags.get(&topic.id).unwrap_or(&no_tags);
+                list_criteria
+                    .filters()
+                    .unwrap_or_default()
+                    .iter()
+                    .all(|f| match f {
                         TopicFilter::Name(n) => topic.name.contains(n),
+                        TopicFilter::AllTags(all) => all.iter().all(|t| topic_tags.contains(t)),
+                        TopicFilter::AnyTag(any) => any.iter().any(|t| topic_tags.contains(t)),
+                        TopicFilter::Attributes(attributes) => {
+                            attributes.iter().all(|a| a.matches(&topic.attributes))
+                        }
+                        TopicFilter::Status(statuses) => statuses.contains(&topic.status),
                     })
-                } else {
-                    true
-                }
             })
+            .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
             .take(list_criteria.page_size() as usize)
             .cloned()
             .map(Ok)
             .collect()
     }
 
-    async fn create(&self, new_topic: NewTopic) -> RepoResult<Topic<Self::TopicId>> {
+    async fn list_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+    ) -> RepoResult<impl Stream<Item = RepoResult<Topic<Self::TopicId>>> + Send + 'static + use<>>
+    {
+        let topics = self.list(list_criteria).await?;
+        Ok(tokio_stream::iter(topics.into_iter().map(Ok)))
+    }
+
+    async fn list_fields_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+        fields: Vec<TopicField>,
+    ) -> RepoResult<
+        impl Stream<Item = RepoResult<PartialTopic<Self::TopicId>>> + Send + 'static + use<>,
+    > {
+        let topics = self.list(list_criteria).await?;
+        Ok(tokio_stream::iter(
+            topics
+                .into_iter()
+                .map(move |t| Ok(PartialTopic::project(t, &fields))),
+        ))
+    }
+
+    async fn children(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let db = self.db.read().await;
+        if !db.contains_key(&id) {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            db.values()
+                .filter(|t| t.parent_id == Some(id))
+                .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
+                .take(list_criteria.page_size() as usize)
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    async fn ancestors(&self, id: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let db = self.db.read().await;
+        let Some(topic) = db.get(&id) else {
+            return Ok(None);
+        };
+
+        let mut ancestors = Vec::new();
+        let mut parent_id = topic.parent_id;
+        while let Some(parent) = parent_id.and_then(|p| db.get(&p)) {
+            ancestors.push(parent.clone());
+            parent_id = parent.parent_id;
+        }
+        ancestors.reverse();
+
+        Ok(Some(ancestors))
+    }
+
+    async fn subtree(&self, id: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let db = self.db.read().await;
+        if !db.contains_key(&id) {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            subtree_ids(&db, id)
+                .iter()
+                .filter_map(|id| db.get(id).cloned())
+                .collect(),
+        ))
+    }
+
+    async fn create(&self, new_topic: NewTopic<Self::TopicId>) -> RepoResult<Topic<Self::TopicId>> {
+        if self.panic_on_create.swap(false, Ordering::SeqCst) {
+            panic!("create was set up to panic");
+        }
         let mut db = self.db.write().await;
+        if name_taken(&db, self.unique_names, &new_topic.name, None) {
+            return Err(TopicRepoError::DuplicateName.into_report());
+        }
+        check_parent(&db, None, new_topic.parent_id)?;
         let id = TopicId::new();
-        let topic = Topic::create(id, new_topic.name, new_topic.description);
+        let topic = Topic::create(id, new_topic.name, new_topic.description)
+            .with_parent(new_topic.parent_id)
+            .with_attributes(new_topic.attributes);
         db.insert(id, topic.clone());
+        record_version(&mut *self.versions.write().await, &topic);
 
         Ok(topic)
     }
 
     async fn create_many(
         &self,
-        topics: Vec<NewTopic>,
+        topics: Vec<NewTopic<Self::TopicId>>,
     ) -> RepoResult<Vec<RepoResult<Topic<Self::TopicId>>>> {
         let mut db = self.db.write().await;
-        Ok(topics
+        let results: Vec<_> = topics
             .into_iter()
-            .map(|t| Topic::create(TopicId::new(), t.name, t.description))
+            .map(|t| {
+                Topic::create(TopicId::new(), t.name, t.description)
+                    .with_parent(t.parent_id)
+                    .with_attributes(t.attributes)
+            })
             .map(|topic| {
+                if name_taken(&db, self.unique_names, &topic.name, None) {
+                    return Err(TopicRepoError::DuplicateName.into_report());
+                }
+                check_parent(&db, None, topic.parent_id)?;
                 db.insert(topic.id, topic.clone());
                 Ok(topic)
             })
-            .collect())
+            .collect();
+
+        let mut versions = self.versions.write().await;
+        for topic in results.iter().flatten() {
+            record_version(&mut versions, topic);
+        }
+        Ok(results)
+    }
+
+    async fn create_many_atomic(
+        &self,
+        topics: Vec<NewTopic<Self::TopicId>>,
+    ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
+        let mut db = self.db.write().await;
+        let mut created: IndexMap<TopicId, Topic<TopicId>> = IndexMap::new();
+
+        for new_topic in topics {
+            if name_taken(&db, self.unique_names, &new_topic.name, None)
+                || name_taken(&created, self.unique_names, &new_topic.name, None)
+            {
+                return Err(TopicRepoError::DuplicateName.into_report());
+            }
+            check_parent(&db, None, new_topic.parent_id)?;
+            let id = TopicId::new();
+            let topic = Topic::create(id, new_topic.name, new_topic.description)
+                .with_parent(new_topic.parent_id)
+                .with_attributes(new_topic.attributes);
+            created.insert(id, topic);
+        }
+
+        let topics: Vec<_> = created.values().cloned().collect();
+        db.extend(created);
+        let mut versions = self.versions.write().await;
+        for topic in &topics {
+            record_version(&mut versions, topic);
+        }
+        Ok(topics)
+    }
+
+    async fn upsert(
+        &self,
+        id: Self::TopicId,
+        topic: NewTopic<Self::TopicId>,
+    ) -> RepoResult<Upserted<Topic<Self::TopicId>>> {
+        let mut db = self.db.write().await;
+        if name_taken(&db, self.unique_names, &topic.name, Some(id)) {
+            return Err(TopicRepoError::DuplicateName.into_report());
+        }
+        check_parent(&db, Some(id), topic.parent_id)?;
+
+        let upserted = match db.get_mut(&id) {
+            Some(existing) => {
+                existing.name = topic.name;
+                existing.description = topic.description;
+                existing.parent_id = topic.parent_id;
+                existing.attributes = topic.attributes;
+                existing.updated = Some(Utc::now());
+                Upserted::Replaced(existing.clone())
+            }
+            None => {
+                let created = Topic::create(id, topic.name, topic.description)
+                    .with_parent(topic.parent_id)
+                    .with_attributes(topic.attributes);
+                db.insert(id, created.clone());
+                Upserted::Created(created)
+            }
+        };
+        let (Upserted::Created(topic) | Upserted::Replaced(topic)) = &upserted;
+        record_version(&mut *self.versions.write().await, topic);
+        Ok(upserted)
     }
 
     async fn patch(
         &self,
         id: Self::TopicId,
-        patch: PatchTopic,
+        patch: PatchTopic<Self::TopicId>,
     ) -> OptRepoResult<Topic<Self::TopicId>> {
         let mut db = self.db.write().await;
 
-        Ok(db.get_mut(&id).map(|topic| {
-            if let Some(name) = patch.name {
-                topic.name = name;
-            }
+        if let Some(name) = &patch.name
+            && name_taken(&db, self.unique_names, name, Some(id))
+        {
+            return Err(TopicRepoError::DuplicateName.into_report());
+        }
+        if let Field::Present(parent_id) = patch.parent_id
+            && db.contains_key(&id)
+        {
+            check_parent(&db, Some(id), parent_id)?;
+        }
 
-            if let Field::Present(desc) = patch.description {
-                topic.description = desc
+        let Some(topic) = db.get_mut(&id) else {
+            return Ok(None);
+        };
+        let before = topic.clone();
+        if let Some(name) = patch.name {
+            topic.name = name;
+        }
+
+        if let Field::Present(desc) = patch.description {
+            topic.description = desc
+        }
+        if let Field::Present(parent_id) = patch.parent_id {
+            topic.parent_id = parent_id;
+        }
+        if let Some(attributes) = patch.attributes {
+            attributes::merge(&mut topic.attributes, attributes);
+        }
+        // a patch that changes nothing isn't a new version
+        if *topic != before {
+            record_version(&mut *self.versions.write().await, topic);
+        }
+        Ok(Some(topic.clone()))
+    }
+
+    async fn patch_many(
+        &self,
+        patches: Vec<(Self::TopicId, PatchTopic<Self::TopicId>)>,
+    ) -> RepoResult<Vec<OptRepoResult<Topic<Self::TopicId>>>> {
+        let mut results = Vec::with_capacity(patches.len());
+        for (id, patch) in patches {
+            results.push(self.patch(id, patch).await);
+        }
+        Ok(results)
+    }
+
+    async fn delete(&self, id: Self::TopicId, policy: DeletePolicy) -> OptRepoResult<()> {
+        let mut db = self.db.write().await;
+        let Some(parent_id) = db.get(&id).map(|t| t.parent_id) else {
+            return Ok(None);
+        };
+
+        match policy {
+            DeletePolicy::Reject => {
+                if db.values().any(|t| t.parent_id == Some(id)) {
+                    return Err(TopicRepoError::HasChildren.into_report());
+                }
             }
-            topic.clone()
-        }))
+            DeletePolicy::Cascade => {
+                for descendant in subtree_ids(&db, id).into_iter().skip(1) {
+                    db.shift_remove(&descendant);
+                }
+            }
+            DeletePolicy::Reparent => {
+                let mut versions = self.versions.write().await;
+                for child in db.values_mut().filter(|t| t.parent_id == Some(id)) {
+                    child.parent_id = parent_id;
+                    child.updated = Some(Utc::now());
+                    record_version(&mut versions, child);
+                }
+            }
+        }
+
+        let deleted = db.shift_remove(&id).map(|_| ());
+        drop_dangling_links(&mut *self.links.write().await, &db);
+        self.tags.write().await.retain(|id, _| db.contains_key(id));
+        self.status_changes
+            .write()
+            .await
+            .retain(|id, _| db.contains_key(id));
+        self.versions
+            .write()
+            .await
+            .retain(|id, _| db.contains_key(id));
+        Ok(deleted)
     }
 
-    async fn delete(&self, id: Self::TopicId) -> OptRepoResult<()> {
+    async fn delete_many(&self, ids: Vec<Self::TopicId>) -> RepoResult<Vec<OptRepoResult<()>>> {
         let mut db = self.db.write().await;
-        Ok(db.shift_remove(&id).map(|_| ()))
+        let children: Vec<_> = db
+            .values()
+            .filter_map(|t| t.parent_id.filter(|p| ids.contains(p)).map(|p| (t.id, p)))
+            .collect();
+        let undeletable = undeletable_parents(&ids, &children);
+
+        let deleted = ids
+            .into_iter()
+            .map(|id| {
+                if undeletable.contains(&id) {
+                    Err(TopicRepoError::HasChildren.into_report())
+                } else {
+                    Ok(db.shift_remove(&id).map(|_| ()))
+                }
+            })
+            .collect();
+        drop_dangling_links(&mut *self.links.write().await, &db);
+        self.tags.write().await.retain(|id, _| db.contains_key(id));
+        self.status_changes
+            .write()
+            .await
+            .retain(|id, _| db.contains_key(id));
+        self.versions
+            .write()
+            .await
+            .retain(|id, _| db.contains_key(id));
+        Ok(deleted)
+    }
+
+    async fn create_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        if source_id == target_id {
+            return Err(TopicRepoError::SelfLink.into_report());
+        }
+
+        let db = self.db.read().await;
+        if !db.contains_key(&source_id) {
+            return Ok(None);
+        }
+        if !db.contains_key(&target_id) {
+            return Err(TopicRepoError::LinkTargetNotFound.into_report());
+        }
+
+        let mut links = self.links.write().await;
+        if links.contains_key(&(source_id, target_id)) {
+            return Err(TopicRepoError::LinkExists.into_report());
+        }
+
+        let link = TopicLink::new(source_id, target_id, link_type, Utc::now());
+        links.insert((source_id, target_id), link.clone());
+        Ok(Some(link))
+    }
+
+    async fn get_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        Ok(self
+            .links
+            .read()
+            .await
+            .get(&(source_id, target_id))
+            .cloned())
+    }
+
+    async fn update_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        Ok(self
+            .links
+            .write()
+            .await
+            .get_mut(&(source_id, target_id))
+            .map(|link| {
+                link.link_type = link_type;
+                link.updated = Some(Utc::now());
+                link.clone()
+            }))
+    }
+
+    async fn delete_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> OptRepoResult<()> {
+        Ok(self
+            .links
+            .write()
+            .await
+            .shift_remove(&(source_id, target_id))
+            .map(|_| ()))
+    }
+
+    async fn links(
+        &self,
+        id: Self::TopicId,
+        direction: LinkDirection,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicLink<Self::TopicId>>> {
+        if !self.db.read().await.contains_key(&id) {
+            return Ok(None);
+        }
+
+        let matches = |link: &TopicLink<TopicId>| match direction {
+            LinkDirection::Outgoing => link.source_id == id,
+            LinkDirection::Incoming => link.target_id == id,
+            LinkDirection::Both => link.source_id == id || link.target_id == id,
+        };
+
+        Ok(Some(
+            self.links
+                .read()
+                .await
+                .values()
+                .filter(|&l| matches(l))
+                .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
+                .take(list_criteria.page_size() as usize)
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    async fn links_touching(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<TopicLink<Self::TopicId>>> {
+        Ok(self
+            .links
+            .read()
+            .await
+            .values()
+            .filter(|l| ids.contains(&l.source_id) || ids.contains(&l.target_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn tags(&self, id: Self::TopicId) -> OptRepoResult<Vec<String>> {
+        if !self.db.read().await.contains_key(&id) {
+            return Ok(None);
+        }
+
+        let tags = self.tags.read().await;
+        Ok(Some(tags.get(&id).into_iter().flatten().cloned().collect()))
+    }
+
+    async fn add_tags(&self, id: Self::TopicId, tags: Vec<String>) -> OptRepoResult<Vec<String>> {
+        if !self.db.read().await.contains_key(&id) {
+            return Ok(None);
+        }
+
+        let mut all = self.tags.write().await;
+        let topic_tags = all.entry(id).or_default();
+        topic_tags.extend(tags);
+        Ok(Some(topic_tags.iter().cloned().collect()))
+    }
+
+    async fn remove_tags(
+        &self,
+        id: Self::TopicId,
+        tags: Vec<String>,
+    ) -> OptRepoResult<Vec<String>> {
+        if !self.db.read().await.contains_key(&id) {
+            return Ok(None);
+        }
+
+        let mut all = self.tags.write().await;
+        let topic_tags = all.entry(id).or_default();
+        topic_tags.retain(|t| !tags.contains(t));
+        Ok(Some(topic_tags.iter().cloned().collect()))
+    }
+
+    async fn tag_counts(&self) -> RepoResult<Vec<TagCount>> {
+        let mut counts = IndexMap::<String, u64>::new();
+        for tag in self.tags.read().await.values().flatten() {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+        counts.sort_keys();
+
+        Ok(counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    }
+
+    async fn change_status(
+        &self,
+        id: Self::TopicId,
+        change: StatusChange,
+    ) -> OptRepoResult<Topic<Self::TopicId>> {
+        let mut db = self.db.write().await;
+        let Some(topic) = db.get_mut(&id) else {
+            return Ok(None);
+        };
+        if topic.status != change.from {
+            return Err(TopicRepoError::StatusChanged.into_report());
+        }
+
+        topic.status = change.to;
+        topic.updated = Some(change.changed);
+        let topic = topic.clone();
+        record_version(&mut *self.versions.write().await, &topic);
+        self.status_changes
+            .write()
+            .await
+            .entry(id)
+            .or_default()
+            .push(change);
+        Ok(Some(topic))
+    }
+
+    async fn status_changes(&self, id: Self::TopicId) -> OptRepoResult<Vec<StatusChange>> {
+        if !self.db.read().await.contains_key(&id) {
+            return Ok(None);
+        }
+
+        let changes = self.status_changes.read().await;
+        Ok(Some(changes.get(&id).cloned().unwrap_or_default()))
+    }
+
+    async fn versions(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicVersion<Self::TopicId>>> {
+        if !self.db.read().await.contains_key(&id) {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            self.versions
+                .read()
+                .await
+                .get(&id)
+                .into_iter()
+                .flatten()
+                .skip((list_criteria.page().saturating_sub(1) * list_criteria.page_size()) as usize)
+                .take(list_criteria.page_size() as usize)
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    async fn version(
+        &self,
+        id: Self::TopicId,
+        version: u64,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        Ok(self
+            .versions
+            .read()
+            .await
+            .get(&id)
+            .and_then(|history| history.iter().find(|v| v.version == version))
+            .cloned())
+    }
+
+    async fn version_at(
+        &self,
+        id: Self::TopicId,
+        at: DateTime<Utc>,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        Ok(self
+            .versions
+            .read()
+            .await
+            .get(&id)
+            .and_then(|history| history.iter().rev().find(|v| v.recorded <= at))
+            .cloned())
     }
 }
 
@@ -119,26 +748,195 @@ impl TopicRepository for FailingTopicsRepo {
         Err(TopicRepoError::Get.into_report())
     }
 
+    async fn get_many(
+        &self,
+        _: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<Option<Topic<Self::TopicId>>>> {
+        Err(TopicRepoError::Get.into_report())
+    }
+
     async fn list(&self, _: TopicListCriteria) -> RepoResult<Vec<Topic<Self::TopicId>>> {
         Err(TopicRepoError::List.into_report())
     }
 
-    async fn create(&self, _: NewTopic) -> RepoResult<Topic<Self::TopicId>> {
+    async fn list_stream(
+        &self,
+        _: TopicListCriteria,
+    ) -> RepoResult<impl Stream<Item = RepoResult<Topic<Self::TopicId>>> + Send + 'static + use<>>
+    {
+        Err::<tokio_stream::Empty<_>, _>(TopicRepoError::List.into_report())
+    }
+
+    async fn list_fields_stream(
+        &self,
+        _: TopicListCriteria,
+        _: Vec<TopicField>,
+    ) -> RepoResult<
+        impl Stream<Item = RepoResult<PartialTopic<Self::TopicId>>> + Send + 'static + use<>,
+    > {
+        Err::<tokio_stream::Empty<_>, _>(TopicRepoError::List.into_report())
+    }
+
+    async fn children(
+        &self,
+        _: Self::TopicId,
+        _: TopicListCriteria,
+    ) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        Err(TopicRepoError::List.into_report())
+    }
+
+    async fn ancestors(&self, _: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        Err(TopicRepoError::Get.into_report())
+    }
+
+    async fn subtree(&self, _: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        Err(TopicRepoError::Get.into_report())
+    }
+
+    async fn create(&self, _: NewTopic<Self::TopicId>) -> RepoResult<Topic<Self::TopicId>> {
         Err(TopicRepoError::Create(self.create_err_reason.clone()).into_report())
     }
 
     async fn create_many(
         &self,
-        _: Vec<NewTopic>,
+        _: Vec<NewTopic<Self::TopicId>>,
     ) -> RepoResult<Vec<RepoResult<Topic<Self::TopicId>>>> {
         Err(TopicRepoError::Create(self.create_err_reason.clone()).into_report())
     }
 
-    async fn patch(&self, _: Self::TopicId, _: PatchTopic) -> OptRepoResult<Topic<Self::TopicId>> {
+    async fn create_many_atomic(
+        &self,
+        _: Vec<NewTopic<Self::TopicId>>,
+    ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
+        Err(TopicRepoError::Create(self.create_err_reason.clone()).into_report())
+    }
+
+    async fn upsert(
+        &self,
+        _: Self::TopicId,
+        _: NewTopic<Self::TopicId>,
+    ) -> RepoResult<Upserted<Topic<Self::TopicId>>> {
+        Err(TopicRepoError::Upsert.into_report())
+    }
+
+    async fn patch(
+        &self,
+        _: Self::TopicId,
+        _: PatchTopic<Self::TopicId>,
+    ) -> OptRepoResult<Topic<Self::TopicId>> {
         Err(TopicRepoError::Patch.into_report())
     }
 
-    async fn delete(&self, _: Self::TopicId) -> OptRepoResult<()> {
+    async fn patch_many(
+        &self,
+        _: Vec<(Self::TopicId, PatchTopic<Self::TopicId>)>,
+    ) -> RepoResult<Vec<OptRepoResult<Topic<Self::TopicId>>>> {
+        Err(TopicRepoError::Patch.into_report())
+    }
+
+    async fn delete(&self, _: Self::TopicId, _: DeletePolicy) -> OptRepoResult<()> {
         Err(TopicRepoError::Delete.into_report())
     }
+
+    async fn delete_many(&self, _: Vec<Self::TopicId>) -> RepoResult<Vec<OptRepoResult<()>>> {
+        Err(TopicRepoError::Delete.into_report())
+    }
+
+    async fn create_link(
+        &self,
+        _: Self::TopicId,
+        _: Self::TopicId,
+        _: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        Err(TopicRepoError::Link.into_report())
+    }
+
+    async fn get_link(
+        &self,
+        _: Self::TopicId,
+        _: Self::TopicId,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        Err(TopicRepoError::Link.into_report())
+    }
+
+    async fn update_link(
+        &self,
+        _: Self::TopicId,
+        _: Self::TopicId,
+        _: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        Err(TopicRepoError::Link.into_report())
+    }
+
+    async fn delete_link(&self, _: Self::TopicId, _: Self::TopicId) -> OptRepoResult<()> {
+        Err(TopicRepoError::Link.into_report())
+    }
+
+    async fn links(
+        &self,
+        _: Self::TopicId,
+        _: LinkDirection,
+        _: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicLink<Self::TopicId>>> {
+        Err(TopicRepoError::Link.into_report())
+    }
+
+    async fn links_touching(
+        &self,
+        _: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<TopicLink<Self::TopicId>>> {
+        Err(TopicRepoError::Link.into_report())
+    }
+
+    async fn tags(&self, _: Self::TopicId) -> OptRepoResult<Vec<String>> {
+        Err(TopicRepoError::Tags.into_report())
+    }
+
+    async fn add_tags(&self, _: Self::TopicId, _: Vec<String>) -> OptRepoResult<Vec<String>> {
+        Err(TopicRepoError::Tags.into_report())
+    }
+
+    async fn remove_tags(&self, _: Self::TopicId, _: Vec<String>) -> OptRepoResult<Vec<String>> {
+        Err(TopicRepoError::Tags.into_report())
+    }
+
+    async fn tag_counts(&self) -> RepoResult<Vec<TagCount>> {
+        Err(TopicRepoError::Tags.into_report())
+    }
+
+    async fn change_status(
+        &self,
+        _: Self::TopicId,
+        _: StatusChange,
+    ) -> OptRepoResult<Topic<Self::TopicId>> {
+        Err(TopicRepoError::Status.into_report())
+    }
+
+    async fn status_changes(&self, _: Self::TopicId) -> OptRepoResult<Vec<StatusChange>> {
+        Err(TopicRepoError::Status.into_report())
+    }
+
+    async fn versions(
+        &self,
+        _: Self::TopicId,
+        _: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicVersion<Self::TopicId>>> {
+        Err(TopicRepoError::Versions.into_report())
+    }
+
+    async fn version(
+        &self,
+        _: Self::TopicId,
+        _: u64,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        Err(TopicRepoError::Versions.into_report())
+    }
+
+    async fn version_at(
+        &self,
+        _: Self::TopicId,
+        _: DateTime<Utc>,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        Err(TopicRepoError::Versions.into_report())
+    }
 }
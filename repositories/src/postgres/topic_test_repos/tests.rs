@@ -40,3 +40,37 @@ async fn in_memory_create_returns_topic_with_specified_attributes_and_adds_to_un
 
     assert_eq!(Some(&topic), db.get(&topic.id));
 }
+
+#[tokio::test]
+async fn in_memory_with_unique_names_rejects_names_differing_by_case() {
+    let repo = InMemoryTopicsRepo::with_unique_names();
+    repo.create(NewTopic::new("Customers", None::<String>))
+        .await
+        .unwrap();
+
+    let results = repo
+        .create_many(vec![
+            NewTopic::new("customers", None::<String>),
+            NewTopic::new("orders", None::<String>),
+        ])
+        .await
+        .unwrap();
+
+    assert!(results[0].is_err());
+    assert!(results[1].is_ok());
+}
+
+#[tokio::test]
+async fn in_memory_create_many_atomic_creates_nothing_on_duplicate() {
+    let repo = InMemoryTopicsRepo::with_unique_names();
+
+    let result = repo
+        .create_many_atomic(vec![
+            NewTopic::new("orders", None::<String>),
+            NewTopic::new("Orders", None::<String>),
+        ])
+        .await;
+
+    assert!(result.is_err());
+    assert!(repo.db.read().await.is_empty());
+}
@@ -1,16 +1,26 @@
 use crate::postgres::insert_many::{InsertMany, InsertManyBuilder, value_set};
-use crate::postgres::statements::TopicStatements;
-use crate::postgres::{RepoInitErr, sanitize_pagination};
+use crate::postgres::statements::{TopicStatements, list_topic_fields};
+use crate::postgres::{
+    RepoInitErr, attribute_params, is_check_violation, is_foreign_key_violation,
+    is_unique_violation, row_attributes, sanitize_pagination,
+};
+use chrono::{DateTime, Utc};
 use deadpool_postgres::{Object, Pool};
-use error_stack::{Report, ResultExt};
+use error_stack::{IntoReport, Report, ResultExt};
 use optional_field::Field;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use tokio_postgres::Row;
 use tokio_postgres::types::ToSql;
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
 use topics_core::TopicRepository;
-use topics_core::list_filter::TopicListCriteria;
-use topics_core::model::{NewTopic, PatchTopic, Topic};
+use topics_core::list_filter::{TopicListCriteria, attribute_filters, status_filter, tag_filters};
+use topics_core::model::{
+    DeletePolicy, LinkDirection, LinkType, NewTopic, PartialTopic, PatchTopic, StatusChange,
+    TagCount, Topic, TopicField, TopicLink, TopicStatus, TopicVersion, Upserted,
+    undeletable_parents,
+};
 use topics_core::result::{CreateErrorType, OptRepoResult, RepoResult, TopicRepoError};
 use tracing::warn;
 use utoipa::ToSchema;
@@ -37,6 +47,8 @@ impl TopicId {
 pub struct TopicRepo {
     pool: Pool,
     statements: TopicStatements,
+    /// Whether the unique names index was created, so bulk patches can check names up front
+    unique_names: bool,
 }
 
 impl TopicRepo {
@@ -45,17 +57,129 @@ impl TopicRepo {
 
         let client = &mut **handle;
 
+        let unique_names = client
+            .query_one(
+                "select to_regclass('topics_name_unique_idx') is not null as unique_names",
+                &[],
+            )
+            .await
+            .change_context(RepoInitErr::topics())?
+            .get("unique_names");
+
         Ok(Self {
             statements: TopicStatements::prepare(client)
                 .await
                 .change_context(RepoInitErr::topics())?,
             pool,
+            unique_names,
         })
     }
 
     async fn client(&self, on_fail: TopicRepoError) -> RepoResult<Object> {
         self.pool.get().await.change_context(on_fail)
     }
+
+    /// Which topic holds each of the lowercased names `patches` rename topics to
+    async fn names_taken(
+        &self,
+        client: &Object,
+        patches: &[(TopicId, PatchTopic<TopicId>)],
+    ) -> RepoResult<HashMap<String, Uuid>> {
+        let names: Vec<String> = patches
+            .iter()
+            .filter_map(|(_, patch)| patch.name.as_ref().map(|n| n.to_lowercase()))
+            .collect();
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        Ok(client
+            .query(&self.statements.names_taken, &[&names])
+            .await
+            .change_context(TopicRepoError::Patch)?
+            .into_iter()
+            .map(|row| (row.get("name"), row.get("id")))
+            .collect())
+    }
+
+    async fn patch_with_parent(
+        &self,
+        id: TopicId,
+        patch: PatchTopic<TopicId>,
+        parent_id: Option<TopicId>,
+    ) -> OptRepoResult<Topic<TopicId>> {
+        let set_description = matches!(patch.description, Field::Present(_));
+        let description = patch.description.unwrap_present_or(None);
+
+        self.client(TopicRepoError::Patch)
+            .await?
+            .query_opt(
+                &self.statements.patch_parent,
+                &[
+                    &id.0,
+                    &patch.name,
+                    &set_description,
+                    &description,
+                    &parent_id.map(|p| p.0),
+                ],
+            )
+            .await
+            .map(|row| row.map(row_to_topic))
+            .map_err(|e| write_error(e, TopicRepoError::Patch))
+    }
+}
+
+/// The arrays `PATCH_MANY_TOPICS` is given, one entry per topic
+#[derive(Debug, Default)]
+struct PatchColumns {
+    ids: Vec<Uuid>,
+    names: Vec<Option<String>>,
+    set_descriptions: Vec<bool>,
+    descriptions: Vec<Option<String>>,
+    set_parents: Vec<bool>,
+    parents: Vec<Option<Uuid>>,
+    attributes: Vec<Option<Value>>,
+}
+
+impl PatchColumns {
+    fn push(&mut self, id: TopicId, patch: PatchTopic<TopicId>) {
+        self.ids.push(id.0);
+        self.names.push(patch.name);
+        self.set_descriptions
+            .push(matches!(patch.description, Field::Present(_)));
+        self.descriptions
+            .push(patch.description.unwrap_present_or(None));
+        self.set_parents
+            .push(matches!(patch.parent_id, Field::Present(_)));
+        self.parents
+            .push(patch.parent_id.unwrap_present_or(None).map(|p| p.0));
+        self.attributes.push(patch.attributes.map(Value::Object));
+    }
+
+    /// Just the patch at `i`
+    fn single(&self, i: usize) -> Self {
+        Self {
+            ids: vec![self.ids[i]],
+            names: vec![self.names[i].clone()],
+            set_descriptions: vec![self.set_descriptions[i]],
+            descriptions: vec![self.descriptions[i].clone()],
+            set_parents: vec![self.set_parents[i]],
+            parents: vec![self.parents[i]],
+            attributes: vec![self.attributes[i].clone()],
+        }
+    }
+
+    fn params(&self) -> [&(dyn ToSql + Sync); 7] {
+        [
+            &self.ids,
+            &self.names,
+            &self.set_descriptions,
+            &self.descriptions,
+            &self.set_parents,
+            &self.parents,
+            &self.attributes,
+        ]
+    }
 }
 
 fn row_to_topic(row: Row) -> Topic<TopicId> {
@@ -66,6 +190,91 @@ fn row_to_topic(row: Row) -> Topic<TopicId> {
         row.get("created"),
         row.get("updated"),
     )
+    .with_parent(row.get::<_, Option<Uuid>>("parent_id").map(TopicId))
+    .with_attributes(row_attributes(&row))
+    .with_status(row_status(&row, "status"))
+}
+
+/// The table's check constraint keeps statuses to the ones `TopicStatus` knows
+fn row_status(row: &Row, column: &str) -> TopicStatus {
+    TopicStatus::from_name(row.get(column)).unwrap_or_default()
+}
+
+fn row_to_status_change(row: Row) -> StatusChange {
+    StatusChange {
+        from: row_status(&row, "from_status"),
+        to: row_status(&row, "to_status"),
+        reason: row.get("reason"),
+        changed: row.get("changed"),
+    }
+}
+
+fn row_to_version(row: Row) -> TopicVersion<TopicId> {
+    let version = row.get::<_, i32>("version") as u64;
+    let recorded = row.get("recorded");
+    TopicVersion::new(version, row_to_topic(row), recorded)
+}
+
+fn row_to_link(row: Row) -> RepoResult<TopicLink<TopicId>> {
+    let link_type: &str = row.get("link_type");
+    let link_type = LinkType::from_name(link_type)
+        .ok_or(TopicRepoError::Link)
+        .attach_with(|| format!("unknown link type {link_type}"))?;
+
+    Ok(TopicLink {
+        source_id: TopicId(row.get("source_id")),
+        target_id: TopicId(row.get("target_id")),
+        link_type,
+        created: row.get("created"),
+        updated: row.get("updated"),
+    })
+}
+
+/// The errors the topics table's constraints raise, anything else fails with `on_fail`
+fn write_error(e: tokio_postgres::Error, on_fail: TopicRepoError) -> Report<TopicRepoError> {
+    let context = if is_unique_violation(&e) {
+        TopicRepoError::DuplicateName
+    } else if is_foreign_key_violation(&e) {
+        TopicRepoError::ParentNotFound
+    } else if is_check_violation(&e) {
+        TopicRepoError::ParentCycle
+    } else {
+        on_fail
+    };
+    e.into_report().change_context(context)
+}
+
+/// Deleting a topic that still has children breaks the parent foreign key
+fn delete_error(e: tokio_postgres::Error) -> Report<TopicRepoError> {
+    let context = if is_foreign_key_violation(&e) {
+        TopicRepoError::HasChildren
+    } else {
+        TopicRepoError::Delete
+    };
+    e.into_report().change_context(context)
+}
+
+fn status_params(list_criteria: &TopicListCriteria) -> Option<Vec<&'static str>> {
+    status_filter(list_criteria).map(|statuses| statuses.iter().map(|s| s.name()).collect())
+}
+
+fn row_to_partial_topic(row: Row, fields: &[TopicField]) -> PartialTopic<TopicId> {
+    let mut topic = PartialTopic::default();
+    for field in fields {
+        match field {
+            TopicField::Id => topic.id = Some(TopicId(row.get("id"))),
+            TopicField::ParentId => {
+                topic.parent_id = Some(row.get::<_, Option<Uuid>>("parent_id").map(TopicId))
+            }
+            TopicField::Name => topic.name = Some(row.get("name")),
+            TopicField::Description => topic.description = Some(row.get("description")),
+            TopicField::Attributes => topic.attributes = Some(row_attributes(&row)),
+            TopicField::Status => topic.status = Some(row_status(&row, "status")),
+            TopicField::Created => topic.created = Some(row.get("created")),
+            TopicField::Updated => topic.updated = Some(row.get("updated")),
+        }
+    }
+    topic
 }
 
 impl TopicRepository for TopicRepo {
@@ -82,75 +291,309 @@ impl TopicRepository for TopicRepo {
         Ok(topic)
     }
 
+    async fn get_many(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<Option<Topic<Self::TopicId>>>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids: Vec<Uuid> = ids.into_iter().map(|id| id.0).collect();
+
+        let found: HashMap<Uuid, Topic<TopicId>> = self
+            .client(TopicRepoError::Get)
+            .await?
+            .query(&self.statements.get_many, &[&ids])
+            .await
+            .change_context(TopicRepoError::Get)?
+            .into_iter()
+            .map(row_to_topic)
+            .map(|topic| (topic.id.0, topic))
+            .collect();
+
+        Ok(ids.iter().map(|id| found.get(id).cloned()).collect())
+    }
+
     async fn list(
         &self,
         list_criteria: TopicListCriteria,
     ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
+        self.list_stream(list_criteria)
+            .await?
+            .collect::<Result<_, _>>()
+            .await
+    }
+
+    async fn list_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+    ) -> RepoResult<impl Stream<Item = RepoResult<Topic<Self::TopicId>>> + Send + 'static + use<>>
+    {
+        let pagination = sanitize_pagination(&list_criteria, TopicRepoError::List)?;
+        let (all_tags, any_tag) = tag_filters(&list_criteria);
+        let (attribute_keys, attribute_values) =
+            attribute_params(attribute_filters(&list_criteria));
+        let statuses = status_params(&list_criteria);
+        let params: [&(dyn ToSql + Sync); 7] = [
+            &pagination.page,
+            &pagination.page_size,
+            &all_tags,
+            &any_tag,
+            &attribute_keys,
+            &attribute_values,
+            &statuses,
+        ];
+
+        let client = self.client(TopicRepoError::List).await?;
+
+        let rows = client
+            .query_raw(&self.statements.list, params)
+            .await
+            .change_context(TopicRepoError::List)?;
+
+        // the stream owns the connection, so it only goes back to the pool once the stream is dropped
+        Ok(rows.map(move |row| {
+            let _ = &client;
+            row.map(row_to_topic).change_context(TopicRepoError::List)
+        }))
+    }
+
+    async fn list_fields_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+        fields: Vec<TopicField>,
+    ) -> RepoResult<
+        impl Stream<Item = RepoResult<PartialTopic<Self::TopicId>>> + Send + 'static + use<>,
+    > {
         let pagination = sanitize_pagination(&list_criteria, TopicRepoError::List)?;
+        let (all_tags, any_tag) = tag_filters(&list_criteria);
+        let (attribute_keys, attribute_values) =
+            attribute_params(attribute_filters(&list_criteria));
+        let statuses = status_params(&list_criteria);
+        let params: [&(dyn ToSql + Sync); 7] = [
+            &pagination.page,
+            &pagination.page_size,
+            &all_tags,
+            &any_tag,
+            &attribute_keys,
+            &attribute_values,
+            &statuses,
+        ];
 
         let client = self.client(TopicRepoError::List).await?;
 
-        let topics = client
-            .query_raw(
-                &self.statements.list,
-                &[&pagination.page, &pagination.page_size],
+        let rows = client
+            .query_raw(list_topic_fields(&fields).as_str(), params)
+            .await
+            .change_context(TopicRepoError::List)?;
+
+        Ok(rows.map(move |row| {
+            let _ = &client;
+            row.map(|row| row_to_partial_topic(row, &fields))
+                .change_context(TopicRepoError::List)
+        }))
+    }
+
+    async fn children(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let pagination = sanitize_pagination(&list_criteria, TopicRepoError::List)?;
+
+        let children: Vec<_> = self
+            .client(TopicRepoError::List)
+            .await?
+            .query(
+                &self.statements.children,
+                &[&id.0, &pagination.page, &pagination.page_size],
             )
             .await
             .change_context(TopicRepoError::List)?
-            .map(|r| r.map(row_to_topic))
-            .collect::<Result<_, _>>()
-            .await;
+            .into_iter()
+            .map(row_to_topic)
+            .collect();
 
-        topics.change_context(TopicRepoError::List)
+        // no children could also mean there's no topic
+        if children.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(children))
     }
 
-    async fn create(&self, new_topic: NewTopic) -> RepoResult<Topic<Self::TopicId>> {
-        let client = self
-            .client(TopicRepoError::Create(CreateErrorType::DbError))
-            .await?;
+    async fn ancestors(&self, id: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let mut ancestors: Vec<_> = self
+            .client(TopicRepoError::Get)
+            .await?
+            .query(&self.statements.ancestors, &[&id.0])
+            .await
+            .change_context(TopicRepoError::Get)?
+            .into_iter()
+            .map(row_to_topic)
+            .collect();
+
+        // the topic itself comes last
+        Ok(ancestors.pop().map(|_| ancestors))
+    }
+
+    async fn subtree(&self, id: Self::TopicId) -> OptRepoResult<Vec<Topic<Self::TopicId>>> {
+        let subtree: Vec<_> = self
+            .client(TopicRepoError::Get)
+            .await?
+            .query(&self.statements.subtree, &[&id.0])
+            .await
+            .change_context(TopicRepoError::Get)?
+            .into_iter()
+            .map(row_to_topic)
+            .collect();
+
+        Ok((!subtree.is_empty()).then_some(subtree))
+    }
+
+    async fn create(&self, new_topic: NewTopic<Self::TopicId>) -> RepoResult<Topic<Self::TopicId>> {
+        let on_fail = TopicRepoError::Create(CreateErrorType::DbError);
+        let client = self.client(on_fail).await?;
 
         client
             .query_one(
                 &self.statements.create,
-                &[&TopicId::new().0, &new_topic.name, &new_topic.description],
+                &[
+                    &TopicId::new().0,
+                    &new_topic.name,
+                    &new_topic.description,
+                    &new_topic.parent_id.map(|p| p.0),
+                    &Value::Object(new_topic.attributes),
+                ],
             )
             .await
-            .change_context(TopicRepoError::Create(CreateErrorType::DbError))
             .map(row_to_topic)
+            .map_err(|e| write_error(e, on_fail))
     }
 
     async fn create_many(
         &self,
-        new_topics: Vec<NewTopic>,
+        new_topics: Vec<NewTopic<Self::TopicId>>,
     ) -> RepoResult<Vec<RepoResult<Topic<Self::TopicId>>>> {
-        let Some(insert) = generate_create_many_insert(new_topics) else {
+        let Some((insert, ids)) = generate_create_many_insert(new_topics, true) else {
+            warn!("no topic requests sent to data layer, not creating any new topics");
+            return Ok(vec![]);
+        };
+
+        let on_fail = TopicRepoError::Create(CreateErrorType::DbError);
+        let client = self.client(on_fail).await?;
+
+        // the insert is a single statement, so any failing row means none were created
+        let mut created: HashMap<_, _> = client
+            .query_raw(&insert.query, insert.params())
+            .await
+            .map_err(|e| write_error(e, on_fail))?
+            .map(|r| r.map(|row| (row.get::<_, Uuid>("id"), row_to_topic(row))))
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .map_err(|e| write_error(e, on_fail))?
+            .into_iter()
+            .collect();
+
+        // conflicting rows are skipped rather than failing the insert, so they're the ones missing
+        let topics = ids
+            .into_iter()
+            .map(|id| {
+                created
+                    .remove(&id)
+                    .ok_or_else(|| TopicRepoError::DuplicateName.into_report())
+            })
+            .collect();
+
+        Ok(topics)
+    }
+
+    async fn create_many_atomic(
+        &self,
+        new_topics: Vec<NewTopic<Self::TopicId>>,
+    ) -> RepoResult<Vec<Topic<Self::TopicId>>> {
+        let Some((insert, ids)) = generate_create_many_insert(new_topics, false) else {
             warn!("no topic requests sent to data layer, not creating any new topics");
             return Ok(vec![]);
         };
 
-        let client = self
-            .client(TopicRepoError::Create(CreateErrorType::DbError))
-            .await?;
+        let on_fail = TopicRepoError::Create(CreateErrorType::DbError);
+        let mut client = self.client(on_fail).await?;
+        let transaction = client.transaction().await.change_context(on_fail)?;
 
-        let topics = client
+        // without ON CONFLICT, a duplicate name fails the whole insert. Dropping the transaction
+        // on any error rolls it back.
+        let result = transaction
             .query_raw(&insert.query, insert.params())
             .await
-            .change_context(TopicRepoError::Create(CreateErrorType::DbError))?
-            .map(|r| {
-                r.map(row_to_topic)
-                    .change_context(TopicRepoError::Create(CreateErrorType::DbError))
+            .map(|rows| rows.map(|r| r.map(|row| (row.get::<_, Uuid>("id"), row_to_topic(row)))));
+
+        let created = match result {
+            Ok(rows) => rows.collect::<Result<Vec<_>, _>>().await,
+            Err(e) => Err(e),
+        };
+
+        let mut created: HashMap<_, _> = created
+            .map_err(|e| write_error(e, on_fail))?
+            .into_iter()
+            .collect();
+
+        transaction.commit().await.change_context(on_fail)?;
+
+        ids.into_iter()
+            .map(|id| {
+                created.remove(&id).ok_or_else(|| {
+                    TopicRepoError::Create(CreateErrorType::MatchFailure).into_report()
+                })
             })
             .collect()
+    }
+
+    async fn upsert(
+        &self,
+        id: Self::TopicId,
+        topic: NewTopic<Self::TopicId>,
+    ) -> RepoResult<Upserted<Topic<Self::TopicId>>> {
+        let result = self
+            .client(TopicRepoError::Upsert)
+            .await?
+            .query_one(
+                &self.statements.upsert,
+                &[
+                    &id.0,
+                    &topic.name,
+                    &topic.description,
+                    &topic.parent_id.map(|p| p.0),
+                    &Value::Object(topic.attributes),
+                ],
+            )
             .await;
 
-        Ok(topics)
+        match result {
+            Ok(row) if row.get("inserted") => Ok(Upserted::Created(row_to_topic(row))),
+            Ok(row) => Ok(Upserted::Replaced(row_to_topic(row))),
+            Err(e) => Err(write_error(e, TopicRepoError::Upsert)),
+        }
     }
 
     async fn patch(
         &self,
         id: Self::TopicId,
-        patch: PatchTopic,
+        patch: PatchTopic<Self::TopicId>,
     ) -> OptRepoResult<Topic<Self::TopicId>> {
+        // only the bulk statement merges attributes
+        if patch.attributes.is_some() {
+            return self
+                .patch_many(vec![(id, patch)])
+                .await?
+                .pop()
+                .unwrap_or(Ok(None));
+        }
+
+        if let Field::Present(parent_id) = patch.parent_id {
+            return self.patch_with_parent(id, patch, parent_id).await;
+        }
+
         let (stmt, params) = match (&patch.name, &patch.description) {
             (Some(name), Field::Present(description)) => (
                 &self.statements.patch_name_desc,
@@ -181,44 +624,539 @@ impl TopicRepository for TopicRepo {
             }
         };
 
-        let topic = self
+        let result = self
             .client(TopicRepoError::Patch)
             .await?
             .query_opt(stmt, params)
+            .await;
+
+        result
+            .map(|row| row.map(row_to_topic))
+            .map_err(|e| write_error(e, TopicRepoError::Patch))
+    }
+
+    async fn patch_many(
+        &self,
+        patches: Vec<(Self::TopicId, PatchTopic<Self::TopicId>)>,
+    ) -> RepoResult<Vec<OptRepoResult<Topic<Self::TopicId>>>> {
+        if patches.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let client = self.client(TopicRepoError::Patch).await?;
+
+        let taken_names = if self.unique_names {
+            self.names_taken(&client, &patches).await?
+        } else {
+            HashMap::new()
+        };
+
+        let mut results: Vec<Option<OptRepoResult<Topic<TopicId>>>> =
+            patches.iter().map(|_| None).collect();
+        let mut pending = PatchColumns::default();
+        let mut pending_indexes = Vec::new();
+        let mut batch_names = HashSet::new();
+
+        for (i, (id, patch)) in patches.into_iter().enumerate() {
+            if self.unique_names
+                && let Some(name) = &patch.name
+            {
+                let name = name.to_lowercase();
+                let taken = taken_names.get(&name).is_some_and(|owner| *owner != id.0);
+                if taken || !batch_names.insert(name) {
+                    results[i] = Some(Err(TopicRepoError::DuplicateName.into_report()));
+                    continue;
+                }
+            }
+            pending.push(id, patch);
+            pending_indexes.push(i);
+        }
+
+        if !pending.ids.is_empty() {
+            let patched = match client
+                .query(&self.statements.patch_many, &pending.params())
+                .await
+            {
+                Ok(rows) => {
+                    let mut patched: HashMap<_, _> = rows
+                        .into_iter()
+                        .map(|row| (row.get::<_, Uuid>("id"), row_to_topic(row)))
+                        .collect();
+                    pending
+                        .ids
+                        .iter()
+                        .map(|id| Ok(patched.remove(id)))
+                        .collect()
+                }
+                Err(e) => {
+                    let e = write_error(e, TopicRepoError::Patch);
+                    if matches!(e.current_context(), TopicRepoError::Patch) {
+                        return Err(e);
+                    }
+
+                    // a name taken since it was checked, or a missing parent or cycle, fails the
+                    // whole statement. Each topic is patched on its own so only the broken ones fail
+                    let mut patched = Vec::with_capacity(pending.ids.len());
+                    for i in 0..pending.ids.len() {
+                        let single = pending.single(i);
+                        patched.push(
+                            client
+                                .query_opt(&self.statements.patch_many, &single.params())
+                                .await
+                                .map(|row| row.map(row_to_topic))
+                                .map_err(|e| write_error(e, TopicRepoError::Patch)),
+                        );
+                    }
+                    patched
+                }
+            };
+
+            for (i, result) in pending_indexes.into_iter().zip(patched) {
+                results[i] = Some(result);
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.expect("every patch has a result"))
+            .collect())
+    }
+
+    async fn delete(&self, id: Self::TopicId, policy: DeletePolicy) -> OptRepoResult<()> {
+        let mut client = self.client(TopicRepoError::Delete).await?;
+
+        let rows_deleted = match policy {
+            DeletePolicy::Reject => client
+                .execute(&self.statements.delete, &[&id.0])
+                .await
+                .map_err(delete_error)?,
+            DeletePolicy::Cascade => client
+                .execute(&self.statements.delete_subtree, &[&id.0])
+                .await
+                .map_err(delete_error)?,
+            DeletePolicy::Reparent => {
+                let transaction = client
+                    .transaction()
+                    .await
+                    .change_context(TopicRepoError::Delete)?;
+                transaction
+                    .execute(&self.statements.reparent_children, &[&id.0])
+                    .await
+                    .map_err(delete_error)?;
+                let rows_deleted = transaction
+                    .execute(&self.statements.delete, &[&id.0])
+                    .await
+                    .map_err(delete_error)?;
+                transaction
+                    .commit()
+                    .await
+                    .change_context(TopicRepoError::Delete)?;
+                rows_deleted
+            }
+        };
+
+        Ok((rows_deleted > 0).then_some(()))
+    }
+
+    async fn delete_many(&self, ids: Vec<Self::TopicId>) -> RepoResult<Vec<OptRepoResult<()>>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids: Vec<Uuid> = ids.into_iter().map(|id| id.0).collect();
+        let client = self.client(TopicRepoError::Delete).await?;
+
+        let children: Vec<(Uuid, Uuid)> = client
+            .query(&self.statements.children_of_any, &[&ids])
             .await
-            .change_context(TopicRepoError::Patch)?
-            .map(row_to_topic);
+            .change_context(TopicRepoError::Delete)?
+            .into_iter()
+            .map(|row| (row.get("id"), row.get("parent_id")))
+            .collect();
+        let undeletable = undeletable_parents(&ids, &children);
 
-        Ok(topic)
+        let deletable: Vec<Uuid> = ids
+            .iter()
+            .copied()
+            .filter(|id| !undeletable.contains(id))
+            .collect();
+
+        // a child added since they were looked up still fails every delete with `HasChildren`
+        let deleted: HashSet<Uuid> = if deletable.is_empty() {
+            HashSet::new()
+        } else {
+            client
+                .query(&self.statements.delete_many, &[&deletable])
+                .await
+                .map_err(delete_error)?
+                .into_iter()
+                .map(|row| row.get("id"))
+                .collect()
+        };
+
+        Ok(ids
+            .iter()
+            .map(|id| {
+                if undeletable.contains(id) {
+                    Err(TopicRepoError::HasChildren.into_report()).attach_with(|| {
+                        format!("topic {id} has children that aren't being deleted")
+                    })
+                } else {
+                    Ok(deleted.contains(id).then_some(()))
+                }
+            })
+            .collect())
     }
 
-    async fn delete(&self, id: Self::TopicId) -> OptRepoResult<()> {
+    async fn create_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        if source_id == target_id {
+            return Err(TopicRepoError::SelfLink.into_report());
+        }
+
+        let result = self
+            .client(TopicRepoError::Link)
+            .await?
+            .query_one(
+                &self.statements.create_link,
+                &[&source_id.0, &target_id.0, &link_type.name()],
+            )
+            .await;
+
+        match result {
+            Ok(row) => row_to_link(row).map(Some),
+            Err(e) if is_unique_violation(&e) => {
+                Err(e.into_report()).change_context(TopicRepoError::LinkExists)
+            }
+            // either end can be missing, only a missing target is an error
+            Err(e) if is_foreign_key_violation(&e) => {
+                let constraint = e.as_db_error().and_then(|e| e.constraint());
+                if constraint == Some("tl_source_fk") {
+                    Ok(None)
+                } else {
+                    Err(e.into_report()).change_context(TopicRepoError::LinkTargetNotFound)
+                }
+            }
+            Err(e) => Err(e.into_report()).change_context(TopicRepoError::Link),
+        }
+    }
+
+    async fn get_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        self.client(TopicRepoError::Link)
+            .await?
+            .query_opt(&self.statements.get_link, &[&source_id.0, &target_id.0])
+            .await
+            .change_context(TopicRepoError::Link)?
+            .map(row_to_link)
+            .transpose()
+    }
+
+    async fn update_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> OptRepoResult<TopicLink<Self::TopicId>> {
+        self.client(TopicRepoError::Link)
+            .await?
+            .query_opt(
+                &self.statements.update_link,
+                &[&source_id.0, &target_id.0, &link_type.name()],
+            )
+            .await
+            .change_context(TopicRepoError::Link)?
+            .map(row_to_link)
+            .transpose()
+    }
+
+    async fn delete_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> OptRepoResult<()> {
         let rows_deleted = self
-            .client(TopicRepoError::Delete)
+            .client(TopicRepoError::Link)
             .await?
-            .execute(&self.statements.delete, &[&id.0])
+            .execute(&self.statements.delete_link, &[&source_id.0, &target_id.0])
             .await
-            .change_context(TopicRepoError::Delete)?;
+            .change_context(TopicRepoError::Link)?;
 
         Ok((rows_deleted > 0).then_some(()))
     }
+
+    async fn links(
+        &self,
+        id: Self::TopicId,
+        direction: LinkDirection,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicLink<Self::TopicId>>> {
+        let pagination = sanitize_pagination(&list_criteria, TopicRepoError::Link)?;
+        let statement = match direction {
+            LinkDirection::Outgoing => &self.statements.outgoing_links,
+            LinkDirection::Incoming => &self.statements.incoming_links,
+            LinkDirection::Both => &self.statements.links,
+        };
+
+        let links = self
+            .client(TopicRepoError::Link)
+            .await?
+            .query(statement, &[&id.0, &pagination.page, &pagination.page_size])
+            .await
+            .change_context(TopicRepoError::Link)?
+            .into_iter()
+            .map(row_to_link)
+            .collect::<RepoResult<Vec<_>>>()?;
+
+        // no links could also mean there's no topic
+        if links.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(links))
+    }
+
+    async fn links_touching(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> RepoResult<Vec<TopicLink<Self::TopicId>>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids: Vec<Uuid> = ids.into_iter().map(|id| id.0).collect();
+
+        self.client(TopicRepoError::Link)
+            .await?
+            .query(&self.statements.links_touching, &[&ids])
+            .await
+            .change_context(TopicRepoError::Link)?
+            .into_iter()
+            .map(row_to_link)
+            .collect()
+    }
+
+    async fn tags(&self, id: Self::TopicId) -> OptRepoResult<Vec<String>> {
+        let tags: Vec<String> = self
+            .client(TopicRepoError::Tags)
+            .await?
+            .query(&self.statements.tags, &[&id.0])
+            .await
+            .change_context(TopicRepoError::Tags)?
+            .into_iter()
+            .map(|row| row.get("tag"))
+            .collect();
+
+        // no tags could also mean there's no topic
+        if tags.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(tags))
+    }
+
+    async fn add_tags(&self, id: Self::TopicId, tags: Vec<String>) -> OptRepoResult<Vec<String>> {
+        let result = self
+            .client(TopicRepoError::Tags)
+            .await?
+            .execute(&self.statements.add_tags, &[&id.0, &tags])
+            .await;
+
+        match result {
+            Ok(_) => self.tags(id).await,
+            // the topic doesn't exist
+            Err(e) if is_foreign_key_violation(&e) => Ok(None),
+            Err(e) => Err(e.into_report()).change_context(TopicRepoError::Tags),
+        }
+    }
+
+    async fn remove_tags(
+        &self,
+        id: Self::TopicId,
+        tags: Vec<String>,
+    ) -> OptRepoResult<Vec<String>> {
+        self.client(TopicRepoError::Tags)
+            .await?
+            .execute(&self.statements.remove_tags, &[&id.0, &tags])
+            .await
+            .change_context(TopicRepoError::Tags)?;
+
+        self.tags(id).await
+    }
+
+    async fn tag_counts(&self) -> RepoResult<Vec<TagCount>> {
+        let rows = self
+            .client(TopicRepoError::Tags)
+            .await?
+            .query(&self.statements.tag_counts, &[])
+            .await
+            .change_context(TopicRepoError::Tags)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TagCount {
+                tag: row.get("tag"),
+                count: row.get::<_, i64>("count") as u64,
+            })
+            .collect())
+    }
+
+    async fn change_status(
+        &self,
+        id: Self::TopicId,
+        change: StatusChange,
+    ) -> OptRepoResult<Topic<Self::TopicId>> {
+        let row = self
+            .client(TopicRepoError::Status)
+            .await?
+            .query_opt(
+                &self.statements.change_status,
+                &[
+                    &id.0,
+                    &change.from.name(),
+                    &change.to.name(),
+                    &change.reason,
+                    &change.changed,
+                ],
+            )
+            .await
+            .change_context(TopicRepoError::Status)?;
+
+        match row {
+            Some(row) => Ok(Some(row_to_topic(row))),
+            // nothing changed, so tell a missing topic apart from one that's already moved on
+            None => match self.get(id).await? {
+                None => Ok(None),
+                Some(topic) => Err(TopicRepoError::StatusChanged.into_report()).attach_with(|| {
+                    format!(
+                        "topic is {}, not {}",
+                        topic.status.name(),
+                        change.from.name()
+                    )
+                }),
+            },
+        }
+    }
+
+    async fn status_changes(&self, id: Self::TopicId) -> OptRepoResult<Vec<StatusChange>> {
+        let changes: Vec<_> = self
+            .client(TopicRepoError::Status)
+            .await?
+            .query(&self.statements.status_changes, &[&id.0])
+            .await
+            .change_context(TopicRepoError::Status)?
+            .into_iter()
+            .map(row_to_status_change)
+            .collect();
+
+        // no changes could also mean there's no topic
+        if changes.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(changes))
+    }
+
+    async fn versions(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptRepoResult<Vec<TopicVersion<Self::TopicId>>> {
+        let pagination = sanitize_pagination(&list_criteria, TopicRepoError::Versions)?;
+
+        let versions: Vec<_> = self
+            .client(TopicRepoError::Versions)
+            .await?
+            .query(
+                &self.statements.versions,
+                &[&id.0, &pagination.page, &pagination.page_size],
+            )
+            .await
+            .change_context(TopicRepoError::Versions)?
+            .into_iter()
+            .map(row_to_version)
+            .collect();
+
+        // every topic has a version, but a page past the last one is empty
+        if versions.is_empty() && self.get(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(versions))
+    }
+
+    async fn version(
+        &self,
+        id: Self::TopicId,
+        version: u64,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        // the column is an integer, so there's no version past its max
+        let Ok(version) = i32::try_from(version) else {
+            return Ok(None);
+        };
+
+        self.client(TopicRepoError::Versions)
+            .await?
+            .query_opt(&self.statements.version, &[&id.0, &version])
+            .await
+            .change_context(TopicRepoError::Versions)
+            .map(|row| row.map(row_to_version))
+    }
+
+    async fn version_at(
+        &self,
+        id: Self::TopicId,
+        at: DateTime<Utc>,
+    ) -> OptRepoResult<TopicVersion<Self::TopicId>> {
+        self.client(TopicRepoError::Versions)
+            .await?
+            .query_opt(&self.statements.version_at, &[&id.0, &at])
+            .await
+            .change_context(TopicRepoError::Versions)
+            .map(|row| row.map(row_to_version))
+    }
 }
 
-fn generate_create_many_insert(new_topics: Vec<NewTopic>) -> Option<InsertMany> {
+/// The ids are in the same order as `new_topics`. Conflicting names are skipped rather than
+/// failing the insert if `skip_conflicts` is set.
+fn generate_create_many_insert(
+    new_topics: Vec<NewTopic<TopicId>>,
+    skip_conflicts: bool,
+) -> Option<(InsertMany, Vec<Uuid>)> {
     let mut new_topic_iter = new_topics.into_iter();
+    let mut ids = Vec::with_capacity(new_topic_iter.len());
 
     let first = new_topic_iter.next()?;
+    let id = TopicId::new().0;
+    ids.push(id);
     let mut builder = InsertManyBuilder::new(
         "topics",
-        ["id", "name", "description"],
-        value_set![TopicId::new().0 => Uuid, first.name => String, first.description => Option<String>],
+        ["id", "name", "description", "parent_id", "attributes"],
+        value_set![id => Uuid, first.name => String, first.description => Option<String>, first.parent_id.map(|p| p.0) => Option<Uuid>, Value::Object(first.attributes) => Value],
     );
 
     for new_topic in new_topic_iter {
-        builder.add_value_set(value_set![TopicId::new().0 => Uuid, new_topic.name => String, new_topic.description => Option<String>]);
+        let id = TopicId::new().0;
+        ids.push(id);
+        builder.add_value_set(value_set![id => Uuid, new_topic.name => String, new_topic.description => Option<String>, new_topic.parent_id.map(|p| p.0) => Option<Uuid>, Value::Object(new_topic.attributes) => Value]);
     }
 
-    builder.returning(&["id", "name", "description", "created", "updated"]);
+    if skip_conflicts {
+        builder.on_conflict_do_nothing();
+    }
+    builder.returning(&[
+        "id",
+        "parent_id",
+        "name",
+        "description",
+        "attributes",
+        "status",
+        "created",
+        "updated",
+    ]);
 
-    Some(builder.build())
+    Some((builder.build(), ids))
 }
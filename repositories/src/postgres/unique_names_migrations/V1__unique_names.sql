@@ -0,0 +1,2 @@
+create unique index if not exists topics_name_unique_idx on topics (lower(name));
+create unique index if not exists sets_topic_name_unique_idx on sets (topic_id, lower(name));
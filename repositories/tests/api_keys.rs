@@ -0,0 +1,113 @@
+use routing::api_key::{ApiKeyRepository, NewApiKey};
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::testcontainers::ContainerAsync;
+use uuid::Uuid;
+
+fn new_key(owner: &str, key_hash: &str) -> NewApiKey {
+    NewApiKey {
+        id: Uuid::now_v7(),
+        owner: owner.to_string(),
+        roles: vec!["TOPIC_READ".to_string(), "TOPIC_WRITE".to_string()],
+        key_hash: key_hash.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn create_then_find_by_hash_returns_key() {
+    let (_container, repo) = postgres::runtime().await;
+
+    let created = repo.create(new_key("svc-a", "hash-a")).await.unwrap();
+    let found = repo
+        .find_by_hash("hash-a")
+        .await
+        .unwrap()
+        .expect("created key is found by its hash");
+
+    assert_eq!(created, found);
+    assert_eq!("svc-a", found.owner);
+    assert_eq!(vec!["TOPIC_READ", "TOPIC_WRITE"], found.roles);
+    assert!(found.revoked.is_none());
+}
+
+#[tokio::test]
+async fn find_by_unknown_hash_returns_none() {
+    let (_container, repo) = postgres::runtime().await;
+
+    assert!(repo.find_by_hash("nope").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn revoke_marks_key_revoked_once() {
+    let (_container, repo) = postgres::runtime().await;
+
+    let created = repo.create(new_key("svc-a", "hash-a")).await.unwrap();
+    let revoked = repo.revoke(created.id).await.unwrap().expect("key exists");
+    let revoked_again = repo.revoke(created.id).await.unwrap().expect("key exists");
+
+    assert!(revoked.revoked.is_some());
+    assert_eq!(revoked.revoked, revoked_again.revoked);
+    assert_eq!(
+        revoked.revoked,
+        repo.find_by_hash("hash-a").await.unwrap().unwrap().revoked
+    );
+}
+
+#[tokio::test]
+async fn revoke_unknown_key_returns_none() {
+    let (_container, repo) = postgres::runtime().await;
+
+    assert!(repo.revoke(Uuid::now_v7()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn list_returns_all_keys() {
+    let (_container, repo) = postgres::runtime().await;
+
+    let a = repo.create(new_key("svc-a", "hash-a")).await.unwrap();
+    let b = repo.create(new_key("svc-b", "hash-b")).await.unwrap();
+    repo.revoke(a.id).await.unwrap();
+
+    let ids: Vec<_> = repo
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|k| k.id)
+        .collect();
+
+    assert_eq!(vec![a.id, b.id], ids);
+}
+
+mod postgres {
+    use super::*;
+    use repositories::postgres::ConnectionDetails;
+    use repositories::postgres::api_keys::ApiKeyRepo;
+    use repositories::postgres::initializer::RepoCreator;
+    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+    pub async fn runtime() -> (ContainerAsync<Postgres>, ApiKeyRepo) {
+        let container = Postgres::default()
+            .with_db_name("topics")
+            .with_user("testuser")
+            .with_password("testpass")
+            .start()
+            .await
+            .unwrap();
+        let host = container.get_host().await.unwrap();
+        let port = container.get_host_port_ipv4(5432).await.unwrap();
+
+        let (_, repo) = RepoCreator::default()
+            .with_topics()
+            .with_api_keys()
+            .create(
+                ConnectionDetails::Url(format!(
+                    "postgresql://testuser:testpass@{host}:{port}/topics"
+                )),
+                Some(1),
+            )
+            .await
+            .unwrap();
+
+        (container, repo)
+    }
+}
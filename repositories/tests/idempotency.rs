@@ -0,0 +1,194 @@
+use chrono::{TimeDelta, Utc};
+use routing::idempotency::{
+    IdempotencyKey, IdempotencyRepository, IdempotentRequest, StoredResponse,
+};
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::testcontainers::ContainerAsync;
+
+fn key(owner: &str, key: &str) -> IdempotencyKey {
+    IdempotencyKey {
+        owner: owner.to_string(),
+        key: key.to_string(),
+    }
+}
+
+fn created() -> StoredResponse {
+    StoredResponse {
+        status: 201,
+        content_type: Some("application/json".to_string()),
+        body: b"{\"id\":1}".to_vec(),
+    }
+}
+
+#[tokio::test]
+async fn reserved_key_is_returned_until_completed_then_with_its_response() {
+    let (_container, repo) = postgres::runtime().await;
+    let expires = Utc::now() + TimeDelta::hours(1);
+
+    assert!(
+        repo.reserve(&key("svc-a", "k1"), "hash", expires)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        Some(IdempotentRequest {
+            request_hash: "hash".to_string(),
+            response: None,
+        }),
+        repo.reserve(&key("svc-a", "k1"), "hash", expires)
+            .await
+            .unwrap()
+    );
+
+    repo.complete(&key("svc-a", "k1"), &created())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        Some(IdempotentRequest {
+            request_hash: "hash".to_string(),
+            response: Some(created()),
+        }),
+        repo.reserve(&key("svc-a", "k1"), "other", expires)
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn keys_are_scoped_to_their_owner() {
+    let (_container, repo) = postgres::runtime().await;
+    let expires = Utc::now() + TimeDelta::hours(1);
+
+    assert!(
+        repo.reserve(&key("svc-a", "k1"), "hash", expires)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        repo.reserve(&key("svc-b", "k1"), "hash", expires)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn released_and_expired_keys_can_be_reserved_again() {
+    let (_container, repo) = postgres::runtime().await;
+    let expires = Utc::now() + TimeDelta::hours(1);
+
+    repo.reserve(&key("svc-a", "released"), "hash", expires)
+        .await
+        .unwrap();
+    repo.release(&key("svc-a", "released")).await.unwrap();
+    assert!(
+        repo.reserve(&key("svc-a", "released"), "hash", expires)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    repo.reserve(
+        &key("svc-a", "expired"),
+        "hash",
+        Utc::now() - TimeDelta::seconds(1),
+    )
+    .await
+    .unwrap();
+    assert!(
+        repo.reserve(&key("svc-a", "expired"), "hash", expires)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn completed_keys_are_not_released() {
+    let (_container, repo) = postgres::runtime().await;
+    let expires = Utc::now() + TimeDelta::hours(1);
+
+    repo.reserve(&key("svc-a", "k1"), "hash", expires)
+        .await
+        .unwrap();
+    repo.complete(&key("svc-a", "k1"), &created())
+        .await
+        .unwrap();
+    repo.release(&key("svc-a", "k1")).await.unwrap();
+
+    let existing = repo
+        .reserve(&key("svc-a", "k1"), "hash", expires)
+        .await
+        .unwrap()
+        .expect("completed key is kept");
+    assert_eq!(Some(created()), existing.response);
+}
+
+#[tokio::test]
+async fn purge_only_deletes_expired_keys() {
+    let (_container, repo) = postgres::runtime().await;
+
+    repo.reserve(
+        &key("svc-a", "expired"),
+        "hash",
+        Utc::now() - TimeDelta::seconds(1),
+    )
+    .await
+    .unwrap();
+    repo.reserve(
+        &key("svc-a", "live"),
+        "hash",
+        Utc::now() + TimeDelta::hours(1),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(1, repo.purge_expired().await.unwrap());
+    assert!(
+        repo.reserve(
+            &key("svc-a", "live"),
+            "hash",
+            Utc::now() + TimeDelta::hours(1)
+        )
+        .await
+        .unwrap()
+        .is_some()
+    );
+}
+
+mod postgres {
+    use super::*;
+    use repositories::postgres::ConnectionDetails;
+    use repositories::postgres::idempotency::IdempotencyRepo;
+    use repositories::postgres::initializer::RepoCreator;
+    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+    pub async fn runtime() -> (ContainerAsync<Postgres>, IdempotencyRepo) {
+        let container = Postgres::default()
+            .with_db_name("topics")
+            .with_user("testuser")
+            .with_password("testpass")
+            .start()
+            .await
+            .unwrap();
+        let host = container.get_host().await.unwrap();
+        let port = container.get_host_port_ipv4(5432).await.unwrap();
+
+        let (_, repo) = RepoCreator::default()
+            .with_topics()
+            .with_idempotency_keys()
+            .create(
+                ConnectionDetails::Url(format!(
+                    "postgresql://testuser:testpass@{host}:{port}/topics"
+                )),
+                Some(1),
+            )
+            .await
+            .unwrap();
+
+        (container, repo)
+    }
+}
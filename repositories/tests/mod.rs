@@ -1,2 +1,4 @@
+mod api_keys;
+mod idempotency;
 mod sets;
 mod topics;
@@ -1,13 +1,18 @@
+use chrono::{TimeDelta, Utc};
 use ids::Id;
+use optional_field::Field;
+use routing::attributes::{AttributeFilter, Attributes};
 use routing::pagination::Pagination;
 use rstest::rstest;
-use sets_core::list_filter::SetListCriteria;
-use sets_core::model::NewSet;
+use serde_json::{Value, json};
+use sets_core::list_filter::{SetFilter, SetListCriteria};
+use sets_core::model::{NewSet, PatchSet, TagCount, Upserted};
 use sets_core::result::{Reason, SetRepoError};
 use sets_core::{SetKey, SetRepository};
 use testcontainers_modules::testcontainers::{ContainerAsync, Image};
+use tokio_stream::StreamExt;
 use topics_core::TopicRepository;
-use topics_core::model::NewTopic;
+use topics_core::model::{NewTopic, StatusChange, TopicStatus};
 
 #[rstest]
 #[case::postgres(postgres::runtime())]
@@ -68,6 +73,64 @@ async fn get_topic_not_exist_returns_error<C, R>(
     );
 }
 
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn get_many_returns_the_topics_sets_in_the_order_asked_for<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topics = runtime.repos.topics();
+    let topic = topics
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic creation works");
+    let other_topic = topics
+        .create(NewTopic::new("topic2", None::<String>))
+        .await
+        .expect("topic creation works");
+
+    let sets = runtime.repos.sets();
+    let set1 = sets
+        .create(topic.id, NewSet::new("set1", None::<String>))
+        .await
+        .expect("set creation works");
+    let set2 = sets
+        .create(topic.id, NewSet::new("set2", None::<String>))
+        .await
+        .expect("set creation works");
+    let elsewhere = sets
+        .create(other_topic.id, NewSet::new("set3", None::<String>))
+        .await
+        .expect("set creation works");
+
+    let found = sets
+        .get_many(
+            topic.id,
+            vec![set2.key.set_id(), elsewhere.key.set_id(), set1.key.set_id()],
+        )
+        .await
+        .expect("get many success");
+    let names: Vec<_> = found
+        .iter()
+        .map(|set| set.as_ref().map(|s| s.name.as_str()))
+        .collect();
+    assert_eq!(vec![Some("set2"), None, Some("set1")], names);
+
+    let error = sets
+        .get_many(runtime.random_topic_id(), vec![elsewhere.key.set_id()])
+        .await
+        .expect_err("get many should fail");
+    assert_eq!(
+        &SetRepoError::Get(Reason::TopicNotFound),
+        error.current_context()
+    );
+}
+
 #[rstest]
 #[case::postgres(postgres::runtime())]
 #[tokio::test]
@@ -358,6 +421,63 @@ async fn list_topic_exists_and_single_set_returns_that_set_in_vec<C, R>(
     assert_eq!(Some("set1 desc"), set.description.as_deref());
 }
 
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn list_stream_checks_the_topic_before_streaming_sets<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let sets = runtime.repos.sets();
+    let criteria = || SetListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE);
+
+    let Err(e) = sets
+        .list_stream(runtime.random_topic_id(), criteria())
+        .await
+    else {
+        panic!("a missing topic is an error before anything is streamed");
+    };
+    assert_eq!(
+        &SetRepoError::List(Reason::TopicNotFound),
+        e.current_context()
+    );
+
+    let topic = runtime
+        .repos
+        .topics()
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("created topic");
+
+    let streamed: Vec<_> = sets
+        .list_stream(topic.id, criteria())
+        .await
+        .expect("topic exists")
+        .collect()
+        .await;
+    assert!(streamed.is_empty());
+
+    for name in ["set1", "set2"] {
+        sets.create(topic.id, NewSet::new(name, None::<String>))
+            .await
+            .expect("set created");
+    }
+
+    let mut names: Vec<_> = sets
+        .list_stream(topic.id, criteria())
+        .await
+        .expect("topic exists")
+        .map(|set| set.expect("set read").name)
+        .collect()
+        .await;
+    names.sort();
+    assert_eq!(vec!["set1", "set2"], names);
+}
+
 // TODO create_many sets and list test
 
 #[rstest]
@@ -394,6 +514,525 @@ async fn create_many_no_topics_returns_topic_not_found_error<C, R>(
     );
 }
 
+#[rstest]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_are_per_topic<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topics = runtime.repos.topics();
+    let topic1 = topics
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+    let topic2 = topics
+        .create(NewTopic::new("topic2", None::<String>))
+        .await
+        .expect("topic created");
+
+    let sets = runtime.repos.sets();
+    sets.create(topic1.id, new_set("Set1"))
+        .await
+        .expect("set created");
+
+    let e = sets
+        .create(topic1.id, new_set("set1"))
+        .await
+        .expect_err("name is taken in the topic");
+    assert_eq!(
+        &SetRepoError::Create(Reason::DuplicateName),
+        e.current_context()
+    );
+
+    sets.create(topic2.id, new_set("set1"))
+        .await
+        .expect("names only need to be unique within a topic");
+
+    let results = sets
+        .create_many(topic1.id, vec![new_set("SET1"), new_set("set2")])
+        .await
+        .expect("create many runs");
+    assert_eq!(
+        Some(&SetRepoError::CreateMany(Reason::DuplicateName)),
+        results[0].as_ref().err().map(|e| e.current_context())
+    );
+    assert!(results[1].is_ok());
+}
+
+#[rstest]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn create_many_atomic_creates_nothing_if_one_set_fails<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topic = runtime
+        .repos
+        .topics()
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+
+    let sets = runtime.repos.sets();
+    let e = sets
+        .create_many_atomic(topic.id, vec![new_set("set1"), new_set("SET1")])
+        .await
+        .expect_err("second set has a duplicate name");
+    assert_eq!(
+        &SetRepoError::CreateMany(Reason::DuplicateName),
+        e.current_context()
+    );
+
+    let listed = sets
+        .list(
+            topic.id,
+            SetListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE),
+        )
+        .await
+        .unwrap();
+    assert!(listed.is_empty());
+
+    let created = sets
+        .create_many_atomic(topic.id, vec![new_set("set1"), new_set("set2")])
+        .await
+        .expect("sets created");
+    assert_eq!(
+        vec!["set1", "set2"],
+        created.iter().map(|s| s.name.as_str()).collect::<Vec<_>>()
+    );
+}
+
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn upsert_creates_then_replaces_within_its_topic<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topics = runtime.repos.topics();
+    let topic1 = topics
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+    let topic2 = topics
+        .create(NewTopic::new("topic2", None::<String>))
+        .await
+        .expect("topic created");
+    let sets = runtime.repos.sets();
+
+    let created = sets
+        .upsert(runtime.existing_topic_set_key(topic1.id), new_set("set1"))
+        .await
+        .expect("set upserted");
+    let Upserted::Created(created) = created else {
+        panic!("a set with a new id is created");
+    };
+    let set_id = created.key.set_id();
+
+    let replaced = sets
+        .upsert(
+            (runtime.set_key_gen)(Some(topic1.id), Some(set_id)),
+            NewSet::new("replaced", None::<String>),
+        )
+        .await
+        .expect("set upserted");
+    let Upserted::Replaced(replaced) = replaced else {
+        panic!("a set with an existing id is replaced");
+    };
+    assert_eq!(set_id, replaced.key.set_id());
+    assert_eq!("replaced", replaced.name);
+    assert_eq!(None, replaced.description);
+    assert_eq!(created.created, replaced.created);
+
+    let e = sets
+        .upsert(
+            (runtime.set_key_gen)(Some(topic2.id), Some(set_id)),
+            new_set("moved"),
+        )
+        .await
+        .expect_err("the set id belongs to another topic");
+    assert_eq!(&SetRepoError::Upsert(Reason::IdTaken), e.current_context());
+
+    let e = sets
+        .upsert(runtime.random_set_key(), new_set("orphan"))
+        .await
+        .expect_err("the topic doesn't exist");
+    assert_eq!(
+        &SetRepoError::Upsert(Reason::TopicNotFound),
+        e.current_context()
+    );
+}
+
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn tagged_sets_can_be_listed_by_tag<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topic = runtime
+        .repos
+        .topics()
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+    let sets = runtime.repos.sets();
+    let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    let key = |set_id| (runtime.set_key_gen)(Some(topic.id), Some(set_id));
+
+    let pii = sets
+        .create(topic.id, new_set("pii"))
+        .await
+        .unwrap()
+        .key
+        .set_id();
+    let both = sets
+        .create(topic.id, new_set("both"))
+        .await
+        .unwrap()
+        .key
+        .set_id();
+    sets.create(topic.id, new_set("untagged")).await.unwrap();
+
+    assert_eq!(
+        Some(tags(&["pii"])),
+        sets.add_tags(key(pii), tags(&["pii"])).await.unwrap()
+    );
+    assert_eq!(
+        Some(tags(&["finance", "pii"])),
+        sets.add_tags(key(both), tags(&["pii", "finance", "pii"]))
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        sets.add_tags(runtime.existing_topic_set_key(topic.id), tags(&["pii"]))
+            .await
+            .unwrap()
+    );
+    let e = sets
+        .tags(runtime.random_set_key())
+        .await
+        .expect_err("the topic doesn't exist");
+    assert_eq!(
+        &SetRepoError::Tags(Reason::TopicNotFound),
+        e.current_context()
+    );
+
+    assert_eq!(
+        vec!["both", "pii"],
+        set_names(&sets, topic.id, SetFilter::AllTags(tags(&["pii"]))).await
+    );
+    assert_eq!(
+        vec!["both"],
+        set_names(
+            &sets,
+            topic.id,
+            SetFilter::AllTags(tags(&["pii", "finance"]))
+        )
+        .await
+    );
+    assert_eq!(
+        vec!["both"],
+        set_names(
+            &sets,
+            topic.id,
+            SetFilter::AnyTag(tags(&["finance", "other"]))
+        )
+        .await
+    );
+
+    assert_eq!(
+        vec![
+            TagCount {
+                tag: "finance".to_string(),
+                count: 1
+            },
+            TagCount {
+                tag: "pii".to_string(),
+                count: 2
+            },
+        ],
+        sets.tag_counts().await.unwrap()
+    );
+
+    assert_eq!(
+        Some(tags(&["finance"])),
+        sets.remove_tags(key(both), tags(&["pii", "other"]))
+            .await
+            .unwrap()
+    );
+    sets.delete(key(pii)).await.unwrap();
+    assert_eq!(
+        vec![TagCount {
+            tag: "finance".to_string(),
+            count: 1
+        }],
+        sets.tag_counts().await.unwrap()
+    );
+}
+
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn set_attributes_are_merged_on_patch_and_can_be_listed_by<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topic = runtime
+        .repos
+        .topics()
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+    let sets = runtime.repos.sets();
+    let key = |set_id| (runtime.set_key_gen)(Some(topic.id), Some(set_id));
+
+    let ledger = sets
+        .create(
+            topic.id,
+            new_set("ledger").with_attributes(attributes(json!({ "team": "payments", "sla": 99 }))),
+        )
+        .await
+        .unwrap();
+    sets.create(topic.id, new_set("plain")).await.unwrap();
+    assert_eq!(
+        attributes(json!({ "team": "payments", "sla": 99 })),
+        ledger.attributes
+    );
+
+    let patch = PatchSet {
+        name: Some("ledger v2".to_string()),
+        description: Field::Missing,
+        attributes: Some(attributes(json!({ "sla": null, "region": "eu" }))),
+    };
+    let patched = sets
+        .patch(key(ledger.key.set_id()), patch.clone())
+        .await
+        .unwrap()
+        .expect("set patched");
+    assert_eq!("ledger v2", patched.name);
+    assert_eq!(
+        attributes(json!({ "team": "payments", "region": "eu" })),
+        patched.attributes
+    );
+    assert!(
+        sets.patch(runtime.existing_topic_set_key(topic.id), patch)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let equals = |key: &str, value: &str| AttributeFilter::Equals {
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    assert_eq!(
+        vec!["ledger v2"],
+        set_names(
+            &sets,
+            topic.id,
+            SetFilter::Attributes(vec![equals("team", "payments")])
+        )
+        .await
+    );
+    assert!(
+        set_names(
+            &sets,
+            topic.id,
+            SetFilter::Attributes(vec![AttributeFilter::Exists("sla".to_string())])
+        )
+        .await
+        .is_empty()
+    );
+}
+
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn sets_of_archived_topics_are_read_only<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topics = runtime.repos.topics();
+    let topic = topics
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+    let sets = runtime.repos.sets();
+    let key = |set_id| (runtime.set_key_gen)(Some(topic.id), Some(set_id));
+    let set = sets.create(topic.id, new_set("set1")).await.unwrap();
+    let set_id = set.key.set_id();
+
+    for (from, to) in [
+        (TopicStatus::Draft, TopicStatus::Active),
+        (TopicStatus::Active, TopicStatus::Deprecated),
+        (TopicStatus::Deprecated, TopicStatus::Archived),
+    ] {
+        topics
+            .change_status(topic.id, StatusChange::new(from, to, None))
+            .await
+            .unwrap()
+            .expect("status changed");
+    }
+
+    let e = sets
+        .create(topic.id, new_set("set2"))
+        .await
+        .expect_err("archived topic");
+    assert_eq!(
+        &SetRepoError::Create(Reason::TopicArchived),
+        e.current_context()
+    );
+    let patch = PatchSet {
+        name: Some("renamed".to_string()),
+        description: Field::Missing,
+        attributes: None,
+    };
+    let e = sets
+        .patch(key(set_id), patch)
+        .await
+        .expect_err("archived topic");
+    assert_eq!(
+        &SetRepoError::Patch(Reason::TopicArchived),
+        e.current_context()
+    );
+    let e = sets
+        .add_tags(key(set_id), vec!["pii".to_string()])
+        .await
+        .expect_err("archived topic");
+    assert_eq!(
+        &SetRepoError::Tags(Reason::TopicArchived),
+        e.current_context()
+    );
+    let e = sets.delete(key(set_id)).await.expect_err("archived topic");
+    assert_eq!(
+        &SetRepoError::Delete(Reason::TopicArchived),
+        e.current_context()
+    );
+    let unchanged = sets
+        .get(key(set_id))
+        .await
+        .unwrap()
+        .expect("set still exists");
+    assert_eq!(set.name, unchanged.name);
+}
+
+#[rstest]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn sets_can_be_listed_as_they_were<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: Repos,
+{
+    let topic = runtime
+        .repos
+        .topics()
+        .create(NewTopic::new("topic1", None::<String>))
+        .await
+        .expect("topic created");
+    let sets = runtime.repos.sets();
+    let key = |set_id| (runtime.set_key_gen)(Some(topic.id), Some(set_id));
+    let set = sets.create(topic.id, new_set("set1")).await.unwrap();
+    let then = Utc::now();
+    let patch = PatchSet {
+        name: Some("set1 v2".to_string()),
+        description: Field::Missing,
+        attributes: None,
+    };
+    sets.patch(key(set.key.set_id()), patch)
+        .await
+        .unwrap()
+        .expect("set patched");
+    sets.create(topic.id, new_set("set2")).await.unwrap();
+
+    let names_at = |at| {
+        let sets = sets.clone();
+        async move {
+            sets.list_at(
+                topic.id,
+                at,
+                SetListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE),
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(vec!["set1"], names_at(then).await);
+    assert_eq!(vec!["set1 v2", "set2"], names_at(Utc::now()).await);
+    assert!(names_at(then - TimeDelta::days(1)).await.is_empty());
+
+    let e = sets
+        .list_at(
+            runtime.random_topic_id(),
+            then,
+            SetListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE),
+        )
+        .await
+        .expect_err("missing topic");
+    assert_eq!(
+        &SetRepoError::List(Reason::TopicNotFound),
+        e.current_context()
+    );
+}
+
+fn attributes(value: Value) -> Attributes {
+    match value {
+        Value::Object(attributes) => attributes,
+        _ => panic!("attributes are an object"),
+    }
+}
+
+/// The names of the topic's sets that pass `filter`, sorted
+async fn set_names<S: SetRepository>(
+    sets: &S,
+    topic_id: <S::SetKey as SetKey>::TopicId,
+    filter: SetFilter,
+) -> Vec<String> {
+    let criteria = SetListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE).with(filter);
+    let mut names: Vec<_> = sets
+        .list(topic_id, criteria)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    names.sort();
+    names
+}
+
 fn new_set(name: &str) -> NewSet {
     NewSet::new(name, Some(format!("{name} desc")))
 }
@@ -431,6 +1070,14 @@ mod postgres {
     }
 
     pub async fn runtime() -> TestRuntime<Postgres, PostgresRepos> {
+        runtime_with(RepoCreator::default()).await
+    }
+
+    pub async fn unique_names_runtime() -> TestRuntime<Postgres, PostgresRepos> {
+        runtime_with(RepoCreator::default().with_unique_names()).await
+    }
+
+    async fn runtime_with(creator: RepoCreator) -> TestRuntime<Postgres, PostgresRepos> {
         let container = container().await;
         let host = container.get_host().await.unwrap();
         let port = container.get_host_port_ipv4(5432).await.unwrap();
@@ -439,7 +1086,7 @@ mod postgres {
             "postgresql://testuser:testpass@{host}:{port}/topics"
         ));
 
-        let (topics, sets) = RepoCreator::default()
+        let (topics, sets) = creator
             .with_sets()
             .create(connection_details, Some(1))
             .await
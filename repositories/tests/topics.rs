@@ -1,10 +1,19 @@
+use chrono::{TimeDelta, Utc};
+use futures::future::join_all;
 use optional_field::Field;
+use routing::attributes::{AttributeFilter, Attributes};
 use routing::pagination::Pagination;
 use rstest::rstest;
+use serde_json::{Value, json};
 use testcontainers_modules::testcontainers::{ContainerAsync, Image};
+use tokio_stream::StreamExt;
 use topics_core::TopicRepository;
-use topics_core::list_filter::TopicListCriteria;
-use topics_core::model::{NewTopic, PatchTopic};
+use topics_core::list_filter::{TopicFilter, TopicListCriteria};
+use topics_core::model::{
+    DeletePolicy, LinkDirection, LinkType, NewTopic, PartialTopic, PatchTopic, StatusChange,
+    TagCount, Topic, TopicField, TopicStatus, TopicVersion, Upserted,
+};
+use topics_core::result::TopicRepoError;
 const DEFAULT_PAGINATION: Pagination = Pagination {
     page: 1,
     page_size: None,
@@ -55,6 +64,32 @@ async fn create_then_get_returns_created_topic<C, R>(
     assert_eq!(&created, &found);
 }
 
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn get_many_returns_topics_in_the_order_asked_for<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let topic1 = repo.create(default_new_topic()).await.unwrap();
+    let topic2 = repo.create(default_new_topic()).await.unwrap();
+
+    let results = repo
+        .get_many(vec![topic2.id, runtime.generate_new_id(), topic1.id])
+        .await
+        .unwrap();
+
+    assert_eq!(vec![Some(topic2), None, Some(topic1)], results);
+    assert!(repo.get_many(Vec::new()).await.unwrap().is_empty());
+}
+
 #[rstest]
 #[case::mongo(mongo::runtime())]
 #[case::postgres(postgres::runtime())]
@@ -135,6 +170,73 @@ async fn list_page_1_or_0_returns_first_page<C, R>(
     assert_eq!(&created, listed.first().unwrap(), "page = 0");
 }
 
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn list_stream_yields_created_topics_in_order<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    let mut created = Vec::with_capacity(3);
+    for _ in 0..3 {
+        created.push(repo.create(default_new_topic()).await.unwrap());
+    }
+
+    let streamed: Vec<_> = repo
+        .list_stream(default_list_criteria())
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(created, streamed);
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn list_fields_stream_only_reads_the_given_fields<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+    let created = repo
+        .create(NewTopic::new("topic", None::<String>))
+        .await
+        .unwrap();
+
+    let streamed: Vec<_> = repo
+        .list_fields_stream(
+            default_list_criteria(),
+            vec![TopicField::Name, TopicField::Description],
+        )
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    let expected = PartialTopic {
+        name: Some(created.name),
+        description: Some(None),
+        ..PartialTopic::default()
+    };
+    assert_eq!(vec![expected], streamed);
+}
+
 #[rstest]
 #[case::mongo(mongo::runtime())]
 #[case::postgres(postgres::runtime())]
@@ -587,7 +689,7 @@ async fn delete_no_topics_created_returns_none<C, R>(
     let repo = &runtime.repo;
 
     let result = repo
-        .delete(runtime.generate_new_id())
+        .delete(runtime.generate_new_id(), DeletePolicy::Reject)
         .await
         .expect("topic delete should not fail");
 
@@ -614,7 +716,7 @@ async fn delete_no_matching_existing_topics_returns_none<C, R>(
         .unwrap();
 
     let result = repo
-        .delete(runtime.generate_new_id())
+        .delete(runtime.generate_new_id(), DeletePolicy::Reject)
         .await
         .expect("topic delete should not fail");
 
@@ -645,7 +747,7 @@ async fn delete_with_existing_topic_deletes_topic<C, R>(
         .unwrap();
 
     let result = repo
-        .delete(topic.id)
+        .delete(topic.id, DeletePolicy::Reject)
         .await
         .expect("topic delete should not fail");
 
@@ -656,90 +758,1346 @@ async fn delete_with_existing_topic_deletes_topic<C, R>(
     assert!(topic.is_none());
 }
 
-pub fn default_new_topic() -> NewTopic {
-    NewTopic::new("test topic 1", Some("test topic 1 description"))
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn patch_many_patches_each_topic_in_order<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let topic1 = repo
+        .create(NewTopic::new("topic1", Some("topic1 desc")))
+        .await
+        .unwrap();
+    let topic2 = repo
+        .create(NewTopic::new("topic2", Some("topic2 desc")))
+        .await
+        .unwrap();
+    let topic3 = repo
+        .create(NewTopic::new("topic3", Some("topic3 desc")))
+        .await
+        .unwrap();
+
+    let results = repo
+        .patch_many(vec![
+            (topic2.id, PatchTopic::new(None, Field::Present(None))),
+            (
+                runtime.generate_new_id(),
+                PatchTopic::new(Some("nope".into()), Field::Missing),
+            ),
+            (
+                topic1.id,
+                PatchTopic::new(Some("renamed".into()), Field::Missing),
+            ),
+            (topic3.id, PatchTopic::new(None, Field::Missing)),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(4, results.len());
+
+    let patched2 = results[0]
+        .as_ref()
+        .unwrap()
+        .as_ref()
+        .expect("topic2 exists");
+    assert_eq!("topic2", &patched2.name);
+    assert_eq!(None, patched2.description);
+    assert!(patched2.updated.is_some());
+
+    assert!(results[1].as_ref().unwrap().is_none());
+
+    let patched1 = results[2]
+        .as_ref()
+        .unwrap()
+        .as_ref()
+        .expect("topic1 exists");
+    assert_eq!("renamed", &patched1.name);
+    assert_eq!(topic1.description, patched1.description);
+    assert_eq!(Some(patched1), repo.get(topic1.id).await.unwrap().as_ref());
+
+    // nothing to change, so it's left alone
+    let unchanged3 = results[3]
+        .as_ref()
+        .unwrap()
+        .as_ref()
+        .expect("topic3 exists");
+    assert_eq!(&topic3, unchanged3);
 }
 
-fn default_list_criteria() -> TopicListCriteria {
-    TopicListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE)
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn delete_many_reports_which_topics_existed<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let topic1 = repo.create(default_new_topic()).await.unwrap();
+    let topic2 = repo.create(default_new_topic()).await.unwrap();
+    let kept = repo.create(default_new_topic()).await.unwrap();
+
+    let results = repo
+        .delete_many(vec![topic2.id, runtime.generate_new_id(), topic1.id])
+        .await
+        .unwrap();
+
+    let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(vec![Some(()), None, Some(())], results);
+    assert!(repo.get(topic1.id).await.unwrap().is_none());
+    assert!(repo.get(topic2.id).await.unwrap().is_none());
+    assert!(repo.get(kept.id).await.unwrap().is_some());
+
+    assert!(repo.delete_many(Vec::new()).await.unwrap().is_empty());
 }
 
-struct TestRuntime<C, R>
-where
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn hierarchy_reads_follow_parent_ids<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
     C: Image,
     R: TopicRepository,
 {
-    _container: ContainerAsync<C>,
-    repo: R,
-    new_id_fn: Box<dyn Fn() -> R::TopicId>,
+    let repo = &runtime.repo;
+
+    let root = repo.create(default_new_topic()).await.unwrap();
+    let child = repo
+        .create(default_new_topic().with_parent(Some(root.id)))
+        .await
+        .unwrap();
+    let grandchild = repo
+        .create(default_new_topic().with_parent(Some(child.id)))
+        .await
+        .unwrap();
+    assert_eq!(Some(child.id), grandchild.parent_id);
+
+    let ids = |topics: Vec<Topic<R::TopicId>>| topics.into_iter().map(|t| t.id).collect::<Vec<_>>();
+
+    let children = repo
+        .children(root.id, default_list_criteria())
+        .await
+        .unwrap()
+        .expect("root exists");
+    assert_eq!(vec![child.id], ids(children));
+
+    let ancestors = repo.ancestors(grandchild.id).await.unwrap().unwrap();
+    assert_eq!(vec![root.id, child.id], ids(ancestors));
+    assert!(repo.ancestors(root.id).await.unwrap().unwrap().is_empty());
+
+    let subtree = repo.subtree(root.id).await.unwrap().unwrap();
+    assert_eq!(vec![root.id, child.id, grandchild.id], ids(subtree));
+
+    let missing = runtime.generate_new_id();
+    assert!(
+        repo.children(missing, default_list_criteria())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(repo.ancestors(missing).await.unwrap().is_none());
+    assert!(repo.subtree(missing).await.unwrap().is_none());
+
+    let e = repo
+        .create(default_new_topic().with_parent(Some(missing)))
+        .await
+        .expect_err("the parent doesn't exist");
+    assert!(matches!(
+        e.current_context(),
+        TopicRepoError::ParentNotFound
+    ));
 }
 
-impl<C, R> TestRuntime<C, R>
-where
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn moves_cant_nest_a_topic_under_its_descendants<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
     C: Image,
     R: TopicRepository,
 {
-    fn new<F>(container: ContainerAsync<C>, repo: R, new_id_fn: F) -> Self
-    where
-        F: Fn() -> R::TopicId + 'static,
-    {
-        Self {
-            _container: container,
-            repo,
-            new_id_fn: Box::new(new_id_fn),
-        }
-    }
+    let repo = &runtime.repo;
 
-    fn generate_new_id(&self) -> R::TopicId {
-        (self.new_id_fn)()
+    let root = repo.create(default_new_topic()).await.unwrap();
+    let child = repo
+        .create(default_new_topic().with_parent(Some(root.id)))
+        .await
+        .unwrap();
+
+    for parent in [root.id, child.id] {
+        let e = repo
+            .patch(
+                root.id,
+                PatchTopic::new(None, Field::Missing).with_parent(Field::Present(Some(parent))),
+            )
+            .await
+            .expect_err("the topic would be nested under itself");
+        assert!(matches!(e.current_context(), TopicRepoError::ParentCycle));
     }
+
+    let moved = repo
+        .patch(
+            child.id,
+            PatchTopic::new(None, Field::Missing).with_parent(Field::Present(None)),
+        )
+        .await
+        .unwrap()
+        .expect("child exists");
+    assert_eq!(None, moved.parent_id);
+    assert!(moved.updated.is_some());
+
+    let moved = repo
+        .patch(
+            root.id,
+            PatchTopic::new(None, Field::Missing).with_parent(Field::Present(Some(child.id))),
+        )
+        .await
+        .unwrap()
+        .expect("root exists");
+    assert_eq!(Some(child.id), moved.parent_id);
 }
 
-mod mongo {
-    use super::TestRuntime;
-    use bson::oid::ObjectId;
-    use repositories::mongodb::topics as mongo_repo;
-    use testcontainers_modules::mongo::Mongo;
-    use testcontainers_modules::testcontainers::ContainerAsync;
-    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+#[rstest]
+#[case::mongo(mongo::repl_set_runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn concurrent_moves_cant_nest_two_topics_under_each_other<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
 
-    pub async fn runtime() -> TestRuntime<Mongo, mongo_repo::TopicRepo> {
-        let mongo_container = container().await;
-        let host = mongo_container.get_host().await.unwrap();
-        let port = mongo_container.get_host_port_ipv4(27017).await.unwrap();
+    let first = repo.create(default_new_topic()).await.unwrap();
+    let second = repo.create(default_new_topic()).await.unwrap();
+    let move_under = |parent: R::TopicId| {
+        PatchTopic::new(None, Field::Missing).with_parent(Field::Present(Some(parent)))
+    };
 
-        let repo = mongo_repo::TopicRepo::init(mongo_repo::ConnectionDetails::Url(format!(
-            "mongodb://{host}:{port}/?authSource=admin"
-        )))
-        .await
-        .unwrap();
+    let (first_moved, second_moved) = tokio::join!(
+        repo.patch(first.id, move_under(second.id)),
+        repo.patch(second.id, move_under(first.id)),
+    );
 
-        TestRuntime::new(mongo_container, repo, || {
-            mongo_repo::TopicId::new_with(ObjectId::new())
+    let moved = [first_moved, second_moved]
+        .into_iter()
+        .filter_map(|moved| match moved {
+            Ok(topic) => topic,
+            Err(e) => {
+                assert!(matches!(
+                    e.current_context(),
+                    TopicRepoError::ParentCycle | TopicRepoError::ConcurrentChange
+                ));
+                None
+            }
         })
-    }
+        .count();
+    assert!(moved <= 1, "only one of the topics can be moved");
 
-    pub async fn container() -> ContainerAsync<Mongo> {
-        Mongo::default().start().await.unwrap()
-    }
+    let first = repo.get(first.id).await.unwrap().unwrap();
+    let second = repo.get(second.id).await.unwrap().unwrap();
+    assert!(first.parent_id.is_none() || second.parent_id.is_none());
 }
 
-mod postgres {
-    use super::TestRuntime;
-    use repositories::postgres::ConnectionDetails;
-    use repositories::postgres::initializer::RepoCreator;
-    use repositories::postgres::topics as postgres_repo;
-    use repositories::postgres::topics::TopicId;
-    use testcontainers_modules::postgres::Postgres;
-    use testcontainers_modules::testcontainers::ContainerAsync;
-    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn delete_policies_decide_what_happens_to_children<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
 
-    pub async fn runtime() -> TestRuntime<Postgres, postgres_repo::TopicRepo> {
+    let root = repo.create(default_new_topic()).await.unwrap();
+    let child = repo
+        .create(default_new_topic().with_parent(Some(root.id)))
+        .await
+        .unwrap();
+    let grandchild = repo
+        .create(default_new_topic().with_parent(Some(child.id)))
+        .await
+        .unwrap();
+
+    let e = repo
+        .delete(root.id, DeletePolicy::Reject)
+        .await
+        .expect_err("root has children");
+    assert!(matches!(e.current_context(), TopicRepoError::HasChildren));
+
+    let results = repo.delete_many(vec![child.id]).await.unwrap();
+    assert!(
+        results[0]
+            .as_ref()
+            .is_err_and(|e| matches!(e.current_context(), TopicRepoError::HasChildren))
+    );
+    assert!(repo.get(child.id).await.unwrap().is_some());
+
+    repo.delete(child.id, DeletePolicy::Reparent)
+        .await
+        .unwrap()
+        .expect("child exists");
+    let reparented = repo.get(grandchild.id).await.unwrap().unwrap();
+    assert_eq!(Some(root.id), reparented.parent_id);
+
+    repo.delete(root.id, DeletePolicy::Cascade)
+        .await
+        .unwrap()
+        .expect("root exists");
+    assert!(repo.get(root.id).await.unwrap().is_none());
+    assert!(repo.get(grandchild.id).await.unwrap().is_none());
+}
+
+#[rstest]
+#[case::mongo(mongo::unique_names_runtime())]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_reject_names_differing_by_case<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    let existing = repo
+        .create(NewTopic::new("Customers", None::<String>))
+        .await
+        .unwrap();
+
+    let e = repo
+        .create(NewTopic::new("customers", None::<String>))
+        .await
+        .expect_err("name is taken");
+    assert!(matches!(e.current_context(), TopicRepoError::DuplicateName));
+
+    let other = repo
+        .create(NewTopic::new("orders", None::<String>))
+        .await
+        .unwrap();
+    let e = repo
+        .patch(
+            other.id,
+            PatchTopic::new(Some("CUSTOMERS".to_string()), Field::Missing),
+        )
+        .await
+        .expect_err("name is taken");
+    assert!(matches!(e.current_context(), TopicRepoError::DuplicateName));
+
+    // renaming a topic to itself with a different case isn't a conflict
+    repo.patch(
+        existing.id,
+        PatchTopic::new(Some("CUSTOMERS".to_string()), Field::Missing),
+    )
+    .await
+    .unwrap();
+}
+
+#[rstest]
+#[case::mongo(mongo::unique_names_runtime())]
+#[case::mongo_repl_set(mongo::unique_names_repl_set_runtime())]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_create_many_fails_only_duplicates<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    repo.create(NewTopic::new("topic1", None::<String>))
+        .await
+        .unwrap();
+
+    let results = repo
+        .create_many(vec![
+            NewTopic::new("TOPIC1", None::<String>),
+            NewTopic::new("topic2", None::<String>),
+            NewTopic::new("Topic2", None::<String>),
+            NewTopic::new("topic3", None::<String>),
+        ])
+        .await
+        .unwrap();
+
+    let duplicates: Vec<_> = results
+        .iter()
+        .map(|r| {
+            r.as_ref()
+                .is_err_and(|e| matches!(e.current_context(), TopicRepoError::DuplicateName))
+        })
+        .collect();
+    assert_eq!(vec![true, false, true, false], duplicates);
+    assert_eq!(3, repo.list(default_list_criteria()).await.unwrap().len());
+    for topic in results.iter().flatten() {
+        let versions = repo
+            .versions(topic.id, default_list_criteria())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, versions.len());
+    }
+}
+
+#[rstest]
+#[case::mongo(mongo::unique_names_runtime())]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_patch_many_conflict_is_duplicate_name<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    repo.create(NewTopic::new("customers", None::<String>))
+        .await
+        .unwrap();
+    let other = repo
+        .create(NewTopic::new("orders", None::<String>))
+        .await
+        .unwrap();
+
+    let renamed = repo
+        .create(NewTopic::new("invoices", None::<String>))
+        .await
+        .unwrap();
+
+    let results = repo
+        .patch_many(vec![
+            (
+                other.id,
+                PatchTopic::new(Some("Customers".into()), Field::Missing),
+            ),
+            (
+                renamed.id,
+                PatchTopic::new(Some("bills".into()), Field::Missing),
+            ),
+        ])
+        .await
+        .unwrap();
+
+    assert!(
+        results[0]
+            .as_ref()
+            .is_err_and(|e| matches!(e.current_context(), TopicRepoError::DuplicateName))
+    );
+    assert_eq!(
+        "bills",
+        results[1].as_ref().unwrap().as_ref().unwrap().name.as_str()
+    );
+    assert_eq!(
+        "orders",
+        repo.get(other.id).await.unwrap().unwrap().name.as_str()
+    );
+}
+
+#[rstest]
+#[case::mongo(mongo::unique_names_runtime())]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_patch_many_rejects_a_name_repeated_in_the_batch<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    let first = repo
+        .create(NewTopic::new("orders", None::<String>))
+        .await
+        .unwrap();
+    let second = repo
+        .create(NewTopic::new("invoices", None::<String>))
+        .await
+        .unwrap();
+
+    let results = repo
+        .patch_many(vec![
+            (
+                first.id,
+                PatchTopic::new(Some("accounts".into()), Field::Missing),
+            ),
+            (
+                second.id,
+                PatchTopic::new(Some("Accounts".into()), Field::Missing),
+            ),
+        ])
+        .await
+        .unwrap();
+
+    assert!(results[0].as_ref().is_ok_and(Option::is_some));
+    assert!(
+        results[1]
+            .as_ref()
+            .is_err_and(|e| matches!(e.current_context(), TopicRepoError::DuplicateName))
+    );
+    assert_eq!(
+        "invoices",
+        repo.get(second.id).await.unwrap().unwrap().name.as_str()
+    );
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn delete_many_only_fails_topics_with_children_left_behind<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    let parent = repo.create(default_new_topic()).await.unwrap();
+    let child = repo
+        .create(default_new_topic().with_parent(Some(parent.id)))
+        .await
+        .unwrap();
+    let leaf_parent = repo.create(default_new_topic()).await.unwrap();
+    let leaf = repo
+        .create(default_new_topic().with_parent(Some(leaf_parent.id)))
+        .await
+        .unwrap();
+    let lone = repo.create(default_new_topic()).await.unwrap();
+
+    let results = repo
+        .delete_many(vec![parent.id, leaf_parent.id, leaf.id, lone.id])
+        .await
+        .unwrap();
+
+    assert!(
+        results[0]
+            .as_ref()
+            .is_err_and(|e| matches!(e.current_context(), TopicRepoError::HasChildren))
+    );
+    assert!(results[1..].iter().all(|r| matches!(r, Ok(Some(())))));
+    assert!(repo.get(parent.id).await.unwrap().is_some());
+    assert!(repo.get(child.id).await.unwrap().is_some());
+    assert!(repo.get(leaf_parent.id).await.unwrap().is_none());
+    assert!(repo.get(lone.id).await.unwrap().is_none());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn create_many_atomic_creates_every_topic_in_order<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    let created = repo
+        .create_many_atomic(vec![
+            NewTopic::new("topic1", Some("topic1 desc")),
+            NewTopic::new("topic2", None::<String>),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec!["topic1", "topic2"],
+        created.iter().map(|t| t.name.as_str()).collect::<Vec<_>>()
+    );
+    for topic in &created {
+        assert_eq!(Some(topic), repo.get(topic.id).await.unwrap().as_ref());
+    }
+}
+
+#[rstest]
+#[case::mongo(mongo::unique_names_runtime())]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_create_many_atomic_creates_nothing_on_duplicate<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = runtime.repo;
+
+    repo.create(NewTopic::new("topic1", None::<String>))
+        .await
+        .unwrap();
+
+    let e = repo
+        .create_many_atomic(vec![
+            NewTopic::new("topic2", None::<String>),
+            NewTopic::new("TOPIC1", None::<String>),
+            NewTopic::new("topic3", None::<String>),
+        ])
+        .await
+        .expect_err("one of the names is taken");
+
+    assert!(matches!(e.current_context(), TopicRepoError::DuplicateName));
+    assert_eq!(1, repo.list(default_list_criteria()).await.unwrap().len());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn upsert_creates_then_replaces_with_the_given_id<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let id = runtime.generate_new_id();
+    let repo = runtime.repo;
+
+    let Upserted::Created(created) = repo.upsert(id, default_new_topic()).await.unwrap() else {
+        panic!("a topic with a new id is created");
+    };
+    assert_eq!(id, created.id);
+    assert!(created.updated.is_none());
+
+    let Upserted::Replaced(replaced) = repo
+        .upsert(id, NewTopic::new("replaced", None::<String>))
+        .await
+        .unwrap()
+    else {
+        panic!("a topic with an existing id is replaced");
+    };
+    assert_eq!(id, replaced.id);
+    assert_eq!("replaced", replaced.name);
+    assert_eq!(None, replaced.description);
+    assert!(replaced.updated.is_some());
+    assert_eq!(
+        created.created.timestamp_millis(),
+        replaced.created.timestamp_millis()
+    );
+
+    assert_eq!(Some(replaced), repo.get(id).await.unwrap());
+    assert_eq!(1, repo.list(default_list_criteria()).await.unwrap().len());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn concurrent_upserts_keep_a_status_changed_in_between<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let orders = repo.create(default_new_topic()).await.unwrap();
+    let launch = StatusChange::new(TopicStatus::Draft, TopicStatus::Active, None);
+
+    let (upserts, changed) = tokio::join!(
+        join_all((0..10).map(|i| {
+            repo.upsert(
+                orders.id,
+                NewTopic::new(format!("orders {i}"), None::<String>),
+            )
+        })),
+        repo.change_status(orders.id, launch),
+    );
+
+    assert!(changed.unwrap().is_some());
+    for upserted in upserts {
+        if let Err(e) = upserted {
+            assert!(matches!(
+                e.current_context(),
+                TopicRepoError::ConcurrentChange
+            ));
+        }
+    }
+    assert_eq!(
+        Some(TopicStatus::Active),
+        repo.get(orders.id).await.unwrap().map(|t| t.status)
+    );
+}
+
+#[rstest]
+#[case::mongo(mongo::unique_names_runtime())]
+#[case::postgres(postgres::unique_names_runtime())]
+#[tokio::test]
+async fn unique_names_upsert_conflict_is_duplicate_name<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let id = runtime.generate_new_id();
+    let repo = runtime.repo;
+
+    repo.create(NewTopic::new("topic1", None::<String>))
+        .await
+        .unwrap();
+
+    let e = repo
+        .upsert(id, NewTopic::new("TOPIC1", None::<String>))
+        .await
+        .expect_err("the name is taken");
+
+    assert!(matches!(e.current_context(), TopicRepoError::DuplicateName));
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn links_are_read_both_ways_and_go_with_their_topics<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let missing = runtime.generate_new_id();
+    let repo = &runtime.repo;
+
+    let a = repo.create(default_new_topic()).await.unwrap();
+    let b = repo.create(default_new_topic()).await.unwrap();
+    let c = repo.create(default_new_topic()).await.unwrap();
+
+    let ab = repo
+        .create_link(a.id, b.id, LinkType::RelatedTo)
+        .await
+        .unwrap()
+        .expect("a exists");
+    let cb = repo
+        .create_link(c.id, b.id, LinkType::DerivedFrom)
+        .await
+        .unwrap()
+        .expect("c exists");
+    assert_eq!(Some(&ab), repo.get_link(a.id, b.id).await.unwrap().as_ref());
+    assert_eq!(None, repo.get_link(b.id, a.id).await.unwrap());
+
+    let e = repo
+        .create_link(a.id, b.id, LinkType::Supersedes)
+        .await
+        .expect_err("a is already linked to b");
+    assert!(matches!(e.current_context(), TopicRepoError::LinkExists));
+    let e = repo
+        .create_link(a.id, a.id, LinkType::RelatedTo)
+        .await
+        .expect_err("a can't link to itself");
+    assert!(matches!(e.current_context(), TopicRepoError::SelfLink));
+    let e = repo
+        .create_link(a.id, missing, LinkType::RelatedTo)
+        .await
+        .expect_err("the target doesn't exist");
+    assert!(matches!(
+        e.current_context(),
+        TopicRepoError::LinkTargetNotFound
+    ));
+    assert_eq!(
+        None,
+        repo.create_link(missing, a.id, LinkType::RelatedTo)
+            .await
+            .unwrap()
+    );
+
+    let links = |id, direction| repo.links(id, direction, default_list_criteria());
+    assert_eq!(
+        Some(vec![ab.clone(), cb.clone()]),
+        links(b.id, LinkDirection::Incoming).await.unwrap()
+    );
+    assert_eq!(
+        Some(vec![]),
+        links(b.id, LinkDirection::Outgoing).await.unwrap()
+    );
+    assert_eq!(
+        Some(vec![cb.clone()]),
+        links(c.id, LinkDirection::Both).await.unwrap()
+    );
+    assert_eq!(None, links(missing, LinkDirection::Both).await.unwrap());
+
+    let updated = repo
+        .update_link(a.id, b.id, LinkType::Supersedes)
+        .await
+        .unwrap()
+        .expect("a is linked to b");
+    assert_eq!(LinkType::Supersedes, updated.link_type);
+    assert!(updated.updated.is_some());
+
+    let mut touching = repo.links_touching(vec![a.id, c.id]).await.unwrap();
+    touching.sort_by_key(|l| l.created);
+    assert_eq!(vec![updated, cb], touching);
+
+    repo.delete(b.id, DeletePolicy::Reject).await.unwrap();
+    assert_eq!(
+        Some(vec![]),
+        links(a.id, LinkDirection::Both).await.unwrap()
+    );
+    assert_eq!(None, repo.delete_link(a.id, b.id).await.unwrap());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn tagged_topics_can_be_listed_by_tag<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+    let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+    let pii = repo
+        .create(NewTopic::new("pii", None::<String>))
+        .await
+        .unwrap();
+    let both = repo
+        .create(NewTopic::new("both", None::<String>))
+        .await
+        .unwrap();
+    repo.create(NewTopic::new("untagged", None::<String>))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        Some(tags(&["pii"])),
+        repo.add_tags(pii.id, tags(&["pii"])).await.unwrap()
+    );
+    assert_eq!(
+        Some(tags(&["finance", "pii"])),
+        repo.add_tags(both.id, tags(&["pii", "finance", "pii"]))
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        repo.add_tags(runtime.generate_new_id(), tags(&["pii"]))
+            .await
+            .unwrap()
+    );
+    assert_eq!(None, repo.tags(runtime.generate_new_id()).await.unwrap());
+    assert_eq!(Some(tags(&["pii"])), repo.tags(pii.id).await.unwrap());
+
+    let names = |filter| async move {
+        let mut names: Vec<_> = repo
+            .list(default_list_criteria().with(filter))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(
+        vec!["both", "pii"],
+        names(TopicFilter::AllTags(tags(&["pii"]))).await
+    );
+    assert_eq!(
+        vec!["both"],
+        names(TopicFilter::AllTags(tags(&["pii", "finance"]))).await
+    );
+    assert_eq!(
+        vec!["both"],
+        names(TopicFilter::AnyTag(tags(&["finance", "other"]))).await
+    );
+
+    assert_eq!(
+        vec![
+            TagCount {
+                tag: "finance".to_string(),
+                count: 1
+            },
+            TagCount {
+                tag: "pii".to_string(),
+                count: 2
+            },
+        ],
+        repo.tag_counts().await.unwrap()
+    );
+
+    assert_eq!(
+        Some(tags(&["finance"])),
+        repo.remove_tags(both.id, tags(&["pii", "other"]))
+            .await
+            .unwrap()
+    );
+    repo.delete(pii.id, DeletePolicy::Reject).await.unwrap();
+    assert_eq!(
+        vec![TagCount {
+            tag: "finance".to_string(),
+            count: 1
+        }],
+        repo.tag_counts().await.unwrap()
+    );
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn attributes_are_merged_on_patch_and_can_be_listed_by<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let payments = repo
+        .create(
+            NewTopic::new("payments", None::<String>).with_attributes(attributes(
+                json!({ "team": "payments", "sla": 99, "tier": "gold" }),
+            )),
+        )
+        .await
+        .unwrap();
+    repo.create(
+        NewTopic::new("orders", None::<String>)
+            .with_attributes(attributes(json!({ "team": "orders" }))),
+    )
+    .await
+    .unwrap();
+    repo.create(NewTopic::new("plain", None::<String>))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        attributes(json!({ "team": "payments", "sla": 99, "tier": "gold" })),
+        payments.attributes
+    );
+
+    let patch = PatchTopic::new(None, Field::Missing).with_attributes(Some(attributes(
+        json!({ "sla": 95, "tier": null, "region": "eu" }),
+    )));
+    let patched = repo.patch(payments.id, patch).await.unwrap().unwrap();
+    let expected = attributes(json!({ "team": "payments", "sla": 95, "region": "eu" }));
+    assert_eq!(expected, patched.attributes);
+    assert_eq!(
+        Some(expected),
+        repo.get(payments.id).await.unwrap().map(|t| t.attributes)
+    );
+
+    let names = |filters| async move {
+        let mut names: Vec<_> = repo
+            .list(default_list_criteria().with(TopicFilter::Attributes(filters)))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        names
+    };
+    let equals = |key: &str, value: &str| AttributeFilter::Equals {
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    let exists = |key: &str| AttributeFilter::Exists(key.to_string());
+
+    assert_eq!(
+        vec!["payments"],
+        names(vec![equals("team", "payments")]).await
+    );
+    assert_eq!(vec!["payments"], names(vec![equals("sla", "95")]).await);
+    assert_eq!(
+        vec!["orders", "payments"],
+        names(vec![exists("team")]).await
+    );
+    assert_eq!(
+        vec!["payments"],
+        names(vec![exists("team"), exists("region")]).await
+    );
+    assert!(names(vec![equals("team", "billing")]).await.is_empty());
+    assert!(names(vec![exists("tier")]).await.is_empty());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn status_changes_are_recorded_and_can_be_listed_by<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let orders = repo
+        .create(NewTopic::new("orders", None::<String>))
+        .await
+        .unwrap();
+    repo.create(NewTopic::new("clicks", None::<String>))
+        .await
+        .unwrap();
+    assert_eq!(TopicStatus::Draft, orders.status);
+
+    let launch = StatusChange::new(
+        TopicStatus::Draft,
+        TopicStatus::Active,
+        Some("launched".to_string()),
+    );
+    let changed = repo
+        .change_status(orders.id, launch.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(TopicStatus::Active, changed.status);
+    assert_eq!(
+        Some(TopicStatus::Active),
+        repo.get(orders.id).await.unwrap().map(|t| t.status)
+    );
+
+    // the topic is no longer a draft, so the same change again is a conflict
+    let e = repo.change_status(orders.id, launch).await.unwrap_err();
+    assert!(matches!(e.current_context(), TopicRepoError::StatusChanged));
+    assert!(
+        repo.change_status(
+            runtime.generate_new_id(),
+            StatusChange::new(TopicStatus::Draft, TopicStatus::Active, None),
+        )
+        .await
+        .unwrap()
+        .is_none()
+    );
+
+    repo.change_status(
+        orders.id,
+        StatusChange::new(TopicStatus::Active, TopicStatus::Deprecated, None),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    let history = repo.status_changes(orders.id).await.unwrap().unwrap();
+    let moves: Vec<_> = history.iter().map(|c| (c.from, c.to)).collect();
+    assert_eq!(
+        vec![
+            (TopicStatus::Draft, TopicStatus::Active),
+            (TopicStatus::Active, TopicStatus::Deprecated),
+        ],
+        moves
+    );
+    assert_eq!(Some("launched"), history[0].reason.as_deref());
+    assert!(
+        repo.status_changes(runtime.generate_new_id())
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let names = |statuses| async move {
+        let mut names: Vec<_> = repo
+            .list(default_list_criteria().with(TopicFilter::Status(statuses)))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(vec!["orders"], names(vec![TopicStatus::Deprecated]).await);
+    assert_eq!(
+        vec!["clicks", "orders"],
+        names(vec![TopicStatus::Draft, TopicStatus::Deprecated]).await
+    );
+    assert!(names(vec![TopicStatus::Archived]).await.is_empty());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn every_change_is_kept_as_a_version<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let orders = repo
+        .create(NewTopic::new("orders", Some("first")))
+        .await
+        .unwrap();
+    repo.patch(
+        orders.id,
+        PatchTopic::new(Some("orders v2".to_string()), Field::Missing),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let versions = repo
+        .versions(orders.id, default_list_criteria())
+        .await
+        .unwrap()
+        .unwrap();
+    let names: Vec<_> = versions
+        .iter()
+        .map(|v| (v.version, v.topic.name.as_str()))
+        .collect();
+    assert_eq!(vec![(1, "orders"), (2, "orders v2")], names);
+    assert!(versions.iter().all(|v| v.topic.id == orders.id));
+
+    let second_page = TopicListCriteria::new(
+        Pagination {
+            page: 2,
+            page_size: Some(1),
+        },
+        DEFAULT_PAGE_SIZE,
+    );
+    let page: Vec<_> = repo
+        .versions(orders.id, second_page)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_iter()
+        .map(|v| v.version)
+        .collect();
+    assert_eq!(vec![2], page);
+
+    let first: TopicVersion<_> = repo.version(orders.id, 1).await.unwrap().unwrap();
+    assert_eq!("orders", first.topic.name);
+    assert_eq!(Some("first"), first.topic.description.as_deref());
+    assert!(repo.version(orders.id, 3).await.unwrap().is_none());
+
+    let now = repo.version_at(orders.id, Utc::now()).await.unwrap();
+    assert_eq!(Some(2), now.map(|v| v.version));
+    let before = Utc::now() - TimeDelta::days(1);
+    assert!(repo.version_at(orders.id, before).await.unwrap().is_none());
+
+    let missing = runtime.generate_new_id();
+    assert!(
+        repo.versions(missing, default_list_criteria())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(repo.version(missing, 1).await.unwrap().is_none());
+}
+
+#[rstest]
+#[case::mongo(mongo::runtime())]
+#[case::mongo_repl_set(mongo::repl_set_runtime())]
+#[case::postgres(postgres::runtime())]
+#[tokio::test]
+async fn concurrent_changes_each_keep_their_own_version<C, R>(
+    #[future(awt)]
+    #[case]
+    runtime: TestRuntime<C, R>,
+) where
+    C: Image,
+    R: TopicRepository,
+{
+    let repo = &runtime.repo;
+
+    let orders = repo.create(default_new_topic()).await.unwrap();
+    let patches = (0..10).map(|i| {
+        repo.patch(
+            orders.id,
+            PatchTopic::new(None, Field::Present(Some(format!("change {i}")))),
+        )
+    });
+    for patched in join_all(patches).await {
+        patched.unwrap().unwrap();
+    }
+
+    let versions = repo
+        .versions(orders.id, default_list_criteria())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (1..=11).collect::<Vec<u64>>(),
+        versions.iter().map(|v| v.version).collect::<Vec<_>>()
+    );
+    // every version after the first is one of the changes, each kept once
+    let mut descriptions: Vec<_> = versions[1..]
+        .iter()
+        .filter_map(|v| v.topic.description.clone())
+        .collect();
+    descriptions.sort();
+    descriptions.dedup();
+    assert_eq!(10, descriptions.len());
+}
+
+fn attributes(value: Value) -> Attributes {
+    match value {
+        Value::Object(attributes) => attributes,
+        _ => panic!("attributes are an object"),
+    }
+}
+
+pub fn default_new_topic<T>() -> NewTopic<T> {
+    NewTopic::new("test topic 1", Some("test topic 1 description"))
+}
+
+fn default_list_criteria() -> TopicListCriteria {
+    TopicListCriteria::new(DEFAULT_PAGINATION, DEFAULT_PAGE_SIZE)
+}
+
+struct TestRuntime<C, R>
+where
+    C: Image,
+    R: TopicRepository,
+{
+    _container: ContainerAsync<C>,
+    repo: R,
+    new_id_fn: Box<dyn Fn() -> R::TopicId>,
+}
+
+impl<C, R> TestRuntime<C, R>
+where
+    C: Image,
+    R: TopicRepository,
+{
+    fn new<F>(container: ContainerAsync<C>, repo: R, new_id_fn: F) -> Self
+    where
+        F: Fn() -> R::TopicId + 'static,
+    {
+        Self {
+            _container: container,
+            repo,
+            new_id_fn: Box::new(new_id_fn),
+        }
+    }
+
+    fn generate_new_id(&self) -> R::TopicId {
+        (self.new_id_fn)()
+    }
+}
+
+mod mongo {
+    use super::TestRuntime;
+    use bson::oid::ObjectId;
+    use repositories::mongodb::topics as mongo_repo;
+    use testcontainers_modules::mongo::Mongo;
+    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+    pub async fn unique_names_runtime() -> TestRuntime<Mongo, mongo_repo::TopicRepo> {
+        let runtime = runtime().await;
+        runtime.repo.enforce_unique_names().await.unwrap();
+        runtime
+    }
+
+    pub async fn runtime() -> TestRuntime<Mongo, mongo_repo::TopicRepo> {
+        runtime_with(Mongo::default(), "authSource=admin").await
+    }
+
+    /// A single node replica set, so the repo runs its writes in transactions
+    pub async fn repl_set_runtime() -> TestRuntime<Mongo, mongo_repo::TopicRepo> {
+        runtime_with(Mongo::repl_set(), "directConnection=true").await
+    }
+
+    pub async fn unique_names_repl_set_runtime() -> TestRuntime<Mongo, mongo_repo::TopicRepo> {
+        let runtime = repl_set_runtime().await;
+        runtime.repo.enforce_unique_names().await.unwrap();
+        runtime
+    }
+
+    async fn runtime_with(
+        image: Mongo,
+        options: &str,
+    ) -> TestRuntime<Mongo, mongo_repo::TopicRepo> {
+        let mongo_container = image.start().await.unwrap();
+        let host = mongo_container.get_host().await.unwrap();
+        let port = mongo_container.get_host_port_ipv4(27017).await.unwrap();
+
+        let repo = mongo_repo::TopicRepo::init(mongo_repo::ConnectionDetails::Url(format!(
+            "mongodb://{host}:{port}/?{options}"
+        )))
+        .await
+        .unwrap();
+
+        TestRuntime::new(mongo_container, repo, || {
+            mongo_repo::TopicId::new_with(ObjectId::new())
+        })
+    }
+}
+
+mod postgres {
+    use super::TestRuntime;
+    use repositories::postgres::ConnectionDetails;
+    use repositories::postgres::initializer::RepoCreator;
+    use repositories::postgres::topics as postgres_repo;
+    use repositories::postgres::topics::TopicId;
+    use testcontainers_modules::postgres::Postgres;
+    use testcontainers_modules::testcontainers::ContainerAsync;
+    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+    pub async fn runtime() -> TestRuntime<Postgres, postgres_repo::TopicRepo> {
+        runtime_with(RepoCreator::default()).await
+    }
+
+    pub async fn unique_names_runtime() -> TestRuntime<Postgres, postgres_repo::TopicRepo> {
+        runtime_with(RepoCreator::default().with_unique_names()).await
+    }
+
+    async fn runtime_with(creator: RepoCreator) -> TestRuntime<Postgres, postgres_repo::TopicRepo> {
         let container = container().await;
         let host = container.get_host().await.unwrap();
         let port = container.get_host_port_ipv4(5432).await.unwrap();
 
-        let repo = RepoCreator::default()
+        let repo = creator
             .with_topics()
             .create(
                 ConnectionDetails::Url(format!(
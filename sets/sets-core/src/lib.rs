@@ -1,13 +1,17 @@
 use crate::list_filter::SetListCriteria;
-use crate::model::{NewSet, PatchSet, Set};
+use crate::model::{NewSet, PatchSet, Set, TagCount, Upserted};
 use crate::result::{OptRepoResult, RepoResult};
+use chrono::{DateTime, Utc};
 use ids::Id;
 use std::fmt::Debug;
+use tokio_stream::Stream;
 
 pub mod model;
 
 pub mod list_filter;
 pub mod result;
+pub mod validation;
+
 pub trait SetKey: Debug {
     type SetId: Id;
     type TopicId: Id;
@@ -31,12 +35,41 @@ pub trait SetRepository: Clone + Send + Sync + 'static {
         key: Self::SetKey,
     ) -> impl Future<Output = OptRepoResult<Set<Self::SetKey>>> + Send;
 
+    /// The sets of one topic. Results are in the same order as `set_ids`, a missing set is `None`.
+    /// A missing topic is an error
+    fn get_many(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        set_ids: Vec<<Self::SetKey as SetKey>::SetId>,
+    ) -> impl Future<Output = RepoResult<Vec<Option<Set<Self::SetKey>>>>> + Send;
+
     fn list(
         &self,
         topic_id: <Self::SetKey as SetKey>::TopicId,
         list_criteria: SetListCriteria,
     ) -> impl Future<Output = RepoResult<Vec<Set<Self::SetKey>>>> + Send;
 
+    /// A page of the topic's sets as they were at `at`, in the same order as `list`. Only the
+    /// page of `list_criteria` is used, since tags have no history. Sets made after `at` are
+    /// left out, and so are deleted sets since their history goes with them. A missing topic is
+    /// an error
+    fn list_at(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        at: DateTime<Utc>,
+        list_criteria: SetListCriteria,
+    ) -> impl Future<Output = RepoResult<Vec<Set<Self::SetKey>>>> + Send;
+
+    /// Like `list`, but sets are read as the stream is polled instead of all up front.
+    /// A missing topic is still an error up front
+    fn list_stream(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        list_criteria: SetListCriteria,
+    ) -> impl Future<
+        Output = RepoResult<impl Stream<Item = RepoResult<Set<Self::SetKey>>> + Send + 'static>,
+    > + Send;
+
     fn create(
         &self,
         topic_id: <Self::SetKey as SetKey>::TopicId,
@@ -49,6 +82,21 @@ pub trait SetRepository: Clone + Send + Sync + 'static {
         sets: Vec<NewSet>,
     ) -> impl Future<Output = RepoResult<Vec<RepoResult<Set<Self::SetKey>>>>> + Send;
 
+    /// Either every set is created or none are, results are in the same order as `sets`
+    fn create_many_atomic(
+        &self,
+        topic_id: <Self::SetKey as SetKey>::TopicId,
+        sets: Vec<NewSet>,
+    ) -> impl Future<Output = RepoResult<Vec<Set<Self::SetKey>>>> + Send;
+
+    /// Replaces the name and description of the set with `key`, creating it if there isn't one.
+    /// Fails with `IdTaken` if the set id is already used in another topic.
+    fn upsert(
+        &self,
+        key: Self::SetKey,
+        set: NewSet,
+    ) -> impl Future<Output = RepoResult<Upserted<Set<Self::SetKey>>>> + Send;
+
     fn patch(
         &self,
         key: Self::SetKey,
@@ -56,4 +104,26 @@ pub trait SetRepository: Clone + Send + Sync + 'static {
     ) -> impl Future<Output = OptRepoResult<Set<Self::SetKey>>> + Send;
 
     fn delete(&self, key: Self::SetKey) -> impl Future<Output = OptRepoResult<()>> + Send;
+
+    /// The set's tags in alphabetical order, `None` if there's no such set in the topic
+    fn tags(&self, key: Self::SetKey) -> impl Future<Output = OptRepoResult<Vec<String>>> + Send;
+
+    /// Tags the set, tags it already has are skipped. Returns all of its tags like [tags]
+    ///
+    /// [tags]: SetRepository::tags
+    fn add_tags(
+        &self,
+        key: Self::SetKey,
+        tags: Vec<String>,
+    ) -> impl Future<Output = OptRepoResult<Vec<String>>> + Send;
+
+    /// Untags the set, tags it doesn't have are skipped. Returns the tags it has left
+    fn remove_tags(
+        &self,
+        key: Self::SetKey,
+        tags: Vec<String>,
+    ) -> impl Future<Output = OptRepoResult<Vec<String>>> + Send;
+
+    /// Every tag in use and how many sets have it, in alphabetical order
+    fn tag_counts(&self) -> impl Future<Output = RepoResult<Vec<TagCount>>> + Send;
 }
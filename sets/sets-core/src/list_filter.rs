@@ -1,11 +1,18 @@
+use routing::attributes::AttributeFilter;
 use routing::list_criteria::{ListCriteria, ListFilter, Tag};
 use routing::pagination::Pagination;
 
-const MAX_FILTER_COUNT: usize = 1;
+const MAX_FILTER_COUNT: usize = 4;
 pub type SetListCriteria = ListCriteria<SetFilter, MAX_FILTER_COUNT>;
 
 pub enum SetFilter {
     Name(String),
+    /// Sets with every one of the tags
+    AllTags(Vec<String>),
+    /// Sets with at least one of the tags
+    AnyTag(Vec<String>),
+    /// Sets whose attributes pass every filter
+    Attributes(Vec<AttributeFilter>),
 }
 
 impl ListFilter for SetFilter {
@@ -15,6 +22,9 @@ impl ListFilter for SetFilter {
     fn tag(&self) -> Tag {
         match self {
             SetFilter::Name(_) => Tag::One,
+            SetFilter::AllTags(_) => Tag::Two,
+            SetFilter::AnyTag(_) => Tag::Four,
+            SetFilter::Attributes(_) => Tag::Eight,
         }
     }
 
@@ -22,3 +32,30 @@ impl ListFilter for SetFilter {
         ListCriteria::new(pagination, default_page_size)
     }
 }
+
+/// The tags a set needs every one of and the tags it needs at least one of, `None` when the
+/// criteria doesn't filter on them
+pub fn tag_filters(list_criteria: &SetListCriteria) -> (Option<&[String]>, Option<&[String]>) {
+    let (mut all, mut any) = (None, None);
+    for filter in list_criteria.filters().unwrap_or_default() {
+        match filter {
+            SetFilter::Name(_) | SetFilter::Attributes(_) => {}
+            SetFilter::AllTags(tags) => all = Some(tags.as_slice()),
+            SetFilter::AnyTag(tags) => any = Some(tags.as_slice()),
+        }
+    }
+    (all, any)
+}
+
+/// The attribute filters a set has to pass, empty when the criteria doesn't filter on them
+pub fn attribute_filters(list_criteria: &SetListCriteria) -> &[AttributeFilter] {
+    list_criteria
+        .filters()
+        .unwrap_or_default()
+        .iter()
+        .find_map(|filter| match filter {
+            SetFilter::Attributes(filters) => Some(filters.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use optional_field::Field;
+use routing::attributes::Attributes;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -9,26 +10,67 @@ pub struct Set<K> {
     pub key: K,
     pub name: String,
     pub description: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub attributes: Attributes,
     pub created: DateTime<Utc>,
     pub updated: Option<DateTime<Utc>>,
 }
 
-#[derive(Clone)]
+/// What an upsert did, holding the set as it now is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upserted<T> {
+    Created(T),
+    Replaced(T),
+}
+
+impl<T> Upserted<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Upserted::Created(t) | Upserted::Replaced(t) => t,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct NewSet {
     pub name: String,
     pub description: Option<String>,
+    pub attributes: Attributes,
 }
 impl NewSet {
     pub fn new(name: impl Into<String>, description: Option<impl Into<String>>) -> Self {
         Self {
             name: name.into(),
             description: description.map(Into::into),
+            attributes: Attributes::new(),
         }
     }
+
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct PatchSet {
     pub name: Option<String>,
     pub description: Field<String>,
+    /// Merged into the set's attributes, a null attribute is removed
+    pub attributes: Option<Attributes>,
+}
+
+impl PatchSet {
+    pub fn with_attributes(mut self, attributes: Option<Attributes>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+}
+
+/// How many sets have a tag
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
 }
@@ -1,4 +1,6 @@
 use error_stack::Report;
+use routing::problem::{ProblemDetails, StatusCode};
+use std::borrow::Cow;
 
 pub type RepoResult<T> = Result<T, Report<SetRepoError>>;
 pub type OptRepoResult<T> = Result<Option<T>, Report<SetRepoError>>;
@@ -13,10 +15,14 @@ pub enum SetRepoError {
     List(Reason),
     #[error("failed to create many sets: {0}")]
     CreateMany(Reason),
+    #[error("failed to upsert set: {0}")]
+    Upsert(Reason),
     #[error("failed to patch set: {0}")]
     Patch(Reason),
     #[error("failed to delete set: {0}")]
     Delete(Reason),
+    #[error("failed to read or write set tags: {0}")]
+    Tags(Reason),
 }
 
 #[derive(Debug, thiserror::Error, PartialEq, Eq, Copy, Clone)]
@@ -27,4 +33,106 @@ pub enum Reason {
     Db,
     #[error("input failed validation")]
     Validation,
+    #[error("a set with the same name already exists in the topic")]
+    DuplicateName,
+    #[error("the set id is already used in another topic")]
+    IdTaken,
+    #[error("the topic is archived, its sets are read-only")]
+    TopicArchived,
+}
+
+impl ProblemDetails for Reason {
+    fn status(&self) -> StatusCode {
+        match self {
+            Reason::TopicNotFound => StatusCode::NOT_FOUND,
+            Reason::Db => StatusCode::INTERNAL_SERVER_ERROR,
+            Reason::Validation => StatusCode::UNPROCESSABLE_ENTITY,
+            Reason::DuplicateName | Reason::IdTaken | Reason::TopicArchived => StatusCode::CONFLICT,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            Reason::TopicNotFound => "topic_not_found",
+            Reason::Db => "database_error",
+            Reason::Validation => "validation_failed",
+            Reason::DuplicateName => "duplicate_name",
+            Reason::IdTaken => "id_taken",
+            Reason::TopicArchived => "topic_archived",
+        }
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        match self {
+            Reason::Db => None,
+            reason => Some(Cow::Owned(reason.to_string())),
+        }
+    }
+}
+
+impl SetRepoError {
+    pub fn reason(&self) -> Reason {
+        match self {
+            SetRepoError::Get(reason)
+            | SetRepoError::Create(reason)
+            | SetRepoError::List(reason)
+            | SetRepoError::CreateMany(reason)
+            | SetRepoError::Upsert(reason)
+            | SetRepoError::Patch(reason)
+            | SetRepoError::Delete(reason)
+            | SetRepoError::Tags(reason) => *reason,
+        }
+    }
+}
+
+// the operation doesn't change what the client should do about it, so defer to the reason
+impl ProblemDetails for SetRepoError {
+    fn status(&self) -> StatusCode {
+        self.reason().status()
+    }
+
+    fn code(&self) -> &'static str {
+        self.reason().code()
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        self.reason().detail()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_not_found_is_a_404() {
+        let problem = SetRepoError::Get(Reason::TopicNotFound).to_problem();
+
+        assert_eq!(404, problem.status);
+        assert_eq!("topic_not_found", problem.code);
+    }
+
+    #[test]
+    fn validation_is_a_422() {
+        let problem = SetRepoError::List(Reason::Validation).to_problem();
+
+        assert_eq!(422, problem.status);
+        assert_eq!("validation_failed", problem.code);
+    }
+
+    #[test]
+    fn duplicate_name_is_a_409() {
+        let problem = SetRepoError::Patch(Reason::DuplicateName).to_problem();
+
+        assert_eq!(409, problem.status);
+        assert_eq!("duplicate_name", problem.code);
+    }
+
+    #[test]
+    fn db_failures_do_not_leak_details() {
+        let problem = SetRepoError::Create(Reason::Db).to_problem();
+
+        assert_eq!(500, problem.status);
+        assert!(problem.detail.is_none());
+    }
 }
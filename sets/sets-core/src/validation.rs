@@ -0,0 +1,98 @@
+use optional_field::Field;
+use routing::attributes::Attributes;
+use routing::patch::PatchFields;
+use routing::validation::{FieldError, FieldErrors, TextField};
+
+use crate::model::{NewSet, PatchSet};
+
+/// Matches the `varchar` limits of the sets table
+pub const NAME_MAX_LEN: usize = 255;
+pub const DESCRIPTION_MAX_LEN: usize = 4096;
+
+pub const NAME: TextField = TextField::new("name", NAME_MAX_LEN);
+pub const DESCRIPTION: TextField = TextField::new("description", DESCRIPTION_MAX_LEN).multiline();
+
+/// What a merge patch or JSON Patch of a set can change, the rest of its fields can only be read
+pub const PATCH_FIELDS: PatchFields = PatchFields::new(
+    &["name", "description", "attributes"],
+    &["set_id", "topic_id", "created", "updated"],
+);
+
+/// Trims and checks a new set, a blank description is stored as no description
+pub fn new_set(name: &str, description: Option<&str>) -> Result<NewSet, FieldErrors> {
+    let mut errors = FieldErrors::default();
+    let name = errors.check(NAME.required(name));
+    let description = errors.check(DESCRIPTION.optional(description));
+
+    match (name, description) {
+        (Some(name), Some(description)) if errors.is_empty() => Ok(NewSet {
+            name,
+            description,
+            attributes: Attributes::new(),
+        }),
+        _ => Err(errors),
+    }
+}
+
+/// Like [new_set], except a null name is an error and a blank description clears it
+pub fn patch_set(name: Field<String>, description: Field<String>) -> Result<PatchSet, FieldErrors> {
+    let mut errors = FieldErrors::default();
+
+    let name = match name {
+        Field::Missing => None,
+        Field::Present(None) => {
+            errors.push(FieldError::required(NAME.name()));
+            None
+        }
+        Field::Present(Some(name)) => errors.check(NAME.required(&name)),
+    };
+
+    let description = match description {
+        Field::Missing => Field::Missing,
+        Field::Present(d) => {
+            Field::Present(errors.check(DESCRIPTION.optional(d.as_deref())).flatten())
+        }
+    };
+
+    if errors.is_empty() {
+        Ok(PatchSet {
+            name,
+            description,
+            attributes: None,
+        })
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use routing::validation::ValidationRule;
+
+    use super::*;
+
+    #[test]
+    fn new_set_is_trimmed() {
+        let set = new_set(" name\t", None).unwrap();
+
+        assert_eq!("name", set.name);
+    }
+
+    #[test]
+    fn every_failed_field_is_reported() {
+        let errors = new_set(&"n".repeat(NAME_MAX_LEN + 1), Some("a\u{0}")).unwrap_err();
+
+        let rules: Vec<_> = errors.iter().map(|e| e.rule).collect();
+        assert_eq!(
+            vec![ValidationRule::TooLong, ValidationRule::ControlCharacters],
+            rules
+        );
+    }
+
+    #[test]
+    fn patch_null_name_is_required() {
+        let errors = patch_set(Field::Present(None), Field::Missing).unwrap_err();
+
+        assert_eq!(ValidationRule::Required, errors.first().unwrap().rule);
+    }
+}
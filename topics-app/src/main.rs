@@ -5,6 +5,8 @@ use error_stack::ResultExt;
 use error_stack::fmt::ColorMode;
 use repositories::postgres::initializer::RepoCreator;
 use routing::AuthState;
+use routing::idempotency::spawn_purge;
+use std::time::Duration;
 use topics_core::TopicRepository;
 use topics_routes::state::TopicAppState;
 use tracing::{debug, error, info, instrument, warn};
@@ -45,14 +47,21 @@ async fn try_main() -> AppResult<()> {
 }
 
 async fn build_routes() -> AppResult<Router> {
-    let repo = build_repo().await?;
+    let (repo, set_repo, api_key_repo, idempotency_repo) = build_repo().await?;
+
+    spawn_purge(idempotency_repo.clone(), Duration::from_secs(60 * 60));
 
     debug!("building routes..");
     Ok(topics_routes::routes::build(
         TopicAppState::new_with_metrics(TopicEngine::new(repo))
             .await
-            .change_context(AppError)?,
-        AuthState::create().await.change_context(AppError)?,
+            .change_context(AppError)?
+            .with_sets(set_repo)
+            .with_idempotency(idempotency_repo),
+        AuthState::create()
+            .await
+            .change_context(AppError)?
+            .with_api_keys(api_key_repo),
     ))
     .inspect(|_| debug!("routes built"))
 }
@@ -72,21 +81,40 @@ async fn build_routes() -> AppResult<Router> {
 // }
 
 #[instrument]
-async fn build_repo() -> AppResult<repositories::postgres::topics::TopicRepo> {
+async fn build_repo() -> AppResult<(
+    repositories::postgres::topics::TopicRepo,
+    repositories::postgres::sets::SetRepo,
+    repositories::postgres::api_keys::ApiKeyRepo,
+    repositories::postgres::idempotency::IdempotencyRepo,
+)> {
     use repositories::postgres::ConnectionDetails;
 
     let db_connection_str = std::env::var("DATABASE_URL")
         .change_context(AppError)
         .attach("DATABASE_URL is missing")?;
 
-    debug!("initializing repository");
-    RepoCreator::default()
+    let mut creator = RepoCreator::default()
         .with_topics()
+        .with_sets()
+        .with_api_keys()
+        .with_idempotency_keys();
+    if unique_names_enabled() {
+        debug!("enforcing unique topic names");
+        creator = creator.with_unique_names();
+    }
+
+    debug!("initializing repository");
+    creator
         .create(ConnectionDetails::Url(db_connection_str), None)
         .await
         .change_context(AppError)
 }
 
+/// Opt in with `TOPICS_UNIQUE_NAMES=true`
+fn unique_names_enabled() -> bool {
+    std::env::var("TOPICS_UNIQUE_NAMES").is_ok_and(|v| v.eq_ignore_ascii_case("true") || v == "1")
+}
+
 #[derive(Debug, Clone)]
 struct TopicEngine<T> {
     repo: T,
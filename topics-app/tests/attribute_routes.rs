@@ -0,0 +1,152 @@
+use axum_test::TestResponse;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use routing::patch::{JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE};
+use serde_json::{Value, json};
+use support::TestApp;
+use topics_core::model::Topic;
+
+mod support;
+
+async fn patch(
+    app: &TestApp,
+    id: TopicId,
+    content_type: &'static str,
+    body: Value,
+) -> TestResponse {
+    app.server
+        .patch(&format!("/topics/{}", id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .bytes(serde_json::to_vec(&body).unwrap().into())
+        .content_type(content_type)
+        .await
+}
+
+#[tokio::test]
+async fn null_attributes_are_dropped_on_create() {
+    let app = TestApp::builder().build().await;
+
+    let payments = app
+        .create_topic(json!({
+            "name": "payments",
+            "attributes": { "team": "payments", "sla": 99, "tier": null },
+        }))
+        .await;
+
+    assert_eq!(
+        json!({ "team": "payments", "sla": 99 }),
+        Value::Object(payments.attributes)
+    );
+}
+
+#[tokio::test]
+async fn attribute_keys_are_validated() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "bad", "attributes": { "cost.centre": 1 } }))
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+}
+
+#[tokio::test]
+async fn merge_patch_merges_attributes() {
+    let app = TestApp::builder().build().await;
+    let payments = app
+        .create_topic(
+            json!({ "name": "payments", "attributes": { "team": "payments", "sla": 99 } }),
+        )
+        .await;
+
+    let patched = patch(
+        &app,
+        payments.id,
+        MERGE_PATCH_CONTENT_TYPE,
+        json!({ "attributes": { "sla": null, "region": "eu" } }),
+    )
+    .await
+    .json::<Topic<TopicId>>();
+
+    assert_eq!(
+        json!({ "team": "payments", "region": "eu" }),
+        Value::Object(patched.attributes)
+    );
+}
+
+#[tokio::test]
+async fn json_patch_removes_an_attribute() {
+    let app = TestApp::builder().build().await;
+    let payments = app
+        .create_topic(
+            json!({ "name": "payments", "attributes": { "team": "payments", "region": "eu" } }),
+        )
+        .await;
+
+    let patched = patch(
+        &app,
+        payments.id,
+        JSON_PATCH_CONTENT_TYPE,
+        json!([{ "op": "remove", "path": "/attributes/region" }]),
+    )
+    .await
+    .json::<Topic<TopicId>>();
+
+    assert_eq!(
+        json!({ "team": "payments" }),
+        Value::Object(patched.attributes)
+    );
+}
+
+#[tokio::test]
+async fn attributes_cant_be_patched_to_null() {
+    let app = TestApp::builder().build().await;
+    let payments = app
+        .create_topic(json!({ "name": "payments", "attributes": { "team": "payments" } }))
+        .await;
+
+    let response = patch(
+        &app,
+        payments.id,
+        MERGE_PATCH_CONTENT_TYPE,
+        json!({ "attributes": null }),
+    )
+    .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+}
+
+#[tokio::test]
+async fn topics_are_listed_by_attribute() {
+    let app = TestApp::builder().build().await;
+    app.create_topic(json!({ "name": "payments", "attributes": { "team": "payments" } }))
+        .await;
+    app.create_topic(json!({ "name": "orders", "attributes": { "team": "orders" } }))
+        .await;
+    app.create_topic(json!({ "name": "clicks" })).await;
+
+    assert_eq!(
+        vec!["payments"],
+        app.topic_names("attr.team=payments").await
+    );
+    assert_eq!(
+        vec!["payments", "orders"],
+        app.topic_names("attr.team=").await
+    );
+}
+
+#[tokio::test]
+async fn invalid_attribute_filter_is_rejected() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .get("/topics?attr.a$b=1")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+}
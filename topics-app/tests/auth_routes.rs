@@ -0,0 +1,145 @@
+use reqwest::StatusCode;
+use routing::test_support::TestIssuer;
+use serde_json::{Value, json};
+use support::TestApp;
+
+mod support;
+
+#[tokio::test]
+async fn listing_topics_needs_the_read_role() {
+    let app = TestApp::builder().build().await;
+
+    let response = app.server.get("/topics").await;
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        response.status_code(),
+        "GET /topics without authorization is unauthorized"
+    );
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        response.status_code(),
+        "GET /topics with write role is forbidden",
+    );
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+    assert_eq!(
+        StatusCode::NO_CONTENT,
+        response.status_code(),
+        "GET /topics with read role is allowed",
+    );
+}
+
+#[tokio::test]
+async fn writing_a_topic_needs_the_write_role() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .put(&format!("/topics/{}", uuid_path()))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .json(&json!({ "name": "orders" }))
+        .await;
+
+    assert_eq!(StatusCode::FORBIDDEN, response.status_code());
+}
+
+#[tokio::test]
+async fn invalid_tokens_are_unauthorized() {
+    let app = TestApp::builder().build().await;
+    let untrusted = TestIssuer::new("http://someone-else");
+
+    let tokens = [
+        (
+            "expired",
+            app.token().roles(["TOPIC_READ"]).expired().mint(),
+        ),
+        (
+            "wrong audience",
+            app.token()
+                .roles(["TOPIC_READ"])
+                .audience("other-api")
+                .mint(),
+        ),
+        (
+            "unknown kid",
+            app.token().roles(["TOPIC_READ"]).unknown_kid().mint(),
+        ),
+        (
+            "untrusted issuer",
+            untrusted.token().roles(["TOPIC_READ"]).mint(),
+        ),
+        ("not a jwt", "definitely-not-a-jwt".to_string()),
+    ];
+
+    for (case, token) in tokens {
+        let response = app.server.get("/topics").authorization_bearer(&token).await;
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            response.status_code(),
+            "{case} token is unauthorized"
+        );
+    }
+}
+
+#[tokio::test]
+async fn roles_are_read_from_configured_claim_path() {
+    let app = TestApp::builder()
+        .issuer(
+            TestIssuer::default().with_roles(
+                routing::RoleMapping::new(["realm_access.roles"])
+                    .with_role("topics-reader", "TOPIC_READ"),
+            ),
+        )
+        .build()
+        .await;
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["topics-reader"]))
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+}
+
+#[tokio::test]
+async fn keys_fetched_from_jwks_server_validate_tokens() {
+    let app = TestApp::builder().serve_jwks().build().await;
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+}
+
+#[tokio::test]
+async fn auth_failures_are_problems() {
+    let app = TestApp::builder().build().await;
+
+    let unauthorized = app.server.get("/topics").await;
+    let forbidden = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+
+    assert_eq!("unauthorized", unauthorized.json::<Value>()["code"]);
+    assert_eq!("forbidden", forbidden.json::<Value>()["code"]);
+}
+
+fn uuid_path() -> String {
+    repositories::postgres::topics::TopicId::new().0.to_string()
+}
@@ -0,0 +1,346 @@
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use support::TestApp;
+
+mod support;
+
+#[tokio::test]
+async fn bulk_outcomes_name_the_broken_rule() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([
+            { "name": "fine" },
+            { "name": "n".repeat(256) },
+            { "name": "fine", "description": "esc\u{1b}" },
+        ]))
+        .await;
+
+    assert_eq!(StatusCode::MULTI_STATUS, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!("fine", body["outcomes"][0]["Success"]["name"]);
+    assert_eq!("NameTooLong", body["outcomes"][1]["Fail"]["reason"]);
+    assert_eq!(
+        "DescriptionControlCharacters",
+        body["outcomes"][2]["Fail"]["reason"]
+    );
+}
+
+#[tokio::test]
+async fn bulk_create_fails_only_the_topic_whose_name_is_taken() {
+    let app = TestApp::builder()
+        .repo(InMemoryTopicsRepo::with_unique_names())
+        .build()
+        .await;
+    app.create_topic(json!({ "name": "Customers" })).await;
+
+    let response = app
+        .server
+        .post("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([{ "name": "orders" }, { "name": "CUSTOMERS" }]))
+        .await;
+
+    assert_eq!(StatusCode::MULTI_STATUS, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!(1, body["created"]);
+    assert_eq!("orders", body["outcomes"][0]["Success"]["name"]);
+    assert_eq!("DuplicateName", body["outcomes"][1]["Fail"]["reason"]);
+}
+
+#[tokio::test]
+async fn bulk_patch_reports_each_topic() {
+    let app = TestApp::builder().build().await;
+    let created = app
+        .create_topic(json!({ "name": "before", "description": "kept" }))
+        .await;
+
+    let response = app
+        .server
+        .patch("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([
+            { "id": created.id, "name": "after" },
+            { "id": TopicId::new(), "description": null },
+            { "id": created.id, "name": "again" },
+            { "id": TopicId::new(), "name": null },
+        ]))
+        .await;
+
+    assert_eq!(StatusCode::MULTI_STATUS, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!(1, body["patched"]);
+    assert_eq!(3, body["failed"]);
+    assert_eq!("after", body["outcomes"][0]["Success"]["name"]);
+    assert_eq!("kept", body["outcomes"][0]["Success"]["description"]);
+    assert_eq!("NotFound", body["outcomes"][1]["Fail"]["reason"]);
+    assert_eq!("DuplicateId", body["outcomes"][2]["Fail"]["reason"]);
+    assert_eq!("MissingName", body["outcomes"][3]["Fail"]["reason"]);
+}
+
+#[tokio::test]
+async fn bulk_patch_fails_only_the_topic_whose_name_is_taken() {
+    let app = TestApp::builder()
+        .repo(InMemoryTopicsRepo::with_unique_names())
+        .build()
+        .await;
+    app.create_topic(json!({ "name": "customers" })).await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let invoices = app.create_topic(json!({ "name": "invoices" })).await;
+
+    let response = app
+        .server
+        .patch("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([
+            { "id": orders.id, "name": "Customers" },
+            { "id": invoices.id, "name": "bills" },
+        ]))
+        .await;
+
+    assert_eq!(StatusCode::MULTI_STATUS, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!(1, body["patched"]);
+    assert_eq!("DuplicateName", body["outcomes"][0]["Fail"]["reason"]);
+    assert_eq!("bills", body["outcomes"][1]["Success"]["name"]);
+}
+
+#[tokio::test]
+async fn bulk_patch_fails_the_second_topic_given_the_same_name() {
+    let app = TestApp::builder()
+        .repo(InMemoryTopicsRepo::with_unique_names())
+        .build()
+        .await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let invoices = app.create_topic(json!({ "name": "invoices" })).await;
+
+    let response = app
+        .server
+        .patch("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([
+            { "id": orders.id, "name": "accounts" },
+            { "id": invoices.id, "name": "ACCOUNTS" },
+        ]))
+        .await;
+
+    assert_eq!(StatusCode::MULTI_STATUS, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!("accounts", body["outcomes"][0]["Success"]["name"]);
+    assert_eq!("DuplicateName", body["outcomes"][1]["Fail"]["reason"]);
+}
+
+#[tokio::test]
+async fn bulk_delete_deletes_every_topic() {
+    let app = TestApp::builder().build().await;
+    let doomed = app.create_topic(json!({ "name": "doomed" })).await;
+
+    let response = app
+        .server
+        .delete("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([doomed.id]))
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    assert_eq!(1, response.json::<Value>()["deleted"]);
+}
+
+#[tokio::test]
+async fn bulk_delete_where_every_topic_fails_is_unprocessable() {
+    let app = TestApp::builder().build().await;
+    let missing = TopicId::new();
+
+    let response = app
+        .server
+        .delete("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([missing, missing]))
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!("NotFound", body["outcomes"][0]["Fail"]["reason"]);
+    assert_eq!("DuplicateId", body["outcomes"][1]["Fail"]["reason"]);
+}
+
+#[tokio::test]
+async fn empty_bulk_delete_is_a_bad_request() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .delete("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([]))
+        .await;
+
+    assert_eq!(StatusCode::BAD_REQUEST, response.status_code());
+}
+
+#[tokio::test]
+async fn bulk_delete_fails_only_the_topic_with_children_left_behind() {
+    let app = TestApp::builder().build().await;
+    let parent = app.create_topic(json!({ "name": "parent" })).await;
+    let child = app
+        .create_topic(json!({ "name": "child", "parent_id": parent.id }))
+        .await;
+    let lone = app.create_topic(json!({ "name": "lone" })).await;
+
+    let response = app
+        .server
+        .delete("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([parent.id, lone.id]))
+        .await;
+
+    assert_eq!(StatusCode::MULTI_STATUS, response.status_code());
+    let body = response.json::<Value>();
+    assert_eq!(1, body["deleted"]);
+    assert_eq!("HasChildren", body["outcomes"][0]["Fail"]["reason"]);
+    assert_eq!(
+        json!({ "topic_id": lone.id }),
+        body["outcomes"][1]["Success"]
+    );
+
+    let read_access = app.token_with_roles(&["TOPIC_READ"]);
+    for (id, status) in [
+        (parent.id, StatusCode::OK),
+        (child.id, StatusCode::OK),
+        (lone.id, StatusCode::NOT_FOUND),
+    ] {
+        let response = app
+            .server
+            .get(&format!("/topics/{}", id.0))
+            .authorization_bearer(&read_access)
+            .await;
+        assert_eq!(status, response.status_code());
+    }
+}
+
+#[tokio::test]
+async fn bulk_delete_of_a_whole_subtree_succeeds() {
+    let app = TestApp::builder().build().await;
+    let parent = app.create_topic(json!({ "name": "parent" })).await;
+    let child = app
+        .create_topic(json!({ "name": "child", "parent_id": parent.id }))
+        .await;
+
+    let response = app
+        .server
+        .delete("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([parent.id, child.id]))
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    assert_eq!(2, response.json::<Value>()["deleted"]);
+}
+
+#[tokio::test]
+async fn atomic_bulk_create_lists_every_invalid_topic() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/bulk?atomic=true")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([{ "name": "fine" }, { "description": "no name" }, { "name": " " }]))
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+    let problem = response.json::<Value>();
+    assert_eq!("[1].name", problem["errors"][0]["field"]);
+    assert_eq!("[2].name", problem["errors"][1]["field"]);
+}
+
+#[tokio::test]
+async fn atomic_bulk_create_creates_nothing_when_one_topic_fails() {
+    let app = TestApp::builder()
+        .repo(InMemoryTopicsRepo::with_unique_names())
+        .build()
+        .await;
+
+    let response = app
+        .server
+        .post("/topics/bulk?atomic=true")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([{ "name": "orders" }, { "name": "ORDERS" }]))
+        .await;
+
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+}
+
+#[tokio::test]
+async fn atomic_bulk_create_creates_every_topic() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/bulk?atomic=true")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!([{ "name": "orders" }, { "name": "customers" }]))
+        .await;
+
+    assert_eq!(StatusCode::CREATED, response.status_code());
+    assert_eq!(2, response.json::<Value>()["created"]);
+}
+
+#[tokio::test]
+async fn ndjson_bulk_create_streams_an_outcome_per_line() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .text("{\"name\":\"orders\"}\nnot json\n\n{\"name\":\" \"}\r\n{\"name\":\"customers\"}")
+        .content_type("application/x-ndjson")
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    assert_eq!(
+        "application/x-ndjson",
+        response.header("content-type").to_str().unwrap()
+    );
+    let lines: Vec<Value> = response
+        .text()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(5, lines.len());
+    assert_eq!("orders", lines[0]["Success"]["name"]);
+    assert_eq!("MalformedLine", lines[1]["Fail"]["reason"]);
+    assert_eq!("EmptyName", lines[2]["Fail"]["reason"]);
+    assert_eq!("customers", lines[3]["Success"]["name"]);
+    assert_eq!(
+        json!({ "summary": { "created": 2, "failed": 2 } }),
+        lines[4]
+    );
+}
+
+#[tokio::test]
+async fn ndjson_bulk_create_cant_be_atomic() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/bulk?atomic=true")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .text("{\"name\":\"orders\"}")
+        .content_type("application/x-ndjson")
+        .await;
+
+    assert_eq!(StatusCode::BAD_REQUEST, response.status_code());
+}
@@ -0,0 +1,145 @@
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use reqwest::header::{self, HeaderValue};
+use routing::wire::WireFormat;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use support::TestApp;
+use topics_core::model::Topic;
+
+mod support;
+
+/// The bulk create response, read back typed since binary formats carry ids as bytes
+#[derive(Deserialize)]
+struct Created {
+    created: usize,
+    outcomes: Vec<Outcome>,
+}
+
+#[derive(Deserialize)]
+enum Outcome {
+    Success(Topic<TopicId>),
+    Fail {},
+}
+
+async fn app_with_topics() -> TestApp {
+    let app = TestApp::builder().build().await;
+    app.create_topic(json!({ "name": "orders", "description": "all, of them" }))
+        .await;
+    app.create_topic(json!({ "name": "customers" })).await;
+    app
+}
+
+#[tokio::test]
+async fn topics_are_listed_as_csv() {
+    let app = app_with_topics().await;
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .add_header(header::ACCEPT, HeaderValue::from_static("text/csv"))
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    assert_eq!(
+        "text/csv",
+        response.header("content-type").to_str().unwrap()
+    );
+    let text = response.text();
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        "created,description,id,name,parent_id,status,updated",
+        rows[0]
+    );
+    assert_eq!(3, rows.len());
+    assert!(rows[1].contains(",\"all, of them\","));
+}
+
+#[tokio::test]
+async fn topics_are_listed_as_ndjson() {
+    let app = app_with_topics().await;
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .add_header(
+            header::ACCEPT,
+            HeaderValue::from_static("application/x-ndjson"),
+        )
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    let names: Vec<String> = response
+        .text()
+        .lines()
+        .map(|l| serde_json::from_str::<Topic<TopicId>>(l).unwrap().name)
+        .collect();
+    assert_eq!(vec!["orders", "customers"], names);
+}
+
+#[tokio::test]
+async fn unknown_accepted_format_is_not_acceptable() {
+    let app = app_with_topics().await;
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .add_header(header::ACCEPT, HeaderValue::from_static("application/xml"))
+        .await;
+
+    assert_eq!(StatusCode::NOT_ACCEPTABLE, response.status_code());
+    assert_eq!("not_acceptable", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn bulk_create_speaks_binary_formats() {
+    let app = TestApp::builder().build().await;
+    let write_access = app.token_with_roles(&["TOPIC_WRITE"]);
+
+    for format in [WireFormat::MessagePack, WireFormat::Cbor, WireFormat::Bson] {
+        let name = format!("{format:?} topic");
+        let body = format.serialize(&json!([{ "name": name }])).unwrap();
+
+        let response = app
+            .server
+            .post("/topics/bulk")
+            .authorization_bearer(&write_access)
+            .bytes(body.into())
+            .content_type(format.content_type())
+            .add_header(
+                header::ACCEPT,
+                HeaderValue::from_static(format.content_type()),
+            )
+            .await;
+
+        assert_eq!(StatusCode::CREATED, response.status_code(), "{format:?}");
+        assert_eq!(
+            format.content_type(),
+            response.header("content-type").to_str().unwrap()
+        );
+        let body: Created = format.deserialize(response.as_bytes()).unwrap();
+        assert_eq!(1, body.created, "{format:?}");
+        let [Outcome::Success(topic)] = &body.outcomes[..] else {
+            panic!("{format:?} topic wasn't created");
+        };
+        assert_eq!(name, topic.name, "{format:?}");
+    }
+}
+
+#[tokio::test]
+async fn unknown_body_format_is_unsupported() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/bulk")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .text("<topics/>")
+        .content_type("application/xml")
+        .await;
+
+    assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, response.status_code());
+}
@@ -0,0 +1,127 @@
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use support::TestApp;
+use topics_core::model::Topic;
+
+mod support;
+
+/// A root topic, its child and its grandchild
+async fn family(app: &TestApp) -> [Topic<TopicId>; 3] {
+    let root = app.create_topic(json!({ "name": "root" })).await;
+    let child = app
+        .create_topic(json!({ "name": "child", "parent_id": root.id }))
+        .await;
+    let grandchild = app
+        .create_topic(json!({ "name": "grandchild", "parent_id": child.id }))
+        .await;
+    [root, child, grandchild]
+}
+
+async fn read(app: &TestApp, path: &str) -> Value {
+    app.server
+        .get(path)
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn hierarchy_can_be_read_down_and_up() {
+    let app = TestApp::builder().build().await;
+    let [root, child, grandchild] = family(&app).await;
+
+    let children = read(&app, &format!("/topics/{}/children", root.id.0)).await;
+    let ancestors = read(&app, &format!("/topics/{}/ancestors", grandchild.id.0)).await;
+    let subtree = read(&app, &format!("/topics/{}/subtree", root.id.0)).await;
+
+    assert_eq!(json!([child]), children);
+    assert_eq!(json!([root, child]), ancestors);
+    assert_eq!(json!([root, child, grandchild]), subtree);
+}
+
+#[tokio::test]
+async fn moving_a_topic_under_its_descendant_conflicts() {
+    let app = TestApp::builder().build().await;
+    let [root, _, grandchild] = family(&app).await;
+
+    let response = app
+        .server
+        .post(&format!("/topics/{}/move", root.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "parent_id": grandchild.id }))
+        .await;
+
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    assert_eq!("parent_cycle", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn topic_can_be_moved_to_the_root() {
+    let app = TestApp::builder().build().await;
+    let [_, _, grandchild] = family(&app).await;
+
+    let moved = app
+        .server
+        .post(&format!("/topics/{}/move", grandchild.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "parent_id": null }))
+        .await
+        .json::<Topic<TopicId>>();
+
+    assert_eq!(None, moved.parent_id);
+}
+
+#[tokio::test]
+async fn deleting_a_topic_with_children_conflicts() {
+    let app = TestApp::builder().build().await;
+    let [root, ..] = family(&app).await;
+
+    let response = app
+        .server
+        .delete(&format!("/topics/{}", root.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    assert_eq!("topic_has_children", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn reparent_delete_moves_children_to_the_grandparent() {
+    let app = TestApp::builder().build().await;
+    let [root, child, grandchild] = family(&app).await;
+
+    let response = app
+        .server
+        .delete(&format!("/topics/{}?policy=reparent", child.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+    let moved = read(&app, &format!("/topics/{}", grandchild.id.0)).await;
+    assert_eq!(json!(root.id), moved["parent_id"]);
+}
+
+#[tokio::test]
+async fn cascade_delete_removes_the_subtree() {
+    let app = TestApp::builder().build().await;
+    let [root, child, grandchild] = family(&app).await;
+
+    let response = app
+        .server
+        .delete(&format!("/topics/{}?policy=cascade", root.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+    let read_access = app.token_with_roles(&["TOPIC_READ"]);
+    for topic in [root, child, grandchild] {
+        let response = app
+            .server
+            .get(&format!("/topics/{}", topic.id.0))
+            .authorization_bearer(&read_access)
+            .await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status_code());
+    }
+}
@@ -0,0 +1,94 @@
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde_json::{Value, json};
+use support::TestApp;
+use topics_core::model::Topic;
+
+mod support;
+
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+async fn create(
+    app: &TestApp,
+    name: &str,
+    key: &'static str,
+    token: &str,
+) -> axum_test::TestResponse {
+    app.server
+        .post("/topics")
+        .authorization_bearer(token)
+        .add_header(IDEMPOTENCY_KEY, HeaderValue::from_static(key))
+        .json(&json!({ "name": name }))
+        .await
+}
+
+#[tokio::test]
+async fn idempotency_key_replays_the_first_create() {
+    let app = TestApp::builder().build().await;
+    let write_access = app.token_with_roles(&["TOPIC_WRITE"]);
+
+    let first = create(&app, "orders", "create-orders", &write_access).await;
+    let retry = create(&app, "orders", "create-orders", &write_access).await;
+
+    assert_eq!(StatusCode::CREATED, first.status_code());
+    assert_eq!(StatusCode::CREATED, retry.status_code());
+    assert_eq!("true", retry.header("idempotent-replayed"));
+    assert_eq!(
+        first.json::<Topic<TopicId>>().id,
+        retry.json::<Topic<TopicId>>().id
+    );
+    assert_eq!(vec!["orders"], app.topic_names("").await);
+}
+
+#[tokio::test]
+async fn reused_key_with_another_body_is_rejected() {
+    let app = TestApp::builder().build().await;
+    let write_access = app.token_with_roles(&["TOPIC_WRITE"]);
+    create(&app, "orders", "create-orders", &write_access).await;
+
+    let reused = create(&app, "customers", "create-orders", &write_access).await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, reused.status_code());
+    assert_eq!("idempotency_key_reused", reused.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn keys_belong_to_whoever_sent_them() {
+    let app = TestApp::builder().build().await;
+    create(
+        &app,
+        "orders",
+        "create-orders",
+        &app.token_with_roles(&["TOPIC_WRITE"]),
+    )
+    .await;
+    let someone_else = app
+        .token()
+        .subject("someone-else")
+        .roles(["TOPIC_WRITE"])
+        .mint();
+
+    let other_caller = create(&app, "orders", "create-orders", &someone_else).await;
+
+    assert_eq!(StatusCode::CREATED, other_caller.status_code());
+    assert!(other_caller.maybe_header("idempotent-replayed").is_none());
+    assert_eq!(vec!["orders", "orders"], app.topic_names("").await);
+}
+
+#[tokio::test]
+async fn key_is_released_when_the_create_panics() {
+    let repo = InMemoryTopicsRepo::default();
+    let app = TestApp::builder().repo(repo.clone()).build().await;
+    let write_access = app.token_with_roles(&["TOPIC_WRITE"]);
+    repo.panic_on_next_create();
+
+    let failed = create(&app, "orders", "create-orders", &write_access).await;
+    let retry = create(&app, "orders", "create-orders", &write_access).await;
+
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, failed.status_code());
+    assert_eq!(StatusCode::CREATED, retry.status_code());
+    assert!(retry.maybe_header("idempotent-replayed").is_none());
+    assert_eq!(vec!["orders"], app.topic_names("").await);
+}
@@ -0,0 +1,161 @@
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use support::TestApp;
+use topics_core::TopicRepository;
+use topics_core::model::{LinkType, NewTopic, Topic, TopicLink};
+use topics_routes::service::{MAX_GRAPH_EDGES, MAX_GRAPH_NODES};
+
+mod support;
+
+/// Five topics, each `related_to` the next
+async fn chain(app: &TestApp) -> Vec<Topic<TopicId>> {
+    let mut topics = Vec::new();
+    for name in ["a", "b", "c", "d", "e"] {
+        topics.push(app.create_topic(json!({ "name": name })).await);
+    }
+    for pair in topics.windows(2) {
+        let response = app
+            .server
+            .post(&format!("/topics/{}/links", pair[0].id.0))
+            .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+            .json(&json!({ "target_id": pair[1].id, "link_type": "related_to" }))
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status_code());
+    }
+    topics
+}
+
+#[tokio::test]
+async fn second_link_between_the_same_topics_conflicts() {
+    let app = TestApp::builder().build().await;
+    let topics = chain(&app).await;
+
+    let response = app
+        .server
+        .post(&format!("/topics/{}/links", topics[0].id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "target_id": topics[1].id, "link_type": "supersedes" }))
+        .await;
+
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    assert_eq!("link_exists", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn changed_link_is_listed_as_incoming() {
+    let app = TestApp::builder().build().await;
+    let topics = chain(&app).await;
+    let (a, b) = (&topics[0], &topics[1]);
+
+    let link = app
+        .server
+        .put(&format!("/topics/{}/links/{}", a.id.0, b.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "link_type": "supersedes" }))
+        .await
+        .json::<TopicLink<TopicId>>();
+    let incoming = app
+        .server
+        .get(&format!("/topics/{}/links?direction=incoming", b.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Vec<TopicLink<TopicId>>>();
+
+    assert_eq!(LinkType::Supersedes, link.link_type);
+    assert_eq!(vec![link], incoming);
+}
+
+#[tokio::test]
+async fn graph_is_walked_to_the_asked_for_depth() {
+    let app = TestApp::builder().build().await;
+    let topics = chain(&app).await;
+
+    let graph = app
+        .server
+        .get(&format!("/topics/{}/graph?depth=3", topics[0].id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Value>();
+
+    let nodes = graph["nodes"].as_array().unwrap();
+    assert_eq!(
+        json!([topics[0].id, topics[1].id, topics[2].id, topics[3].id]),
+        json!(nodes.iter().map(|n| &n["id"]).collect::<Vec<_>>())
+    );
+    assert_eq!(3, graph["edges"].as_array().unwrap().len());
+    assert_eq!(false, graph["truncated"]);
+}
+
+#[tokio::test]
+async fn only_admins_walk_deep_graphs() {
+    let app = TestApp::builder().build().await;
+    let topics = chain(&app).await;
+    let path = format!("/topics/{}/graph?depth=4", topics[0].id.0);
+
+    let response = app
+        .server
+        .get(&path)
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+    let graph = app
+        .server
+        .get(&format!("{path}&link_type=supersedes"))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ", "TOPIC_ADMIN"]))
+        .await
+        .json::<Value>();
+
+    assert_eq!(StatusCode::FORBIDDEN, response.status_code());
+    assert_eq!("graph_too_deep", response.json::<Value>()["code"]);
+    assert_eq!(1, graph["nodes"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn big_graphs_are_truncated() {
+    let repo = InMemoryTopicsRepo::default();
+    let app = TestApp::builder().repo(repo.clone()).build().await;
+    let hub = repo.create(NewTopic::new("hub", None::<String>)).await.unwrap();
+    for i in 0..MAX_GRAPH_NODES {
+        let spoke = repo
+            .create(NewTopic::new(format!("spoke {i}"), None::<String>))
+            .await
+            .unwrap();
+        repo.create_link(hub.id, spoke.id, LinkType::RelatedTo)
+            .await
+            .unwrap();
+    }
+
+    let graph = app
+        .server
+        .get(&format!("/topics/{}/graph", hub.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Value>();
+
+    assert_eq!(true, graph["truncated"]);
+    assert_eq!(MAX_GRAPH_NODES, graph["nodes"].as_array().unwrap().len());
+    assert!(graph["edges"].as_array().unwrap().len() < MAX_GRAPH_EDGES);
+}
+
+#[tokio::test]
+async fn deleted_link_is_not_found() {
+    let app = TestApp::builder().build().await;
+    let topics = chain(&app).await;
+    let path = format!("/topics/{}/links/{}", topics[0].id.0, topics[1].id.0);
+
+    let response = app
+        .server
+        .delete(&path)
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+
+    let response = app
+        .server
+        .get(&path)
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status_code());
+    assert_eq!("link_not_found", response.json::<Value>()["code"]);
+}
@@ -0,0 +1,124 @@
+use axum_test::TestResponse;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use routing::patch::{JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE};
+use serde_json::{Value, json};
+use support::TestApp;
+use topics_core::model::Topic;
+
+mod support;
+
+async fn patch(
+    app: &TestApp,
+    id: TopicId,
+    content_type: &'static str,
+    body: Value,
+) -> TestResponse {
+    app.server
+        .patch(&format!("/topics/{}", id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .bytes(serde_json::to_vec(&body).unwrap().into())
+        .content_type(content_type)
+        .await
+}
+
+#[tokio::test]
+async fn merge_patch_clears_a_null_field() {
+    let app = TestApp::builder().build().await;
+    let orders = app
+        .create_topic(json!({ "name": "orders", "description": "all orders" }))
+        .await;
+
+    let response = patch(
+        &app,
+        orders.id,
+        MERGE_PATCH_CONTENT_TYPE,
+        json!({ "description": null }),
+    )
+    .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    let patched = response.json::<Topic<TopicId>>();
+    assert_eq!("orders", patched.name);
+    assert_eq!(None, patched.description);
+}
+
+#[tokio::test]
+async fn json_patch_applies_every_operation() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let response = patch(
+        &app,
+        orders.id,
+        JSON_PATCH_CONTENT_TYPE,
+        json!([
+            { "op": "test", "path": "/name", "value": "orders" },
+            { "op": "replace", "path": "/name", "value": "customers" },
+            { "op": "add", "path": "/description", "value": "all customers" },
+        ]),
+    )
+    .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    let patched = response.json::<Topic<TopicId>>();
+    assert_eq!("customers", patched.name);
+    assert_eq!(Some("all customers".to_string()), patched.description);
+}
+
+#[tokio::test]
+async fn failed_json_patch_test_conflicts() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "customers" })).await;
+
+    let response = patch(
+        &app,
+        orders.id,
+        JSON_PATCH_CONTENT_TYPE,
+        json!([
+            { "op": "test", "path": "/name", "value": "orders" },
+            { "op": "replace", "path": "/name", "value": "stale" },
+        ]),
+    )
+    .await;
+
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    assert_eq!("patch_test_failed", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn patches_cant_touch_immutable_or_unknown_fields() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let immutable = patch(
+        &app,
+        orders.id,
+        MERGE_PATCH_CONTENT_TYPE,
+        json!({ "created": null }),
+    )
+    .await;
+    let unknown = patch(
+        &app,
+        orders.id,
+        JSON_PATCH_CONTENT_TYPE,
+        json!([{ "op": "add", "path": "/colour", "value": "red" }]),
+    )
+    .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, immutable.status_code());
+    assert_eq!("immutable_field", immutable.json::<Value>()["code"]);
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, unknown.status_code());
+    assert_eq!("unknown_field", unknown.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn empty_json_patch_changes_nothing() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let response = patch(&app, orders.id, JSON_PATCH_CONTENT_TYPE, json!([])).await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    assert_eq!("orders", response.json::<Topic<TopicId>>().name);
+}
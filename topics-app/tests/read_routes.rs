@@ -0,0 +1,109 @@
+use repositories::postgres::set_test_repos::InMemorySetsRepo;
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sets_core::SetRepository;
+use sets_core::model::NewSet;
+use support::TestApp;
+
+mod support;
+
+#[tokio::test]
+async fn listed_topics_have_only_the_asked_for_fields() {
+    let app = TestApp::builder().build().await;
+    let created = app
+        .create_topic(json!({ "name": "orders", "description": "all orders" }))
+        .await;
+
+    let listed = app
+        .server
+        .get("/topics?fields=id,name")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Value>();
+
+    assert_eq!(json!([{ "id": created.id, "name": "orders" }]), listed);
+}
+
+#[tokio::test]
+async fn read_topic_has_only_the_asked_for_fields_and_expansions() {
+    let topics = InMemoryTopicsRepo::default();
+    let sets = InMemorySetsRepo::new(topics.clone());
+    let app = TestApp::builder()
+        .repo(topics)
+        .sets(sets.clone())
+        .build()
+        .await;
+    let created = app
+        .create_topic(json!({ "name": "orders", "description": "all orders" }))
+        .await;
+    sets.create(created.id, NewSet::new("eu", None::<String>))
+        .await
+        .unwrap();
+
+    let read = app
+        .server
+        .get(&format!("/topics/{}?fields=name&expand=sets", created.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Value>();
+
+    assert_eq!("orders", read["name"]);
+    assert_eq!(None, read.get("description"));
+    assert_eq!("eu", read["sets"][0]["name"]);
+}
+
+#[tokio::test]
+async fn unknown_fields_and_expansions_are_bad_requests() {
+    let app = TestApp::builder().build().await;
+    let created = app.create_topic(json!({ "name": "orders" })).await;
+    let read_access = app.token_with_roles(&["TOPIC_READ"]);
+
+    for (query, code) in [
+        ("fields=colour", "unknown_field"),
+        ("expand=subscribers", "unknown_expansion"),
+    ] {
+        let response = app
+            .server
+            .get(&format!("/topics/{}?{query}", created.id.0))
+            .authorization_bearer(&read_access)
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status_code(), "{query}");
+        assert_eq!(code, response.json::<Value>()["code"], "{query}");
+    }
+}
+
+#[tokio::test]
+async fn batch_get_returns_found_topics_and_missing_ids() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let customers = app.create_topic(json!({ "name": "customers" })).await;
+    let missing = TopicId::new();
+
+    let response = app
+        .server
+        .post("/topics/batch-get")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .json(&json!([customers.id, missing, orders.id, customers.id]))
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    let batch = response.json::<Value>();
+    assert_eq!(json!([customers, orders]), batch["topics"]);
+    assert_eq!(json!([missing]), batch["missing"]);
+}
+
+#[tokio::test]
+async fn empty_batch_get_is_a_bad_request() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics/batch-get")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .json(&json!([]))
+        .await;
+
+    assert_eq!(StatusCode::BAD_REQUEST, response.status_code());
+}
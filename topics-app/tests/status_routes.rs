@@ -0,0 +1,175 @@
+use axum_test::TestResponse;
+use repositories::postgres::set_test_repos::InMemorySetsRepo;
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sets_core::SetRepository;
+use sets_core::model::NewSet;
+use sets_core::result::{Reason, SetRepoError};
+use support::TestApp;
+use topics_core::model::{StatusChange, Topic, TopicStatus};
+
+mod support;
+
+async fn change(app: &TestApp, id: TopicId, token: &str, body: Value) -> TestResponse {
+    app.server
+        .post(&format!("/topics/{}/status", id.0))
+        .authorization_bearer(token)
+        .json(&body)
+        .await
+}
+
+/// Walks `id` through `statuses` as a writer
+async fn walk(app: &TestApp, id: TopicId, statuses: &[&str]) {
+    let write_access = app.token_with_roles(&["TOPIC_WRITE"]);
+    for status in statuses {
+        let response = change(app, id, &write_access, json!({ "status": status })).await;
+        assert_eq!(StatusCode::OK, response.status_code(), "{status}");
+    }
+}
+
+#[tokio::test]
+async fn new_topics_are_drafts_that_cant_skip_ahead() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let response = change(
+        &app,
+        orders.id,
+        &app.token_with_roles(&["TOPIC_WRITE"]),
+        json!({ "status": "deprecated" }),
+    )
+    .await;
+
+    assert_eq!(TopicStatus::Draft, orders.status);
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    assert_eq!("transition_not_allowed", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn topics_follow_the_lifecycle_to_archived() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let active = change(
+        &app,
+        orders.id,
+        &app.token_with_roles(&["TOPIC_WRITE"]),
+        json!({ "status": "active" }),
+    )
+    .await
+    .json::<Topic<TopicId>>();
+    walk(&app, orders.id, &["deprecated", "archived"]).await;
+
+    assert_eq!(TopicStatus::Active, active.status);
+}
+
+#[tokio::test]
+async fn archived_topics_take_no_new_sets() {
+    let topics = InMemoryTopicsRepo::default();
+    let sets = InMemorySetsRepo::new(topics.clone());
+    let app = TestApp::builder()
+        .repo(topics)
+        .sets(sets.clone())
+        .build()
+        .await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    walk(&app, orders.id, &["active", "deprecated", "archived"]).await;
+
+    let e = sets
+        .create(orders.id, NewSet::new("eu", None::<String>))
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        &SetRepoError::Create(Reason::TopicArchived),
+        e.current_context()
+    );
+}
+
+#[tokio::test]
+async fn only_admins_unarchive() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    walk(&app, orders.id, &["active", "deprecated", "archived"]).await;
+    let activate = json!({ "status": "active" });
+
+    let writer = change(
+        &app,
+        orders.id,
+        &app.token_with_roles(&["TOPIC_WRITE"]),
+        activate.clone(),
+    )
+    .await;
+    let admin = change(
+        &app,
+        orders.id,
+        &app.token_with_roles(&["TOPIC_WRITE", "TOPIC_ADMIN"]),
+        activate,
+    )
+    .await;
+
+    assert_eq!(StatusCode::FORBIDDEN, writer.status_code());
+    assert_eq!("unarchive_forbidden", writer.json::<Value>()["code"]);
+    assert_eq!(StatusCode::OK, admin.status_code());
+}
+
+#[tokio::test]
+async fn status_history_lists_every_change_with_its_reason() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    change(
+        &app,
+        orders.id,
+        &app.token_with_roles(&["TOPIC_WRITE"]),
+        json!({ "status": "active", "reason": " launched " }),
+    )
+    .await;
+    walk(&app, orders.id, &["deprecated"]).await;
+
+    let history = app
+        .server
+        .get(&format!("/topics/{}/status", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Vec<StatusChange>>();
+
+    let moves: Vec<_> = history.iter().map(|c| (c.from, c.to)).collect();
+    assert_eq!(
+        vec![
+            (TopicStatus::Draft, TopicStatus::Active),
+            (TopicStatus::Active, TopicStatus::Deprecated),
+        ],
+        moves
+    );
+    assert_eq!(Some("launched"), history[0].reason.as_deref());
+    assert_eq!(None, history[1].reason);
+}
+
+#[tokio::test]
+async fn topics_are_listed_by_status() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    app.create_topic(json!({ "name": "clicks" })).await;
+    walk(&app, orders.id, &["active"]).await;
+
+    assert_eq!(vec!["orders"], app.topic_names("status=active").await);
+    assert_eq!(
+        vec!["orders", "clicks"],
+        app.topic_names("status=active&status=draft").await
+    );
+}
+
+#[tokio::test]
+async fn unknown_status_filter_is_rejected() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .get("/topics?status=retired")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+}
@@ -0,0 +1,148 @@
+// Each test binary only uses some of these helpers
+#![allow(dead_code)]
+
+use axum_test::TestServer;
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use routing::AuthState;
+use routing::idempotency::InMemoryIdempotencyRepo;
+use routing::test_support::{JwksServer, TestIssuer, TokenBuilder};
+use serde_json::Value;
+use sets_core::{SetKey, SetRepository};
+use topics_core::model::Topic;
+use topics_core::{TopicEngine, TopicRepository};
+use topics_routes::expand::TopicSets;
+use topics_routes::state::TopicAppState;
+
+/// The topic routes backed by an in memory repo, trusting a `TestIssuer` instead of a real identity provider.
+pub struct TestApp {
+    pub server: TestServer,
+    pub issuer: TestIssuer,
+    _jwks_server: Option<JwksServer>,
+}
+
+impl TestApp {
+    pub fn builder() -> TestAppBuilder<InMemoryTopicsRepo> {
+        TestAppBuilder {
+            repo: InMemoryTopicsRepo::default(),
+            sets: None,
+            issuer: TestIssuer::default(),
+            serve_jwks: false,
+        }
+    }
+
+    pub fn token(&self) -> TokenBuilder {
+        self.issuer.token()
+    }
+
+    pub fn token_with_roles(&self, roles: &[&str]) -> String {
+        self.token().roles(roles.iter().copied()).mint()
+    }
+
+    /// Creates a topic from `body` as a writer
+    pub async fn create_topic(&self, body: Value) -> Topic<TopicId> {
+        self.server
+            .post("/topics")
+            .authorization_bearer(self.token_with_roles(&["TOPIC_WRITE"]))
+            .json(&body)
+            .await
+            .json()
+    }
+
+    /// The names of the topics `GET /topics?{query}` lists, in order
+    pub async fn topic_names(&self, query: &str) -> Vec<String> {
+        self.server
+            .get(&format!("/topics?{query}"))
+            .authorization_bearer(self.token_with_roles(&["TOPIC_READ"]))
+            .await
+            .json::<Vec<Topic<TopicId>>>()
+            .into_iter()
+            .map(|t| t.name)
+            .collect()
+    }
+}
+
+pub struct TestAppBuilder<R: TopicRepository> {
+    repo: R,
+    sets: Option<TopicSets<R::TopicId>>,
+    issuer: TestIssuer,
+    serve_jwks: bool,
+}
+
+impl<R> TestAppBuilder<R>
+where
+    R: TopicRepository + Clone + Send + Sync + 'static,
+{
+    pub fn repo<R2: TopicRepository>(self, repo: R2) -> TestAppBuilder<R2> {
+        TestAppBuilder {
+            repo,
+            sets: None,
+            issuer: self.issuer,
+            serve_jwks: self.serve_jwks,
+        }
+    }
+
+    /// Lets `?expand=sets` embed sets read from `repo`
+    pub fn sets<S>(mut self, repo: S) -> Self
+    where
+        S: SetRepository<SetKey: SetKey<TopicId = R::TopicId>>,
+    {
+        self.sets = Some(TopicSets::new(repo));
+        self
+    }
+
+    pub fn issuer(mut self, issuer: TestIssuer) -> Self {
+        self.issuer = issuer;
+        self
+    }
+
+    /// Fetch the keys from a local JWKS server like the app does, instead of injecting them
+    pub fn serve_jwks(mut self) -> Self {
+        self.serve_jwks = true;
+        self
+    }
+
+    pub async fn build(self) -> TestApp {
+        let (auth_state, jwks_server) = if self.serve_jwks {
+            let jwks_server = self.issuer.serve_jwks().await;
+            let auth_state = AuthState::create_with(self.issuer.oauth_config(jwks_server.url()))
+                .await
+                .expect("auth state created from served jwks");
+            (auth_state, Some(jwks_server))
+        } else {
+            (self.issuer.auth_state(), None)
+        };
+
+        let mut app_state = TopicAppState::new_without_metrics(TestEngine { repo: self.repo })
+            .await
+            .expect("creation of topic app state")
+            .with_idempotency(InMemoryIdempotencyRepo::default());
+        if let Some(sets) = self.sets {
+            app_state.service = app_state.service.with_sets(sets);
+        }
+
+        TestApp {
+            server: TestServer::new(topics_routes::routes::build(app_state, auth_state))
+                .expect("test server created"),
+            issuer: self.issuer,
+            _jwks_server: jwks_server,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TestEngine<R> {
+    repo: R,
+}
+
+impl<R> TopicEngine for TestEngine<R>
+where
+    R: TopicRepository + Clone + Send + Sync + 'static,
+{
+    type TopicId = R::TopicId;
+    type Repo = R;
+
+    fn repo(&self) -> Self::Repo {
+        self.repo.clone()
+    }
+}
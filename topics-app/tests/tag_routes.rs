@@ -0,0 +1,119 @@
+use repositories::postgres::set_test_repos::InMemorySetsRepo;
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sets_core::SetRepository;
+use sets_core::model::NewSet;
+use support::TestApp;
+
+mod support;
+
+async fn tag(app: &TestApp, id: TopicId, tags: Value) -> axum_test::TestResponse {
+    app.server
+        .post(&format!("/topics/{}/tags", id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "tags": tags }))
+        .await
+}
+
+#[tokio::test]
+async fn tags_are_normalised() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let tags = tag(&app, orders.id, json!([" PII ", "finance", "pii"]))
+        .await
+        .json::<Vec<String>>();
+
+    assert_eq!(vec!["finance", "pii"], tags);
+}
+
+#[tokio::test]
+async fn blank_tag_is_rejected() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+
+    let response = tag(&app, orders.id, json!([" "])).await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+}
+
+#[tokio::test]
+async fn topics_are_listed_by_all_or_any_tag() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let payments = app.create_topic(json!({ "name": "payments" })).await;
+    app.create_topic(json!({ "name": "clicks" })).await;
+    tag(&app, orders.id, json!(["finance", "pii"])).await;
+    tag(&app, payments.id, json!(["finance"])).await;
+
+    assert_eq!(
+        vec!["orders", "payments"],
+        app.topic_names("tag=finance").await
+    );
+    assert_eq!(vec!["orders"], app.topic_names("tag=finance&tag=pii").await);
+    assert_eq!(
+        vec!["orders", "payments"],
+        app.topic_names("any_tag=pii&any_tag=finance").await
+    );
+}
+
+#[tokio::test]
+async fn tag_directory_counts_topics_and_sets() {
+    let topics = InMemoryTopicsRepo::default();
+    let sets = InMemorySetsRepo::new(topics.clone());
+    let app = TestApp::builder()
+        .repo(topics)
+        .sets(sets.clone())
+        .build()
+        .await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let payments = app.create_topic(json!({ "name": "payments" })).await;
+    tag(&app, orders.id, json!(["finance", "pii"])).await;
+    tag(&app, payments.id, json!(["finance"])).await;
+    let set = sets
+        .create(orders.id, NewSet::new("eu", None::<String>))
+        .await
+        .unwrap();
+    sets.add_tags(set.key, vec!["pii".to_string()])
+        .await
+        .unwrap();
+
+    let directory = app
+        .server
+        .get("/topics/tags")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Value>();
+
+    assert_eq!(
+        json!([
+            { "tag": "finance", "topics": 2, "sets": 0 },
+            { "tag": "pii", "topics": 1, "sets": 1 },
+        ]),
+        directory
+    );
+}
+
+#[tokio::test]
+async fn tag_is_removed_whatever_its_case() {
+    let app = TestApp::builder().build().await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    tag(&app, orders.id, json!(["finance", "pii"])).await;
+
+    let response = app
+        .server
+        .delete(&format!("/topics/{}/tags/PII", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, response.status_code());
+    let tags = app
+        .server
+        .get(&format!("/topics/{}/tags", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Vec<String>>();
+    assert_eq!(vec!["finance"], tags);
+}
@@ -0,0 +1,228 @@
+use futures::future::join_all;
+use repositories::postgres::topic_test_repos::{FailingTopicsRepo, InMemoryTopicsRepo};
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use std::future::IntoFuture;
+use support::TestApp;
+use topics_core::model::{Topic, TopicStatus};
+
+mod support;
+
+#[tokio::test]
+async fn created_topics_are_listed() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({
+            "name": "test topic",
+            "description": "test topic description",
+        }))
+        .await;
+    assert_eq!(StatusCode::CREATED, response.status_code());
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+    assert_eq!(StatusCode::OK, response.status_code());
+    let topics: Vec<Topic<TopicId>> = response.json();
+    assert_eq!(1, topics.len());
+    assert_eq!("test topic", &topics[0].name);
+    assert_eq!(
+        Some("test topic description"),
+        topics[0].description.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn repo_failure_is_internal_server_error() {
+    let app = TestApp::builder()
+        .repo(FailingTopicsRepo::default())
+        .build()
+        .await;
+
+    let response = app
+        .server
+        .get("/topics")
+        .authorization_bearer(app.token().subject("reader").roles(["TOPIC_READ"]).mint())
+        .await;
+
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status_code());
+    assert_eq!("database_error", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn missing_topic_is_a_problem() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .get(&format!("/topics/{}", TopicId::new().0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await;
+
+    assert_eq!(StatusCode::NOT_FOUND, response.status_code());
+    assert_eq!(
+        "application/problem+json",
+        response.header("content-type").to_str().unwrap()
+    );
+    let problem = response.json::<Value>();
+    assert_eq!("topic_not_found", problem["code"]);
+    assert_eq!(404, problem["status"]);
+}
+
+#[tokio::test]
+async fn invalid_topic_lists_every_failed_field() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({
+            "name": "   ",
+            "description": "bell\u{7}",
+        }))
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status_code());
+    let problem = response.json::<Value>();
+    assert_eq!("validation_failed", problem["code"]);
+    assert_eq!(
+        json!(["empty", "control_characters"]),
+        json!([problem["errors"][0]["rule"], problem["errors"][1]["rule"]])
+    );
+}
+
+#[tokio::test]
+async fn created_topic_is_trimmed() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .post("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "  padded  ", "description": " " }))
+        .await;
+
+    assert_eq!(StatusCode::CREATED, response.status_code());
+    let topic = response.json::<Topic<TopicId>>();
+    assert_eq!("padded", topic.name);
+    assert_eq!(None, topic.description);
+}
+
+#[tokio::test]
+async fn duplicate_names_conflict_when_unique_names_are_enforced() {
+    let app = TestApp::builder()
+        .repo(InMemoryTopicsRepo::with_unique_names())
+        .build()
+        .await;
+    app.create_topic(json!({ "name": "Customers" })).await;
+
+    let response = app
+        .server
+        .post("/topics")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "customers" }))
+        .await;
+
+    assert_eq!(StatusCode::CONFLICT, response.status_code());
+    assert_eq!("duplicate_name", response.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn put_creates_a_topic_with_the_given_id() {
+    let app = TestApp::builder().build().await;
+    let id = TopicId::new();
+
+    let response = app
+        .server
+        .put(&format!("/topics/{}", id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "orders", "description": "from master data" }))
+        .await;
+
+    assert_eq!(StatusCode::CREATED, response.status_code());
+    assert_eq!(id, response.json::<Topic<TopicId>>().id);
+}
+
+#[tokio::test]
+async fn put_replaces_an_existing_topic() {
+    let app = TestApp::builder().build().await;
+    let created = app
+        .create_topic(json!({ "name": "orders", "description": "from master data" }))
+        .await;
+
+    let response = app
+        .server
+        .put(&format!("/topics/{}", created.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "orders v2" }))
+        .await;
+
+    assert_eq!(StatusCode::OK, response.status_code());
+    let replaced = response.json::<Topic<TopicId>>();
+    assert_eq!(created.id, replaced.id);
+    assert_eq!("orders v2", replaced.name);
+    assert_eq!(None, replaced.description);
+    assert_eq!(created.created, replaced.created);
+}
+
+#[tokio::test]
+async fn put_with_an_invalid_id_is_a_bad_request() {
+    let app = TestApp::builder().build().await;
+
+    let response = app
+        .server
+        .put("/topics/not-a-uuid")
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "orders" }))
+        .await;
+
+    assert_eq!(StatusCode::BAD_REQUEST, response.status_code());
+}
+
+#[tokio::test]
+async fn concurrent_puts_keep_a_status_changed_in_between() {
+    let app = TestApp::builder().build().await;
+    let write_access = app.token_with_roles(&["TOPIC_WRITE"]);
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let path = format!("/topics/{}", orders.id.0);
+
+    let puts = join_all((0..8).map(|i| {
+        app.server
+            .put(&path)
+            .authorization_bearer(&write_access)
+            .json(&json!({ "name": format!("orders v{i}") }))
+            .into_future()
+    }));
+    let activate = app
+        .server
+        .post(&format!("{path}/status"))
+        .authorization_bearer(&write_access)
+        .json(&json!({ "status": "active" }));
+    let (puts, activated) = tokio::join!(puts, activate);
+
+    assert_eq!(StatusCode::OK, activated.status_code());
+    for response in puts {
+        match response.status_code() {
+            StatusCode::OK => {}
+            StatusCode::CONFLICT => {
+                assert_eq!("concurrent_change", response.json::<Value>()["code"])
+            }
+            status => panic!("unexpected PUT status {status}"),
+        }
+    }
+    let read = app
+        .server
+        .get(&path)
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json::<Topic<TopicId>>();
+    assert_eq!(TopicStatus::Active, read.status);
+}
@@ -368,7 +368,7 @@ async fn oauth(auth_server: &RawContainer) -> OAuthConfig {
     let config = routing::OAuthConfig {
         jwks_url: open_id_config.jwks_uri,
         issuer_url: open_id_config.issuer,
-        roles_claims_path: "roles".into(),
+        roles: routing::RoleMapping::new(["roles"]),
         audience: "topics-api".into(),
     };
 
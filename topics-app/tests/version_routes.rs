@@ -0,0 +1,199 @@
+use repositories::postgres::set_test_repos::InMemorySetsRepo;
+use repositories::postgres::topic_test_repos::InMemoryTopicsRepo;
+use repositories::postgres::topics::TopicId;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sets_core::SetRepository;
+use sets_core::model::NewSet;
+use support::TestApp;
+use topics_core::model::{Topic, TopicVersion};
+
+mod support;
+
+/// A topic created as "orders" then patched to "orders v2"
+async fn patched_topic(app: &TestApp) -> Topic<TopicId> {
+    let orders = app
+        .create_topic(json!({ "name": "orders", "attributes": { "team": "orders" } }))
+        .await;
+    let response = app
+        .server
+        .patch(&format!("/topics/{}", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "orders v2", "attributes": { "sla": 99 } }))
+        .await;
+    assert_eq!(StatusCode::OK, response.status_code());
+    response.json()
+}
+
+async fn versions(app: &TestApp, id: TopicId) -> Vec<TopicVersion<TopicId>> {
+    app.server
+        .get(&format!("/topics/{}/versions", id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+        .json()
+}
+
+async fn read(app: &TestApp, path: &str) -> axum_test::TestResponse {
+    app.server
+        .get(path)
+        .authorization_bearer(app.token_with_roles(&["TOPIC_READ"]))
+        .await
+}
+
+#[tokio::test]
+async fn every_change_is_listed_as_a_version() {
+    let app = TestApp::builder().build().await;
+    let orders = patched_topic(&app).await;
+
+    let history = versions(&app, orders.id).await;
+
+    let names: Vec<_> = history
+        .iter()
+        .map(|v| (v.version, v.topic.name.as_str()))
+        .collect();
+    assert_eq!(vec![(1, "orders"), (2, "orders v2")], names);
+}
+
+#[tokio::test]
+async fn single_version_can_be_read() {
+    let app = TestApp::builder().build().await;
+    let orders = patched_topic(&app).await;
+
+    let first = read(&app, &format!("/topics/{}/versions/1", orders.id.0)).await;
+    let missing = read(&app, &format!("/topics/{}/versions/9", orders.id.0)).await;
+
+    assert_eq!("orders", first.json::<Value>()["topic"]["name"]);
+    assert_eq!(StatusCode::NOT_FOUND, missing.status_code());
+    assert_eq!("version_not_found", missing.json::<Value>()["code"]);
+}
+
+#[tokio::test]
+async fn versions_of_a_missing_topic_are_not_found() {
+    let app = TestApp::builder().build().await;
+
+    let response = read(&app, &format!("/topics/{}/versions", TopicId::new().0)).await;
+
+    assert_eq!(StatusCode::NOT_FOUND, response.status_code());
+}
+
+#[tokio::test]
+async fn as_of_reads_the_topic_as_it_was() {
+    let app = TestApp::builder().build().await;
+    let orders = patched_topic(&app).await;
+    let first = read(&app, &format!("/topics/{}/versions/1", orders.id.0))
+        .await
+        .json::<Value>();
+
+    let then = read(
+        &app,
+        &format!(
+            "/topics/{}?as_of={}",
+            orders.id.0,
+            first["recorded"].as_str().unwrap()
+        ),
+    )
+    .await;
+    let before = read(
+        &app,
+        &format!("/topics/{}?as_of=2000-01-01T00:00:00Z", orders.id.0),
+    )
+    .await;
+
+    assert_eq!("orders", then.json::<Topic<TopicId>>().name);
+    assert_eq!(StatusCode::NOT_FOUND, before.status_code());
+}
+
+#[tokio::test]
+async fn as_of_embeds_the_sets_as_they_were() {
+    let topics = InMemoryTopicsRepo::default();
+    let sets = InMemorySetsRepo::new(topics.clone());
+    let app = TestApp::builder()
+        .repo(topics)
+        .sets(sets.clone())
+        .build()
+        .await;
+    let orders = app.create_topic(json!({ "name": "orders" })).await;
+    let eu = sets
+        .create(orders.id, NewSet::new("eu", None::<String>))
+        .await
+        .unwrap();
+    app.server
+        .patch(&format!("/topics/{}", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .json(&json!({ "name": "orders v2" }))
+        .await;
+    let second = read(&app, &format!("/topics/{}/versions/2", orders.id.0))
+        .await
+        .json::<Value>();
+    sets.upsert(eu.key, NewSet::new("eu v2", None::<String>))
+        .await
+        .unwrap();
+    sets.create(orders.id, NewSet::new("us", None::<String>))
+        .await
+        .unwrap();
+
+    let then = read(
+        &app,
+        &format!(
+            "/topics/{}?expand=sets&as_of={}",
+            orders.id.0,
+            second["recorded"].as_str().unwrap()
+        ),
+    )
+    .await
+    .json::<Value>();
+    let now = read(&app, &format!("/topics/{}?expand=sets", orders.id.0))
+        .await
+        .json::<Value>();
+
+    let set_names = |topic: &Value| -> Vec<String> {
+        topic["sets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|set| set["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!("orders v2", then["name"]);
+    assert_eq!(vec!["eu"], set_names(&then));
+    assert_eq!(vec!["eu v2", "us"], set_names(&now));
+}
+
+#[tokio::test]
+async fn revert_adds_the_old_version_as_a_new_one() {
+    let app = TestApp::builder().build().await;
+    let orders = patched_topic(&app).await;
+
+    let reverted = app
+        .server
+        .post(&format!("/topics/{}/revert/1", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await
+        .json::<Topic<TopicId>>();
+
+    assert_eq!("orders", reverted.name);
+    assert_eq!(
+        json!({ "team": "orders" }),
+        Value::Object(reverted.attributes)
+    );
+    let history = versions(&app, orders.id).await;
+    assert_eq!(
+        vec![1, 2, 3],
+        history.iter().map(|v| v.version).collect::<Vec<_>>()
+    );
+    assert_eq!("orders", history[2].topic.name);
+}
+
+#[tokio::test]
+async fn revert_to_a_missing_version_is_not_found() {
+    let app = TestApp::builder().build().await;
+    let orders = patched_topic(&app).await;
+
+    let response = app
+        .server
+        .post(&format!("/topics/{}/revert/9", orders.id.0))
+        .authorization_bearer(app.token_with_roles(&["TOPIC_WRITE"]))
+        .await;
+
+    assert_eq!(StatusCode::NOT_FOUND, response.status_code());
+}
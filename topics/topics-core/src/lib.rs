@@ -1,28 +1,44 @@
+use chrono::{DateTime, Utc};
 use ids::Id;
 use list_filter::TopicListCriteria;
-use model::{NewTopic, PatchTopic, Topic};
+use model::{
+    DeletePolicy, LinkDirection, LinkType, NewTopic, PartialTopic, PatchTopic, StatusChange,
+    TagCount, Topic, TopicField, TopicLink, TopicVersion, Upserted,
+};
 use result::{OptRepoResult, RepoResult};
 use serde::Serialize;
 use std::fmt::Debug;
+use std::hash::Hash;
+use tokio_stream::Stream;
 use utoipa::ToSchema;
 
 pub mod list_filter;
 pub mod model;
 pub mod result;
+pub mod validation;
 
 pub trait TopicEngine: Clone + Send + Sync + 'static {
-    type TopicId: Id;
+    type TopicId: Id + Eq + Hash;
     type Repo: TopicRepository<TopicId = Self::TopicId>;
     // type Cache // bound not necessarily from this crate, since this will be common to all services
 
     fn repo(&self) -> Self::Repo;
 }
 
-// more reasons can be added, for example if we end up having restrictions on name or description
+/// Why a topic in a bulk create failed, validation failures name the rule that was broken
 #[derive(Debug, Serialize, ToSchema, Copy, Clone, PartialEq, Eq)]
 pub enum CreateManyFailReason {
     ServiceError,
     MissingName,
+    EmptyName,
+    NameTooLong,
+    NameControlCharacters,
+    DescriptionTooLong,
+    DescriptionControlCharacters,
+    /// Another topic, or an earlier one in the same request, already has the name
+    DuplicateName,
+    /// A line of a streamed request wasn't a topic
+    MalformedLine,
 }
 
 #[derive(Debug, Serialize, ToSchema, Clone, PartialEq, Eq)]
@@ -39,34 +55,263 @@ pub enum CreateManyTopicStatus<T> {
     },
 }
 
+/// Why a topic in a bulk patch failed, validation failures name the rule that was broken
+#[derive(Debug, Serialize, ToSchema, Copy, Clone, PartialEq, Eq)]
+pub enum PatchManyFailReason {
+    ServiceError,
+    NotFound,
+    /// The id was already given earlier in the same request
+    DuplicateId,
+    MissingName,
+    EmptyName,
+    NameTooLong,
+    NameControlCharacters,
+    DescriptionTooLong,
+    DescriptionControlCharacters,
+    DuplicateName,
+}
+
+#[derive(Debug, Serialize, ToSchema, Clone, PartialEq, Eq)]
+pub enum PatchManyTopicStatus<T> {
+    Success(Topic<T>),
+    Fail {
+        topic_id: T,
+        reason: PatchManyFailReason,
+    },
+}
+
+#[derive(Debug, Serialize, ToSchema, Copy, Clone, PartialEq, Eq)]
+pub enum DeleteManyFailReason {
+    ServiceError,
+    NotFound,
+    /// The id was already given earlier in the same request
+    DuplicateId,
+    /// The topic has children that aren't being deleted with it
+    HasChildren,
+}
+
+#[derive(Debug, Serialize, ToSchema, Clone, PartialEq, Eq)]
+pub enum DeleteManyTopicStatus<T> {
+    Success {
+        topic_id: T,
+    },
+    Fail {
+        topic_id: T,
+        reason: DeleteManyFailReason,
+    },
+}
+
 pub trait TopicRepository: Send + Sync + Clone + 'static {
-    type TopicId: Id;
+    type TopicId: Id + Eq + Hash;
 
     fn get(
         &self,
         id: Self::TopicId,
     ) -> impl Future<Output = OptRepoResult<Topic<Self::TopicId>>> + Send;
 
+    /// Results are in the same order as `ids`, a missing topic is `None`
+    fn get_many(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> impl Future<Output = RepoResult<Vec<Option<Topic<Self::TopicId>>>>> + Send;
+
     fn list(
         &self,
         list_criteria: TopicListCriteria,
     ) -> impl Future<Output = RepoResult<Vec<Topic<Self::TopicId>>>> + Send;
 
+    /// Like `list`, but topics are read as the stream is polled instead of all up front
+    fn list_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+    ) -> impl Future<
+        Output = RepoResult<
+            impl Stream<Item = RepoResult<Topic<Self::TopicId>>> + Send + 'static + use<Self>,
+        >,
+    > + Send;
+
+    /// Like `list_stream`, but only `fields` are read from the database
+    fn list_fields_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+        fields: Vec<TopicField>,
+    ) -> impl Future<
+        Output = RepoResult<
+            impl Stream<Item = RepoResult<PartialTopic<Self::TopicId>>> + Send + 'static + use<Self>,
+        >,
+    > + Send;
+
+    /// The topics nested directly under the topic with `id`, `None` if there's no such topic
+    fn children(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> impl Future<Output = OptRepoResult<Vec<Topic<Self::TopicId>>>> + Send;
+
+    /// The topics the topic with `id` is nested under, starting from its root
+    fn ancestors(
+        &self,
+        id: Self::TopicId,
+    ) -> impl Future<Output = OptRepoResult<Vec<Topic<Self::TopicId>>>> + Send;
+
+    /// The topic with `id` followed by every topic nested under it, parents before their children
+    fn subtree(
+        &self,
+        id: Self::TopicId,
+    ) -> impl Future<Output = OptRepoResult<Vec<Topic<Self::TopicId>>>> + Send;
+
+    /// Fails with `ParentNotFound` if the topic's parent doesn't exist
     fn create(
         &self,
-        new_topic: NewTopic,
+        new_topic: NewTopic<Self::TopicId>,
     ) -> impl Future<Output = RepoResult<Topic<Self::TopicId>>> + Send;
 
     fn create_many(
         &self,
-        topics: Vec<NewTopic>,
+        topics: Vec<NewTopic<Self::TopicId>>,
     ) -> impl Future<Output = RepoResult<Vec<RepoResult<Topic<Self::TopicId>>>>> + Send;
 
+    /// Either every topic is created or none are, results are in the same order as `topics`
+    fn create_many_atomic(
+        &self,
+        topics: Vec<NewTopic<Self::TopicId>>,
+    ) -> impl Future<Output = RepoResult<Vec<Topic<Self::TopicId>>>> + Send;
+
+    /// Replaces the name and description of the topic with `id`, creating it if there isn't one.
+    /// A replaced topic keeps when it was created and is marked updated. Fails with
+    /// `ConcurrentChange` if other changes to the topic keep landing before it can be replaced.
+    fn upsert(
+        &self,
+        id: Self::TopicId,
+        topic: NewTopic<Self::TopicId>,
+    ) -> impl Future<Output = RepoResult<Upserted<Topic<Self::TopicId>>>> + Send;
+
+    /// Changing the parent fails with `ParentCycle` if it's the topic itself or one of its
+    /// descendants, and with `ParentNotFound` if it doesn't exist
     fn patch(
         &self,
         id: Self::TopicId,
-        patch: PatchTopic,
+        patch: PatchTopic<Self::TopicId>,
     ) -> impl Future<Output = OptRepoResult<Topic<Self::TopicId>>> + Send;
 
-    fn delete(&self, id: Self::TopicId) -> impl Future<Output = OptRepoResult<()>> + Send;
+    /// Results are in the same order as `patches`, a missing topic is `Ok(None)`
+    fn patch_many(
+        &self,
+        patches: Vec<(Self::TopicId, PatchTopic<Self::TopicId>)>,
+    ) -> impl Future<Output = RepoResult<Vec<OptRepoResult<Topic<Self::TopicId>>>>> + Send;
+
+    /// `policy` decides what happens to the topic's children, rejecting fails with `HasChildren`
+    fn delete(
+        &self,
+        id: Self::TopicId,
+        policy: DeletePolicy,
+    ) -> impl Future<Output = OptRepoResult<()>> + Send;
+
+    /// Results are in the same order as `ids`, a missing topic is `Ok(None)`. A topic with
+    /// children that aren't being deleted fails with `HasChildren`, the others are still deleted
+    fn delete_many(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> impl Future<Output = RepoResult<Vec<OptRepoResult<()>>>> + Send;
+
+    /// Links the topic with `source_id` to the topic with `target_id`, `None` if there's no
+    /// source topic. Fails with `LinkTargetNotFound`, `LinkExists` or `SelfLink`
+    fn create_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> impl Future<Output = OptRepoResult<TopicLink<Self::TopicId>>> + Send;
+
+    fn get_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> impl Future<Output = OptRepoResult<TopicLink<Self::TopicId>>> + Send;
+
+    /// Changes the type of the link, `None` if the topics aren't linked
+    fn update_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+        link_type: LinkType,
+    ) -> impl Future<Output = OptRepoResult<TopicLink<Self::TopicId>>> + Send;
+
+    fn delete_link(
+        &self,
+        source_id: Self::TopicId,
+        target_id: Self::TopicId,
+    ) -> impl Future<Output = OptRepoResult<()>> + Send;
+
+    /// A page of the links to or from the topic with `id`, oldest first. `None` if there's no
+    /// such topic
+    fn links(
+        &self,
+        id: Self::TopicId,
+        direction: LinkDirection,
+        list_criteria: TopicListCriteria,
+    ) -> impl Future<Output = OptRepoResult<Vec<TopicLink<Self::TopicId>>>> + Send;
+
+    /// Every link to or from any of the topics with `ids`, in no particular order
+    fn links_touching(
+        &self,
+        ids: Vec<Self::TopicId>,
+    ) -> impl Future<Output = RepoResult<Vec<TopicLink<Self::TopicId>>>> + Send;
+
+    /// The topic's tags in alphabetical order, `None` if there's no such topic
+    fn tags(&self, id: Self::TopicId) -> impl Future<Output = OptRepoResult<Vec<String>>> + Send;
+
+    /// Tags the topic, tags it already has are skipped. Returns all of its tags like [tags]
+    ///
+    /// [tags]: TopicRepository::tags
+    fn add_tags(
+        &self,
+        id: Self::TopicId,
+        tags: Vec<String>,
+    ) -> impl Future<Output = OptRepoResult<Vec<String>>> + Send;
+
+    /// Untags the topic, tags it doesn't have are skipped. Returns the tags it has left
+    fn remove_tags(
+        &self,
+        id: Self::TopicId,
+        tags: Vec<String>,
+    ) -> impl Future<Output = OptRepoResult<Vec<String>>> + Send;
+
+    /// Every tag in use and how many topics have it, in alphabetical order
+    fn tag_counts(&self) -> impl Future<Output = RepoResult<Vec<TagCount>>> + Send;
+
+    /// Moves the topic from `change.from` to `change.to` and records the change, `None` if
+    /// there's no such topic. Fails with `StatusChanged` if the topic is no longer in `from`
+    fn change_status(
+        &self,
+        id: Self::TopicId,
+        change: StatusChange,
+    ) -> impl Future<Output = OptRepoResult<Topic<Self::TopicId>>> + Send;
+
+    /// Every recorded status change of the topic, oldest first. `None` if there's no such topic
+    fn status_changes(
+        &self,
+        id: Self::TopicId,
+    ) -> impl Future<Output = OptRepoResult<Vec<StatusChange>>> + Send;
+
+    /// A page of the topic's versions, oldest first. `None` if there's no such topic
+    fn versions(
+        &self,
+        id: Self::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> impl Future<Output = OptRepoResult<Vec<TopicVersion<Self::TopicId>>>> + Send;
+
+    /// `None` if there's no such topic or it has no version with that number
+    fn version(
+        &self,
+        id: Self::TopicId,
+        version: u64,
+    ) -> impl Future<Output = OptRepoResult<TopicVersion<Self::TopicId>>> + Send;
+
+    /// The version that was current at `at`, `None` if there's no such topic or it didn't exist yet
+    fn version_at(
+        &self,
+        id: Self::TopicId,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = OptRepoResult<TopicVersion<Self::TopicId>>> + Send;
 }
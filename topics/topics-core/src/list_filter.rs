@@ -1,8 +1,18 @@
+use crate::model::TopicStatus;
+use routing::attributes::AttributeFilter;
 use routing::list_criteria::{ListCriteria, ListFilter, Tag};
 use routing::pagination::Pagination;
 
 pub enum TopicFilter {
     Name(String),
+    /// Topics with every one of the tags
+    AllTags(Vec<String>),
+    /// Topics with at least one of the tags
+    AnyTag(Vec<String>),
+    /// Topics whose attributes pass every filter
+    Attributes(Vec<AttributeFilter>),
+    /// Topics in any one of the statuses
+    Status(Vec<TopicStatus>),
 }
 
 impl ListFilter for TopicFilter {
@@ -12,6 +22,10 @@ impl ListFilter for TopicFilter {
     fn tag(&self) -> Tag {
         match self {
             TopicFilter::Name(_) => Tag::One,
+            TopicFilter::AllTags(_) => Tag::Two,
+            TopicFilter::AnyTag(_) => Tag::Four,
+            TopicFilter::Attributes(_) => Tag::Eight,
+            TopicFilter::Status(_) => Tag::Sixteen,
         }
     }
 
@@ -22,4 +36,43 @@ impl ListFilter for TopicFilter {
 
 pub type TopicListCriteria = ListCriteria<TopicFilter, MAX_FILTER_COUNT>;
 
-const MAX_FILTER_COUNT: usize = 1;
+const MAX_FILTER_COUNT: usize = 5;
+
+/// The tags a topic needs every one of and the tags it needs at least one of, `None` when the
+/// criteria doesn't filter on them
+pub fn tag_filters(list_criteria: &TopicListCriteria) -> (Option<&[String]>, Option<&[String]>) {
+    let (mut all, mut any) = (None, None);
+    for filter in list_criteria.filters().unwrap_or_default() {
+        match filter {
+            TopicFilter::Name(_) | TopicFilter::Attributes(_) | TopicFilter::Status(_) => {}
+            TopicFilter::AllTags(tags) => all = Some(tags.as_slice()),
+            TopicFilter::AnyTag(tags) => any = Some(tags.as_slice()),
+        }
+    }
+    (all, any)
+}
+
+/// The attribute filters a topic has to pass, empty when the criteria doesn't filter on them
+pub fn attribute_filters(list_criteria: &TopicListCriteria) -> &[AttributeFilter] {
+    list_criteria
+        .filters()
+        .unwrap_or_default()
+        .iter()
+        .find_map(|filter| match filter {
+            TopicFilter::Attributes(filters) => Some(filters.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// The statuses a topic has to be in one of, `None` when the criteria doesn't filter on them
+pub fn status_filter(list_criteria: &TopicListCriteria) -> Option<&[TopicStatus]> {
+    list_criteria
+        .filters()
+        .unwrap_or_default()
+        .iter()
+        .find_map(|filter| match filter {
+            TopicFilter::Status(statuses) => Some(statuses.as_slice()),
+            _ => None,
+        })
+}
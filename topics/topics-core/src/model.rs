@@ -1,39 +1,134 @@
 use chrono::{DateTime, Utc};
 use optional_field::Field;
+use routing::attributes::Attributes;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Clone)]
-pub struct NewTopic {
+#[derive(Debug, Clone)]
+pub struct NewTopic<T> {
     pub name: String,
     pub description: Option<String>,
+    /// Created as a root topic when `None`
+    pub parent_id: Option<T>,
+    pub attributes: Attributes,
 }
 
-impl NewTopic {
+impl<T> NewTopic<T> {
     pub fn new(name: impl Into<String>, description: Option<impl Into<String>>) -> Self {
         Self {
             name: name.into(),
             description: description.map(Into::into),
+            parent_id: None,
+            attributes: Attributes::new(),
         }
     }
+
+    pub fn with_parent(mut self, parent_id: Option<T>) -> Self {
+        self.parent_id = parent_id;
+        self
+    }
+
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
 }
 
-pub struct PatchTopic {
+#[derive(Debug)]
+pub struct PatchTopic<T> {
     pub name: Option<String>,
     pub description: Field<String>,
+    /// `Present(None)` makes it a root topic, `Missing` leaves it where it is
+    pub parent_id: Field<T>,
+    /// Merged into the topic's attributes, a null attribute is removed
+    pub attributes: Option<Attributes>,
 }
 
-impl PatchTopic {
+impl<T> PatchTopic<T> {
     pub fn new(name: Option<String>, description: Field<String>) -> Self {
-        Self { name, description }
+        Self {
+            name,
+            description,
+            parent_id: Field::Missing,
+            attributes: None,
+        }
+    }
+
+    pub fn with_parent(mut self, parent_id: Field<T>) -> Self {
+        self.parent_id = parent_id;
+        self
+    }
+
+    pub fn with_attributes(mut self, attributes: Option<Attributes>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+}
+
+/// What happens to the children of a topic when it's deleted
+#[derive(Debug, Default, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletePolicy {
+    /// Topics with children aren't deleted
+    #[default]
+    Reject,
+    /// The topic's whole subtree is deleted with it
+    Cascade,
+    /// The children are moved up to the deleted topic's parent
+    Reparent,
+}
+
+/// The topics in `ids` that can't be deleted together with [`DeletePolicy::Reject`], because one
+/// of their children isn't in `ids` or can't be deleted itself. `children` holds a
+/// `(child, parent)` pair for every topic whose parent is in `ids`.
+pub fn undeletable_parents<T: PartialEq + Copy>(ids: &[T], children: &[(T, T)]) -> Vec<T> {
+    let mut blocked = Vec::new();
+    loop {
+        let newly_blocked: Vec<T> = children
+            .iter()
+            .filter(|(child, parent)| {
+                !blocked.contains(parent) && (!ids.contains(child) || blocked.contains(child))
+            })
+            .map(|(_, parent)| *parent)
+            .collect();
+        if newly_blocked.is_empty() {
+            return blocked;
+        }
+        for parent in newly_blocked {
+            if !blocked.contains(&parent) {
+                blocked.push(parent);
+            }
+        }
+    }
+}
+
+/// What an upsert did, holding the topic as it now is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upserted<T> {
+    Created(T),
+    Replaced(T),
+}
+
+impl<T> Upserted<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Upserted::Created(t) | Upserted::Replaced(t) => t,
+        }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
 pub struct Topic<T> {
     pub id: T,
+    /// The topic this one is nested under, `None` for a root topic
+    pub parent_id: Option<T>,
     pub name: String,
     pub description: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub attributes: Attributes,
+    #[serde(default)]
+    pub status: TopicStatus,
     pub created: DateTime<Utc>,
     pub updated: Option<DateTime<Utc>>,
 }
@@ -52,10 +147,370 @@ impl<T> Topic<T> {
     ) -> Self {
         Self {
             id,
+            parent_id: None,
             name,
             description,
+            attributes: Attributes::new(),
+            status: TopicStatus::default(),
             created,
             updated,
         }
     }
+
+    pub fn with_parent(mut self, parent_id: Option<T>) -> Self {
+        self.parent_id = parent_id;
+        self
+    }
+
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    pub fn with_status(mut self, status: TopicStatus) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// Where a topic is in its lifecycle, new topics are drafts
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicStatus {
+    #[default]
+    Draft,
+    Active,
+    Deprecated,
+    /// The topic's sets are read-only
+    Archived,
+}
+
+impl TopicStatus {
+    pub const ALL: [TopicStatus; 4] = [
+        TopicStatus::Draft,
+        TopicStatus::Active,
+        TopicStatus::Deprecated,
+        TopicStatus::Archived,
+    ];
+
+    /// The name it's serialized and stored as
+    pub fn name(self) -> &'static str {
+        match self {
+            TopicStatus::Draft => "draft",
+            TopicStatus::Active => "active",
+            TopicStatus::Deprecated => "deprecated",
+            TopicStatus::Archived => "archived",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    /// Whether a topic can go straight from this status to `to`. Nothing goes back to draft
+    pub fn can_become(self, to: TopicStatus) -> bool {
+        use TopicStatus::*;
+        matches!(
+            (self, to),
+            (Draft, Active)
+                | (Draft, Archived)
+                | (Active, Deprecated)
+                | (Deprecated, Active)
+                | (Deprecated, Archived)
+                | (Archived, Active)
+                | (Archived, Deprecated)
+        )
+    }
+
+    /// Taking a topic out of the archive, which only admins can do
+    pub fn is_unarchive(self, to: TopicStatus) -> bool {
+        self == TopicStatus::Archived && to != TopicStatus::Archived
+    }
+}
+
+/// A recorded move of a topic from one status to another
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct StatusChange {
+    pub from: TopicStatus,
+    pub to: TopicStatus,
+    pub reason: Option<String>,
+    pub changed: DateTime<Utc>,
+}
+
+impl StatusChange {
+    pub fn new(from: TopicStatus, to: TopicStatus, reason: Option<String>) -> Self {
+        Self {
+            from,
+            to,
+            reason,
+            changed: Utc::now(),
+        }
+    }
+}
+
+/// The topic as it was after one of its changes. Versions are numbered from 1 and never change,
+/// `recorded` is when it became the topic's current version
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct TopicVersion<T> {
+    pub version: u64,
+    pub topic: Topic<T>,
+    pub recorded: DateTime<Utc>,
+}
+
+impl<T> TopicVersion<T> {
+    pub fn new(version: u64, topic: Topic<T>, recorded: DateTime<Utc>) -> Self {
+        Self {
+            version,
+            topic,
+            recorded,
+        }
+    }
+}
+
+/// A field of a topic that reads can be limited to
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicField {
+    Id,
+    ParentId,
+    Name,
+    Description,
+    Attributes,
+    Status,
+    Created,
+    Updated,
+}
+
+impl TopicField {
+    pub const ALL: [TopicField; 8] = [
+        TopicField::Id,
+        TopicField::ParentId,
+        TopicField::Name,
+        TopicField::Description,
+        TopicField::Attributes,
+        TopicField::Status,
+        TopicField::Created,
+        TopicField::Updated,
+    ];
+
+    /// The name of the field, which is also its column in the topics table
+    pub fn name(self) -> &'static str {
+        match self {
+            TopicField::Id => "id",
+            TopicField::ParentId => "parent_id",
+            TopicField::Name => "name",
+            TopicField::Description => "description",
+            TopicField::Attributes => "attributes",
+            TopicField::Status => "status",
+            TopicField::Created => "created",
+            TopicField::Updated => "updated",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    /// A comma separated list like `id,name`, repeats are ignored. `Err` holds the first name
+    /// that isn't a field
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        let mut fields = Vec::new();
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let field = Self::from_name(name).ok_or_else(|| name.to_string())?;
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        Ok(fields)
+    }
+}
+
+/// A topic with only some of its fields read. Fields that weren't are `None` and left out when
+/// it's serialized, so `parent_id`, `description` and `updated` are only null when they were read
+/// as null.
+#[derive(Debug, Serialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct PartialTopic<T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Option<T>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub attributes: Option<Attributes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TopicStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<Option<DateTime<Utc>>>,
+}
+
+impl<T> Default for PartialTopic<T> {
+    fn default() -> Self {
+        Self {
+            id: None,
+            parent_id: None,
+            name: None,
+            description: None,
+            attributes: None,
+            status: None,
+            created: None,
+            updated: None,
+        }
+    }
+}
+
+impl<T> PartialTopic<T> {
+    /// Keeps only `fields` of an already read topic
+    pub fn project(topic: Topic<T>, fields: &[TopicField]) -> Self {
+        let keep = |field| fields.contains(&field);
+        Self {
+            id: keep(TopicField::Id).then_some(topic.id),
+            parent_id: keep(TopicField::ParentId).then_some(topic.parent_id),
+            name: keep(TopicField::Name).then_some(topic.name),
+            description: keep(TopicField::Description).then_some(topic.description),
+            attributes: keep(TopicField::Attributes).then_some(topic.attributes),
+            status: keep(TopicField::Status).then_some(topic.status),
+            created: keep(TopicField::Created).then_some(topic.created),
+            updated: keep(TopicField::Updated).then_some(topic.updated),
+        }
+    }
+}
+
+impl<T> From<Topic<T>> for PartialTopic<T> {
+    fn from(topic: Topic<T>) -> Self {
+        Self::project(topic, &TopicField::ALL)
+    }
+}
+
+/// How a link's source topic relates to its target
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkType {
+    /// The source replaces the target
+    Supersedes,
+    RelatedTo,
+    /// The source was made from the target
+    DerivedFrom,
+}
+
+impl LinkType {
+    pub const ALL: [LinkType; 3] = [
+        LinkType::Supersedes,
+        LinkType::RelatedTo,
+        LinkType::DerivedFrom,
+    ];
+
+    /// The name it's serialized and stored as
+    pub fn name(self) -> &'static str {
+        match self {
+            LinkType::Supersedes => "supersedes",
+            LinkType::RelatedTo => "related_to",
+            LinkType::DerivedFrom => "derived_from",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+}
+
+/// Which of a topic's links to read, relative to the topic
+#[derive(Debug, Default, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkDirection {
+    /// Links from the topic to others
+    Outgoing,
+    /// Links from others to the topic
+    Incoming,
+    #[default]
+    Both,
+}
+
+/// A typed link from one topic to another. There's at most one link for each source and target
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct TopicLink<T> {
+    pub source_id: T,
+    pub target_id: T,
+    pub link_type: LinkType,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl<T> TopicLink<T> {
+    pub fn new(source_id: T, target_id: T, link_type: LinkType, created: DateTime<Utc>) -> Self {
+        Self {
+            source_id,
+            target_id,
+            link_type,
+            created,
+            updated: None,
+        }
+    }
+}
+
+/// How many topics have a tag
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_lists_are_deduplicated_and_checked() {
+        assert_eq!(
+            Ok(vec![TopicField::Id, TopicField::Name]),
+            TopicField::parse_list("id, name,id,")
+        );
+        assert_eq!(
+            Err("colour".to_string()),
+            TopicField::parse_list("id,colour")
+        );
+    }
+
+    #[test]
+    fn projected_topics_only_serialize_the_read_fields() {
+        let topic = Topic::create(1, "orders".to_string(), None);
+
+        let partial = PartialTopic::project(topic, &[TopicField::Id, TopicField::Description]);
+
+        assert_eq!(
+            r#"{"id":1,"description":null}"#,
+            serde_json::to_string(&partial).unwrap()
+        );
+    }
+
+    #[test]
+    fn parents_of_kept_children_are_undeletable_up_the_tree() {
+        // 1 is the parent of 2, which is the parent of 3, and 4 is the parent of 5
+        assert_eq!(
+            vec![4],
+            undeletable_parents(&[1, 2, 3, 4], &[(2, 1), (3, 2), (5, 4)])
+        );
+        assert_eq!(vec![2, 1], undeletable_parents(&[1, 2], &[(2, 1), (3, 2)]));
+        assert!(undeletable_parents(&[1, 2, 3], &[(2, 1), (3, 2)]).is_empty());
+    }
+
+    #[test]
+    fn only_listed_status_transitions_are_allowed() {
+        use TopicStatus::*;
+
+        assert!(Draft.can_become(Active));
+        assert!(Deprecated.can_become(Archived));
+        assert!(Archived.can_become(Active));
+        assert!(!Active.can_become(Draft));
+        assert!(!Active.can_become(Archived));
+        assert!(!Active.can_become(Active));
+
+        assert!(Archived.is_unarchive(Deprecated));
+        assert!(!Deprecated.is_unarchive(Archived));
+    }
 }
@@ -1,4 +1,6 @@
 use error_stack::Report;
+use routing::problem::{ProblemDetails, StatusCode};
+use std::borrow::Cow;
 
 pub type RepoResult<T> = Result<T, Report<TopicRepoError>>;
 pub type OptRepoResult<T> = Result<Option<T>, Report<TopicRepoError>>;
@@ -11,10 +13,41 @@ pub enum TopicRepoError {
     List,
     #[error("failed to create topic: {0}")]
     Create(CreateErrorType),
+    #[error("failed to upsert topic")]
+    Upsert,
     #[error("failed to patch topic")]
     Patch,
     #[error("failed to delete topic")]
     Delete,
+    /// Only returned when the repo enforces unique names
+    #[error("a topic with the same name already exists")]
+    DuplicateName,
+    #[error("the parent topic doesn't exist")]
+    ParentNotFound,
+    #[error("a topic can't be nested under itself or one of its descendants")]
+    ParentCycle,
+    #[error("the topic has children")]
+    HasChildren,
+    #[error("failed to read or write topic links")]
+    Link,
+    #[error("the topics are already linked")]
+    LinkExists,
+    #[error("the linked topic doesn't exist")]
+    LinkTargetNotFound,
+    #[error("a topic can't be linked to itself")]
+    SelfLink,
+    #[error("failed to read or write topic tags")]
+    Tags,
+    #[error("failed to read or change topic status")]
+    Status,
+    /// The topic's status was changed by someone else first
+    #[error("the topic's status has changed")]
+    StatusChanged,
+    /// Other changes to the topic kept landing while it was being written
+    #[error("the topic was changed by another request at the same time")]
+    ConcurrentChange,
+    #[error("failed to read topic versions")]
+    Versions,
 }
 
 #[derive(Debug, thiserror::Error, Copy, Clone)]
@@ -24,3 +57,51 @@ pub enum CreateErrorType {
     #[error("database returned with an error")]
     DbError,
 }
+
+impl ProblemDetails for TopicRepoError {
+    fn status(&self) -> StatusCode {
+        match self {
+            TopicRepoError::DuplicateName
+            | TopicRepoError::ParentCycle
+            | TopicRepoError::HasChildren
+            | TopicRepoError::LinkExists
+            | TopicRepoError::StatusChanged
+            | TopicRepoError::ConcurrentChange => StatusCode::CONFLICT,
+            TopicRepoError::ParentNotFound
+            | TopicRepoError::LinkTargetNotFound
+            | TopicRepoError::SelfLink => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            TopicRepoError::DuplicateName => "duplicate_name",
+            TopicRepoError::ParentNotFound => "parent_not_found",
+            TopicRepoError::ParentCycle => "parent_cycle",
+            TopicRepoError::HasChildren => "topic_has_children",
+            TopicRepoError::LinkExists => "link_exists",
+            TopicRepoError::LinkTargetNotFound => "link_target_not_found",
+            TopicRepoError::SelfLink => "self_link",
+            TopicRepoError::StatusChanged => "status_changed",
+            TopicRepoError::ConcurrentChange => "concurrent_change",
+            TopicRepoError::Create(CreateErrorType::MatchFailure) => "internal_error",
+            _ => "database_error",
+        }
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        match self {
+            TopicRepoError::DuplicateName
+            | TopicRepoError::ParentNotFound
+            | TopicRepoError::ParentCycle
+            | TopicRepoError::HasChildren
+            | TopicRepoError::LinkExists
+            | TopicRepoError::LinkTargetNotFound
+            | TopicRepoError::SelfLink
+            | TopicRepoError::StatusChanged
+            | TopicRepoError::ConcurrentChange => Some(Cow::Owned(self.to_string())),
+            _ => None,
+        }
+    }
+}
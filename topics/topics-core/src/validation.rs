@@ -0,0 +1,312 @@
+use optional_field::Field;
+use routing::attributes::Attributes;
+use routing::patch::PatchFields;
+use routing::validation::{FieldError, FieldErrors, TextField, ValidationRule};
+
+use crate::model::{NewTopic, PatchTopic, TopicStatus};
+use crate::{CreateManyFailReason, PatchManyFailReason};
+
+/// Matches the `varchar` limits of the topics table
+pub const NAME_MAX_LEN: usize = 255;
+pub const DESCRIPTION_MAX_LEN: usize = 4096;
+pub const TAG_MAX_LEN: usize = 64;
+pub const REASON_MAX_LEN: usize = 1024;
+
+pub const NAME: TextField = TextField::new("name", NAME_MAX_LEN);
+pub const DESCRIPTION: TextField = TextField::new("description", DESCRIPTION_MAX_LEN).multiline();
+pub const TAG: TextField = TextField::new("tags", TAG_MAX_LEN);
+pub const REASON: TextField = TextField::new("reason", REASON_MAX_LEN).multiline();
+
+/// What a merge patch or JSON Patch of a topic can change, the rest of its fields can only be read.
+/// Topics are moved to another parent and change status with their own endpoints
+pub const PATCH_FIELDS: PatchFields = PatchFields::new(
+    &["name", "description", "attributes"],
+    &["id", "parent_id", "status", "created", "updated"],
+);
+
+/// Trims and checks a new topic, a blank description is stored as no description
+pub fn new_topic<T>(name: &str, description: Option<&str>) -> Result<NewTopic<T>, FieldErrors> {
+    let mut errors = FieldErrors::default();
+    let name = errors.check(NAME.required(name));
+    let description = errors.check(DESCRIPTION.optional(description));
+
+    match (name, description) {
+        (Some(name), Some(description)) if errors.is_empty() => Ok(NewTopic {
+            name,
+            description,
+            parent_id: None,
+            attributes: Attributes::new(),
+        }),
+        _ => Err(errors),
+    }
+}
+
+/// Like [new_topic], except a null name is an error and a blank description clears it
+pub fn patch_topic<T>(
+    name: Field<String>,
+    description: Field<String>,
+) -> Result<PatchTopic<T>, FieldErrors> {
+    let mut errors = FieldErrors::default();
+
+    let name = match name {
+        Field::Missing => None,
+        Field::Present(None) => {
+            errors.push(FieldError::required(NAME.name()));
+            None
+        }
+        Field::Present(Some(name)) => errors.check(NAME.required(&name)),
+    };
+
+    let description = match description {
+        Field::Missing => Field::Missing,
+        Field::Present(d) => {
+            Field::Present(errors.check(DESCRIPTION.optional(d.as_deref())).flatten())
+        }
+    };
+
+    if errors.is_empty() {
+        Ok(PatchTopic::new(name, description))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Checks the keys of a new topic's attributes. Null attributes are dropped, there's nothing for
+/// them to remove
+pub fn new_attributes(mut attributes: Attributes) -> Result<Attributes, FieldErrors> {
+    routing::attributes::validate(&attributes)?;
+    attributes.retain(|_, value| !value.is_null());
+    Ok(attributes)
+}
+
+/// Like [new_attributes], except null attributes are kept so the patch removes them. Null in
+/// place of the whole object is an error rather than clearing every attribute
+pub fn patch_attributes(attributes: Field<Attributes>) -> Result<Option<Attributes>, FieldErrors> {
+    match attributes {
+        Field::Missing => Ok(None),
+        Field::Present(None) => Err(FieldError::required(routing::attributes::FIELD).into()),
+        Field::Present(Some(attributes)) => {
+            routing::attributes::validate(&attributes)?;
+            Ok(Some(attributes))
+        }
+    }
+}
+
+/// Trims the reason for a status change, a blank reason is no reason
+pub fn status_reason(reason: Option<&str>) -> Result<Option<String>, FieldErrors> {
+    Ok(REASON.optional(reason)?)
+}
+
+/// Reads the statuses a list is filtered on, repeats are dropped. Every name that isn't a status
+/// is an error
+pub fn statuses(names: &[String]) -> Result<Vec<TopicStatus>, FieldErrors> {
+    let mut errors = FieldErrors::default();
+    let mut statuses = Vec::with_capacity(names.len());
+    for name in names {
+        match TopicStatus::from_name(name.trim()) {
+            Some(status) if !statuses.contains(&status) => statuses.push(status),
+            Some(_) => {}
+            None => errors.push(FieldError::new(
+                "status",
+                ValidationRule::Format,
+                format!("{name} isn't a status, it can be draft, active, deprecated or archived"),
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(statuses)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Trims and lowercases tags so `PII` and ` pii` are the same tag, repeats are dropped. Errors
+/// name the tag by its index, like `tags[1]`
+pub fn tags(tags: &[String]) -> Result<Vec<String>, FieldErrors> {
+    let mut errors = FieldErrors::default();
+    let mut normalised = Vec::with_capacity(tags.len());
+    for (i, tag) in tags.iter().enumerate() {
+        match TAG.required(tag) {
+            Ok(tag) => {
+                let tag = tag.to_lowercase();
+                if !normalised.contains(&tag) {
+                    normalised.push(tag);
+                }
+            }
+            Err(mut e) => {
+                e.field = format!("{}[{i}]", TAG.name()).into();
+                errors.push(e);
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(normalised)
+    } else {
+        Err(errors)
+    }
+}
+
+impl From<&FieldError> for CreateManyFailReason {
+    fn from(error: &FieldError) -> Self {
+        let is_name = error.field == NAME.name();
+        match error.rule {
+            ValidationRule::Required => CreateManyFailReason::MissingName,
+            ValidationRule::Empty => CreateManyFailReason::EmptyName,
+            ValidationRule::TooLong if is_name => CreateManyFailReason::NameTooLong,
+            ValidationRule::TooLong => CreateManyFailReason::DescriptionTooLong,
+            ValidationRule::ControlCharacters if is_name => {
+                CreateManyFailReason::NameControlCharacters
+            }
+            ValidationRule::ControlCharacters => CreateManyFailReason::DescriptionControlCharacters,
+            // only attribute keys have a format, and bulk requests don't take attributes
+            ValidationRule::Format => CreateManyFailReason::ServiceError,
+        }
+    }
+}
+
+impl From<&FieldError> for PatchManyFailReason {
+    fn from(error: &FieldError) -> Self {
+        let is_name = error.field == NAME.name();
+        match error.rule {
+            ValidationRule::Required => PatchManyFailReason::MissingName,
+            ValidationRule::Empty => PatchManyFailReason::EmptyName,
+            ValidationRule::TooLong if is_name => PatchManyFailReason::NameTooLong,
+            ValidationRule::TooLong => PatchManyFailReason::DescriptionTooLong,
+            ValidationRule::ControlCharacters if is_name => {
+                PatchManyFailReason::NameControlCharacters
+            }
+            ValidationRule::ControlCharacters => PatchManyFailReason::DescriptionControlCharacters,
+            ValidationRule::Format => PatchManyFailReason::ServiceError,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_topic_is_trimmed() {
+        let topic = new_topic::<u32>("  name ", Some(" desc\n")).unwrap();
+
+        assert_eq!("name", topic.name);
+        assert_eq!(Some("desc".to_string()), topic.description);
+    }
+
+    #[test]
+    fn blank_description_is_none() {
+        assert_eq!(
+            None,
+            new_topic::<u32>("name", Some("  ")).unwrap().description
+        );
+    }
+
+    #[test]
+    fn every_failed_field_is_reported() {
+        let description = "d".repeat(DESCRIPTION_MAX_LEN + 1);
+
+        let errors = new_topic::<u32>(" ", Some(&description)).unwrap_err();
+
+        let reasons: Vec<_> = errors.iter().map(CreateManyFailReason::from).collect();
+        assert_eq!(
+            vec![
+                CreateManyFailReason::EmptyName,
+                CreateManyFailReason::DescriptionTooLong
+            ],
+            reasons
+        );
+    }
+
+    #[test]
+    fn name_at_limit_is_valid() {
+        assert!(new_topic::<u32>(&"n".repeat(NAME_MAX_LEN), None).is_ok());
+        assert!(new_topic::<u32>(&"n".repeat(NAME_MAX_LEN + 1), None).is_err());
+    }
+
+    #[test]
+    fn patch_null_name_is_required() {
+        let errors = patch_topic::<u32>(Field::Present(None), Field::Missing).unwrap_err();
+
+        assert_eq!(ValidationRule::Required, errors.first().unwrap().rule);
+    }
+
+    #[test]
+    fn patch_blank_description_clears_it() {
+        let patch =
+            patch_topic::<u32>(Field::Missing, Field::Present(Some(" ".to_string()))).unwrap();
+
+        assert_eq!(None, patch.name);
+        assert!(matches!(patch.description, Field::Present(None)));
+    }
+
+    #[test]
+    fn patch_name_control_characters_are_rejected() {
+        let errors =
+            patch_topic::<u32>(Field::Present(Some("a\u{1b}b".to_string())), Field::Missing)
+                .unwrap_err();
+
+        assert_eq!(
+            CreateManyFailReason::NameControlCharacters,
+            CreateManyFailReason::from(errors.first().unwrap())
+        );
+    }
+
+    #[test]
+    fn patch_null_name_is_a_missing_name_in_bulk() {
+        let errors = patch_topic::<u32>(Field::Present(None), Field::Missing).unwrap_err();
+
+        assert_eq!(
+            PatchManyFailReason::MissingName,
+            PatchManyFailReason::from(errors.first().unwrap())
+        );
+    }
+
+    #[test]
+    fn tags_are_normalised_and_deduplicated() {
+        let tags = tags(&[
+            " PII".to_string(),
+            "finance".to_string(),
+            "pii ".to_string(),
+        ]);
+
+        assert_eq!(Ok(vec!["pii".to_string(), "finance".to_string()]), tags);
+    }
+
+    #[test]
+    fn blank_tags_are_reported_by_index() {
+        let errors = tags(&["ok".to_string(), " ".to_string()]).unwrap_err();
+
+        assert_eq!("tags[1]", errors.first().unwrap().field);
+    }
+
+    #[test]
+    fn new_attributes_drop_nulls_and_patches_keep_them() {
+        let attributes = |value: serde_json::Value| match value {
+            serde_json::Value::Object(attributes) => attributes,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            Ok(attributes(serde_json::json!({ "team": "payments" }))),
+            new_attributes(attributes(
+                serde_json::json!({ "team": "payments", "sla": null })
+            ))
+        );
+        assert_eq!(
+            Ok(Some(attributes(serde_json::json!({ "sla": null })))),
+            patch_attributes(Field::Present(Some(attributes(
+                serde_json::json!({ "sla": null })
+            ))))
+        );
+        assert_eq!(
+            ValidationRule::Required,
+            patch_attributes(Field::Present(None))
+                .unwrap_err()
+                .first()
+                .unwrap()
+                .rule
+        );
+    }
+}
@@ -1,3 +1,25 @@
+use error_stack::Report;
+use routing::problem::{Problem, ProblemDetails, StatusCode};
+use topics_core::result::TopicRepoError;
+
 #[derive(Debug, thiserror::Error)]
 #[error("topic service failed")]
 pub struct TopicServiceError;
+
+impl ProblemDetails for TopicServiceError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn code(&self) -> &'static str {
+        "internal_error"
+    }
+
+    // the service error only wraps whatever went wrong underneath, which says more
+    fn report_problem(report: &Report<Self>) -> Problem {
+        match report.downcast_ref::<TopicRepoError>() {
+            Some(repo_err) => repo_err.to_problem(),
+            None => report.current_context().to_problem(),
+        }
+    }
+}
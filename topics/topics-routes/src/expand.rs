@@ -0,0 +1,122 @@
+use crate::ServiceResult;
+use crate::error::TopicServiceError;
+use chrono::{DateTime, Utc};
+use error_stack::ResultExt;
+use routing::pagination::Pagination;
+use serde::Serialize;
+use serde_json::Value;
+use sets_core::list_filter::SetListCriteria;
+use sets_core::model::{Set, TagCount};
+use sets_core::{SetKey, SetRepository};
+use std::fmt::{Debug, Formatter};
+use std::pin::Pin;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// How many of a topic's sets `?expand=sets` embeds
+pub const EMBEDDED_SET_PAGE_SIZE: u64 = 25;
+
+/// A set embedded in its topic. The topic id is left out since it's the topic's own id
+pub type EmbeddedSet = Set<EmbeddedSetId>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EmbeddedSetId {
+    /// The set's id, the same type a set repo gives it elsewhere
+    #[schema(value_type = String)]
+    pub id: Value,
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// object safe version of the set repo reads topics need, so the app state isn't generic over it
+trait DynSetLister<I>: Send + Sync + 'static {
+    fn first_page(
+        &self,
+        topic_id: I,
+        as_of: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, ServiceResult<Vec<EmbeddedSet>>>;
+
+    fn tag_counts(&self) -> BoxFuture<'_, ServiceResult<Vec<TagCount>>>;
+}
+
+impl<R: SetRepository> DynSetLister<<R::SetKey as SetKey>::TopicId> for R {
+    fn first_page(
+        &self,
+        topic_id: <R::SetKey as SetKey>::TopicId,
+        as_of: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, ServiceResult<Vec<EmbeddedSet>>> {
+        Box::pin(async move {
+            let criteria = SetListCriteria::new(
+                Pagination::with_default_page_size(1),
+                EMBEDDED_SET_PAGE_SIZE,
+            );
+            let sets = match as_of {
+                Some(at) => self.list_at(topic_id, at, criteria).await,
+                None => self.list(topic_id, criteria).await,
+            }
+            .change_context(TopicServiceError)?;
+
+            sets.into_iter()
+                .map(|set| {
+                    let id =
+                        serde_json::to_value(set.key.set_id()).change_context(TopicServiceError)?;
+                    Ok(Set {
+                        key: EmbeddedSetId { id },
+                        name: set.name,
+                        description: set.description,
+                        attributes: set.attributes,
+                        created: set.created,
+                        updated: set.updated,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn tag_counts(&self) -> BoxFuture<'_, ServiceResult<Vec<TagCount>>> {
+        Box::pin(async move {
+            SetRepository::tag_counts(self)
+                .await
+                .change_context(TopicServiceError)
+        })
+    }
+}
+
+/// The sets `?expand=sets` embeds in a topic, read from whichever set repo the app was given
+pub struct TopicSets<I>(Arc<dyn DynSetLister<I>>);
+
+impl<I> Clone for TopicSets<I> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<I> Debug for TopicSets<I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TopicSets")
+    }
+}
+
+impl<I: 'static> TopicSets<I> {
+    pub fn new<R>(repo: R) -> Self
+    where
+        R: SetRepository<SetKey: SetKey<TopicId = I>>,
+    {
+        Self(Arc::new(repo))
+    }
+
+    /// The first page of the topic's sets, in the order the set repo lists them. As they were
+    /// at `as_of` when given
+    pub async fn first_page(
+        &self,
+        topic_id: I,
+        as_of: Option<DateTime<Utc>>,
+    ) -> ServiceResult<Vec<EmbeddedSet>> {
+        self.0.first_page(topic_id, as_of).await
+    }
+
+    /// How many sets have each tag, in alphabetical order
+    pub async fn tag_counts(&self) -> ServiceResult<Vec<TagCount>> {
+        self.0.tag_counts().await
+    }
+}
@@ -4,6 +4,7 @@ use error_stack::Report;
 pub type ServiceResult<T> = Result<T, Report<TopicServiceError>>;
 pub type OptServiceResult<T> = Result<Option<T>, Report<TopicServiceError>>;
 mod error;
+pub mod expand;
 mod metrics;
 mod roles;
 pub mod routes;
@@ -1,4 +1,5 @@
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use topics_core::model::TopicStatus;
 
 const TOPICS_RETRIEVED_METRIC_NAME: &str = "topics_retrieved";
 const REQUEST_DURATION_METRIC_NAME: &str = "http_requests_duration_seconds";
@@ -8,6 +9,8 @@ const TOPICS_CREATED_METRIC_NAME: &str = "num_topics_created";
 
 const TOPICS_DELETED_METRIC_NAME: &str = "num_topics_deleted";
 const TOPICS_PATCHED_METRIC_NAME: &str = "num_topics_patched";
+const TOPIC_STATUS_CHANGES_METRIC_NAME: &str = "num_topic_status_changes";
+const TOPICS_REVERTED_METRIC_NAME: &str = "num_topics_reverted";
 
 pub fn setup_recorder() -> PrometheusHandle {
     const EXPONENTIAL_SECONDS: &[f64] = &[0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
@@ -82,7 +85,29 @@ pub fn increment_topics_deleted() {
     metrics::counter!(TOPICS_DELETED_METRIC_NAME).increment(1);
 }
 
+#[inline]
+pub fn increment_topics_deleted_by(amt: usize) {
+    metrics::counter!(TOPICS_DELETED_METRIC_NAME).increment(amt as u64);
+}
+
 #[inline]
 pub fn increment_topics_patched() {
     metrics::counter!(TOPICS_PATCHED_METRIC_NAME).increment(1);
 }
+
+#[inline]
+pub fn increment_topics_patched_by(amt: usize) {
+    metrics::counter!(TOPICS_PATCHED_METRIC_NAME).increment(amt as u64);
+}
+
+/// Counted per transition, labelled with the statuses it went from and to
+#[inline]
+pub fn increment_topic_status_changes(from: TopicStatus, to: TopicStatus) {
+    metrics::counter!(TOPIC_STATUS_CHANGES_METRIC_NAME, "from" => from.name(), "to" => to.name())
+        .increment(1);
+}
+
+#[inline]
+pub fn increment_topics_reverted() {
+    metrics::counter!(TOPICS_REVERTED_METRIC_NAME).increment(1);
+}
@@ -128,7 +128,7 @@ impl FromStr for TopicRoles {
 
     #[instrument]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim_matches('"') {
+        match s {
             "TOPIC_ADMIN" => Ok(TopicRoles::TOPIC_ADMIN),
             "TOPIC_READ" => Ok(TopicRoles::TOPIC_READ),
             "TOPIC_WRITE" => Ok(TopicRoles::TOPIC_WRITE),
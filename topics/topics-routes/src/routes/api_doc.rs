@@ -67,4 +67,56 @@ pub mod examples {
             &BULK_NO_SUCCESS
         }
     }
+
+    pub mod patch {
+        use super::*;
+        use crate::routes::responses::BulkPatchResponse;
+        use topics_core::{PatchManyFailReason, PatchManyTopicStatus};
+
+        static BULK_MIXED_SUCCESS: LazyLock<Value> = LazyLock::new(|| {
+            serde_json::to_value(BulkPatchResponse::new(vec![
+                PatchManyTopicStatus::Success(Topic::create(
+                    "some-id1",
+                    "renamed".to_string(),
+                    Some("this topic was successfully patched".to_string()),
+                )),
+                PatchManyTopicStatus::Fail {
+                    topic_id: "some-id2",
+                    reason: PatchManyFailReason::NotFound,
+                },
+                PatchManyTopicStatus::Fail {
+                    topic_id: "some-id1",
+                    reason: PatchManyFailReason::DuplicateId,
+                },
+            ]))
+            .expect("bulk patch response is serializable to Value")
+        });
+
+        pub fn bulk_mixed_success() -> &'static Value {
+            &BULK_MIXED_SUCCESS
+        }
+    }
+
+    pub mod delete {
+        use super::*;
+        use crate::routes::responses::BulkDeleteResponse;
+        use topics_core::{DeleteManyFailReason, DeleteManyTopicStatus};
+
+        static BULK_MIXED_SUCCESS: LazyLock<Value> = LazyLock::new(|| {
+            serde_json::to_value(BulkDeleteResponse::new(vec![
+                DeleteManyTopicStatus::Success {
+                    topic_id: "some-id1",
+                },
+                DeleteManyTopicStatus::Fail {
+                    topic_id: "some-id2",
+                    reason: DeleteManyFailReason::NotFound,
+                },
+            ]))
+            .expect("bulk delete response is serializable to Value")
+        });
+
+        pub fn bulk_mixed_success() -> &'static Value {
+            &BULK_MIXED_SUCCESS
+        }
+    }
 }
@@ -0,0 +1,144 @@
+use crate::routes::requests::BulkCreateTopicRequest;
+use crate::service::{CreateManyTopic, TopicService};
+use axum::body::Body;
+use axum::http::StatusCode;
+use routing::ndjson::NdjsonLines;
+use routing::problem::{Problem, ProblemDetails};
+use routing::stream::StreamingResponse;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use topics_core::{CreateManyFailReason, CreateManyTopicStatus, TopicEngine};
+use tracing::{debug, error, warn};
+use utoipa::ToSchema;
+
+/// Lines are read and created this many at a time, which bounds how much of a request is held
+const CHUNK_SIZE: usize = 500;
+/// Plenty for a name and description at their limits, even fully escaped
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// A line of a streamed bulk create response. Outcomes look the same as the elements of
+/// `outcomes` in a JSON bulk create, and the summary is always the last line.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum BulkCreateLine<T> {
+    Outcome(CreateManyTopicStatus<T>),
+    Summary { summary: BulkCreateSummary },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkCreateSummary {
+    created: usize,
+    failed: usize,
+    /// Why the rest of the request wasn't read, if it wasn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Problem>,
+}
+
+/// Creates the topics on each line of `body`, streaming back an outcome per line in order as
+/// each chunk is created. The status is always 200 since it's sent before anything is created.
+pub fn stream_bulk_create<T: TopicEngine>(
+    service: TopicService<T>,
+    body: Body,
+) -> StreamingResponse<BulkCreateLine<T::TopicId>> {
+    // reading stops while a chunk of outcomes is waiting to be sent, so a slow client can't
+    // make outcomes pile up
+    let (tx, rx) = mpsc::channel(CHUNK_SIZE);
+    tokio::spawn(ingest(service, NdjsonLines::new(body, MAX_LINE_LEN), tx));
+
+    StreamingResponse::ndjson(StatusCode::OK, ReceiverStream::new(rx))
+}
+
+async fn ingest<T: TopicEngine>(
+    service: TopicService<T>,
+    mut lines: NdjsonLines,
+    tx: mpsc::Sender<BulkCreateLine<T::TopicId>>,
+) {
+    let mut created = 0;
+    let mut failed = 0;
+    let mut error = None;
+
+    loop {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        while chunk.len() < CHUNK_SIZE {
+            match lines.next_line().await {
+                Some(Ok(line)) => chunk.push(serde_json::from_slice(&line).ok()),
+                Some(Err(e)) => {
+                    warn!("stopped reading bulk create lines: {e:?}");
+                    error = Some(e.current_context().to_problem());
+                    break;
+                }
+                None => break,
+            }
+        }
+        let last_chunk = chunk.len() < CHUNK_SIZE;
+
+        for outcome in create_chunk(&service, chunk).await {
+            match outcome {
+                CreateManyTopicStatus::Success(_) => created += 1,
+                _ => failed += 1,
+            }
+            if tx.send(BulkCreateLine::Outcome(outcome)).await.is_err() {
+                debug!("client stopped reading, {created} topics were created");
+                return;
+            }
+        }
+
+        if last_chunk {
+            break;
+        }
+    }
+
+    debug!("streamed bulk create finished, {created} created and {failed} failed");
+    let summary = BulkCreateSummary {
+        created,
+        failed,
+        error,
+    };
+    // the client may have gone, and there's no one left to tell
+    let _ = tx.send(BulkCreateLine::Summary { summary }).await;
+}
+
+/// `None` is a line that wasn't a topic
+async fn create_chunk<T: TopicEngine>(
+    service: &TopicService<T>,
+    chunk: Vec<Option<BulkCreateTopicRequest>>,
+) -> Vec<CreateManyTopicStatus<T::TopicId>> {
+    if chunk.is_empty() {
+        return vec![];
+    }
+
+    let malformed: Vec<bool> = chunk.iter().map(Option::is_none).collect();
+    let topics: Vec<_> = chunk
+        .into_iter()
+        .flatten()
+        .map(|t| CreateManyTopic::new(t.name, t.description))
+        .collect();
+    let topic_count = topics.len();
+
+    let mut outcomes = match service.create_many(topics.into_iter()).await {
+        Ok(outcomes) => outcomes.into_iter(),
+        Err(e) => {
+            error!("failed to create a chunk of {topic_count} topics: {e:?}");
+            Vec::new().into_iter()
+        }
+    };
+
+    malformed
+        .into_iter()
+        .map(|malformed| {
+            let reason = if malformed {
+                CreateManyFailReason::MalformedLine
+            } else if let Some(outcome) = outcomes.next() {
+                return outcome;
+            } else {
+                CreateManyFailReason::ServiceError
+            };
+            CreateManyTopicStatus::Fail {
+                topic_name: None,
+                topic_description: None,
+                reason,
+            }
+        })
+        .collect()
+}
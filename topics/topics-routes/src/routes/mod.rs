@@ -1,34 +1,59 @@
+use crate::ServiceResult;
 use crate::error::TopicServiceError;
 use crate::metrics;
 use crate::roles::TopicRoles;
-use crate::routes::requests::{BulkCreateTopicRequest, TopicPatchRequest};
-use crate::routes::responses::{BulkCreateResponse, TopicError};
-use crate::service::{CreateManyTopic, PatchOutcome, TopicCreation, TopicService};
+use crate::routes::ingest::BulkCreateLine;
+use crate::routes::requests::{
+    BulkCreateBody, BulkCreateOptions, BulkCreateTopicRequest, BulkPatchTopicRequest,
+    CreateLinkRequest, DeleteOptions, FilterQuery, GraphOptions, LinkOptions, MoveTopicRequest,
+    ReadOptions, StatusChangeRequest, TagsRequest, TopicPatchRequest, UpdateLinkRequest,
+};
+use crate::routes::responses::{
+    BatchGetResponse, BulkCreateResponse, BulkDeleteResponse, BulkPatchResponse,
+    TopicGraphResponse, TopicProblem, TopicView,
+};
+use crate::service::{
+    CreateManyAtomicOutcome, CreateManyTopic, CreateOutcome, PatchManyTopic, PatchOutcome,
+    StatusOutcome, TagOutcome, TagUsage, TopicCreation, TopicService, UpsertOutcome,
+};
 use crate::state::TopicAppState;
 use axum::{
-    Json, Router,
+    Extension, Router,
     extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response, Result},
 };
+use optional_field::Field;
 use requests::CreateTopicRequest;
 use responses::TopicResponse;
-use routing::AuthState;
+use routing::accept::NotAcceptable;
+use routing::attributes::{self, Attributes};
 use routing::error::EndpointError;
 use routing::list_criteria::ListFilter;
 use routing::pagination::Pagination;
+use routing::patch::{PatchBody, PatchError, PatchOp, PatchedFields};
+use routing::problem::{CommonProblems, Problem};
 use routing::router::RouterBuilder;
-use routing::stream::StreamingResponse;
+use routing::stream::{StreamFormat, StreamingResponse};
+use routing::wire::{Wire, WireFormat};
+use routing::{AuthState, AuthedUser};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
-use topics_core::TopicEngine;
+use tokio_stream::{Stream, StreamExt};
 use topics_core::list_filter::TopicFilter;
-use topics_core::model::Topic;
-use tracing::instrument;
+use topics_core::model::{
+    DeletePolicy, LinkDirection, LinkType, PartialTopic, StatusChange, Topic, TopicLink,
+    TopicStatus, TopicVersion,
+};
+use topics_core::validation::{DESCRIPTION, NAME, PATCH_FIELDS};
+use topics_core::{CreateManyTopicStatus, TopicEngine};
+use tracing::field::Empty;
+use tracing::{Span, instrument};
 use utoipa::OpenApi;
 use utoipa::ToSchema;
 
 mod api_doc;
+mod ingest;
 mod requests;
 mod responses;
 
@@ -46,10 +71,33 @@ struct ApiDoc;
 #[openapi(paths(
     list_topics,
     get_topic,
+    list_topic_children,
+    get_topic_ancestors,
+    get_topic_subtree,
+    batch_get_topics,
     create_topic,
     bulk_create_topics,
+    upsert_topic,
     delete_topic,
+    bulk_delete_topics,
     patch_topic,
+    move_topic,
+    bulk_patch_topics,
+    list_topic_links,
+    create_topic_link,
+    get_topic_link,
+    update_topic_link,
+    delete_topic_link,
+    get_topic_graph,
+    get_tag_directory,
+    list_topic_tags,
+    add_topic_tags,
+    remove_topic_tag,
+    change_topic_status,
+    list_topic_status_changes,
+    list_topic_versions,
+    get_topic_version,
+    revert_topic,
 ))]
 struct TopicDocs;
 
@@ -57,23 +105,114 @@ const DEFAULT_TOPIC_SEARCH_PAGE_SIZE: u64 = 25;
 
 const TOPIC_LIST_PATH: &str = "/";
 const TOPIC_GET_PATH: &str = "/{topic_id}";
+const TOPIC_CHILDREN_PATH: &str = "/{topic_id}/children";
+const TOPIC_ANCESTORS_PATH: &str = "/{topic_id}/ancestors";
+const TOPIC_SUBTREE_PATH: &str = "/{topic_id}/subtree";
+const TOPIC_BATCH_GET_PATH: &str = "/batch-get";
 const TOPIC_CREATE_PATH: &str = "/";
 const TOPIC_BULK_CREATE_PATH: &str = "/bulk";
+const TOPIC_UPSERT_PATH: &str = "/{topic_id}";
 const TOPIC_DELETE_PATH: &str = "/{topic_id}";
+const TOPIC_BULK_DELETE_PATH: &str = "/bulk";
 const TOPIC_PATCH_PATH: &str = "/{topic_id}";
+const TOPIC_MOVE_PATH: &str = "/{topic_id}/move";
+const TOPIC_BULK_PATCH_PATH: &str = "/bulk";
+const TOPIC_LINKS_PATH: &str = "/{topic_id}/links";
+const TOPIC_LINK_PATH: &str = "/{topic_id}/links/{target_id}";
+const TOPIC_GRAPH_PATH: &str = "/{topic_id}/graph";
+const TOPIC_TAG_DIRECTORY_PATH: &str = "/tags";
+const TOPIC_TAGS_PATH: &str = "/{topic_id}/tags";
+const TOPIC_TAG_PATH: &str = "/{topic_id}/tags/{tag}";
+const TOPIC_STATUS_PATH: &str = "/{topic_id}/status";
+const TOPIC_VERSIONS_PATH: &str = "/{topic_id}/versions";
+const TOPIC_VERSION_PATH: &str = "/{topic_id}/versions/{version}";
+const TOPIC_REVERT_PATH: &str = "/{topic_id}/revert/{version}";
+
+/// How many links away from a topic the graph goes, admins can go further
+const MAX_GRAPH_DEPTH: u32 = 3;
+const MAX_ADMIN_GRAPH_DEPTH: u32 = 6;
 
 pub fn build<T: TopicEngine>(app_state: TopicAppState<T>, auth_state: AuthState) -> Router {
     let builder = RouterBuilder::new(TOPIC_ROOT_PATH)
+        .with_idempotency(app_state.idempotency.clone())
         .role_protected_get(TOPIC_LIST_PATH, list_topics, TopicRoles::TOPIC_READ)
         .role_protected_get(TOPIC_GET_PATH, get_topic, TopicRoles::TOPIC_READ)
-        .role_protected_post(TOPIC_CREATE_PATH, create_topic, TopicRoles::TOPIC_WRITE)
+        .role_protected_get(
+            TOPIC_CHILDREN_PATH,
+            list_topic_children,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_get(
+            TOPIC_ANCESTORS_PATH,
+            get_topic_ancestors,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_get(
+            TOPIC_SUBTREE_PATH,
+            get_topic_subtree,
+            TopicRoles::TOPIC_READ,
+        )
         .role_protected_post(
+            TOPIC_BATCH_GET_PATH,
+            batch_get_topics,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_idempotent_post(TOPIC_CREATE_PATH, create_topic, TopicRoles::TOPIC_WRITE)
+        .role_protected_idempotent_post(
             TOPIC_BULK_CREATE_PATH,
             bulk_create_topics,
             TopicRoles::TOPIC_WRITE,
         )
+        .role_protected_put(TOPIC_UPSERT_PATH, upsert_topic, TopicRoles::TOPIC_WRITE)
         .role_protected_delete(TOPIC_DELETE_PATH, delete_topic, TopicRoles::TOPIC_WRITE)
-        .role_protected_patch(TOPIC_PATCH_PATH, patch_topic, TopicRoles::TOPIC_WRITE);
+        .role_protected_delete(
+            TOPIC_BULK_DELETE_PATH,
+            bulk_delete_topics,
+            TopicRoles::TOPIC_WRITE,
+        )
+        .role_protected_patch(TOPIC_PATCH_PATH, patch_topic, TopicRoles::TOPIC_WRITE)
+        .role_protected_post(TOPIC_MOVE_PATH, move_topic, TopicRoles::TOPIC_WRITE)
+        .role_protected_patch(
+            TOPIC_BULK_PATCH_PATH,
+            bulk_patch_topics,
+            TopicRoles::TOPIC_WRITE,
+        )
+        .role_protected_get(TOPIC_LINKS_PATH, list_topic_links, TopicRoles::TOPIC_READ)
+        .role_protected_post(TOPIC_LINKS_PATH, create_topic_link, TopicRoles::TOPIC_WRITE)
+        .role_protected_get(TOPIC_LINK_PATH, get_topic_link, TopicRoles::TOPIC_READ)
+        .role_protected_put(TOPIC_LINK_PATH, update_topic_link, TopicRoles::TOPIC_WRITE)
+        .role_protected_delete(TOPIC_LINK_PATH, delete_topic_link, TopicRoles::TOPIC_WRITE)
+        .role_protected_get(TOPIC_GRAPH_PATH, get_topic_graph, TopicRoles::TOPIC_READ)
+        .role_protected_get(
+            TOPIC_TAG_DIRECTORY_PATH,
+            get_tag_directory,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_get(TOPIC_TAGS_PATH, list_topic_tags, TopicRoles::TOPIC_READ)
+        .role_protected_post(TOPIC_TAGS_PATH, add_topic_tags, TopicRoles::TOPIC_WRITE)
+        .role_protected_delete(TOPIC_TAG_PATH, remove_topic_tag, TopicRoles::TOPIC_WRITE)
+        .role_protected_get(
+            TOPIC_STATUS_PATH,
+            list_topic_status_changes,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_post(
+            TOPIC_STATUS_PATH,
+            change_topic_status,
+            TopicRoles::TOPIC_WRITE,
+        )
+        .role_protected_get(
+            TOPIC_VERSIONS_PATH,
+            list_topic_versions,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_get(
+            TOPIC_VERSION_PATH,
+            get_topic_version,
+            TopicRoles::TOPIC_READ,
+        )
+        .role_protected_post(TOPIC_REVERT_PATH, revert_topic, TopicRoles::TOPIC_WRITE)
+        .with_api_key_admin(TopicRoles::TOPIC_ADMIN);
 
     if app_state.metrics_enabled {
         builder.build_with_metrics(
@@ -100,36 +239,78 @@ type ResponseType = Topic<IdType>;
     get,
     path = TOPIC_LIST_PATH,
     responses(
-        (status = OK, description = "Topics were found on the given page", body = Vec<ResponseType>),
+        CommonProblems,
+        (
+            status = OK,
+            description = "Topics were found on the given page. Sent in the format the `Accept` header prefers: a JSON array, NDJSON with a topic per line, or CSV with a header row",
+            content(
+                (Vec<ResponseType> = "application/json"),
+                (ResponseType = "application/x-ndjson"),
+                (String = "text/csv"),
+            ),
+        ),
         (status = NO_CONTENT, description = "No topics exist on the given page"),
+        (status = NOT_ACCEPTABLE, description = "The `Accept` header allows none of JSON, NDJSON or CSV", body = Problem, content_type = "application/problem+json"),
     ),
     params(
         ("page" = u32, Query, description = "The offset page to start the listing with"),
         ("page_size" = u32, Query, description = "The max number of topics to return"),
+        ("fields" = Option<String>, Query, description = "Only return these fields of each topic, comma separated like `id,name`. Only the listed columns are read. An unknown field is a 400 with code `unknown_field`"),
+        ("tag" = Option<Vec<String>>, Query, description = "Only list topics with every one of these tags. Repeat it for more tags, like `tag=a&tag=b`"),
+        ("any_tag" = Option<Vec<String>>, Query, description = "Only list topics with at least one of these tags. Repeat it for more tags, like `any_tag=a&any_tag=b`"),
+        ("attr.<key>" = Option<String>, Query, description = "Only list topics whose attribute `<key>` is a string, number or bool matching this text, like `attr.team=payments`. Left blank, like `attr.sla=`, it only has to be set. Repeat it for more attributes"),
+        ("status" = Option<Vec<TopicStatus>>, Query, description = "Only list topics in one of these statuses. Repeat it for more statuses, like `status=active&status=deprecated`"),
     )
 )]
-#[instrument(skip(service), err(Debug), fields(req.page = pagination.page, req.page_size = pagination.page_size))]
+#[instrument(skip(service, query), err(Debug), fields(req.page = pagination.page, req.page_size = pagination.page_size, req.fields = options.fields))]
 pub async fn list_topics<T>(
     State(service): State<TopicService<T>>,
+    format: StreamFormat,
     Query(pagination): Query<Pagination>,
+    Query(options): Query<ReadOptions>,
+    Query(query): Query<Vec<(String, String)>>,
 ) -> Result<Response, EndpointError<TopicServiceError>>
 where
     T: TopicEngine + Send + Sync + 'static,
 {
+    let fields = match options.fields() {
+        Ok(fields) => fields,
+        Err(problem) => return Ok(problem.into_response()),
+    };
     // TODO can list by name as well
-    let topics = service
-        .list(TopicFilter::criteria(
-            pagination,
-            DEFAULT_TOPIC_SEARCH_PAGE_SIZE,
-        ))
-        .await?;
+    let mut criteria = TopicFilter::criteria(pagination, DEFAULT_TOPIC_SEARCH_PAGE_SIZE);
+    if let Err(errors) = FilterQuery::new(query).apply(&mut criteria) {
+        return Ok(errors.into_response());
+    }
 
-    let res = if topics.is_empty() {
-        StatusCode::NO_CONTENT.into_response()
-    } else {
-        StreamingResponse::ok(topics.into_iter().map(TopicResponse::ok)).into_response()
+    match fields {
+        Some(fields) => {
+            let topics = service.list_fields_stream(criteria, fields).await?;
+            stream_topics(format, topics).await
+        }
+        None => {
+            let topics = service.list_stream(criteria).await?;
+            stream_topics(format, topics.map(|topic| topic.map(TopicResponse::ok))).await
+        }
+    }
+}
+
+async fn stream_topics<R>(
+    format: StreamFormat,
+    topics: impl Stream<Item = ServiceResult<R>> + Send + 'static,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    R: Serialize + Send + Sync + 'static,
+{
+    let mut topics = Box::pin(topics);
+
+    // the first topic decides the status, everything after it is sent as it's read
+    let Some(first) = topics.next().await.transpose()? else {
+        return Ok(StatusCode::NO_CONTENT.into_response());
     };
-    Ok(res)
+    let topics = tokio_stream::once(Ok(first)).chain(topics);
+
+    Ok(StreamingResponse::from_stream(format, StatusCode::OK, topics).into_response())
 }
 
 /// Get the topic associated with the given id.
@@ -138,26 +319,183 @@ where
     get,
     path = TOPIC_GET_PATH,
     responses(
-        (status = OK, description = "A topic was found that matched the given TopicId", body = Topic<IdType>),
-        (status = NOT_FOUND, description = "No topics with the given TopicId were found"),
+        CommonProblems,
+        (status = OK, description = "A topic was found that matched the given TopicId. Only the asked for `fields` are sent, and its sets are embedded as `sets` with `expand=sets`. With `as_of` the topic and its sets are the versions they were at then", body = TopicView<IdType>),
+        (status = BAD_REQUEST, description = "`fields` has an unknown field, with code `unknown_field`, or `expand` has something other than `sets`, with code `unknown_expansion`", body = Problem, content_type = "application/problem+json"),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found, or it didn't exist yet at `as_of`", body = Problem, content_type = "application/problem+json"),
+        (status = NOT_IMPLEMENTED, description = "`expand=sets` was asked for but this service can't read sets", body = Problem, content_type = "application/problem+json"),
     ),
     params(
         ("topic_id" = IdType, Path, description = "The TopicId to find"),
+        ("fields" = Option<String>, Query, description = "Only return these fields of the topic, comma separated like `id,name`"),
+        ("expand" = Option<String>, Query, description = "Embed related resources in the topic. Only `sets` is supported, which embeds the first page of the topic's sets"),
+        ("as_of" = Option<String>, Query, description = "Read the topic as it was at this RFC 3339 time, like `2025-01-31T12:00:00Z`. Embedded sets are read as they were then too, leaving out sets deleted since"),
     )
 )]
 #[instrument(skip(service), err(Debug))]
 pub async fn get_topic<T>(
     State(service): State<TopicService<T>>,
     Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Query(options): Query<ReadOptions>,
 ) -> Result<Response, EndpointError<TopicServiceError>>
 where
     T: TopicEngine,
 {
-    let topic = service.get(topic_id).await?;
+    let (fields, expand_sets) = match (options.fields(), options.expand_sets()) {
+        (Ok(fields), Ok(expand_sets)) => (fields, expand_sets),
+        (Err(problem), _) | (_, Err(problem)) => return Ok(problem.into_response()),
+    };
+
+    let topic = match options.as_of {
+        Some(at) => service.get_as_of(topic_id, at).await?,
+        None => service.get(topic_id).await?,
+    };
+    let Some(topic) = topic else {
+        return Ok(TopicProblem::NotFound.into_response());
+    };
+    if fields.is_none() && !expand_sets {
+        return Ok(TopicResponse::ok(topic).in_format(format).into_response());
+    }
+
+    let sets = if expand_sets {
+        match service.embedded_sets(topic_id, options.as_of).await? {
+            Some(sets) => Some(sets),
+            None => return Ok(TopicProblem::ExpansionUnavailable.into_response()),
+        }
+    } else {
+        None
+    };
+    let topic = match fields {
+        Some(fields) => PartialTopic::project(topic, &fields),
+        None => topic.into(),
+    };
 
-    Ok(topic
-        .map(|t| TopicResponse::ok(t).into_response())
-        .unwrap_or_else(|| TopicError::not_found().into_response()))
+    Ok(TopicView::new(topic, sets)
+        .in_format(format)
+        .into_response())
+}
+
+/// List the topics nested directly under the topic with the given id
+#[utoipa::path(
+    get,
+    path = TOPIC_CHILDREN_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The page of the topic's children, oldest first. Empty if it has none on the page", body = Vec<ResponseType>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId of the parent"),
+        ("page" = u32, Query, description = "The offset page to start the listing with"),
+        ("page_size" = u32, Query, description = "The max number of topics to return"),
+    )
+)]
+#[instrument(skip(service), err(Debug), fields(req.page = pagination.page, req.page_size = pagination.page_size))]
+pub async fn list_topic_children<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Query(pagination): Query<Pagination>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let criteria = TopicFilter::criteria(pagination, DEFAULT_TOPIC_SEARCH_PAGE_SIZE);
+
+    match service.children(topic_id, criteria).await? {
+        Some(children) => Ok(format.respond(StatusCode::OK, children).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Get the breadcrumb of topics the topic with the given id is nested under
+#[utoipa::path(
+    get,
+    path = TOPIC_ANCESTORS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic's ancestors, starting from its root and ending with its parent. Empty for a root topic", body = Vec<ResponseType>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to find the ancestors of"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+pub async fn get_topic_ancestors<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.ancestors(topic_id).await? {
+        Some(ancestors) => Ok(format.respond(StatusCode::OK, ancestors).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Get the topic with the given id and every topic nested under it
+#[utoipa::path(
+    get,
+    path = TOPIC_SUBTREE_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic followed by its descendants, each parent before its children", body = Vec<ResponseType>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId at the root of the subtree"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+pub async fn get_topic_subtree<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.subtree(topic_id).await? {
+        Some(subtree) => Ok(format.respond(StatusCode::OK, subtree).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+type BatchGetType = BatchGetResponse<IdType>;
+
+/// Get several topics at once by their ids
+#[utoipa::path(
+    post,
+    path = TOPIC_BATCH_GET_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topics that were found, in the order their ids were given, and the ids that weren't found in `missing`. Repeated ids are only returned once", body = BatchGetType),
+        (status = BAD_REQUEST, description = "An empty array was given", body = Problem, content_type = "application/problem+json"),
+    ),
+    request_body(content = Vec<IdType>, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip_all, err(Debug), fields(req.topic_count = topic_ids.len()))]
+async fn batch_get_topics<T>(
+    State(service): State<TopicService<T>>,
+    format: WireFormat,
+    Wire(topic_ids): Wire<Vec<T::TopicId>>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    if topic_ids.is_empty() {
+        return Ok(TopicProblem::EmptyBulkRequest.into_response());
+    }
+
+    let batch = service.get_many(topic_ids).await?;
+
+    Ok(BatchGetResponse::new(batch.found, batch.missing)
+        .in_format(format)
+        .into_response())
 }
 
 /// Create a new Topic and return its ID
@@ -165,23 +503,78 @@ where
     post,
     path = TOPIC_CREATE_PATH,
     responses(
+        CommonProblems,
         (status = CREATED, description = "A topic was successfully created", body = TopicResponse<IdType>),
-        (status = UNPROCESSABLE_ENTITY, description = "The name in the request was null"),
+        (status = UNPROCESSABLE_ENTITY, description = "The name or description broke a validation rule, every failed field is listed in `errors`. Or the `parent_id` topic doesn't exist, with code `parent_not_found`. Or the `Idempotency-Key` was already used with a different body, with code `idempotency_key_reused`", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "Unique names are enforced and a topic with the same name, ignoring case, exists. Or a request with the same `Idempotency-Key` is still being handled, with code `idempotency_key_in_progress`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Retrying with the same key replays the first response instead of creating the topic again. Keys are kept for a day"),
     ),
-    request_body = CreateTopicRequest
+    request_body(content = CreateTopicRequest<IdType>, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
 )]
 #[instrument(skip_all, err(Debug), fields(req.name = topic.name, req.description = topic.description))]
 async fn create_topic<T>(
     State(service): State<TopicService<T>>,
-    Json(topic): Json<CreateTopicRequest>,
+    format: WireFormat,
+    Wire(topic): Wire<CreateTopicRequest<T::TopicId>>,
 ) -> Result<Response, EndpointError<TopicServiceError>>
 where
     T: TopicEngine,
 {
-    let new_topic = service
-        .create(TopicCreation::new(topic.name, topic.description))
-        .await?;
-    Ok(TopicResponse::created(new_topic).into_response())
+    let creation = TopicCreation::new(topic.name, topic.description)
+        .with_parent(topic.parent_id)
+        .with_attributes(topic.attributes);
+    let outcome = service.create(creation).await?;
+
+    let res = match outcome {
+        CreateOutcome::Success(t) => TopicResponse::created(t).in_format(format).into_response(),
+        CreateOutcome::Invalid(errors) => errors.into_response(),
+    };
+
+    Ok(res)
+}
+
+/// Replace the topic with the given id, or create it with that id if there isn't one. Fields
+/// missing from the body are cleared, since the whole topic is replaced.
+#[utoipa::path(
+    put,
+    path = TOPIC_UPSERT_PATH,
+    responses(
+        CommonProblems,
+        (status = CREATED, description = "No topic had the id, so one was created with it", body = TopicResponse<IdType>),
+        (status = OK, description = "The topic with the id was replaced", body = TopicResponse<IdType>),
+        (status = BAD_REQUEST, description = "The id isn't a valid TopicId"),
+        (status = UNPROCESSABLE_ENTITY, description = "The name or description broke a validation rule, every failed field is listed in `errors`. Or the `parent_id` topic doesn't exist, with code `parent_not_found`", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "Unique names are enforced and another topic has the name, ignoring case. Or `parent_id` is the topic itself or one of its descendants, with code `parent_cycle`. Or other changes to the topic kept landing first, with code `concurrent_change`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to replace or create, chosen by the client"),
+    ),
+    request_body(content = CreateTopicRequest<IdType>, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip(service, topic), err(Debug), fields(req.name = topic.name, req.description = topic.description))]
+async fn upsert_topic<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Wire(topic): Wire<CreateTopicRequest<T::TopicId>>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let creation = TopicCreation::new(topic.name, topic.description)
+        .with_parent(topic.parent_id)
+        .with_attributes(topic.attributes);
+    let outcome = service.upsert(topic_id, creation).await?;
+
+    let res = match outcome {
+        UpsertOutcome::Created(t) => TopicResponse::created(t).in_format(format).into_response(),
+        UpsertOutcome::Replaced(t) => TopicResponse::ok(t).in_format(format).into_response(),
+        UpsertOutcome::Invalid(errors) => errors.into_response(),
+    };
+
+    Ok(res)
 }
 
 type BuildTopicCreateType = BulkCreateResponse<IdType>;
@@ -190,6 +583,7 @@ type BuildTopicCreateType = BulkCreateResponse<IdType>;
     post,
     path = TOPIC_BULK_CREATE_PATH,
     responses(
+        CommonProblems,
         (
             status = CREATED,
             description = "All topics were successfully created. The outcomes array will contain all 'Success' types", body = Vec<BuildTopicCreateType>,
@@ -207,33 +601,83 @@ type BuildTopicCreateType = BulkCreateResponse<IdType>;
             body = Vec<BuildTopicCreateType>,
             example = json!(api_doc::examples::create::bulk_no_success()),
         ),
-        (status = BAD_REQUEST, description = "An empty array was given", body = TopicError),
+        (status = BAD_REQUEST, description = "An empty array was given", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "Only when atomic. Unique names are enforced and one of the names is taken, so nothing was created. Or a request with the same `Idempotency-Key` is still being handled", body = Problem, content_type = "application/problem+json"),
+        (
+            status = OK,
+            description = "Only for an NDJSON body. A line per topic with its outcome, in the order they were sent, followed by a summary line. Lines that aren't a topic fail as 'MalformedLine'. If the body couldn't be read to the end the summary has the problem as 'error'",
+            body = BulkCreateLine<IdType>,
+            content_type = "application/x-ndjson",
+        ),
     ),
-    request_body = Vec<CreateTopicRequest>
+    params(
+        ("atomic" = Option<bool>, Query, description = "Create every topic or none of them. If any topic is invalid, a single validation problem is returned listing every failed field as `[index].field`. Not supported for NDJSON bodies"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Retrying with the same key replays the first response instead of creating the topics again, reusing it with a different body is a 422. Ignored for NDJSON bodies"),
+    ),
+    request_body(
+        content(
+            (Vec<BulkCreateTopicRequest> = "application/json"),
+            (BulkCreateTopicRequest = "application/x-ndjson"),
+        ),
+        description = "A JSON, MessagePack, CBOR or BSON array, picked from `Content-Type`, or NDJSON with a topic per line. Responses are in the format `Accept` prefers",
+    )
 )]
-#[instrument(skip_all, err(Debug), fields(req.topic_count = topics.len()))]
+#[instrument(skip_all, err(Debug), fields(req.atomic = options.atomic))]
 /// Create several topics at once, given the array of creation requests given in the request.
-/// The outcomes array returned should contain the results of each request in the order they were received
+/// The outcomes array returned should contain the results of each request in the order they were received.
+/// An NDJSON body is created a chunk at a time, and the outcomes are streamed back as NDJSON
 async fn bulk_create_topics<T>(
     State(service): State<TopicService<T>>,
-    Json(topics): Json<Vec<BulkCreateTopicRequest>>,
+    Query(options): Query<BulkCreateOptions>,
+    format: Result<WireFormat, NotAcceptable>,
+    body: BulkCreateBody,
 ) -> Result<Response, EndpointError<TopicServiceError>>
 where
     T: TopicEngine,
 {
+    let topics = match body {
+        BulkCreateBody::Ndjson(_) if options.atomic => {
+            return Ok(TopicProblem::AtomicStream.into_response());
+        }
+        BulkCreateBody::Ndjson(body) => {
+            return Ok(ingest::stream_bulk_create(service, body).into_response());
+        }
+        BulkCreateBody::Array(topics) => topics,
+    };
+    // only array bodies are answered in a wire format, NDJSON is always answered with NDJSON
+    let format = match format {
+        Ok(format) => format,
+        Err(not_acceptable) => return Ok(not_acceptable.into_response()),
+    };
+
     if topics.is_empty() {
-        return Ok(TopicError::bad_request("a non-empty array is required").into_response());
+        return Ok(TopicProblem::EmptyBulkRequest.into_response());
     }
 
-    let topics = service
-        .create_many(
-            topics
-                .into_iter()
-                .map(|t| CreateManyTopic::new(t.name, t.description)),
-        )
-        .await?;
+    let topics = topics
+        .into_iter()
+        .map(|t| CreateManyTopic::new(t.name, t.description));
 
-    Ok(BulkCreateResponse::new(topics).into_response())
+    if options.atomic {
+        let res = match service.create_many_atomic(topics).await? {
+            CreateManyAtomicOutcome::Success(created) => BulkCreateResponse::new(
+                created
+                    .into_iter()
+                    .map(CreateManyTopicStatus::Success)
+                    .collect(),
+            )
+            .in_format(format)
+            .into_response(),
+            CreateManyAtomicOutcome::Invalid(errors) => errors.into_response(),
+        };
+        return Ok(res);
+    }
+
+    let topics = service.create_many(topics).await?;
+
+    Ok(BulkCreateResponse::new(topics)
+        .in_format(format)
+        .into_response())
 }
 
 // Delete the topic associated with the given id
@@ -241,63 +685,741 @@ where
     delete,
     path = TOPIC_DELETE_PATH,
     responses(
-        (status = NO_CONTENT, description = "The topic was successfully deleted, or never existed"),
+        CommonProblems,
+        (status = NO_CONTENT, description = "The topic was successfully deleted"),
+        (status = NOT_FOUND, description = "The topic does not exist", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "The policy is `reject` and the topic has children, with code `topic_has_children`", body = Problem, content_type = "application/problem+json"),
     ),
     params(
-        ("topic_id" = IdType, Path, description = "The ID of the topic to delete to delete")
+        ("topic_id" = IdType, Path, description = "The ID of the topic to delete to delete"),
+        ("policy" = Option<DeletePolicy>, Query, description = "What happens to the topic's children. `reject`, the default, refuses to delete a topic with children, `cascade` deletes its whole subtree and `reparent` moves its children up to its parent"),
     )
 )]
-#[instrument(skip(service), err(Debug))]
+#[instrument(skip(service), err(Debug), fields(req.policy = ?options.policy))]
 pub async fn delete_topic<T>(
     State(service): State<TopicService<T>>,
     Path(topic_id): Path<T::TopicId>,
+    Query(options): Query<DeleteOptions>,
 ) -> Result<Response, EndpointError<TopicServiceError>>
 where
     T: TopicEngine,
 {
-    match service.delete(topic_id).await? {
+    match service.delete(topic_id, options.policy).await? {
         Some(_) => Ok(StatusCode::NO_CONTENT.into_response()),
-        None => Ok(TopicError::not_found().into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
     }
 }
 
+type BulkTopicDeleteType = BulkDeleteResponse<IdType>;
+
+#[utoipa::path(
+    delete,
+    path = TOPIC_BULK_DELETE_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "All topics were deleted. The outcomes array will contain all 'Success' types", body = BulkTopicDeleteType),
+        (
+            status = MULTI_STATUS,
+            description = "Some topics were deleted, some were not. Each 'Fail' outcome has a reason, such as the topic not existing or having children that aren't being deleted with it",
+            body = BulkTopicDeleteType,
+            example = json!(api_doc::examples::delete::bulk_mixed_success()),
+        ),
+        (status = UNPROCESSABLE_ENTITY, description = "None of the topics were deleted. The outcomes array will contain only 'Fail' types", body = BulkTopicDeleteType),
+        (status = BAD_REQUEST, description = "An empty array was given", body = Problem, content_type = "application/problem+json"),
+    ),
+    request_body(content = Vec<IdType>, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip_all, err(Debug), fields(req.topic_count = topic_ids.len()))]
+/// Delete several topics at once. The outcomes array is in the same order as the given ids
+async fn bulk_delete_topics<T>(
+    State(service): State<TopicService<T>>,
+    format: WireFormat,
+    Wire(topic_ids): Wire<Vec<T::TopicId>>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    if topic_ids.is_empty() {
+        return Ok(TopicProblem::EmptyBulkRequest.into_response());
+    }
+
+    let outcomes = service.delete_many(topic_ids).await?;
+
+    Ok(BulkDeleteResponse::new(outcomes)
+        .in_format(format)
+        .into_response())
+}
+
 /// Update the topic associated with the given id using the given information.
 #[utoipa::path(
     patch,
     path = TOPIC_PATCH_PATH,
     responses(
+        CommonProblems,
         (status = OK, description = "The topic was successfully patched", body = Topic<IdType>),
-        (status = UNPROCESSABLE_ENTITY, description = "'name' was set to null"),
-        (status = NOT_FOUND, description = "The topic was not found so could not be updated"),
+        (status = UNPROCESSABLE_ENTITY, description = "'name' was set to null, or a field broke a validation rule. Every failed field is listed in `errors`. Or a merge patch or JSON Patch targeted a field that doesn't exist (`unknown_field`) or can't change (`immutable_field`), like `id` or `created`", body = Problem, content_type = "application/problem+json"),
+        (status = NOT_FOUND, description = "The topic was not found so could not be updated", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "Unique names are enforced and another topic has the new name, ignoring case. Or a JSON Patch `test` op failed, with code `patch_test_failed`", body = Problem, content_type = "application/problem+json"),
     ),
     params(
         ("topic_id" = IdType, Path, description = "The TopicId to patch")
     ),
-    request_body = TopicPatchRequest,
+    request_body(
+        content(
+            (TopicPatchRequest = "application/json"),
+            (TopicPatchRequest = "application/merge-patch+json"),
+            (Vec<PatchOp> = "application/json-patch+json"),
+        ),
+        description = "A JSON Merge Patch (RFC 7396), a JSON Patch (RFC 6902), or the fields to change as JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. JSON Patch ops apply to the topic as it's returned, and `test` ops are checked against the topic as it's read, not atomically with the update. Responses are in the format `Accept` prefers",
+    ),
 )]
-#[instrument(skip(service, topic), err(Debug), fields(
-    topic.name = topic.name.as_ref().map_present_or(None, |d| Some(d.map(String::as_str).unwrap_or("null"))),
-    topic.desc = topic.description.as_ref().map_present_or(None, |d| Some(d.map(String::as_str).unwrap_or("null"))),
-))]
+#[instrument(skip(service, body), err(Debug), fields(topic.name = Empty, topic.desc = Empty))]
 pub async fn patch_topic<T>(
     State(service): State<TopicService<T>>,
     Path(topic_id): Path<T::TopicId>,
-    Json(topic): Json<TopicPatchRequest>,
+    format: WireFormat,
+    body: PatchBody<TopicPatchRequest>,
 ) -> Result<Response, EndpointError<TopicServiceError>>
 where
     T: TopicEngine,
 {
+    let fields = match body {
+        PatchBody::Fields(topic) => Ok((topic.name, topic.description, topic.attributes)),
+        PatchBody::Merge(patch) => PATCH_FIELDS.merge(patch).and_then(|f| patched_fields(&f)),
+        PatchBody::Json(ops) => {
+            let Some(current) = service.get(topic_id).await? else {
+                return Ok(TopicProblem::NotFound.into_response());
+            };
+            // the ops leave the whole attributes object, the repo wants what to merge into it
+            PATCH_FIELDS
+                .apply(&current, ops)
+                .and_then(|f| patched_fields(&f))
+                .map(|(name, description, updated)| {
+                    let attributes = match updated {
+                        Field::Present(Some(updated)) => {
+                            Field::Present(Some(attributes::diff(&current.attributes, updated)))
+                        }
+                        other => other,
+                    };
+                    (name, description, attributes)
+                })
+        }
+    };
+    let (name, description, attributes) = match fields {
+        Ok(fields) => fields,
+        Err(e) => return Ok(e.into_response()),
+    };
+
+    let span = Span::current();
+    span.record(
+        "topic.name",
+        name.as_ref()
+            .map_present_or(None, |n| Some(n.map(String::as_str).unwrap_or("null"))),
+    );
+    span.record(
+        "topic.desc",
+        description
+            .as_ref()
+            .map_present_or(None, |d| Some(d.map(String::as_str).unwrap_or("null"))),
+    );
+
     let outcome = service
-        .patch(topic_id, topic.name, topic.description)
+        .patch(topic_id, name, description, attributes)
         .await?;
 
     let res = match outcome {
-        PatchOutcome::Success(t) => TopicResponse::ok(t).into_response(),
-        PatchOutcome::InvalidName => {
-            TopicError::unprocessable_entity("name cannot be null").into_response()
-        }
-        PatchOutcome::NotFound => TopicError::not_found().into_response(),
+        PatchOutcome::Success(t) => TopicResponse::ok(t).in_format(format).into_response(),
+        PatchOutcome::Invalid(errors) => errors.into_response(),
+        PatchOutcome::NotFound => TopicProblem::NotFound.into_response(),
+    };
+
+    Ok(res)
+}
+
+/// The name, description and attributes a patch body gave
+type TopicPatchFields = (Field<String>, Field<String>, Field<Attributes>);
+
+fn patched_fields(fields: &PatchedFields) -> Result<TopicPatchFields, PatchError> {
+    Ok((
+        fields.text(NAME.name())?,
+        fields.text(DESCRIPTION.name())?,
+        fields.object(attributes::FIELD)?,
+    ))
+}
+
+/// Move the topic with the given id under another topic, or make it a root topic
+#[utoipa::path(
+    post,
+    path = TOPIC_MOVE_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic was moved", body = TopicResponse<IdType>),
+        (status = NOT_FOUND, description = "The topic was not found so could not be moved", body = Problem, content_type = "application/problem+json"),
+        (status = UNPROCESSABLE_ENTITY, description = "The `parent_id` topic doesn't exist, with code `parent_not_found`", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "`parent_id` is the topic itself or one of its descendants, with code `parent_cycle`. Or other moves kept conflicting with this one, with code `concurrent_change`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to move"),
+    ),
+    request_body(content = MoveTopicRequest<IdType>, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip(service), err(Debug))]
+async fn move_topic<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Wire(request): Wire<MoveTopicRequest<T::TopicId>>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.move_topic(topic_id, request.parent_id).await? {
+        Some(topic) => Ok(TopicResponse::ok(topic).in_format(format).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+type LinkResponseType = TopicLink<IdType>;
+
+/// List the links to and from the topic with the given id
+#[utoipa::path(
+    get,
+    path = TOPIC_LINKS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The page of the topic's links, oldest first", body = Vec<LinkResponseType>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to list the links of"),
+        ("direction" = Option<LinkDirection>, Query, description = "`outgoing` lists the links from the topic, `incoming` the links to it and `both`, the default, lists either"),
+        ("page" = u32, Query, description = "The offset page to start the listing with"),
+        ("page_size" = u32, Query, description = "The max number of links to return"),
+    )
+)]
+#[instrument(skip(service), err(Debug), fields(req.page = pagination.page, req.page_size = pagination.page_size))]
+async fn list_topic_links<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Query(options): Query<LinkOptions>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let criteria = TopicFilter::criteria(pagination, DEFAULT_TOPIC_SEARCH_PAGE_SIZE);
+
+    match service.links(topic_id, options.direction, criteria).await? {
+        Some(links) => Ok(format.respond(StatusCode::OK, links).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Link the topic with the given id to another topic
+#[utoipa::path(
+    post,
+    path = TOPIC_LINKS_PATH,
+    responses(
+        CommonProblems,
+        (status = CREATED, description = "The topics were linked", body = LinkResponseType),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+        (status = UNPROCESSABLE_ENTITY, description = "The `target_id` topic doesn't exist, with code `link_target_not_found`. Or it's the topic itself, with code `self_link`", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "The topic is already linked to the target, with code `link_exists`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId the link starts from"),
+    ),
+    request_body(content = CreateLinkRequest<IdType>, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip(service), err(Debug))]
+async fn create_topic_link<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Wire(request): Wire<CreateLinkRequest<T::TopicId>>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service
+        .create_link(topic_id, request.target_id, request.link_type)
+        .await?
+    {
+        Some(link) => Ok(format.respond(StatusCode::CREATED, link).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Get the link from the topic with the given id to the target topic
+#[utoipa::path(
+    get,
+    path = TOPIC_LINK_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topics are linked", body = LinkResponseType),
+        (status = NOT_FOUND, description = "The topic isn't linked to the target, with code `link_not_found`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId the link starts from"),
+        ("target_id" = IdType, Path, description = "The TopicId the link goes to"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn get_topic_link<T>(
+    State(service): State<TopicService<T>>,
+    Path((topic_id, target_id)): Path<(T::TopicId, T::TopicId)>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.get_link(topic_id, target_id).await? {
+        Some(link) => Ok(format.respond(StatusCode::OK, link).into_response()),
+        None => Ok(TopicProblem::LinkNotFound.into_response()),
+    }
+}
+
+/// Change the type of the link from the topic with the given id to the target topic
+#[utoipa::path(
+    put,
+    path = TOPIC_LINK_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The link's type was changed", body = LinkResponseType),
+        (status = NOT_FOUND, description = "The topic isn't linked to the target, with code `link_not_found`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId the link starts from"),
+        ("target_id" = IdType, Path, description = "The TopicId the link goes to"),
+    ),
+    request_body(content = UpdateLinkRequest, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip(service), err(Debug))]
+async fn update_topic_link<T>(
+    State(service): State<TopicService<T>>,
+    Path((topic_id, target_id)): Path<(T::TopicId, T::TopicId)>,
+    format: WireFormat,
+    Wire(request): Wire<UpdateLinkRequest>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service
+        .update_link(topic_id, target_id, request.link_type)
+        .await?
+    {
+        Some(link) => Ok(format.respond(StatusCode::OK, link).into_response()),
+        None => Ok(TopicProblem::LinkNotFound.into_response()),
+    }
+}
+
+/// Remove the link from the topic with the given id to the target topic
+#[utoipa::path(
+    delete,
+    path = TOPIC_LINK_PATH,
+    responses(
+        CommonProblems,
+        (status = NO_CONTENT, description = "The link was removed"),
+        (status = NOT_FOUND, description = "The topic isn't linked to the target, with code `link_not_found`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId the link starts from"),
+        ("target_id" = IdType, Path, description = "The TopicId the link goes to"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn delete_topic_link<T>(
+    State(service): State<TopicService<T>>,
+    Path((topic_id, target_id)): Path<(T::TopicId, T::TopicId)>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.delete_link(topic_id, target_id).await? {
+        Some(_) => Ok(StatusCode::NO_CONTENT.into_response()),
+        None => Ok(TopicProblem::LinkNotFound.into_response()),
+    }
+}
+
+type TopicGraphType = TopicGraphResponse<IdType>;
+
+/// Get the topics linked to the topic with the given id, following links either way, as nodes
+/// and edges. Anyone who can read topics can read every topic in the graph, so only how deep it
+/// goes depends on the caller's roles
+#[utoipa::path(
+    get,
+    path = TOPIC_GRAPH_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topics that were reached as `nodes`, starting with the given one, and the links that were followed as `edges`. `truncated` when the walk stopped at 500 topics or 1000 links", body = TopicGraphType),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+        (status = FORBIDDEN, description = "`depth` is more than the caller's roles allow, with code `graph_too_deep`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to start from"),
+        ("depth" = Option<u32>, Query, description = "How many links away to go, 1 by default. Readers can go 3 deep and admins 6"),
+        ("link_type" = Option<LinkType>, Query, description = "Only follow links of this type"),
+    )
+)]
+#[instrument(skip(service, user), err(Debug), fields(req.depth = options.depth))]
+async fn get_topic_graph<T>(
+    State(service): State<TopicService<T>>,
+    Extension(user): Extension<AuthedUser<TopicRoles>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Query(options): Query<GraphOptions>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let max_depth = if user.has_roles(TopicRoles::TOPIC_ADMIN) {
+        MAX_ADMIN_GRAPH_DEPTH
+    } else {
+        MAX_GRAPH_DEPTH
+    };
+    let depth = options.depth.unwrap_or(1);
+    if depth > max_depth {
+        return Ok(TopicProblem::GraphTooDeep.into_response());
+    }
+
+    match service.graph(topic_id, depth, options.link_type).await? {
+        Some(graph) => Ok(
+            TopicGraphResponse::new(graph.nodes, graph.edges, graph.truncated)
+                .in_format(format)
+                .into_response(),
+        ),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// List every tag on topics or sets, with how many of each have it
+#[utoipa::path(
+    get,
+    path = TOPIC_TAG_DIRECTORY_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The tags in alphabetical order. Set counts are 0 when this service can't read sets", body = Vec<TagUsage>),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn get_tag_directory<T>(
+    State(service): State<TopicService<T>>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let directory = service.tag_directory().await?;
+
+    Ok(format.respond(StatusCode::OK, directory).into_response())
+}
+
+/// List the tags of the topic with the given id
+#[utoipa::path(
+    get,
+    path = TOPIC_TAGS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic's tags in alphabetical order", body = Vec<String>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to list the tags of"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn list_topic_tags<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.tags(topic_id).await? {
+        Some(tags) => Ok(format.respond(StatusCode::OK, tags).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Tag the topic with the given id
+#[utoipa::path(
+    post,
+    path = TOPIC_TAGS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "All of the topic's tags in alphabetical order. Tags it already had are left as they were", body = Vec<String>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+        (status = UNPROCESSABLE_ENTITY, description = "A tag was blank, too long or had control characters, every failed tag is listed in `errors`", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to tag"),
+    ),
+    request_body(content = TagsRequest, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip(service), err(Debug))]
+async fn add_topic_tags<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Wire(request): Wire<TagsRequest>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let res = match service.add_tags(topic_id, request.tags).await? {
+        TagOutcome::Success(tags) => format.respond(StatusCode::OK, tags).into_response(),
+        TagOutcome::Invalid(errors) => errors.into_response(),
+        TagOutcome::NotFound => TopicProblem::NotFound.into_response(),
     };
 
     Ok(res)
 }
+
+/// Remove a tag from the topic with the given id
+#[utoipa::path(
+    delete,
+    path = TOPIC_TAG_PATH,
+    responses(
+        CommonProblems,
+        (status = NO_CONTENT, description = "The topic doesn't have the tag, whether or not it did before"),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to untag"),
+        ("tag" = String, Path, description = "The tag to remove, matched the same way tags are added"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn remove_topic_tag<T>(
+    State(service): State<TopicService<T>>,
+    Path((topic_id, tag)): Path<(T::TopicId, String)>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let res = match service.remove_tags(topic_id, vec![tag]).await? {
+        TagOutcome::Success(_) => StatusCode::NO_CONTENT.into_response(),
+        TagOutcome::Invalid(errors) => errors.into_response(),
+        TagOutcome::NotFound => TopicProblem::NotFound.into_response(),
+    };
+
+    Ok(res)
+}
+
+/// List the status changes of the topic with the given id
+#[utoipa::path(
+    get,
+    path = TOPIC_STATUS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic's status changes, oldest first", body = Vec<StatusChange>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to list the status changes of"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn list_topic_status_changes<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.status_changes(topic_id).await? {
+        Some(changes) => Ok(format.respond(StatusCode::OK, changes).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Move the topic with the given id to another status. Drafts become active or archived, active
+/// topics become deprecated, deprecated ones active again or archived, and archived ones active or
+/// deprecated. The sets of an archived topic are read-only
+#[utoipa::path(
+    post,
+    path = TOPIC_STATUS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic was moved to the status", body = TopicResponse<IdType>),
+        (status = NOT_FOUND, description = "The topic was not found so its status could not change", body = Problem, content_type = "application/problem+json"),
+        (status = FORBIDDEN, description = "Only admins can take a topic out of the archive, with code `unarchive_forbidden`", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "The topic can't move from its status to this one, with code `transition_not_allowed`, or its status changed while this one was being made, with code `status_changed`", body = Problem, content_type = "application/problem+json"),
+        (status = UNPROCESSABLE_ENTITY, description = "The reason was too long or had control characters", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to change the status of"),
+    ),
+    request_body(content = StatusChangeRequest, description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip(service, user), err(Debug))]
+async fn change_topic_status<T>(
+    State(service): State<TopicService<T>>,
+    Extension(user): Extension<AuthedUser<TopicRoles>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Wire(request): Wire<StatusChangeRequest>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let is_admin = user.has_roles(TopicRoles::TOPIC_ADMIN);
+    let res = match service
+        .change_status(topic_id, request.status, request.reason, is_admin)
+        .await?
+    {
+        StatusOutcome::Success(topic) => TopicResponse::ok(topic).in_format(format).into_response(),
+        StatusOutcome::Invalid(errors) => errors.into_response(),
+        StatusOutcome::NotFound => TopicProblem::NotFound.into_response(),
+        StatusOutcome::NotAllowed => TopicProblem::TransitionNotAllowed.into_response(),
+        StatusOutcome::AdminOnly => TopicProblem::UnarchiveForbidden.into_response(),
+    };
+
+    Ok(res)
+}
+
+type VersionType = TopicVersion<IdType>;
+
+/// List the versions of the topic with the given id. Every change to a topic is kept as a new
+/// version, numbered from 1
+#[utoipa::path(
+    get,
+    path = TOPIC_VERSIONS_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "A page of the topic's versions, oldest first", body = Vec<VersionType>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to list the versions of"),
+        ("page" = u32, Query, description = "The offset page to start the listing with"),
+        ("page_size" = u32, Query, description = "The max number of versions to return"),
+    )
+)]
+#[instrument(skip(service), err(Debug), fields(req.page = pagination.page, req.page_size = pagination.page_size))]
+async fn list_topic_versions<T>(
+    State(service): State<TopicService<T>>,
+    Path(topic_id): Path<T::TopicId>,
+    format: WireFormat,
+    Query(pagination): Query<Pagination>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    let criteria = TopicFilter::criteria(pagination, DEFAULT_TOPIC_SEARCH_PAGE_SIZE);
+
+    match service.versions(topic_id, criteria).await? {
+        Some(versions) => Ok(format.respond(StatusCode::OK, versions).into_response()),
+        None => Ok(TopicProblem::NotFound.into_response()),
+    }
+}
+
+/// Get one version of the topic with the given id
+#[utoipa::path(
+    get,
+    path = TOPIC_VERSION_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic as it was at this version", body = VersionType),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found, or it has no such version", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to find the version of"),
+        ("version" = u64, Path, description = "The version number, starting from 1"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn get_topic_version<T>(
+    State(service): State<TopicService<T>>,
+    Path((topic_id, version)): Path<(T::TopicId, u64)>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.version(topic_id, version).await? {
+        Some(version) => Ok(format.respond(StatusCode::OK, version).into_response()),
+        None => Ok(TopicProblem::VersionNotFound.into_response()),
+    }
+}
+
+/// Put the name, description and attributes of the topic with the given id back to how they were
+/// at a version. This makes a new version rather than dropping the ones after it. The topic's
+/// parent and status stay as they are
+#[utoipa::path(
+    post,
+    path = TOPIC_REVERT_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "The topic was reverted", body = TopicResponse<IdType>),
+        (status = NOT_FOUND, description = "No topics with the given TopicId were found, or it has no such version", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "Unique names are enforced and another topic now has the version's name", body = Problem, content_type = "application/problem+json"),
+    ),
+    params(
+        ("topic_id" = IdType, Path, description = "The TopicId to revert"),
+        ("version" = u64, Path, description = "The version to go back to"),
+    )
+)]
+#[instrument(skip(service), err(Debug))]
+async fn revert_topic<T>(
+    State(service): State<TopicService<T>>,
+    Path((topic_id, version)): Path<(T::TopicId, u64)>,
+    format: WireFormat,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    match service.revert(topic_id, version).await? {
+        Some(topic) => Ok(TopicResponse::ok(topic).in_format(format).into_response()),
+        None => Ok(TopicProblem::VersionNotFound.into_response()),
+    }
+}
+
+type BulkTopicPatchType = BulkPatchResponse<IdType>;
+
+#[utoipa::path(
+    patch,
+    path = TOPIC_BULK_PATCH_PATH,
+    responses(
+        CommonProblems,
+        (status = OK, description = "All topics were patched. The outcomes array will contain all 'Success' types", body = BulkTopicPatchType),
+        (
+            status = MULTI_STATUS,
+            description = "Some topics were patched, some were not. Each 'Fail' outcome has a reason, such as a broken validation rule, a name that's taken or the topic not existing",
+            body = BulkTopicPatchType,
+            example = json!(api_doc::examples::patch::bulk_mixed_success()),
+        ),
+        (status = UNPROCESSABLE_ENTITY, description = "None of the topics were patched. The outcomes array will contain only 'Fail' types", body = BulkTopicPatchType),
+        (status = BAD_REQUEST, description = "An empty array was given", body = Problem, content_type = "application/problem+json"),
+    ),
+    request_body(content = [BulkPatchTopicRequest<IdType>], description = "JSON, MessagePack, CBOR or BSON, picked from `Content-Type`. Responses are in the format `Accept` prefers")
+)]
+#[instrument(skip_all, err(Debug), fields(req.topic_count = patches.len()))]
+/// Patch several topics at once. The outcomes array is in the same order as the given patches
+async fn bulk_patch_topics<T>(
+    State(service): State<TopicService<T>>,
+    format: WireFormat,
+    Wire(patches): Wire<Vec<BulkPatchTopicRequest<T::TopicId>>>,
+) -> Result<Response, EndpointError<TopicServiceError>>
+where
+    T: TopicEngine,
+{
+    if patches.is_empty() {
+        return Ok(TopicProblem::EmptyBulkRequest.into_response());
+    }
+
+    let outcomes = service
+        .patch_many(
+            patches
+                .into_iter()
+                .map(|p| PatchManyTopic::new(p.id, p.name, p.description)),
+        )
+        .await?;
+
+    Ok(BulkPatchResponse::new(outcomes)
+        .in_format(format)
+        .into_response())
+}
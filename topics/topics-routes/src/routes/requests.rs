@@ -1,6 +1,17 @@
+use crate::routes::responses::TopicProblem;
+use axum::body::Body;
+use axum::extract::{FromRequest, Request};
+use chrono::{DateTime, Utc};
 use optional_field::{Field, serde_optional_fields};
+use routing::attributes::{self, AttributeFilter, Attributes};
+use routing::ndjson::is_ndjson;
 use routing::patch_field_schema;
+use routing::validation::FieldErrors;
+use routing::wire::{Wire, WireRejection};
 use serde::Deserialize;
+use topics_core::list_filter::{TopicFilter, TopicListCriteria};
+use topics_core::model::{DeletePolicy, LinkDirection, LinkType, TopicField, TopicStatus};
+use topics_core::validation;
 use utoipa::ToSchema;
 
 #[serde_optional_fields]
@@ -13,12 +24,178 @@ pub struct TopicPatchRequest {
     /// If not specified, no update will happen.
     #[schema(schema_with = patch_field_schema)]
     pub description: Field<String>,
+    /// Attributes to merge into the topic's. An attribute set to null is removed, the others are
+    /// added or replaced. Cannot be null itself
+    #[schema(value_type = Option<Object>)]
+    pub attributes: Field<Attributes>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
-pub struct CreateTopicRequest {
+pub struct CreateTopicRequest<T> {
     pub name: String,
     pub description: Option<String>,
+    /// The topic to nest this one under, a root topic if it's left out
+    pub parent_id: Option<T>,
+    /// Free-form values like `{"team": "payments"}`. Keys are letters, digits, `_` or `-`
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub attributes: Attributes,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MoveTopicRequest<T> {
+    /// The topic to nest it under. Null or left out makes it a root topic
+    pub parent_id: Option<T>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteOptions {
+    /// What happens to the topic's children, they stop the delete unless told otherwise
+    #[serde(default)]
+    pub policy: DeletePolicy,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateLinkRequest<T> {
+    /// The topic to link to
+    pub target_id: T,
+    pub link_type: LinkType,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateLinkRequest {
+    pub link_type: LinkType,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LinkOptions {
+    /// Which of the topic's links to list, both ways unless told otherwise
+    #[serde(default)]
+    pub direction: LinkDirection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphOptions {
+    /// How many links away from the topic to go, one when left out
+    pub depth: Option<u32>,
+    /// Only follow links of this type
+    pub link_type: Option<LinkType>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TagsRequest {
+    /// Tags are trimmed and lowercased, so `PII` and `pii` are the same tag
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StatusChangeRequest {
+    /// The status to move the topic to
+    pub status: TopicStatus,
+    /// Why it's moving, kept with the topic's status history
+    pub reason: Option<String>,
+}
+
+/// The `tag`, `any_tag`, `status` and `attr.<key>` list filters, read from every query pair since
+/// they can be repeated like `?tag=a&tag=b`
+#[derive(Debug, Default)]
+pub struct FilterQuery {
+    all: Vec<String>,
+    any: Vec<String>,
+    statuses: Vec<String>,
+    attributes: Vec<AttributeFilter>,
+}
+
+impl FilterQuery {
+    pub fn new(pairs: Vec<(String, String)>) -> Self {
+        let mut query = Self::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "tag" => query.all.push(value),
+                "any_tag" => query.any.push(value),
+                "status" => query.statuses.push(value),
+                _ => query
+                    .attributes
+                    .extend(AttributeFilter::from_query(&key, &value)),
+            }
+        }
+        query
+    }
+
+    /// Adds the filters that were given to `criteria`, tags are normalised the same way they
+    /// are when they're added
+    pub fn apply(self, criteria: &mut TopicListCriteria) -> Result<(), FieldErrors> {
+        if !self.all.is_empty() {
+            criteria.add(TopicFilter::AllTags(validation::tags(&self.all)?));
+        }
+        if !self.any.is_empty() {
+            criteria.add(TopicFilter::AnyTag(validation::tags(&self.any)?));
+        }
+        if !self.statuses.is_empty() {
+            criteria.add(TopicFilter::Status(validation::statuses(&self.statuses)?));
+        }
+        if !self.attributes.is_empty() {
+            let mut errors = FieldErrors::default();
+            for filter in &self.attributes {
+                if let Err(mut e) = attributes::check_key(filter.key()) {
+                    e.field = format!("{}{}", attributes::QUERY_PREFIX, filter.key()).into();
+                    errors.push(e);
+                }
+            }
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+            criteria.add(TopicFilter::Attributes(self.attributes));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BulkCreateOptions {
+    /// Create every topic or none of them
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+/// What a topic read returns, every field and nothing embedded unless given
+#[derive(Debug, Default, Deserialize)]
+pub struct ReadOptions {
+    /// A comma separated list of the fields to return, like `id,name`
+    pub fields: Option<String>,
+    /// A comma separated list of what to embed, only `sets` for now
+    pub expand: Option<String>,
+    /// Read the topic, and any sets embedded in it, as they were at this time, like
+    /// `2025-01-31T12:00:00Z`
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+impl ReadOptions {
+    /// `None` when every field was asked for, either by listing them or leaving `fields` out
+    pub fn fields(&self) -> Result<Option<Vec<TopicField>>, TopicProblem> {
+        let Some(list) = &self.fields else {
+            return Ok(None);
+        };
+        let fields = TopicField::parse_list(list).map_err(|_| TopicProblem::UnknownField)?;
+        if fields.is_empty() || fields.len() == TopicField::ALL.len() {
+            return Ok(None);
+        }
+        Ok(Some(fields))
+    }
+
+    pub fn expand_sets(&self) -> Result<bool, TopicProblem> {
+        let Some(list) = &self.expand else {
+            return Ok(false);
+        };
+        let mut sets = false;
+        for expansion in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match expansion {
+                "sets" => sets = true,
+                _ => return Err(TopicProblem::UnknownExpansion),
+            }
+        }
+        Ok(sets)
+    }
 }
 
 #[serde_optional_fields]
@@ -29,3 +206,39 @@ pub struct BulkCreateTopicRequest {
     #[schema(schema_with = patch_field_schema)]
     pub description: Field<String>,
 }
+
+#[serde_optional_fields]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkPatchTopicRequest<T> {
+    /// The topic to patch, each id can only be given once per request
+    pub id: T,
+    /// Same as `name` in a single topic patch
+    #[schema(schema_with = patch_field_schema)]
+    pub name: Field<String>,
+    /// Same as `description` in a single topic patch
+    #[schema(schema_with = patch_field_schema)]
+    pub description: Field<String>,
+}
+
+/// Either an array in any [`Wire`] format, or NDJSON with a topic per line that's left unread
+/// until it's needed
+pub enum BulkCreateBody {
+    Array(Vec<BulkCreateTopicRequest>),
+    Ndjson(Body),
+}
+
+impl<S> FromRequest<S> for BulkCreateBody
+where
+    S: Send + Sync,
+{
+    type Rejection = WireRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if is_ndjson(req.headers()) {
+            return Ok(Self::Ndjson(req.into_body()));
+        }
+
+        let Wire(topics) = Wire::from_request(req, state).await?;
+        Ok(Self::Array(topics))
+    }
+}
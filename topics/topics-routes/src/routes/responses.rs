@@ -1,11 +1,13 @@
-use axum::Json;
+use crate::expand::EmbeddedSet;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use ids::Id;
+use routing::problem::ProblemDetails;
+use routing::wire::WireFormat;
 use serde::Serialize;
 use std::borrow::Cow;
-use topics_core::CreateManyTopicStatus;
-use topics_core::model::Topic;
+use topics_core::model::{PartialTopic, Topic, TopicLink};
+use topics_core::{CreateManyTopicStatus, DeleteManyTopicStatus, PatchManyTopicStatus};
 use tracing::warn;
 use utoipa::ToSchema;
 
@@ -13,14 +15,23 @@ use utoipa::ToSchema;
 pub struct TopicResponse<T> {
     #[serde(skip)]
     status_code: StatusCode,
+    #[serde(skip)]
+    format: WireFormat,
     #[serde(flatten)]
     topic: Topic<T>,
 }
 
 impl<T> TopicResponse<T> {
+    /// Responses are JSON unless given another format
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     pub fn ok(topic: Topic<T>) -> Self {
         Self {
             status_code: StatusCode::OK,
+            format: WireFormat::default(),
             topic,
         }
     }
@@ -28,6 +39,7 @@ impl<T> TopicResponse<T> {
     pub fn created(topic: Topic<T>) -> Self {
         Self {
             status_code: StatusCode::CREATED,
+            format: WireFormat::default(),
             topic,
         }
     }
@@ -35,7 +47,40 @@ impl<T> TopicResponse<T> {
 
 impl<T: Id> IntoResponse for TopicResponse<T> {
     fn into_response(self) -> Response {
-        (self.status_code, Json(self)).into_response()
+        self.format.respond(self.status_code, self).into_response()
+    }
+}
+
+/// A topic read with `fields` and `expand` applied, fields that weren't asked for are left out
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TopicView<T> {
+    #[serde(skip)]
+    format: WireFormat,
+    #[serde(flatten)]
+    topic: PartialTopic<T>,
+    /// Only with `expand=sets`, the first page of the topic's sets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sets: Option<Vec<EmbeddedSet>>,
+}
+
+impl<T> TopicView<T> {
+    pub fn new(topic: PartialTopic<T>, sets: Option<Vec<EmbeddedSet>>) -> Self {
+        Self {
+            format: WireFormat::default(),
+            topic,
+            sets,
+        }
+    }
+
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl<T: Id> IntoResponse for TopicView<T> {
+    fn into_response(self) -> Response {
+        self.format.respond(StatusCode::OK, self).into_response()
     }
 }
 
@@ -43,12 +88,19 @@ impl<T: Id> IntoResponse for TopicResponse<T> {
 pub struct BulkCreateResponse<T> {
     #[serde(skip)]
     status_code: StatusCode,
+    #[serde(skip)]
+    format: WireFormat,
     created: usize,
     failed: usize,
-    outcomes: Vec<CreateManyTopicStatus<T>>, // can these be streamed? maybe if I rearrange things and don't have all this 'header' info
+    outcomes: Vec<CreateManyTopicStatus<T>>,
 }
 
 impl<T> BulkCreateResponse<T> {
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     pub fn new(outcomes: Vec<CreateManyTopicStatus<T>>) -> Self {
         let (created, failed) =
             outcomes
@@ -62,14 +114,9 @@ impl<T> BulkCreateResponse<T> {
                     }
                 });
 
-        let status_code = match (created, failed) {
-            (0, 1..) => StatusCode::UNPROCESSABLE_ENTITY,
-            (1.., 0) => StatusCode::CREATED,
-            _ => StatusCode::MULTI_STATUS,
-        };
-
         Self {
-            status_code,
+            status_code: bulk_status(created, failed, StatusCode::CREATED),
+            format: WireFormat::default(),
             created,
             failed,
             outcomes,
@@ -79,68 +126,246 @@ impl<T> BulkCreateResponse<T> {
 
 impl<T: Id> IntoResponse for BulkCreateResponse<T> {
     fn into_response(self) -> Response {
-        (self.status_code, Json(self)).into_response()
+        self.format.respond(self.status_code, self).into_response()
+    }
+}
+
+/// `all_succeeded` when nothing failed, 422 when nothing succeeded, otherwise 207
+fn bulk_status(succeeded: usize, failed: usize, all_succeeded: StatusCode) -> StatusCode {
+    match (succeeded, failed) {
+        (0, 1..) => StatusCode::UNPROCESSABLE_ENTITY,
+        (1.., 0) => all_succeeded,
+        _ => StatusCode::MULTI_STATUS,
     }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct TopicError<T = ()> {
+pub struct BulkPatchResponse<T> {
     #[serde(skip)]
     status_code: StatusCode,
-    message: Cow<'static, str>,
-    #[serde(flatten)]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<T>,
+    #[serde(skip)]
+    format: WireFormat,
+    patched: usize,
+    failed: usize,
+    outcomes: Vec<PatchManyTopicStatus<T>>,
+}
+
+impl<T> BulkPatchResponse<T> {
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn new(outcomes: Vec<PatchManyTopicStatus<T>>) -> Self {
+        let patched = outcomes
+            .iter()
+            .filter(|o| matches!(o, PatchManyTopicStatus::Success(_)))
+            .count();
+        let failed = outcomes.len() - patched;
+
+        Self {
+            status_code: bulk_status(patched, failed, StatusCode::OK),
+            format: WireFormat::default(),
+            patched,
+            failed,
+            outcomes,
+        }
+    }
+}
+
+impl<T: Id> IntoResponse for BulkPatchResponse<T> {
+    fn into_response(self) -> Response {
+        self.format.respond(self.status_code, self).into_response()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchGetResponse<T> {
+    #[serde(skip)]
+    format: WireFormat,
+    /// The topics that were found, in the order their ids were given
+    topics: Vec<Topic<T>>,
+    /// The ids no topic was found for
+    missing: Vec<T>,
 }
 
-pub type ErrorMessageType = Cow<'static, str>;
+impl<T> BatchGetResponse<T> {
+    pub fn new(topics: Vec<Topic<T>>, missing: Vec<T>) -> Self {
+        Self {
+            format: WireFormat::default(),
+            topics,
+            missing,
+        }
+    }
+
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
 
-impl TopicError<()> {
-    pub fn not_found() -> Self {
-        Self::new(
-            StatusCode::NOT_FOUND,
-            "the requested topic does not exist",
-            None,
-        )
+impl<T: Id> IntoResponse for BatchGetResponse<T> {
+    fn into_response(self) -> Response {
+        self.format.respond(StatusCode::OK, self).into_response()
     }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TopicGraphResponse<T> {
+    #[serde(skip)]
+    format: WireFormat,
+    /// The topics that were reached, starting with the one the walk began from
+    nodes: Vec<Topic<T>>,
+    /// The links between them that were followed
+    edges: Vec<TopicLink<T>>,
+    /// Whether the walk stopped early because the graph got too big
+    truncated: bool,
+}
 
-    pub fn bad_request(message: impl Into<ErrorMessageType>) -> Self {
-        Self::new(StatusCode::BAD_REQUEST, message.into(), None)
+impl<T> TopicGraphResponse<T> {
+    pub fn new(nodes: Vec<Topic<T>>, edges: Vec<TopicLink<T>>, truncated: bool) -> Self {
+        Self {
+            format: WireFormat::default(),
+            nodes,
+            edges,
+            truncated,
+        }
     }
 
-    pub fn unprocessable_entity(message: impl Into<ErrorMessageType>) -> Self {
-        Self::new(StatusCode::UNPROCESSABLE_ENTITY, message.into(), None)
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
     }
 }
 
-impl<T: Serialize> TopicError<T> {
-    // pub fn with_data(
-    //     status_code: StatusCode,
-    //     message: impl Into<ErrorMessageType>,
-    //     data: T,
-    // ) -> Self {
-    //     Self {
-    //         status_code,
-    //         message: message.into(),
-    //         data: Some(data),
-    //     }
-    // }
+impl<T: Id> IntoResponse for TopicGraphResponse<T> {
+    fn into_response(self) -> Response {
+        self.format.respond(StatusCode::OK, self).into_response()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkDeleteResponse<T> {
+    #[serde(skip)]
+    status_code: StatusCode,
+    #[serde(skip)]
+    format: WireFormat,
+    deleted: usize,
+    failed: usize,
+    outcomes: Vec<DeleteManyTopicStatus<T>>,
+}
+
+impl<T> BulkDeleteResponse<T> {
+    pub fn in_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn new(outcomes: Vec<DeleteManyTopicStatus<T>>) -> Self {
+        let deleted = outcomes
+            .iter()
+            .filter(|o| matches!(o, DeleteManyTopicStatus::Success { .. }))
+            .count();
+        let failed = outcomes.len() - deleted;
 
-    pub fn new(
-        status_code: StatusCode,
-        message: impl Into<ErrorMessageType>,
-        data: Option<T>,
-    ) -> Self {
         Self {
-            status_code,
-            message: message.into(),
-            data,
+            status_code: bulk_status(deleted, failed, StatusCode::OK),
+            format: WireFormat::default(),
+            deleted,
+            failed,
+            outcomes,
         }
     }
 }
 
-impl<T: Serialize> IntoResponse for TopicError<T> {
+impl<T: Id> IntoResponse for BulkDeleteResponse<T> {
+    fn into_response(self) -> Response {
+        self.format.respond(self.status_code, self).into_response()
+    }
+}
+
+/// Problems the topic routes report themselves, rather than ones bubbling up from the service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicProblem {
+    NotFound,
+    EmptyBulkRequest,
+    AtomicStream,
+    UnknownField,
+    UnknownExpansion,
+    /// The app wasn't given a set repo to embed sets from
+    ExpansionUnavailable,
+    /// The topics aren't linked
+    LinkNotFound,
+    /// The graph depth is more than the caller's roles allow
+    GraphTooDeep,
+    /// The topic's status can't go straight to the one asked for
+    TransitionNotAllowed,
+    /// Taking a topic out of the archive needs the admin role
+    UnarchiveForbidden,
+    /// The topic doesn't exist or has no version with that number
+    VersionNotFound,
+}
+
+impl ProblemDetails for TopicProblem {
+    fn status(&self) -> StatusCode {
+        match self {
+            TopicProblem::NotFound | TopicProblem::LinkNotFound | TopicProblem::VersionNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            TopicProblem::EmptyBulkRequest
+            | TopicProblem::AtomicStream
+            | TopicProblem::UnknownField
+            | TopicProblem::UnknownExpansion => StatusCode::BAD_REQUEST,
+            TopicProblem::ExpansionUnavailable => StatusCode::NOT_IMPLEMENTED,
+            TopicProblem::GraphTooDeep | TopicProblem::UnarchiveForbidden => StatusCode::FORBIDDEN,
+            TopicProblem::TransitionNotAllowed => StatusCode::CONFLICT,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            TopicProblem::NotFound => "topic_not_found",
+            TopicProblem::EmptyBulkRequest => "empty_bulk_request",
+            TopicProblem::AtomicStream => "atomic_stream_unsupported",
+            TopicProblem::UnknownField => "unknown_field",
+            TopicProblem::UnknownExpansion => "unknown_expansion",
+            TopicProblem::ExpansionUnavailable => "expansion_unavailable",
+            TopicProblem::LinkNotFound => "link_not_found",
+            TopicProblem::GraphTooDeep => "graph_too_deep",
+            TopicProblem::TransitionNotAllowed => "transition_not_allowed",
+            TopicProblem::UnarchiveForbidden => "unarchive_forbidden",
+            TopicProblem::VersionNotFound => "version_not_found",
+        }
+    }
+
+    fn detail(&self) -> Option<Cow<'static, str>> {
+        Some(Cow::Borrowed(match self {
+            TopicProblem::NotFound => "the requested topic does not exist",
+            TopicProblem::EmptyBulkRequest => "a non-empty array is required",
+            TopicProblem::AtomicStream => {
+                "atomic creates need an array body, NDJSON bodies are created a chunk at a time"
+            }
+            TopicProblem::UnknownField => {
+                "fields can only list id, parent_id, name, description, attributes, status, created \
+                 and updated"
+            }
+            TopicProblem::UnknownExpansion => "only sets can be expanded",
+            TopicProblem::ExpansionUnavailable => "this service can't read the sets of topics",
+            TopicProblem::LinkNotFound => "the topics are not linked",
+            TopicProblem::GraphTooDeep => {
+                "the graph depth is more than your roles allow, admins can go deeper"
+            }
+            TopicProblem::TransitionNotAllowed => {
+                "the topic can't move from its current status to the one requested"
+            }
+            TopicProblem::UnarchiveForbidden => "only admins can take a topic out of the archive",
+            TopicProblem::VersionNotFound => "the topic does not exist or has no such version",
+        }))
+    }
+}
+
+impl IntoResponse for TopicProblem {
     fn into_response(self) -> Response {
-        (self.status_code, Json(self)).into_response()
+        self.to_problem().into_response()
     }
 }
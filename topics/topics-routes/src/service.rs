@@ -1,25 +1,78 @@
 use crate::error::TopicServiceError;
+use crate::expand::{EmbeddedSet, TopicSets};
 use crate::metrics;
 use crate::{OptServiceResult, ServiceResult};
+use chrono::{DateTime, Utc};
 use error_stack::ResultExt;
 use optional_field::Field;
+use routing::attributes::Attributes;
+use routing::validation::{FieldError, FieldErrors};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use tokio_stream::{Stream, StreamExt};
 use topics_core::list_filter::TopicListCriteria;
-use topics_core::model::{NewTopic, PatchTopic, Topic};
-use topics_core::{CreateManyFailReason, CreateManyTopicStatus, TopicEngine, TopicRepository};
+use topics_core::model::{
+    DeletePolicy, LinkDirection, LinkType, NewTopic, PartialTopic, PatchTopic, StatusChange, Topic,
+    TopicField, TopicLink, TopicStatus, TopicVersion, Upserted,
+};
+use topics_core::result::TopicRepoError;
+use topics_core::validation;
+use topics_core::{
+    CreateManyFailReason, CreateManyTopicStatus, DeleteManyFailReason, DeleteManyTopicStatus,
+    PatchManyFailReason, PatchManyTopicStatus, TopicEngine, TopicRepository,
+};
 use tracing::{debug, error, instrument};
+use utoipa::ToSchema;
 
 #[cfg(test)]
 mod tests;
 
-pub struct TopicCreation {
+pub struct TopicCreation<T> {
     name: String,
     description: Option<String>,
+    parent_id: Option<T>,
+    attributes: Attributes,
 }
 
-impl TopicCreation {
+impl<T> TopicCreation<T> {
     pub fn new(name: String, description: Option<String>) -> Self {
-        Self { name, description }
+        Self {
+            name,
+            description,
+            parent_id: None,
+            attributes: Attributes::new(),
+        }
+    }
+
+    pub fn with_parent(mut self, parent_id: Option<T>) -> Self {
+        self.parent_id = parent_id;
+        self
     }
+
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    fn validate(self) -> Result<NewTopic<T>, FieldErrors> {
+        let new_topic = validation::new_topic(&self.name, self.description.as_deref());
+        match (new_topic, validation::new_attributes(self.attributes)) {
+            (Ok(new_topic), Ok(attributes)) => Ok(new_topic
+                .with_parent(self.parent_id)
+                .with_attributes(attributes)),
+            (new_topic, attributes) => Err(all_errors(new_topic.err(), attributes.err())),
+        }
+    }
+}
+
+/// The errors from validating separate parts of a request, reported together
+fn all_errors(first: Option<FieldErrors>, second: Option<FieldErrors>) -> FieldErrors {
+    let mut errors = FieldErrors::default();
+    for part in [first, second].into_iter().flatten() {
+        errors.extend(part);
+    }
+    errors
 }
 
 pub struct CreateManyTopic {
@@ -33,20 +86,77 @@ impl CreateManyTopic {
     }
 }
 
+pub struct PatchManyTopic<T> {
+    id: T,
+    name: Field<String>,
+    description: Field<String>,
+}
+
+impl<T> PatchManyTopic<T> {
+    pub fn new(id: T, name: Field<String>, description: Field<String>) -> Self {
+        Self {
+            id,
+            name,
+            description,
+        }
+    }
+}
+
+/// The topics a batch get found, in the order they were asked for, and the ids it didn't find
+#[derive(Debug)]
+pub struct BatchGet<T> {
+    pub found: Vec<Topic<T>>,
+    pub missing: Vec<T>,
+}
+
+/// The most topics and links a graph walk collects before it stops
+pub const MAX_GRAPH_NODES: usize = 500;
+pub const MAX_GRAPH_EDGES: usize = 1000;
+
+/// The topics reached by following links from a topic and the links that were followed.
+/// `truncated` when the walk stopped at [`MAX_GRAPH_NODES`] or [`MAX_GRAPH_EDGES`]
+#[derive(Debug)]
+pub struct LinkGraph<T> {
+    pub nodes: Vec<Topic<T>>,
+    pub edges: Vec<TopicLink<T>>,
+    pub truncated: bool,
+}
+
+/// How many topics and sets have a tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TagUsage {
+    pub tag: String,
+    pub topics: u64,
+    pub sets: u64,
+}
+
 #[derive(Debug, Clone)]
-pub struct TopicService<T> {
+pub struct TopicService<T: TopicEngine> {
     engine: T,
+    /// Topics can only embed their sets when this is set
+    sets: Option<TopicSets<T::TopicId>>,
 }
 
 fn initial_bulk_create_outcome<T>(topic: CreateManyTopic) -> CreateManyTopicStatus<T> {
+    let description = topic.description.unwrap_present_or(None);
     match topic.name {
-        Field::Present(Some(n)) => CreateManyTopicStatus::Pending {
-            name: n,
-            description: topic.description.unwrap_present_or(None),
+        Field::Present(Some(n)) => match validation::new_topic::<T>(&n, description.as_deref()) {
+            Ok(new_topic) => CreateManyTopicStatus::Pending {
+                name: new_topic.name,
+                description: new_topic.description,
+            },
+            // only the first broken rule is reported per topic
+            Err(errors) => CreateManyTopicStatus::Fail {
+                topic_name: Some(n),
+                topic_description: description,
+                reason: errors
+                    .first()
+                    .map_or(CreateManyFailReason::ServiceError, Into::into),
+            },
         },
         Field::Present(None) | Field::Missing => CreateManyTopicStatus::Fail {
             topic_name: None,
-            topic_description: topic.description.unwrap_present_or(None),
+            topic_description: description,
             reason: CreateManyFailReason::MissingName,
         },
     }
@@ -57,7 +167,12 @@ where
     T: TopicEngine,
 {
     pub fn new(engine: T) -> Self {
-        TopicService { engine }
+        TopicService { engine, sets: None }
+    }
+
+    pub fn with_sets(mut self, sets: TopicSets<T::TopicId>) -> Self {
+        self.sets = Some(sets);
+        self
     }
 
     #[instrument(skip_all, name = "service#get")]
@@ -77,6 +192,55 @@ where
         Ok(topic)
     }
 
+    /// Repeated ids are only looked up once
+    #[instrument(skip_all, fields(req.topic_count = topic_ids.len()))]
+    pub async fn get_many(
+        &self,
+        topic_ids: Vec<T::TopicId>,
+    ) -> ServiceResult<BatchGet<T::TopicId>> {
+        let mut unique = Vec::with_capacity(topic_ids.len());
+        for topic_id in topic_ids {
+            if !unique.contains(&topic_id) {
+                unique.push(topic_id);
+            }
+        }
+
+        let topics = self
+            .engine
+            .repo()
+            .get_many(unique.clone())
+            .await
+            .change_context(TopicServiceError)?;
+
+        let mut batch = BatchGet {
+            found: Vec::with_capacity(unique.len()),
+            missing: Vec::new(),
+        };
+        for (topic_id, topic) in unique.into_iter().zip(topics) {
+            match topic {
+                Some(topic) => batch.found.push(topic),
+                None => batch.missing.push(topic_id),
+            }
+        }
+        metrics::increment_topics_retrieved_by(batch.found.len());
+
+        Ok(batch)
+    }
+
+    /// The first page of the topic's sets, as they were at `as_of` when given. `None` if the
+    /// service wasn't given a set repo
+    #[instrument(skip_all, name = "service#embedded_sets")]
+    pub async fn embedded_sets(
+        &self,
+        topic_id: T::TopicId,
+        as_of: Option<DateTime<Utc>>,
+    ) -> OptServiceResult<Vec<EmbeddedSet>> {
+        match &self.sets {
+            Some(sets) => sets.first_page(topic_id, as_of).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
     pub async fn list(
         &self,
         list_criteria: TopicListCriteria,
@@ -93,18 +257,100 @@ where
         Ok(topics)
     }
 
+    /// Topics are counted as retrieved as they're read from the stream
+    pub async fn list_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+    ) -> ServiceResult<impl Stream<Item = ServiceResult<Topic<T::TopicId>>> + Send + 'static> {
+        let topics = self
+            .engine
+            .repo()
+            .list_stream(list_criteria)
+            .await
+            .change_context(TopicServiceError)?;
+
+        Ok(topics.map(|topic| {
+            if topic.is_ok() {
+                metrics::increment_topics_retrieved_by(1);
+            }
+            topic.change_context(TopicServiceError)
+        }))
+    }
+
+    /// Like `list_stream`, but only `fields` are read
+    pub async fn list_fields_stream(
+        &self,
+        list_criteria: TopicListCriteria,
+        fields: Vec<TopicField>,
+    ) -> ServiceResult<impl Stream<Item = ServiceResult<PartialTopic<T::TopicId>>> + Send + 'static>
+    {
+        let topics = self
+            .engine
+            .repo()
+            .list_fields_stream(list_criteria, fields)
+            .await
+            .change_context(TopicServiceError)?;
+
+        Ok(topics.map(|topic| {
+            if topic.is_ok() {
+                metrics::increment_topics_retrieved_by(1);
+            }
+            topic.change_context(TopicServiceError)
+        }))
+    }
+
     #[instrument(skip_all, name = "service#create")]
-    pub async fn create(&self, topic: TopicCreation) -> ServiceResult<Topic<T::TopicId>> {
+    pub async fn create(
+        &self,
+        topic: TopicCreation<T::TopicId>,
+    ) -> ServiceResult<CreateOutcome<T::TopicId>> {
+        let new_topic = match topic.validate() {
+            Ok(new_topic) => new_topic,
+            Err(errors) => return Ok(CreateOutcome::Invalid(errors)),
+        };
+
         let topic = self
             .engine
             .repo()
-            .create(NewTopic::new(topic.name, topic.description))
+            .create(new_topic)
             .await
             .change_context(TopicServiceError)?;
 
         debug!("created topic");
         metrics::increment_topics_created();
-        Ok(topic)
+        Ok(CreateOutcome::Success(topic))
+    }
+
+    #[instrument(skip_all, name = "service#upsert")]
+    pub async fn upsert(
+        &self,
+        topic_id: T::TopicId,
+        topic: TopicCreation<T::TopicId>,
+    ) -> ServiceResult<UpsertOutcome<T::TopicId>> {
+        let new_topic = match topic.validate() {
+            Ok(new_topic) => new_topic,
+            Err(errors) => return Ok(UpsertOutcome::Invalid(errors)),
+        };
+
+        let upserted = self
+            .engine
+            .repo()
+            .upsert(topic_id, new_topic)
+            .await
+            .change_context(TopicServiceError)?;
+
+        Ok(match upserted {
+            Upserted::Created(topic) => {
+                debug!("created topic {topic_id:?}");
+                metrics::increment_topics_created();
+                UpsertOutcome::Created(topic)
+            }
+            Upserted::Replaced(topic) => {
+                debug!("replaced topic {topic_id:?}");
+                metrics::increment_topics_patched();
+                UpsertOutcome::Replaced(topic)
+            }
+        })
     }
 
     #[instrument(skip_all, name = "service#create_many")]
@@ -153,12 +399,18 @@ where
                     *status = CreateManyTopicStatus::Success(topic);
                 }
                 Err(e) => {
-                    error!("Topic request (idx: {status_idx}) failed with error '{e}'");
+                    let reason = match e.current_context() {
+                        TopicRepoError::DuplicateName => CreateManyFailReason::DuplicateName,
+                        _ => {
+                            error!("Topic request (idx: {status_idx}) failed with error '{e}'");
+                            CreateManyFailReason::ServiceError
+                        }
+                    };
                     if let CreateManyTopicStatus::Pending { name, description } = status {
                         *status = CreateManyTopicStatus::Fail {
                             topic_name: Some(std::mem::take(name)),
                             topic_description: description.take(),
-                            reason: CreateManyFailReason::ServiceError,
+                            reason,
                         };
                     } else {
                         unreachable!("Topic result respective status should only be 'Pending'");
@@ -176,43 +428,750 @@ where
         Ok(statuses)
     }
 
+    /// Outcomes are in the same order as `patches`. Only valid patches with an id not seen
+    /// earlier in the request are sent to the repo.
+    #[instrument(skip_all, name = "service#patch_many")]
+    pub async fn patch_many<I>(
+        &self,
+        patches: I,
+    ) -> ServiceResult<Vec<PatchManyTopicStatus<T::TopicId>>>
+    where
+        I: IntoIterator<Item = PatchManyTopic<T::TopicId>>,
+    {
+        let mut statuses = Vec::new();
+        let mut pending = Vec::new();
+        let mut status_indexes = Vec::new();
+        let mut seen_ids = Vec::new();
+
+        for (i, patch_req) in patches.into_iter().enumerate() {
+            let topic_id = patch_req.id;
+
+            // ids are only PartialEq, and a bulk request is small enough to scan
+            if seen_ids.contains(&topic_id) {
+                statuses.push(PatchManyTopicStatus::Fail {
+                    topic_id,
+                    reason: PatchManyFailReason::DuplicateId,
+                });
+                continue;
+            }
+            seen_ids.push(topic_id);
+
+            match validation::patch_topic(patch_req.name, patch_req.description) {
+                Ok(patch) => {
+                    pending.push((topic_id, patch));
+                    status_indexes.push(i);
+                    // overwritten once the repo has patched it
+                    statuses.push(PatchManyTopicStatus::Fail {
+                        topic_id,
+                        reason: PatchManyFailReason::ServiceError,
+                    });
+                }
+                Err(errors) => statuses.push(PatchManyTopicStatus::Fail {
+                    topic_id,
+                    reason: errors
+                        .first()
+                        .map_or(PatchManyFailReason::ServiceError, Into::into),
+                }),
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(statuses);
+        }
+
+        let results = self
+            .engine
+            .repo()
+            .patch_many(pending)
+            .await
+            .change_context(TopicServiceError)?;
+
+        let mut patched_count = 0;
+
+        for (result, status_idx) in results.into_iter().zip(status_indexes) {
+            let status = &mut statuses[status_idx];
+            let PatchManyTopicStatus::Fail { topic_id, .. } = *status else {
+                unreachable!("pending patch statuses are only ever 'Fail'");
+            };
+
+            *status = match result {
+                Ok(Some(topic)) => {
+                    patched_count += 1;
+                    PatchManyTopicStatus::Success(topic)
+                }
+                Ok(None) => PatchManyTopicStatus::Fail {
+                    topic_id,
+                    reason: PatchManyFailReason::NotFound,
+                },
+                Err(e) => {
+                    let reason = match e.current_context() {
+                        TopicRepoError::DuplicateName => PatchManyFailReason::DuplicateName,
+                        _ => {
+                            error!("Topic patch (idx: {status_idx}) failed with error '{e}'");
+                            PatchManyFailReason::ServiceError
+                        }
+                    };
+                    PatchManyTopicStatus::Fail { topic_id, reason }
+                }
+            };
+        }
+
+        debug!(
+            "patched {} out of {} requested topics",
+            patched_count,
+            statuses.len()
+        );
+        metrics::increment_topics_patched_by(patched_count);
+        Ok(statuses)
+    }
+
+    /// Outcomes are in the same order as `topic_ids`
+    #[instrument(skip_all, name = "service#delete_many")]
+    pub async fn delete_many(
+        &self,
+        topic_ids: Vec<T::TopicId>,
+    ) -> ServiceResult<Vec<DeleteManyTopicStatus<T::TopicId>>> {
+        let mut statuses = Vec::with_capacity(topic_ids.len());
+        let mut pending = Vec::new();
+        let mut status_indexes = Vec::new();
+
+        for (i, topic_id) in topic_ids.into_iter().enumerate() {
+            if pending.contains(&topic_id) {
+                statuses.push(DeleteManyTopicStatus::Fail {
+                    topic_id,
+                    reason: DeleteManyFailReason::DuplicateId,
+                });
+            } else {
+                pending.push(topic_id);
+                status_indexes.push(i);
+                // overwritten once the repo has deleted it
+                statuses.push(DeleteManyTopicStatus::Fail {
+                    topic_id,
+                    reason: DeleteManyFailReason::ServiceError,
+                });
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(statuses);
+        }
+
+        let results = self
+            .engine
+            .repo()
+            .delete_many(pending)
+            .await
+            .change_context(TopicServiceError)?;
+
+        let mut deleted_count = 0;
+
+        for (result, status_idx) in results.into_iter().zip(status_indexes) {
+            let status = &mut statuses[status_idx];
+            let DeleteManyTopicStatus::Fail { topic_id, .. } = *status else {
+                unreachable!("pending delete statuses are only ever 'Fail'");
+            };
+
+            *status = match result {
+                Ok(Some(())) => {
+                    deleted_count += 1;
+                    DeleteManyTopicStatus::Success { topic_id }
+                }
+                Ok(None) => DeleteManyTopicStatus::Fail {
+                    topic_id,
+                    reason: DeleteManyFailReason::NotFound,
+                },
+                Err(e) => {
+                    let reason = match e.current_context() {
+                        TopicRepoError::HasChildren => DeleteManyFailReason::HasChildren,
+                        _ => {
+                            error!("Topic delete (idx: {status_idx}) failed with error '{e}'");
+                            DeleteManyFailReason::ServiceError
+                        }
+                    };
+                    DeleteManyTopicStatus::Fail { topic_id, reason }
+                }
+            };
+        }
+
+        debug!(
+            "deleted {} out of {} requested topics",
+            deleted_count,
+            statuses.len()
+        );
+        metrics::increment_topics_deleted_by(deleted_count);
+        Ok(statuses)
+    }
+
+    /// Creates every topic or none of them. Nothing is sent to the repo unless every topic is
+    /// valid, and the errors name the index of the topic they belong to.
+    #[instrument(skip_all, name = "service#create_many_atomic")]
+    pub async fn create_many_atomic<I>(
+        &self,
+        topics: I,
+    ) -> ServiceResult<CreateManyAtomicOutcome<T::TopicId>>
+    where
+        I: IntoIterator<Item = CreateManyTopic>,
+    {
+        let mut errors = FieldErrors::default();
+        let mut new_topics = Vec::new();
+
+        for (i, topic) in topics.into_iter().enumerate() {
+            let description = topic.description.unwrap_present_or(None);
+            let result = match topic.name {
+                Field::Present(Some(name)) => validation::new_topic(&name, description.as_deref()),
+                Field::Present(None) | Field::Missing => {
+                    Err(FieldError::required(validation::NAME.name()).into())
+                }
+            };
+
+            match result {
+                Ok(new_topic) => new_topics.push(new_topic),
+                Err(topic_errors) => errors.extend_indexed(i, topic_errors),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Ok(CreateManyAtomicOutcome::Invalid(errors));
+        }
+
+        let topics = self
+            .engine
+            .repo()
+            .create_many_atomic(new_topics)
+            .await
+            .change_context(TopicServiceError)?;
+
+        debug!("atomically created {} topics", topics.len());
+        metrics::increment_topics_created_by(topics.len());
+        Ok(CreateManyAtomicOutcome::Success(topics))
+    }
+
     #[instrument(skip_all, name = "service#delete")]
-    pub async fn delete(&self, topic_id: T::TopicId) -> ServiceResult<Option<()>> {
+    pub async fn delete(
+        &self,
+        topic_id: T::TopicId,
+        policy: DeletePolicy,
+    ) -> ServiceResult<Option<()>> {
         let deleted = self
             .engine
             .repo()
-            .delete(topic_id)
+            .delete(topic_id, policy)
             .await
             .change_context(TopicServiceError)?;
 
         if deleted.is_some() {
-            debug!("deleted topic {topic_id:?}");
+            debug!("deleted topic {topic_id:?} with policy {policy:?}");
             metrics::increment_topics_deleted();
         }
 
         Ok(deleted)
     }
 
+    #[instrument(skip_all, name = "service#children")]
+    pub async fn children(
+        &self,
+        topic_id: T::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptServiceResult<Vec<Topic<T::TopicId>>> {
+        let children = self
+            .engine
+            .repo()
+            .children(topic_id, list_criteria)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if let Some(children) = &children {
+            metrics::increment_topics_retrieved_by(children.len());
+        }
+        Ok(children)
+    }
+
+    /// The topics above the topic, starting from its root
+    #[instrument(skip_all, name = "service#ancestors")]
+    pub async fn ancestors(
+        &self,
+        topic_id: T::TopicId,
+    ) -> OptServiceResult<Vec<Topic<T::TopicId>>> {
+        let ancestors = self
+            .engine
+            .repo()
+            .ancestors(topic_id)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if let Some(ancestors) = &ancestors {
+            metrics::increment_topics_retrieved_by(ancestors.len());
+        }
+        Ok(ancestors)
+    }
+
+    /// The topic and everything nested under it, parents before their children
+    #[instrument(skip_all, name = "service#subtree")]
+    pub async fn subtree(&self, topic_id: T::TopicId) -> OptServiceResult<Vec<Topic<T::TopicId>>> {
+        let subtree = self
+            .engine
+            .repo()
+            .subtree(topic_id)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if let Some(subtree) = &subtree {
+            metrics::increment_topics_retrieved_by(subtree.len());
+        }
+        Ok(subtree)
+    }
+
+    /// Nests the topic under `parent_id`, or makes it a root topic when that's `None`
+    #[instrument(skip_all, name = "service#move_topic")]
+    pub async fn move_topic(
+        &self,
+        topic_id: T::TopicId,
+        parent_id: Option<T::TopicId>,
+    ) -> OptServiceResult<Topic<T::TopicId>> {
+        let patch = PatchTopic::new(None, Field::Missing).with_parent(Field::Present(parent_id));
+
+        let topic = self
+            .engine
+            .repo()
+            .patch(topic_id, patch)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if topic.is_some() {
+            debug!("moved {topic_id:?} under {parent_id:?}");
+            metrics::increment_topics_patched();
+        }
+        Ok(topic)
+    }
+
+    /// Links the topic to `target_id`, `None` if there's no such topic
+    #[instrument(skip_all, name = "service#create_link")]
+    pub async fn create_link(
+        &self,
+        topic_id: T::TopicId,
+        target_id: T::TopicId,
+        link_type: LinkType,
+    ) -> OptServiceResult<TopicLink<T::TopicId>> {
+        let link = self
+            .engine
+            .repo()
+            .create_link(topic_id, target_id, link_type)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if link.is_some() {
+            debug!(
+                "linked {topic_id:?} to {target_id:?} as {}",
+                link_type.name()
+            );
+        }
+        Ok(link)
+    }
+
+    #[instrument(skip_all, name = "service#get_link")]
+    pub async fn get_link(
+        &self,
+        topic_id: T::TopicId,
+        target_id: T::TopicId,
+    ) -> OptServiceResult<TopicLink<T::TopicId>> {
+        self.engine
+            .repo()
+            .get_link(topic_id, target_id)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    #[instrument(skip_all, name = "service#update_link")]
+    pub async fn update_link(
+        &self,
+        topic_id: T::TopicId,
+        target_id: T::TopicId,
+        link_type: LinkType,
+    ) -> OptServiceResult<TopicLink<T::TopicId>> {
+        self.engine
+            .repo()
+            .update_link(topic_id, target_id, link_type)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    #[instrument(skip_all, name = "service#delete_link")]
+    pub async fn delete_link(
+        &self,
+        topic_id: T::TopicId,
+        target_id: T::TopicId,
+    ) -> OptServiceResult<()> {
+        let deleted = self
+            .engine
+            .repo()
+            .delete_link(topic_id, target_id)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if deleted.is_some() {
+            debug!("unlinked {topic_id:?} from {target_id:?}");
+        }
+        Ok(deleted)
+    }
+
+    #[instrument(skip_all, name = "service#links")]
+    pub async fn links(
+        &self,
+        topic_id: T::TopicId,
+        direction: LinkDirection,
+        list_criteria: TopicListCriteria,
+    ) -> OptServiceResult<Vec<TopicLink<T::TopicId>>> {
+        self.engine
+            .repo()
+            .links(topic_id, direction, list_criteria)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    /// Walks the links either way from the topic, breadth first, for at most `depth` links and
+    /// only following links of `link_type` when given. Stops early, marking the graph truncated,
+    /// rather than go past [`MAX_GRAPH_NODES`] or [`MAX_GRAPH_EDGES`]. `None` if there's no such
+    /// topic
+    #[instrument(skip_all, name = "service#graph")]
+    pub async fn graph(
+        &self,
+        topic_id: T::TopicId,
+        depth: u32,
+        link_type: Option<LinkType>,
+    ) -> OptServiceResult<LinkGraph<T::TopicId>> {
+        let repo = self.engine.repo();
+        if repo
+            .get(topic_id)
+            .await
+            .change_context(TopicServiceError)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let mut reached = vec![topic_id];
+        let mut visited = HashSet::from([topic_id]);
+        let mut followed = HashSet::new();
+        let mut frontier = vec![topic_id];
+        let mut edges: Vec<TopicLink<T::TopicId>> = Vec::new();
+        let mut truncated = false;
+        'walk: for _ in 0..depth {
+            if frontier.is_empty() {
+                break;
+            }
+
+            let links = repo
+                .links_touching(frontier)
+                .await
+                .change_context(TopicServiceError)?;
+
+            let mut next = Vec::new();
+            for link in links {
+                if link_type.is_some_and(|link_type| link_type != link.link_type) {
+                    continue;
+                }
+                if followed.contains(&(link.source_id, link.target_id)) {
+                    continue;
+                }
+                let unseen = [link.source_id, link.target_id]
+                    .iter()
+                    .filter(|end| !visited.contains(*end))
+                    .count();
+                if edges.len() == MAX_GRAPH_EDGES || reached.len() + unseen > MAX_GRAPH_NODES {
+                    truncated = true;
+                    break 'walk;
+                }
+                for end in [link.source_id, link.target_id] {
+                    if visited.insert(end) {
+                        reached.push(end);
+                        next.push(end);
+                    }
+                }
+                followed.insert((link.source_id, link.target_id));
+                edges.push(link);
+            }
+            frontier = next;
+        }
+
+        // a topic deleted mid walk is left out along with its links
+        let topics = repo
+            .get_many(reached.clone())
+            .await
+            .change_context(TopicServiceError)?;
+        let mut nodes = Vec::with_capacity(topics.len());
+        let mut gone = HashSet::new();
+        for (id, topic) in reached.into_iter().zip(topics) {
+            match topic {
+                Some(topic) => nodes.push(topic),
+                None => {
+                    gone.insert(id);
+                }
+            }
+        }
+        edges.retain(|e| !gone.contains(&e.source_id) && !gone.contains(&e.target_id));
+        metrics::increment_topics_retrieved_by(nodes.len());
+
+        Ok(Some(LinkGraph {
+            nodes,
+            edges,
+            truncated,
+        }))
+    }
+
+    /// The topic's tags in alphabetical order, `None` if there's no such topic
+    #[instrument(skip_all, name = "service#tags")]
+    pub async fn tags(&self, topic_id: T::TopicId) -> OptServiceResult<Vec<String>> {
+        self.engine
+            .repo()
+            .tags(topic_id)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    #[instrument(skip_all, name = "service#add_tags")]
+    pub async fn add_tags(
+        &self,
+        topic_id: T::TopicId,
+        tags: Vec<String>,
+    ) -> ServiceResult<TagOutcome> {
+        let tags = match validation::tags(&tags) {
+            Ok(tags) => tags,
+            Err(errors) => return Ok(TagOutcome::Invalid(errors)),
+        };
+
+        let tags = self
+            .engine
+            .repo()
+            .add_tags(topic_id, tags)
+            .await
+            .change_context(TopicServiceError)?;
+
+        Ok(tags.map_or(TagOutcome::NotFound, TagOutcome::Success))
+    }
+
+    #[instrument(skip_all, name = "service#remove_tags")]
+    pub async fn remove_tags(
+        &self,
+        topic_id: T::TopicId,
+        tags: Vec<String>,
+    ) -> ServiceResult<TagOutcome> {
+        let tags = match validation::tags(&tags) {
+            Ok(tags) => tags,
+            Err(errors) => return Ok(TagOutcome::Invalid(errors)),
+        };
+
+        let tags = self
+            .engine
+            .repo()
+            .remove_tags(topic_id, tags)
+            .await
+            .change_context(TopicServiceError)?;
+
+        Ok(tags.map_or(TagOutcome::NotFound, TagOutcome::Success))
+    }
+
+    /// Every tag in use on topics or sets, in alphabetical order. Set counts are 0 when the
+    /// service wasn't given a set repo
+    #[instrument(skip_all, name = "service#tag_directory")]
+    pub async fn tag_directory(&self) -> ServiceResult<Vec<TagUsage>> {
+        let topic_counts = self
+            .engine
+            .repo()
+            .tag_counts()
+            .await
+            .change_context(TopicServiceError)?;
+        let set_counts = match &self.sets {
+            Some(sets) => sets.tag_counts().await?,
+            None => Vec::new(),
+        };
+
+        let mut directory = BTreeMap::new();
+        for count in topic_counts {
+            directory.insert(count.tag, (count.count, 0));
+        }
+        for count in set_counts {
+            directory.entry(count.tag).or_insert((0, 0)).1 = count.count;
+        }
+
+        Ok(directory
+            .into_iter()
+            .map(|(tag, (topics, sets))| TagUsage { tag, topics, sets })
+            .collect())
+    }
+
+    /// Moves the topic to `to` if its status allows it, only admins can take a topic out of the
+    /// archive. Another change landing first fails with `StatusChanged`
+    #[instrument(skip_all, name = "service#change_status")]
+    pub async fn change_status(
+        &self,
+        topic_id: T::TopicId,
+        to: TopicStatus,
+        reason: Option<String>,
+        is_admin: bool,
+    ) -> ServiceResult<StatusOutcome<T::TopicId>> {
+        let reason = match validation::status_reason(reason.as_deref()) {
+            Ok(reason) => reason,
+            Err(errors) => return Ok(StatusOutcome::Invalid(errors)),
+        };
+
+        let repo = self.engine.repo();
+        let Some(topic) = repo.get(topic_id).await.change_context(TopicServiceError)? else {
+            return Ok(StatusOutcome::NotFound);
+        };
+
+        let from = topic.status;
+        if !from.can_become(to) {
+            return Ok(StatusOutcome::NotAllowed);
+        }
+        if from.is_unarchive(to) && !is_admin {
+            return Ok(StatusOutcome::AdminOnly);
+        }
+
+        let topic = repo
+            .change_status(topic_id, StatusChange::new(from, to, reason))
+            .await
+            .change_context(TopicServiceError)?;
+
+        if topic.is_some() {
+            debug!("moved {topic_id:?} from {} to {}", from.name(), to.name());
+            metrics::increment_topic_status_changes(from, to);
+        }
+        Ok(topic.map_or(StatusOutcome::NotFound, StatusOutcome::Success))
+    }
+
+    /// Every status change of the topic, oldest first. `None` if there's no such topic
+    #[instrument(skip_all, name = "service#status_changes")]
+    pub async fn status_changes(
+        &self,
+        topic_id: T::TopicId,
+    ) -> OptServiceResult<Vec<StatusChange>> {
+        self.engine
+            .repo()
+            .status_changes(topic_id)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    /// A page of the topic's versions, oldest first. `None` if there's no such topic
+    #[instrument(skip_all, name = "service#versions")]
+    pub async fn versions(
+        &self,
+        topic_id: T::TopicId,
+        list_criteria: TopicListCriteria,
+    ) -> OptServiceResult<Vec<TopicVersion<T::TopicId>>> {
+        self.engine
+            .repo()
+            .versions(topic_id, list_criteria)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    /// `None` if there's no such topic or version
+    #[instrument(skip_all, name = "service#version")]
+    pub async fn version(
+        &self,
+        topic_id: T::TopicId,
+        version: u64,
+    ) -> OptServiceResult<TopicVersion<T::TopicId>> {
+        self.engine
+            .repo()
+            .version(topic_id, version)
+            .await
+            .change_context(TopicServiceError)
+    }
+
+    /// The topic as it was at `at`, `None` if there's no such topic or it didn't exist yet
+    #[instrument(skip_all, name = "service#get_as_of")]
+    pub async fn get_as_of(
+        &self,
+        topic_id: T::TopicId,
+        at: DateTime<Utc>,
+    ) -> OptServiceResult<Topic<T::TopicId>> {
+        let version = self
+            .engine
+            .repo()
+            .version_at(topic_id, at)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if let Some(version) = &version {
+            debug!(
+                "topic {topic_id:?} was at version {} at {at}",
+                version.version
+            );
+            metrics::increment_topics_retrieved();
+        }
+        Ok(version.map(|v| v.topic))
+    }
+
+    /// Puts the topic's name, description and attributes back to how they were at `version`,
+    /// which makes a new version. Its parent and status are left alone, since moves and status
+    /// changes have their own checks. `None` if there's no such topic or version
+    #[instrument(skip_all, name = "service#revert")]
+    pub async fn revert(
+        &self,
+        topic_id: T::TopicId,
+        version: u64,
+    ) -> OptServiceResult<Topic<T::TopicId>> {
+        let repo = self.engine.repo();
+        let Some(old) = repo
+            .version(topic_id, version)
+            .await
+            .change_context(TopicServiceError)?
+        else {
+            return Ok(None);
+        };
+        let Some(current) = repo.get(topic_id).await.change_context(TopicServiceError)? else {
+            return Ok(None);
+        };
+
+        // attributes are merged in, so the ones added since are patched to null to remove them
+        let mut attributes = old.topic.attributes;
+        for key in current.attributes.keys() {
+            if !attributes.contains_key(key) {
+                attributes.insert(key.clone(), Value::Null);
+            }
+        }
+        let patch = PatchTopic::new(Some(old.topic.name), Field::Present(old.topic.description))
+            .with_attributes(Some(attributes));
+
+        let topic = repo
+            .patch(topic_id, patch)
+            .await
+            .change_context(TopicServiceError)?;
+
+        if topic.is_some() {
+            debug!("reverted {topic_id:?} to version {version}");
+            metrics::increment_topics_reverted();
+        }
+        Ok(topic)
+    }
+
     #[instrument(skip_all, name = "service#update")]
     pub async fn patch(
         &self,
         topic_id: T::TopicId,
         name: Field<String>,
         description: Field<String>,
+        attributes: Field<Attributes>,
     ) -> ServiceResult<PatchOutcome<T::TopicId>> {
-        let name = match name {
-            Field::Present(Some(n)) => Some(n),
-            Field::Missing => None,
-            Field::Present(None) => {
-                // name cannot be null
-                return Ok(PatchOutcome::InvalidName);
+        let patch = match (
+            validation::patch_topic(name, description),
+            validation::patch_attributes(attributes),
+        ) {
+            (Ok(patch), Ok(attributes)) => patch.with_attributes(attributes),
+            (patch, attributes) => {
+                return Ok(PatchOutcome::Invalid(all_errors(
+                    patch.err(),
+                    attributes.err(),
+                )));
             }
         };
 
         let topic = self
             .engine
             .repo()
-            .patch(topic_id, PatchTopic::new(name, description))
+            .patch(topic_id, patch)
             .await
             .change_context(TopicServiceError)?;
 
@@ -226,8 +1185,41 @@ where
     }
 }
 
+pub enum CreateOutcome<T> {
+    Success(Topic<T>),
+    Invalid(FieldErrors),
+}
+
+pub enum UpsertOutcome<T> {
+    Created(Topic<T>),
+    Replaced(Topic<T>),
+    Invalid(FieldErrors),
+}
+
+pub enum CreateManyAtomicOutcome<T> {
+    Success(Vec<Topic<T>>),
+    Invalid(FieldErrors),
+}
+
 pub enum PatchOutcome<T> {
     Success(Topic<T>),
-    InvalidName,
+    Invalid(FieldErrors),
+    NotFound,
+}
+
+pub enum StatusOutcome<T> {
+    Success(Topic<T>),
+    Invalid(FieldErrors),
+    NotFound,
+    /// The topic's current status can't go straight to the one asked for
+    NotAllowed,
+    /// Taking a topic out of the archive needs `TOPIC_ADMIN`
+    AdminOnly,
+}
+
+/// The topic's tags after they were added or removed
+pub enum TagOutcome {
+    Success(Vec<String>),
+    Invalid(FieldErrors),
     NotFound,
 }
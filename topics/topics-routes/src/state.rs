@@ -1,6 +1,9 @@
+use crate::expand::TopicSets;
 use crate::service::TopicService;
 use axum::extract::FromRef;
 use error_stack::Report;
+use routing::idempotency::{Idempotency, IdempotencyRepository};
+use sets_core::{SetKey, SetRepository};
 use topics_core::TopicEngine;
 use tracing::{info, instrument};
 
@@ -8,6 +11,8 @@ use tracing::{info, instrument};
 pub struct TopicAppState<T: TopicEngine> {
     pub service: TopicService<T>,
     pub metrics_enabled: bool,
+    /// Creates are only idempotent when this is set
+    pub idempotency: Option<Idempotency>,
 }
 
 pub type StateResult<T> = Result<T, Report<StateErr>>;
@@ -31,8 +36,23 @@ impl<T: TopicEngine> TopicAppState<T> {
         Ok(Self {
             service: TopicService::new(engine),
             metrics_enabled,
+            idempotency: None,
         })
     }
+
+    /// Lets `?expand=sets` embed a topic's sets, read from `repo`
+    pub fn with_sets<R>(mut self, repo: R) -> Self
+    where
+        R: SetRepository<SetKey: SetKey<TopicId = T::TopicId>>,
+    {
+        self.service = self.service.with_sets(TopicSets::new(repo));
+        self
+    }
+
+    pub fn with_idempotency(mut self, repo: impl IdempotencyRepository) -> Self {
+        self.idempotency = Some(Idempotency::new(repo));
+        self
+    }
 }
 
 impl<T: TopicEngine + Clone> FromRef<TopicAppState<T>> for TopicService<T> {